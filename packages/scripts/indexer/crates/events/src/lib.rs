@@ -0,0 +1,364 @@
+//! Typed Rust definitions of the events emitted by the Unxversal Move packages, deserializable
+//! from the BCS stored in `unxv_events.contents_bcs`.
+//!
+//! Each Move module is a Rust module holding one struct per event and an `Event` enum over
+//! them; [`decode`] dispatches on `(module, event_type)`. The definitions below are also
+//! rendered into the Move-like [`signatures`] the indexer builds its decoding layouts from, so
+//! the two can't drift. Keep them in sync with the `public struct ... has copy, drop` event
+//! definitions under `packages/unxv*/sources`; phantom type parameters don't affect the layout
+//! and are omitted.
+
+use move_core_types::account_address::AccountAddress;
+use serde::{Deserialize, Serialize};
+
+/// Move types as they appear in event definitions.
+pub mod types {
+    use super::*;
+
+    #[allow(non_camel_case_types)]
+    pub type address = AccountAddress;
+    #[allow(non_camel_case_types)]
+    pub type vector<T> = Vec<T>;
+
+    /// `sui::object::ID`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct ID {
+        pub bytes: AccountAddress,
+    }
+
+    /// `std::type_name::TypeName`, e.g. the coin type of a fee.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct TypeName {
+        pub name: String,
+    }
+}
+
+/// Sample values of the Move types above, for tests.
+#[cfg(test)]
+mod sample {
+    use super::types::{TypeName, ID};
+    use super::*;
+
+    /// A value determined by `seed`, different for different seeds where the type allows.
+    pub trait Sample {
+        fn sample(seed: u8) -> Self;
+    }
+
+    impl Sample for bool {
+        fn sample(seed: u8) -> Self {
+            seed % 2 == 1
+        }
+    }
+
+    impl Sample for u8 {
+        fn sample(seed: u8) -> Self {
+            seed
+        }
+    }
+
+    impl Sample for u64 {
+        fn sample(seed: u8) -> Self {
+            u64::MAX - u64::from(seed)
+        }
+    }
+
+    impl Sample for u128 {
+        fn sample(seed: u8) -> Self {
+            u128::MAX - u128::from(seed)
+        }
+    }
+
+    impl Sample for AccountAddress {
+        fn sample(seed: u8) -> Self {
+            AccountAddress::new([seed; AccountAddress::LENGTH])
+        }
+    }
+
+    impl Sample for String {
+        fn sample(seed: u8) -> Self {
+            format!("sample {seed}")
+        }
+    }
+
+    impl Sample for ID {
+        fn sample(seed: u8) -> Self {
+            ID { bytes: Sample::sample(seed) }
+        }
+    }
+
+    impl Sample for TypeName {
+        fn sample(seed: u8) -> Self {
+            TypeName { name: format!("{}::coin::COIN{seed}", AccountAddress::sample(seed).short_str_lossless()) }
+        }
+    }
+
+    impl<T: Sample> Sample for Vec<T> {
+        fn sample(seed: u8) -> Self {
+            (0..3).map(|i| T::sample(seed.wrapping_add(i))).collect()
+        }
+    }
+}
+
+macro_rules! events {
+    ($(
+        $variant:ident: mod $module:ident {
+            $($name:ident { $($field:ident: $ty:ty),* $(,)? })*
+        }
+    )*) => {
+        $(
+            #[doc = concat!("Events of the `", stringify!($module), "` module.")]
+            pub mod $module {
+                #[allow(unused_imports)]
+                use $crate::types::*;
+                use anyhow::Context;
+                use serde::{Deserialize, Serialize};
+
+                $(
+                    #[doc = concat!("`", stringify!($module), "::", stringify!($name), "`")]
+                    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+                    pub struct $name {
+                        $(pub $field: $ty,)*
+                    }
+                )*
+
+                #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+                pub enum Event {
+                    $($name($name),)*
+                }
+
+                /// Move-like signature of every event in this module.
+                pub const SIGNATURES: &[&str] = &[$(
+                    concat!(
+                        stringify!($module), "::", stringify!($name), " { ",
+                        $(stringify!($field), ": ", stringify!($ty), ", ",)*
+                        "}"
+                    ),
+                )*];
+
+                /// None if `event_type` isn't an event of this module.
+                pub(crate) fn decode(event_type: &str, bytes: &[u8]) -> anyhow::Result<Option<Event>> {
+                    Ok(Some(match event_type {
+                        $(stringify!($name) => Event::$name(
+                            bcs::from_bytes(bytes)
+                                .with_context(|| concat!("Invalid ", stringify!($module), "::", stringify!($name)))?,
+                        ),)*
+                        _ => return Ok(None),
+                    }))
+                }
+
+                #[cfg(test)]
+                mod tests {
+                    use super::*;
+                    use $crate::sample::Sample;
+
+                    $(
+                        /// Encodes a sample with distinct field values and decodes it through
+                        /// [`crate::decode`].
+                        #[test]
+                        #[allow(non_snake_case)]
+                        fn $name() {
+                            let mut seed = 0;
+                            let event = $name { $($field: { seed += 1; Sample::sample(seed) },)* };
+                            let bytes = bcs::to_bytes(&event).unwrap();
+                            let decoded = $crate::decode(stringify!($module), stringify!($name), &bytes).unwrap();
+                            assert_eq!(decoded, $crate::UnxvDecodedEvent::$variant(Event::$name(event)));
+                        }
+                    )*
+                }
+            }
+        )*
+
+        /// An event decoded by [`decode`].
+        #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+        pub enum UnxvDecodedEvent {
+            $($variant($module::Event),)*
+            /// A module or struct not defined here. Its contents are left undecoded.
+            Unknown { module: String, event_type: String },
+        }
+
+        /// Decode the BCS `bytes` of the `module::event_type` event. Unknown types decode to
+        /// [`UnxvDecodedEvent::Unknown`]; known types whose bytes don't match are an error.
+        pub fn decode(module: &str, event_type: &str, bytes: &[u8]) -> anyhow::Result<UnxvDecodedEvent> {
+            let decoded = match module {
+                $(stringify!($module) => $module::decode(event_type, bytes)?.map(UnxvDecodedEvent::$variant),)*
+                _ => None,
+            };
+            Ok(decoded.unwrap_or_else(|| UnxvDecodedEvent::Unknown {
+                module: module.to_string(),
+                event_type: event_type.to_string(),
+            }))
+        }
+
+        /// Move-like signatures of every event, e.g. `rewards::ReferralSet { child: address, ... }`.
+        pub fn signatures() -> impl Iterator<Item = &'static str> {
+            [$($module::SIGNATURES,)*].into_iter().flatten().copied()
+        }
+    };
+}
+
+events! {
+    // unxvcore
+    Rewards: mod rewards {
+        ConfigUpdated { by: address, timestamp_ms: u64 }
+        ReferralSet { child: address, parent: address, timestamp_ms: u64 }
+        DayFinalized { user: address, day_id: u64, points: u128 }
+        TierChanged { user: address, new_tier: u8, seven_day_points: u128, timestamp_ms: u64 }
+        FaucetClaimed { user: address, amount: u64, day_id: u64 }
+    }
+    Staking: mod staking {
+        Staked { pool_id: ID, who: address, amount: u64, activate_week: u64, timestamp_ms: u64 }
+        Unstaked { pool_id: ID, who: address, amount: u64, effective_week: u64, timestamp_ms: u64 }
+        RewardAdded { pool_id: ID, amount: u64, week: u64, timestamp_ms: u64 }
+        RewardsClaimed { pool_id: ID, who: address, from_week: u64, to_week: u64, amount: u64, timestamp_ms: u64 }
+        RewardBuyback { pool_id: ID, asset: TypeName, amount_in: u64, unxv_added: u64, timestamp_ms: u64 }
+    }
+    Unxv: mod unxv {
+        UNXVMinted { amount: u64, to: address, by: address, timestamp_ms: u64 }
+        UNXVBurned { amount: u64, by: address, timestamp_ms: u64 }
+    }
+    Usdu: mod usdu {
+        FaucetInitialized { per_address_limit: u64, by: address, timestamp_ms: u64 }
+        Claimed { who: address, amount: u64, total_claimed: u64, timestamp_ms: u64 }
+        PerAddressLimitUpdated { new_limit: u64, by: address, timestamp_ms: u64 }
+        Paused { paused: bool, by: address, timestamp_ms: u64 }
+    }
+    Fees: mod fees {
+        FeeConfigUpdated { who: address, timestamp_ms: u64, dex_fee_bps: u64, unxv_discount_bps: u64, prefer_deep_backend: bool, stakers_bps: u64, treasury_bps: u64, burn_bps: u64, treasury: address }
+        FeeAccrued { payer: address, asset: TypeName, amount: u64, timestamp_ms: u64 }
+        FeesSwept { actor: address, asset: TypeName, amount_in: u64, unxv_out: u64, pool_id: ID, timestamp_ms: u64 }
+        UnxvFeeSplit { payer: address, total_unxv: u64, stakers_unxv: u64, treasury_unxv: u64, burn_unxv: u64, timestamp_ms: u64 }
+        FeeWithdrawn { who: address, to: address, asset: TypeName, amount: u64, timestamp_ms: u64 }
+    }
+    Oracle: mod oracle {
+        OracleRegistryInitialized { by: address, timestamp: u64 }
+        OracleFeedSet { symbol: String, price_id: vector<u8>, by: address, timestamp: u64 }
+        OracleFeedRemoved { symbol: String, by: address, timestamp: u64 }
+        OracleMaxAgeUpdated { max_age_sec: u64, by: address, timestamp: u64 }
+    }
+    // unxvdex
+    Dex: mod dex {
+        ProtocolFeeTaken { payer: address, base_fee_asset_unxv: bool, amount: u64, asset: TypeName, timestamp_ms: u64 }
+        PoolCreationFeePaid { payer: address, amount_unxv: u64, timestamp_ms: u64 }
+    }
+    // unxvfutures
+    Futures: mod futures {
+        MarketInitialized { market_id: ID, symbol: String, expiry_ms: u64, contract_size: u64, initial_margin_bps: u64, maintenance_margin_bps: u64, liquidation_fee_bps: u64, keeper_incentive_bps: u64 }
+        CollateralDeposited { market_id: ID, who: address, amount: u64, timestamp_ms: u64 }
+        CollateralWithdrawn { market_id: ID, who: address, amount: u64, timestamp_ms: u64 }
+        PositionChanged { market_id: ID, who: address, is_long: bool, qty_delta: u64, exec_price_1e6: u64, realized_gain: u64, realized_loss: u64, new_long: u64, new_short: u64, timestamp_ms: u64 }
+        FeeCharged { market_id: ID, who: address, notional_units: u64, fee_paid: u64, paid_in_unxv: bool, timestamp_ms: u64 }
+        Liquidated { market_id: ID, who: address, qty_closed: u64, exec_price_1e6: u64, penalty_collat: u64, timestamp_ms: u64 }
+        Settled { market_id: ID, who: address, price_1e6: u64, timestamp_ms: u64 }
+        PnlCreditAccrued { market_id: ID, who: address, credited: u64, remaining_credit: u64, timestamp_ms: u64 }
+        PnlCreditPaid { market_id: ID, who: address, amount: u64, remaining_credit: u64, timestamp_ms: u64 }
+        TraderRewardsDeposited { market_id: ID, amount_unxv: u64, total_eligible: u128, acc_1e18: u128, timestamp_ms: u64 }
+        TraderRewardsClaimed { market_id: ID, who: address, amount_unxv: u64, pending_left: u64, timestamp_ms: u64 }
+        OrderPlaced { market_id: ID, order_id: u128, maker: address, is_bid: bool, price_1e6: u64, quantity: u64, expire_ts: u64 }
+        OrderCanceled { market_id: ID, order_id: u128, maker: address, remaining_qty: u64, timestamp_ms: u64 }
+        OrderFilled { market_id: ID, maker_order_id: u128, maker: address, taker: address, price_1e6: u64, base_qty: u64, timestamp_ms: u64 }
+    }
+    // unxvgasfutures
+    GasFutures: mod gas_futures {
+        MarketInitialized { market_id: ID, expiry_ms: u64, contract_size: u64, initial_margin_bps: u64, maintenance_margin_bps: u64, liquidation_fee_bps: u64, keeper_incentive_bps: u64 }
+        CollateralDeposited { market_id: ID, who: address, amount: u64, timestamp_ms: u64 }
+        CollateralWithdrawn { market_id: ID, who: address, amount: u64, timestamp_ms: u64 }
+        PositionChanged { market_id: ID, who: address, is_long: bool, qty_delta: u64, exec_price_1e6: u64, timestamp_ms: u64 }
+        FeeCharged { market_id: ID, who: address, notional_1e6: u128, fee_paid: u64, paid_in_unxv: bool, timestamp_ms: u64 }
+        Liquidated { market_id: ID, who: address, qty_closed: u64, exec_price_1e6: u64, penalty_collat: u64, timestamp_ms: u64 }
+        PnlCreditAccrued { market_id: ID, who: address, credited: u64, remaining_credit: u64, timestamp_ms: u64 }
+        PnlCreditPaid { market_id: ID, who: address, amount: u64, remaining_credit: u64, timestamp_ms: u64 }
+        OrderPlaced { market_id: ID, order_id: u128, maker: address, is_bid: bool, price_1e6: u64, quantity: u64, expire_ts: u64 }
+        OrderCanceled { market_id: ID, order_id: u128, maker: address, remaining_qty: u64, timestamp_ms: u64 }
+        OrderFilled { market_id: ID, maker_order_id: u128, maker: address, taker: address, price_1e6: u64, base_qty: u64, timestamp_ms: u64 }
+    }
+    // unxvperps
+    Perpetuals: mod perpetuals {
+        PerpInitialized { market_id: ID, symbol: String, contract_size: u64, funding_interval_ms: u64, initial_margin_bps: u64, maintenance_margin_bps: u64, liquidation_fee_bps: u64, keeper_incentive_bps: u64, liq_target_buffer_bps: u64 }
+        CollateralDeposited { market_id: ID, who: address, amount: u64, timestamp_ms: u64 }
+        CollateralWithdrawn { market_id: ID, who: address, amount: u64, timestamp_ms: u64 }
+        PositionChanged { market_id: ID, who: address, is_long: bool, qty_delta: u64, exec_price_1e6: u64, realized_gain: u64, realized_loss: u64, new_long: u64, new_short: u64, timestamp_ms: u64 }
+        FeeCharged { market_id: ID, who: address, notional_1e6: u128, fee_paid: u64, paid_in_unxv: bool, timestamp_ms: u64 }
+        FundingIndexUpdated { market_id: ID, longs_pay: bool, delta_1e6: u64, cum_long_pay_1e6: u128, cum_short_pay_1e6: u128, timestamp_ms: u64 }
+        TraderRewardsDeposited { market_id: ID, amount_unxv: u64, total_eligible: u128, acc_1e18: u128, timestamp_ms: u64 }
+        TraderRewardsClaimed { market_id: ID, who: address, amount_unxv: u64, pending_left: u64, timestamp_ms: u64 }
+        FundingSettled { market_id: ID, who: address, amount_paid: u64, amount_credited: u64, credit_left: u64, timestamp_ms: u64 }
+        Liquidated { market_id: ID, who: address, qty_closed: u64, exec_price_1e6: u64, penalty_collat: u64, timestamp_ms: u64 }
+        SaturationWarn { market_id: ID, who: address, context: u8, timestamp_ms: u64 }
+    }
+    // unxvxperps
+    XPerps: mod xperps {
+        XPerpInitialized { market_id: ID, contract_size: u64, funding_interval_ms: u64, initial_mark_1e6: u64, alpha_num: u64, alpha_den: u64, alpha_long_num: u64, alpha_long_den: u64, cap_multiple_bps: u64 }
+        CollateralDeposited { market_id: ID, who: address, amount: u64, timestamp_ms: u64 }
+        CollateralWithdrawn { market_id: ID, who: address, amount: u64, timestamp_ms: u64 }
+        OrderPlaced { market_id: ID, order_id: u128, maker: address, is_bid: bool, price_1e6: u64, quantity: u64, expire_ts: u64 }
+        OrderCanceled { market_id: ID, order_id: u128, maker: address, remaining_qty: u64, timestamp_ms: u64 }
+        OrderFilled { market_id: ID, maker_order_id: u128, maker: address, taker: address, price_1e6: u64, base_qty: u64, timestamp_ms: u64 }
+        PositionChanged { market_id: ID, who: address, is_long: bool, qty_delta: u64, exec_price_1e6: u64, timestamp_ms: u64 }
+        FundingIndexUpdated { market_id: ID, longs_pay: bool, delta_1e6: u64, cum_long_pay_1e6: u128, cum_short_pay_1e6: u128, timestamp_ms: u64 }
+        Liquidated { market_id: ID, who: address, qty_closed: u64, exec_price_1e6: u64, penalty_collat: u64, timestamp_ms: u64 }
+        FeeCharged { market_id: ID, who: address, notional_1e6: u128, fee_paid: u64, paid_in_unxv: bool, timestamp_ms: u64 }
+        TraderRewardsDeposited { market_id: ID, amount_unxv: u64, total_eligible: u128, acc_1e18: u128, timestamp_ms: u64 }
+        TraderRewardsClaimed { market_id: ID, who: address, amount_unxv: u64, pending_left: u64, timestamp_ms: u64 }
+        FundingSettled { market_id: ID, who: address, amount_paid: u64, amount_credited: u64, credit_left: u64, timestamp_ms: u64 }
+    }
+    // unxvoptions
+    Options: mod options {
+        SeriesCreated { key: u128, expiry_ms: u64, strike_1e6: u64, is_call: bool }
+        SeriesCreatedV2 { market_id: ID, key: u128, expiry_ms: u64, strike_1e6: u64, is_call: bool, symbol_bytes: vector<u8>, tick_size: u64, lot_size: u64, min_size: u64 }
+        OrderPlaced { key: u128, order_id: u128, maker: address, price: u64, quantity: u64, is_bid: bool, expire_ts: u64 }
+        OrderCanceled { key: u128, order_id: u128, maker: address, quantity: u64 }
+        OrderFilled { key: u128, maker_order_id: u128, maker: address, taker: address, price: u64, base_qty: u64, premium_quote: u64, maker_remaining_qty: u64, timestamp_ms: u64 }
+        OrderExpired { key: u128, order_id: u128, maker: address, timestamp_ms: u64 }
+        Matched { key: u128, taker: address, total_units: u64, total_premium_quote: u64 }
+        Exercised { key: u128, exerciser: address, amount: u64, spot_1e6: u64 }
+        CollateralLocked { key: u128, writer: address, is_call: bool, amount_base: u64, amount_quote: u64, timestamp_ms: u64 }
+        CollateralUnlocked { key: u128, writer: address, is_call: bool, amount_base: u64, amount_quote: u64, reason: u8, timestamp_ms: u64 }
+        OptionPositionUpdated { key: u128, owner: address, position_id: ID, increase: bool, delta_units: u64, new_amount: u64, timestamp_ms: u64 }
+        WriterClaimed { key: u128, writer: address, amount_base: u64, amount_quote: u64, timestamp_ms: u64 }
+        SeriesSettled { key: u128, price_1e6: u64, timestamp_ms: u64 }
+    }
+    // unxvlending
+    Lending: mod lending {
+        MarketInitialized2 { market_id: ID, symbol: String, ltv_bps: u64, liq_threshold_bps: u64, reserve_bps: u64, liq_bonus_bps: u64, timestamp_ms: u64 }
+        DebtSupplied { market_id: ID, who: address, amount: u64, shares: u128, timestamp_ms: u64 }
+        DebtWithdrawn { market_id: ID, who: address, amount: u64, shares: u128, timestamp_ms: u64 }
+        CollateralDeposited2 { market_id: ID, who: address, amount: u64, timestamp_ms: u64 }
+        CollateralWithdrawn2 { market_id: ID, who: address, amount: u64, timestamp_ms: u64 }
+        DebtBorrowed { market_id: ID, who: address, amount: u64, principal_after: u128, timestamp_ms: u64 }
+        DebtRepaid { market_id: ID, who: address, amount: u64, remaining_principal: u128, timestamp_ms: u64 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_types_are_left_undecoded() {
+        let unknown = |module: &str, event_type: &str| UnxvDecodedEvent::Unknown {
+            module: module.to_string(),
+            event_type: event_type.to_string(),
+        };
+        assert_eq!(decode("vault", "Opened", &[]).unwrap(), unknown("vault", "Opened"));
+        assert_eq!(decode("dex", "Opened", &[]).unwrap(), unknown("dex", "Opened"));
+    }
+
+    #[test]
+    fn malformed_payloads_are_errors() {
+        let event = dex::PoolCreationFeePaid { payer: AccountAddress::ONE, amount_unxv: 1, timestamp_ms: 2 };
+        let mut bytes = bcs::to_bytes(&event).unwrap();
+        bytes.push(0);
+        assert!(decode("dex", "PoolCreationFeePaid", &bytes).is_err());
+        assert!(decode("dex", "PoolCreationFeePaid", &bytes[..bytes.len() - 2]).is_err());
+    }
+
+    #[test]
+    fn signatures_render_every_field() {
+        let fee_paid = "dex::PoolCreationFeePaid { payer: address, amount_unxv: u64, timestamp_ms: u64, }";
+        assert!(signatures().any(|sig| sig == fee_paid));
+        let names: Vec<&str> = signatures().map(|sig| sig.split_once(' ').unwrap().0).collect();
+        for (i, name) in names.iter().enumerate() {
+            assert!(!names[..i].contains(name), "{name} is defined twice");
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use std::process::Command;
+
+// Expose the git revision as GIT_REVISION for `INDEXER_VERSION`; "unknown" outside a checkout.
+fn main() {
+    let revision = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=GIT_REVISION={revision}");
+    println!("cargo:rerun-if-changed=../../../../../.git/HEAD");
+}
@@ -0,0 +1,439 @@
+use diesel::sql_types::{BigInt, Jsonb, Nullable, Text};
+use diesel::QueryableByName;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use sui_pg_db::Db;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+use url::Url;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        })
+    }
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "info" => Ok(Severity::Info),
+            "warning" => Ok(Severity::Warning),
+            "critical" => Ok(Severity::Critical),
+            _ => anyhow::bail!("Unknown severity {s:?}: expected info, warning or critical"),
+        }
+    }
+}
+
+/// Something an operator may need to act on. Alerts with the same `key` are deduplicated.
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    /// Component raising the alert, e.g. "pruner".
+    pub source: String,
+    pub severity: Severity,
+    pub key: String,
+    pub message: String,
+    pub context: serde_json::Value,
+}
+
+impl Alert {
+    pub fn new(source: &str, severity: Severity, key: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            source: source.to_string(),
+            severity,
+            key: key.into(),
+            message: message.into(),
+            context: serde_json::Value::Null,
+        }
+    }
+
+    pub fn with_context(mut self, context: serde_json::Value) -> Self {
+        self.context = context;
+        self
+    }
+}
+
+/// Handle used by alert sources. Cheap to clone; sending never blocks.
+#[derive(Clone)]
+pub struct AlertSender(mpsc::UnboundedSender<Alert>);
+
+impl AlertSender {
+    pub fn send(&self, alert: Alert) {
+        // The router only stops at shutdown; alerts raised after that are dropped.
+        let _ = self.0.send(alert);
+    }
+}
+
+pub fn channel() -> (AlertSender, mpsc::UnboundedReceiver<Alert>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    (AlertSender(tx), rx)
+}
+
+/// Where routed alerts are delivered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sink {
+    Log,
+    /// POSTs the alert as JSON.
+    Webhook(Url),
+    /// POSTs `{"text": ...}` to a Slack incoming webhook.
+    Slack(Url),
+}
+
+/// Deliver alerts of at least `severity` (from `source`, if given) to `sink`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub severity: Severity,
+    pub source: Option<String>,
+    pub sink: Sink,
+}
+
+impl Route {
+    fn matches(&self, alert: &Alert) -> bool {
+        alert.severity >= self.severity && self.source.as_ref().map_or(true, |s| *s == alert.source)
+    }
+}
+
+impl FromStr for Route {
+    type Err = anyhow::Error;
+
+    /// `SEVERITY[@SOURCE]=log|webhook:URL|slack:URL`, e.g. `critical=slack:https://hooks.slack.com/...`.
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (filter, sink) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Expected SEVERITY[@SOURCE]=SINK, got {s:?}"))?;
+        let (severity, source) = match filter.split_once('@') {
+            Some((severity, source)) => (severity, Some(source.trim().to_string())),
+            None => (filter, None),
+        };
+        let sink = match sink.trim().split_once(':') {
+            None if sink.trim() == "log" => Sink::Log,
+            Some(("webhook", url)) => Sink::Webhook(url.parse()?),
+            Some(("slack", url)) => Sink::Slack(url.parse()?),
+            _ => anyhow::bail!("Unknown alert sink {sink:?}: expected log, webhook:URL or slack:URL"),
+        };
+        Ok(Route { severity: severity.parse()?, source, sink })
+    }
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct AlertArgs {
+    /// Alert routing rules, comma-separated `SEVERITY[@SOURCE]=SINK` with SINK one of `log`,
+    /// `webhook:URL` or `slack:URL`. Every matching rule fires; unmatched alerts are only logged
+    #[clap(env, long, value_delimiter = ',')]
+    pub alert_route: Vec<Route>,
+    /// Alerts with the same key within this many seconds are recorded but not routed again
+    #[clap(env, long, default_value_t = 300)]
+    pub alert_dedup_secs: u64,
+}
+
+#[derive(QueryableByName)]
+struct AlertId {
+    #[diesel(sql_type = BigInt)]
+    id: i64,
+}
+
+/// Which alerts get routed: the first of each key per window. Remembers the `unxv_alerts` row
+/// each routed key was recorded as, so repeats can be counted on it.
+struct Dedup {
+    window: Duration,
+    /// key => (when it was last routed, its row in unxv_alerts)
+    recent: HashMap<String, (Instant, Option<i64>)>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Occurrence {
+    /// Not routed within the window: record and route it.
+    First,
+    /// Routed within the window as `row`, None if recording it failed.
+    Repeat(Option<i64>),
+}
+
+impl Dedup {
+    fn new(window: Duration) -> Self {
+        Self { window, recent: HashMap::new() }
+    }
+
+    fn occurrence(&mut self, key: &str, now: Instant) -> Occurrence {
+        let window = self.window;
+        self.recent.retain(|_, (routed, _)| now.duration_since(*routed) < window);
+        match self.recent.get(key) {
+            Some((_, row)) => Occurrence::Repeat(*row),
+            None => Occurrence::First,
+        }
+    }
+
+    fn routed(&mut self, key: &str, now: Instant, row: Option<i64>) {
+        self.recent.insert(key.to_string(), (now, row));
+    }
+}
+
+/// Receive alerts until cancelled: record each in `unxv_alerts` and deliver it to the matching
+/// routes, unless an alert with the same key was routed within the dedup window, in which case
+/// only its occurrence count is bumped.
+pub async fn run(db: Db, args: AlertArgs, mut rx: mpsc::UnboundedReceiver<Alert>, cancel: CancellationToken) {
+    let client = reqwest::Client::new();
+    let mut dedup = Dedup::new(Duration::from_secs(args.alert_dedup_secs));
+    loop {
+        let alert = tokio::select! {
+            _ = cancel.cancelled() => break,
+            alert = rx.recv() => match alert {
+                Some(alert) => alert,
+                None => break,
+            },
+        };
+
+        let now = Instant::now();
+        if let Occurrence::Repeat(row) = dedup.occurrence(&alert.key, now) {
+            if let Some(id) = row {
+                if let Err(e) = record_repeat(&db, id).await {
+                    warn!("Failed to record repeated alert {}: {e:#}", alert.key);
+                }
+            }
+            continue;
+        }
+
+        let id = match record(&db, &alert).await {
+            Ok(id) => Some(id),
+            Err(e) => {
+                warn!("Failed to record alert {}: {e:#}", alert.key);
+                None
+            }
+        };
+        dedup.routed(&alert.key, now, id);
+        route(&client, &args.alert_route, &alert).await;
+    }
+}
+
+/// The distinct sinks of the routes matching `alert`, in rule order; just the log if none do.
+fn sinks<'a>(routes: &'a [Route], alert: &Alert) -> Vec<&'a Sink> {
+    let mut sinks: Vec<&Sink> = vec![];
+    for route in routes.iter().filter(|r| r.matches(alert)) {
+        if !sinks.contains(&&route.sink) {
+            sinks.push(&route.sink);
+        }
+    }
+    if sinks.is_empty() {
+        sinks.push(&Sink::Log);
+    }
+    sinks
+}
+
+async fn route(client: &reqwest::Client, routes: &[Route], alert: &Alert) {
+    for sink in sinks(routes, alert) {
+        let sent = match sink {
+            Sink::Log => {
+                match alert.severity {
+                    Severity::Info => info!(source = %alert.source, key = %alert.key, "{}", alert.message),
+                    Severity::Warning => warn!(source = %alert.source, key = %alert.key, "{}", alert.message),
+                    Severity::Critical => error!(source = %alert.source, key = %alert.key, "{}", alert.message),
+                }
+                Ok(())
+            }
+            Sink::Webhook(url) => post(client, url, &serde_json::to_value(alert).unwrap_or_default()).await,
+            Sink::Slack(url) => {
+                let text = format!("[{}] {}: {}", alert.severity, alert.source, alert.message);
+                post(client, url, &serde_json::json!({ "text": text })).await
+            }
+        };
+        if let Err(e) = sent {
+            warn!("Failed to deliver alert {} to {sink:?}: {e:#}", alert.key);
+        }
+    }
+}
+
+async fn post(client: &reqwest::Client, url: &Url, body: &serde_json::Value) -> anyhow::Result<()> {
+    client.post(url.clone()).json(body).send().await?.error_for_status()?;
+    Ok(())
+}
+
+async fn record(db: &Db, alert: &Alert) -> anyhow::Result<i64> {
+    let mut conn = db.connect().await?;
+    let row: AlertId = diesel::sql_query(
+        "INSERT INTO unxv_alerts (source, severity, key, message, context) \
+         VALUES ($1, $2, $3, $4, $5) RETURNING id",
+    )
+    .bind::<Text, _>(&alert.source)
+    .bind::<Text, _>(alert.severity.to_string())
+    .bind::<Text, _>(&alert.key)
+    .bind::<Text, _>(&alert.message)
+    .bind::<Jsonb, _>(&alert.context)
+    .get_result(&mut conn)
+    .await?;
+    Ok(row.id)
+}
+
+async fn record_repeat(db: &Db, id: i64) -> anyhow::Result<()> {
+    let mut conn = db.connect().await?;
+    diesel::sql_query("UPDATE unxv_alerts SET occurrences = occurrences + 1, last_seen_at = now() WHERE id = $1")
+        .bind::<BigInt, _>(id)
+        .execute(&mut conn)
+        .await?;
+    Ok(())
+}
+
+/// Mark an alert as acknowledged. Returns false if it doesn't exist or was already acknowledged.
+pub async fn acknowledge(db: &Db, id: i64, by: Option<&str>) -> anyhow::Result<bool> {
+    let mut conn = db.connect().await?;
+    let updated = diesel::sql_query(
+        "UPDATE unxv_alerts SET acknowledged_at = now(), acknowledged_by = $2 \
+         WHERE id = $1 AND acknowledged_at IS NULL",
+    )
+    .bind::<BigInt, _>(id)
+    .bind::<Nullable<Text>, _>(by)
+    .execute(&mut conn)
+    .await?;
+    Ok(updated > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestDb;
+    use diesel::sql_types::Bool;
+
+    fn alert(source: &str, severity: Severity) -> Alert {
+        Alert::new(source, severity, format!("{source}:failed"), "failed")
+    }
+
+    #[test]
+    fn repeats_within_the_window_are_deduplicated() {
+        let mut dedup = Dedup::new(Duration::from_secs(60));
+        let start = Instant::now();
+        assert_eq!(dedup.occurrence("pruner:failed", start), Occurrence::First);
+        dedup.routed("pruner:failed", start, Some(7));
+        assert_eq!(dedup.occurrence("pruner:failed", start + Duration::from_secs(59)), Occurrence::Repeat(Some(7)));
+        // Other keys are independent.
+        assert_eq!(dedup.occurrence("maintenance:failed", start), Occurrence::First);
+        // A repeat doesn't extend the window.
+        assert_eq!(dedup.occurrence("pruner:failed", start + Duration::from_secs(60)), Occurrence::First);
+    }
+
+    #[test]
+    fn unrecorded_alerts_are_still_deduplicated() {
+        let mut dedup = Dedup::new(Duration::from_secs(60));
+        let start = Instant::now();
+        dedup.routed("pruner:failed", start, None);
+        assert_eq!(dedup.occurrence("pruner:failed", start), Occurrence::Repeat(None));
+    }
+
+    #[test]
+    fn zero_window_routes_every_alert() {
+        let mut dedup = Dedup::new(Duration::ZERO);
+        let start = Instant::now();
+        dedup.routed("pruner:failed", start, Some(1));
+        assert_eq!(dedup.occurrence("pruner:failed", start), Occurrence::First);
+    }
+
+    #[test]
+    fn routes_parse() {
+        let route: Route = " critical @ pruner =slack:https://hooks.example.com/x".parse().unwrap();
+        assert_eq!(
+            route,
+            Route {
+                severity: Severity::Critical,
+                source: Some("pruner".to_string()),
+                sink: Sink::Slack("https://hooks.example.com/x".parse().unwrap()),
+            }
+        );
+        let route: Route = "Info=log".parse().unwrap();
+        assert_eq!(route, Route { severity: Severity::Info, source: None, sink: Sink::Log });
+        let route: Route = "warning=webhook:https://ops.example.com/hook".parse().unwrap();
+        assert_eq!(route.sink, Sink::Webhook("https://ops.example.com/hook".parse().unwrap()));
+        for invalid in ["critical", "loud=log", "info=email:ops@example.com", "info=webhook:not a url", "info=logs"] {
+            assert!(invalid.parse::<Route>().is_err(), "{invalid:?} parsed");
+        }
+    }
+
+    #[test]
+    fn alerts_go_to_every_matching_sink_once() {
+        let routes: Vec<Route> = [
+            "critical=slack:https://hooks.example.com/x",
+            "warning@pruner=webhook:https://ops.example.com/hook",
+            "info@pruner=webhook:https://ops.example.com/hook",
+            "info@maintenance=log",
+        ]
+        .iter()
+        .map(|r| r.parse().unwrap())
+        .collect();
+        let slack = Sink::Slack("https://hooks.example.com/x".parse().unwrap());
+        let webhook = Sink::Webhook("https://ops.example.com/hook".parse().unwrap());
+
+        assert_eq!(sinks(&routes, &alert("pruner", Severity::Critical)), [&slack, &webhook]);
+        assert_eq!(sinks(&routes, &alert("pruner", Severity::Info)), [&webhook]);
+        assert_eq!(sinks(&routes, &alert("maintenance", Severity::Critical)), [&slack, &Sink::Log]);
+        // Unmatched alerts are logged.
+        assert_eq!(sinks(&routes, &alert("gaps", Severity::Warning)), [&Sink::Log]);
+        assert_eq!(sinks(&[], &alert("gaps", Severity::Critical)), [&Sink::Log]);
+    }
+
+    #[derive(QueryableByName)]
+    struct Row {
+        #[diesel(sql_type = BigInt)]
+        occurrences: i64,
+        #[diesel(sql_type = Bool)]
+        acknowledged: bool,
+        #[diesel(sql_type = Nullable<Text>)]
+        acknowledged_by: Option<String>,
+    }
+
+    async fn rows(db: &Db) -> Vec<Row> {
+        let mut conn = db.connect().await.unwrap();
+        diesel::sql_query(
+            "SELECT occurrences::BIGINT AS occurrences, acknowledged_at IS NOT NULL AS acknowledged, \
+                    acknowledged_by \
+             FROM unxv_alerts ORDER BY id",
+        )
+        .load(&mut conn)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn repeated_alerts_are_recorded_once() {
+        let db = TestDb::new().await;
+        let (tx, rx) = channel();
+        tx.send(alert("pruner", Severity::Warning));
+        tx.send(alert("pruner", Severity::Warning));
+        tx.send(alert("maintenance", Severity::Warning));
+        drop(tx);
+        let args = AlertArgs { alert_route: vec![], alert_dedup_secs: 300 };
+        run(db.db().clone(), args, rx, CancellationToken::new()).await;
+
+        let rows = rows(db.db()).await;
+        assert_eq!(rows.iter().map(|r| r.occurrences).collect::<Vec<_>>(), [2, 1]);
+    }
+
+    #[tokio::test]
+    async fn alerts_are_acknowledged_once() {
+        let db = TestDb::new().await;
+        let id = record(db.db(), &alert("pruner", Severity::Critical)).await.unwrap();
+        assert!(!rows(db.db()).await[0].acknowledged);
+
+        assert!(acknowledge(db.db(), id, Some("ops")).await.unwrap());
+        let row = &rows(db.db()).await[0];
+        assert!(row.acknowledged);
+        assert_eq!(row.acknowledged_by.as_deref(), Some("ops"));
+
+        assert!(!acknowledge(db.db(), id, Some("someone else")).await.unwrap());
+        assert_eq!(rows(db.db()).await[0].acknowledged_by.as_deref(), Some("ops"));
+        assert!(!acknowledge(db.db(), id + 1, None).await.unwrap());
+    }
+}
@@ -0,0 +1,843 @@
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::Utc;
+use diesel::{ExpressionMethods, QueryDsl, SelectableHelper};
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use sui_pg_db::Db;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::{BroadcastStream, ReceiverStream};
+use tokio_stream::{Stream, StreamExt};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::alerts;
+use crate::health;
+use crate::normalize_address;
+use crate::stats::{self, LiveSnapshot};
+use crate::stream;
+use unxv_schema::models::UnxvEvent;
+use unxv_schema::query::{self, EventCursor};
+use unxv_schema::schema::unxv_events;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ApiArgs {
+    /// Address to serve the HTTP API on (default: API disabled)
+    #[clap(env, long)]
+    pub api_address: Option<SocketAddr>,
+    /// Seconds between `stats` broadcasts to WebSocket clients
+    #[clap(env, long, default_value_t = 5)]
+    pub stats_broadcast_secs: u64,
+    /// Address to serve the read-only event query API on (default: disabled). Separate from
+    /// --api-address so it can stay on an internal interface
+    #[clap(env, long)]
+    pub query_api_address: Option<SocketAddr>,
+    /// Largest `limit` accepted by `GET /events`
+    #[clap(env, long, default_value_t = 1000)]
+    pub query_api_max_limit: i64,
+    /// Address to serve `/health` and `/ready` on, for orchestrator probes (default: disabled)
+    #[clap(env, long)]
+    pub health_address: Option<SocketAddr>,
+    /// Lag behind the wall clock, in milliseconds, past which `/health` returns 503
+    #[clap(env, long, default_value_t = 600_000)]
+    pub max_lag_ms: u64,
+    /// Address to serve the SSE stream of newly committed events on (default: disabled)
+    #[clap(env, long)]
+    pub stream_address: Option<SocketAddr>,
+    /// Events a stream client may fall behind by before it starts missing them
+    #[clap(env, long, default_value_t = 1024)]
+    pub stream_buffer: usize,
+    /// Seconds between `heartbeat` messages on ordered event streams
+    #[clap(env, long, default_value_t = 15)]
+    pub stream_heartbeat_secs: u64,
+    /// Address to serve the admin API (alert acknowledgment) on (default: disabled). Keep it on
+    /// an internal interface
+    #[clap(env, long)]
+    pub admin_address: Option<SocketAddr>,
+    /// Bearer token admin API requests must carry (default: none required)
+    #[clap(env, long)]
+    pub admin_token: Option<String>,
+}
+
+#[derive(Clone)]
+struct ApiState {
+    stats_tx: broadcast::Sender<String>,
+}
+
+#[derive(Clone)]
+struct QueryState {
+    db: Db,
+    max_limit: i64,
+}
+
+#[derive(Clone)]
+struct StreamState {
+    db: Db,
+    events_tx: broadcast::Sender<Arc<UnxvEvent>>,
+    heartbeat: Duration,
+}
+
+#[derive(Clone)]
+struct AdminState {
+    db: Db,
+    token: Option<Arc<str>>,
+}
+
+#[derive(Clone)]
+struct HealthState {
+    db: Db,
+    pipelines: Arc<Vec<String>>,
+    max_lag_ms: u64,
+}
+
+/// Serve the configured APIs until cancelled. Returns immediately when no address is configured.
+/// `/health` reports the watermarks of `pipelines`.
+pub async fn run(args: ApiArgs, db: Db, pipelines: Vec<String>, cancel: CancellationToken) -> anyhow::Result<()> {
+    let status = async {
+        let Some(address) = args.api_address else { return Ok(()) };
+        let (stats_tx, _) = broadcast::channel(16);
+        tokio::spawn(broadcast_stats(
+            stats_tx.clone(),
+            Duration::from_secs(args.stats_broadcast_secs.max(1)),
+            cancel.child_token(),
+        ));
+        let router = Router::new()
+            .route("/stats/live", get(live_stats))
+            .route("/stats/ws", get(stats_ws))
+            .with_state(ApiState { stats_tx });
+        serve("API", address, router, cancel.child_token()).await
+    };
+    let query = async {
+        let Some(address) = args.query_api_address else { return Ok(()) };
+        serve("query API", address, query_router(db.clone(), args.query_api_max_limit), cancel.child_token()).await
+    };
+    let health = async {
+        let Some(address) = args.health_address else { return Ok(()) };
+        let router = Router::new()
+            .route("/health", get(health_report))
+            .route("/ready", get(readiness))
+            .with_state(HealthState { db: db.clone(), pipelines: Arc::new(pipelines), max_lag_ms: args.max_lag_ms });
+        serve("health checks", address, router, cancel.child_token()).await
+    };
+    let events = async {
+        // The channel is enabled in `main` before any pipeline commits.
+        let (Some(address), Some(events_tx)) = (args.stream_address, stream::sender()) else { return Ok(()) };
+        let heartbeat = Duration::from_secs(args.stream_heartbeat_secs.max(1));
+        serve("event stream", address, stream_router(db.clone(), events_tx, heartbeat), cancel.child_token()).await
+    };
+    let admin = async {
+        let Some(address) = args.admin_address else { return Ok(()) };
+        serve("admin API", address, admin_router(db.clone(), args.admin_token.clone()), cancel.child_token()).await
+    };
+    tokio::try_join!(status, query, health, events, admin)?;
+    Ok(())
+}
+
+fn query_router(db: Db, max_limit: i64) -> Router {
+    Router::new().route("/events", get(events)).with_state(QueryState { db, max_limit: max_limit.max(1) })
+}
+
+fn stream_router(db: Db, events_tx: broadcast::Sender<Arc<UnxvEvent>>, heartbeat: Duration) -> Router {
+    Router::new().route("/events/stream", get(event_stream)).with_state(StreamState { db, events_tx, heartbeat })
+}
+
+fn admin_router(db: Db, token: Option<String>) -> Router {
+    Router::new()
+        .route("/alerts/:id/ack", post(ack_alert))
+        .with_state(AdminState { db, token: token.map(Arc::from) })
+}
+
+async fn serve(name: &str, address: SocketAddr, router: Router, cancel: CancellationToken) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    info!(%address, "Serving {name}");
+    axum::serve(listener, router)
+        .with_graceful_shutdown(cancel.cancelled_owned())
+        .await?;
+    Ok(())
+}
+
+async fn live_stats() -> Json<LiveSnapshot> {
+    Json(stats::global().snapshot(Utc::now()))
+}
+
+async fn stats_ws(ws: WebSocketUpgrade, State(state): State<ApiState>) -> impl IntoResponse {
+    let rx = state.stats_tx.subscribe();
+    ws.on_upgrade(move |socket| forward_stats(socket, rx))
+}
+
+/// One snapshot for all subscribers per tick, so the cost doesn't grow with clients.
+async fn broadcast_stats(tx: broadcast::Sender<String>, every: Duration, cancel: CancellationToken) {
+    let mut interval = tokio::time::interval(every);
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = interval.tick() => {}
+        }
+        if tx.receiver_count() == 0 {
+            continue;
+        }
+        let message = serde_json::json!({ "type": "stats", "data": stats::global().snapshot(Utc::now()) });
+        let _ = tx.send(message.to_string());
+    }
+}
+
+async fn forward_stats(mut socket: WebSocket, mut rx: broadcast::Receiver<String>) {
+    loop {
+        match rx.recv().await {
+            Ok(message) => {
+                if socket.send(Message::Text(message.into())).await.is_err() {
+                    break;
+                }
+            }
+            // A slow client just misses snapshots; the next one supersedes them anyway.
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+    if let Err(e) = socket.close().await {
+        warn!("Closing stats websocket: {e}");
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamQuery {
+    module: Option<String>,
+    event_type: Option<String>,
+    #[serde(default)]
+    sequenced: bool,
+    resume_from: Option<String>,
+}
+
+/// `GET /events/stream`: Server-Sent Events, one `event` per committed event matching the
+/// filters, as JSON with the `{digest}:{event_index}` as its id. A client that falls more than
+/// `--stream-buffer` events behind gets a `lagged` event with the number it missed and carries
+/// on from there; ingestion never waits for it.
+///
+/// With `sequenced=true`, a `resume_from` cursor or a `Last-Event-ID` header the stream is
+/// ordered instead (see [`ordered_stream`]).
+async fn event_stream(
+    State(state): State<StreamState>,
+    headers: HeaderMap,
+    Query(q): Query<StreamQuery>,
+) -> Result<Response, ApiError> {
+    let filter = stream::Filter::new(q.module.as_deref(), q.event_type.as_deref());
+    // A plain stream's ids aren't cursors, so a browser reconnecting one with them stays plain.
+    let last_event_id = headers.get("last-event-id").and_then(|id| EventCursor::decode(id.to_str().ok()?));
+    let resume_from = match q.resume_from.as_deref() {
+        Some(c) => Some(EventCursor::decode(c).ok_or_else(|| ApiError::BadRequest(format!("Invalid cursor {c:?}")))?),
+        None => last_event_id,
+    };
+    if q.sequenced || resume_from.is_some() {
+        return Ok(Sse::new(ordered_stream(state, filter, resume_from)).into_response());
+    }
+
+    let events = BroadcastStream::new(state.events_tx.subscribe()).filter_map(move |message| match message {
+        Ok(event) => filter.matches(&event).then(|| {
+            Event::default()
+                .event("event")
+                .id(format!("{}:{}", event.digest, event.event_index))
+                .json_data(&*event)
+        }),
+        Err(BroadcastStreamRecvError::Lagged(missed)) => {
+            Some(Ok(Event::default().event("lagged").data(missed.to_string())))
+        }
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()).into_response())
+}
+
+/// An ordered event stream. Events after `resume_from` are first replayed from `unxv_events`,
+/// then a `live` message marks the switch to committed events as they arrive. Every `event`
+/// carries the connection's [`stream::Sequenced`] numbers and has its cursor as id, and a
+/// `heartbeat` with the latest [`stream::Position`] is sent every `--stream-heartbeat-secs`.
+/// Falling `--stream-buffer` events behind ends the stream after a `lagged` message instead of
+/// skipping ahead, so the client resumes from its last id without a gap.
+fn ordered_stream(
+    state: StreamState,
+    filter: stream::Filter,
+    resume_from: Option<EventCursor>,
+) -> impl Stream<Item = Result<Event, axum::Error>> {
+    let (tx, rx) = mpsc::channel(64);
+    tokio::spawn(async move {
+        if let Err(e) = feed_ordered(state, filter, resume_from, tx).await {
+            warn!("Ordered event stream failed: {e:#}");
+        }
+    });
+    ReceiverStream::new(rx)
+}
+
+/// Feed one ordered stream until the client goes away or falls behind.
+async fn feed_ordered(
+    state: StreamState,
+    filter: stream::Filter,
+    resume_from: Option<EventCursor>,
+    tx: mpsc::Sender<Result<Event, axum::Error>>,
+) -> anyhow::Result<()> {
+    fn sequenced_event(sent: stream::Sequenced) -> Result<Event, axum::Error> {
+        Event::default().event("event").id(sent.cursor.clone()).json_data(&sent)
+    }
+
+    // Subscribed before the replay reads anything, so an event committed during it is either
+    // read from the database or received here, and [`stream::Replayed`] drops it if both.
+    let mut live = state.events_tx.subscribe();
+    let mut sequencer = stream::Sequencer::default();
+    let mut replayed = stream::Replayed::default();
+    if let Some(mut cursor) = resume_from {
+        let mut conn = state.db.connect().await?;
+        loop {
+            let page = replay_page(&mut conn, &filter, &cursor).await?;
+            let Some(last) = page.last() else { break };
+            cursor = EventCursor::from(last);
+            for event in &page {
+                replayed.insert(event);
+                if tx.send(sequenced_event(sequencer.next(event))).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+    let marker = Event::default().event("live").json_data(serde_json::json!({
+        "live": true,
+        "seq": sequencer.position().seq,
+        "cursor": sequencer.position().cursor,
+    }));
+    if tx.send(marker).await.is_err() {
+        return Ok(());
+    }
+
+    let start = tokio::time::Instant::now() + state.heartbeat;
+    let mut heartbeat = tokio::time::interval_at(start, state.heartbeat);
+    loop {
+        let message = tokio::select! {
+            received = live.recv() => match received {
+                Ok(event) if filter.matches(&event) && replayed.is_new(&event) => {
+                    sequenced_event(sequencer.next(&event))
+                }
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    let _ = tx.send(Ok(Event::default().event("lagged").data(missed.to_string()))).await;
+                    return Ok(());
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            _ = heartbeat.tick() => Event::default().event("heartbeat").json_data(sequencer.position()),
+            _ = tx.closed() => return Ok(()),
+        };
+        if tx.send(message).await.is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// The next page of stored events after `cursor` that pass `filter`, in stream order. Only the
+/// default schema is read, not `--module-schemas` targets.
+async fn replay_page(
+    conn: &mut sui_pg_db::Connection<'_>,
+    filter: &stream::Filter,
+    cursor: &EventCursor,
+) -> anyhow::Result<Vec<UnxvEvent>> {
+    let mut query = query::after(unxv_events::table.into_boxed(), cursor);
+    if let Some(modules) = filter.modules() {
+        query = query.filter(unxv_events::module.eq_any(modules.iter().cloned().collect::<Vec<_>>()));
+    }
+    if let Some(event_types) = filter.event_types() {
+        query = query.filter(unxv_events::event_type.eq_any(event_types.iter().cloned().collect::<Vec<_>>()));
+    }
+    Ok(query
+        .order((unxv_events::checkpoint, unxv_events::digest, unxv_events::event_index))
+        .limit(query::MAX_PAGE_SIZE as i64)
+        .select(UnxvEvent::as_select())
+        .load(conn)
+        .await?)
+}
+
+/// `GET /health`: 200 while every pipeline is within `--max-lag-ms` of the wall clock, else 503,
+/// with the lag figures either way.
+async fn health_report(State(state): State<HealthState>) -> Response {
+    match health::check(&state.db, &state.pipelines, state.max_lag_ms).await {
+        Ok(report) if report.healthy => (StatusCode::OK, Json(report)).into_response(),
+        Ok(report) => (StatusCode::SERVICE_UNAVAILABLE, Json(report)).into_response(),
+        Err(e) => {
+            warn!("Health check failed: {e:#}");
+            let body = serde_json::json!({ "healthy": false, "error": "Database unavailable" });
+            (StatusCode::SERVICE_UNAVAILABLE, Json(body)).into_response()
+        }
+    }
+}
+
+/// `GET /ready`: 200 once the database answers, else 503.
+async fn readiness(State(state): State<HealthState>) -> Response {
+    match health::ready(&state.db).await {
+        Ok(()) => (StatusCode::OK, Json(serde_json::json!({ "ready": true }))).into_response(),
+        Err(e) => {
+            warn!("Readiness check failed: {e:#}");
+            (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({ "ready": false }))).into_response()
+        }
+    }
+}
+
+/// Request errors are the caller's; anything else is logged and reported as a 500.
+enum ApiError {
+    BadRequest(String),
+    Unauthorized,
+    NotFound(String),
+    Internal(anyhow::Error),
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Internal(e)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            Self::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            Self::Unauthorized => (StatusCode::UNAUTHORIZED, "Missing or invalid bearer token".to_string()),
+            Self::NotFound(message) => (StatusCode::NOT_FOUND, message),
+            Self::Internal(e) => {
+                warn!("API request failed: {e:#}");
+                (StatusCode::INTERNAL_SERVER_ERROR, "Internal error".to_string())
+            }
+        };
+        (status, Json(serde_json::json!({ "error": message }))).into_response()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct EventsQuery {
+    module: Option<String>,
+    event_type: Option<String>,
+    sender: Option<String>,
+    package: Option<String>,
+    from_checkpoint: Option<i64>,
+    to_checkpoint: Option<i64>,
+    limit: Option<i64>,
+    cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+struct EventsPage {
+    events: Vec<UnxvEvent>,
+    /// Pass as `cursor` to fetch the next page; absent on the last page.
+    next_cursor: Option<String>,
+}
+
+/// `GET /events`: stored events matching every given filter, oldest first, keyset-paginated so
+/// pages stay stable while new checkpoints are indexed.
+async fn events(State(state): State<QueryState>, Query(q): Query<EventsQuery>) -> Result<Json<EventsPage>, ApiError> {
+    let limit = q.limit.unwrap_or(100);
+    if !(1..=state.max_limit).contains(&limit) {
+        return Err(ApiError::BadRequest(format!("limit must be between 1 and {}", state.max_limit)));
+    }
+    let cursor = q
+        .cursor
+        .as_deref()
+        .map(|c| EventCursor::decode(c).ok_or_else(|| ApiError::BadRequest(format!("Invalid cursor {c:?}"))))
+        .transpose()?;
+
+    let mut query = unxv_events::table.into_boxed();
+    if let Some(module) = q.module {
+        query = query.filter(unxv_events::module.eq(module));
+    }
+    if let Some(event_type) = q.event_type {
+        query = query.filter(unxv_events::event_type.eq(event_type));
+    }
+    let address =
+        |param: &str, value: &str| normalize_address(value).map_err(|e| ApiError::BadRequest(format!("{param}: {e}")));
+    if let Some(sender) = q.sender {
+        query = query.filter(unxv_events::sender.eq(address("sender", &sender)?));
+    }
+    if let Some(package) = q.package {
+        query = query.filter(unxv_events::package.eq(address("package", &package)?));
+    }
+    if let Some(from) = q.from_checkpoint {
+        query = query.filter(unxv_events::checkpoint.ge(from));
+    }
+    if let Some(to) = q.to_checkpoint {
+        query = query.filter(unxv_events::checkpoint.le(to));
+    }
+    if let Some(c) = cursor {
+        query = query::after(query, &c);
+    }
+
+    let mut conn = state.db.connect().await.map_err(anyhow::Error::from)?;
+    // One extra row tells whether there is a next page.
+    let mut events: Vec<UnxvEvent> = query
+        .order((unxv_events::checkpoint, unxv_events::digest, unxv_events::event_index))
+        .limit(limit + 1)
+        .select(UnxvEvent::as_select())
+        .load(&mut conn)
+        .await
+        .map_err(anyhow::Error::from)?;
+    let next_cursor = if events.len() as i64 > limit {
+        events.truncate(limit as usize);
+        events.last().map(|e| EventCursor::from(e).encode())
+    } else {
+        None
+    };
+    Ok(Json(EventsPage { events, next_cursor }))
+}
+
+#[derive(Debug, Deserialize)]
+struct AckQuery {
+    by: Option<String>,
+}
+
+/// `POST /alerts/{id}/ack[?by=NAME]`: acknowledge an open alert. 404 if no open alert has
+/// that id, including one already acknowledged.
+async fn ack_alert(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+    Query(q): Query<AckQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    if let Some(token) = &state.token {
+        let bearer = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        if !bearer.is_some_and(|bearer| token_matches(bearer.as_bytes(), token.as_bytes())) {
+            return Err(ApiError::Unauthorized);
+        }
+    }
+    if !alerts::acknowledge(&state.db, id, q.by.as_deref()).await? {
+        return Err(ApiError::NotFound(format!("No open alert with id {id}")));
+    }
+    Ok(Json(serde_json::json!({ "id": id, "acknowledged": true })))
+}
+
+/// Whether `given` equals `expected`, taking the same time wherever they differ, so the admin
+/// token can't be guessed a byte at a time from response times. Only the length leaks.
+fn token_matches(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len() && given.iter().zip(expected).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{stored_event, TestDb};
+
+    /// Serve the admin API on a free local port, returning its base URL.
+    async fn serve_admin(db: &TestDb, token: Option<&str>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = admin_router(db.db().clone(), token.map(str::to_string));
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        format!("http://{address}")
+    }
+
+    async fn serve_query(db: &TestDb) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = query_router(db.db().clone(), 100);
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        format!("http://{address}")
+    }
+
+    async fn open_alert(db: &TestDb) -> i64 {
+        #[derive(diesel::QueryableByName)]
+        struct Id {
+            #[diesel(sql_type = diesel::sql_types::BigInt)]
+            id: i64,
+        }
+        let mut conn = db.db().connect().await.unwrap();
+        let row: Id = diesel::sql_query(
+            "INSERT INTO unxv_alerts (source, severity, key, message, context) \
+             VALUES ('pruner', 'critical', 'pruner:failed', 'failed', 'null') RETURNING id",
+        )
+        .get_result(&mut conn)
+        .await
+        .unwrap();
+        row.id
+    }
+
+    #[tokio::test]
+    async fn alerts_are_acknowledged_over_http() {
+        let db = TestDb::new().await;
+        let id = open_alert(&db).await;
+        let base = serve_admin(&db, None).await;
+        let client = reqwest::Client::new();
+        let ack = |id: i64| client.post(format!("{base}/alerts/{id}/ack?by=ops")).send();
+
+        let response = ack(id).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body, serde_json::json!({ "id": id, "acknowledged": true }));
+
+        assert_eq!(ack(id).await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(ack(id + 1).await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(client.post(format!("{base}/alerts/x/ack")).send().await.unwrap().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn admin_token_is_required_when_set() {
+        let db = TestDb::new().await;
+        let id = open_alert(&db).await;
+        let base = serve_admin(&db, Some("s3cret")).await;
+        let client = reqwest::Client::new();
+        let url = format!("{base}/alerts/{id}/ack");
+
+        assert_eq!(client.post(&url).send().await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let wrong = client.post(&url).bearer_auth("guess").send().await.unwrap();
+        assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(client.post(&url).bearer_auth("s3cret").send().await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn event_filters_reject_invalid_addresses() {
+        let db = TestDb::new().await;
+        let base = serve_query(&db).await;
+        let get = |query: &str| reqwest::get(format!("{base}/events?{query}"));
+
+        for query in ["sender=0xnope", "package=0x2g", &format!("package=0x1{}", "0".repeat(64))] {
+            let response = get(query).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{query}");
+        }
+        for query in ["sender=0x2", "package=0XA11CE"] {
+            assert_eq!(get(query).await.unwrap().status(), StatusCode::OK, "{query}");
+        }
+    }
+
+    /// `GET /events?{query}`: the `(digest, event_index)` of each event and the next cursor.
+    async fn page(base: &str, query: &str) -> (Vec<(String, i64)>, Option<String>) {
+        let response = reqwest::get(format!("{base}/events?{query}")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{query}");
+        let body: serde_json::Value = response.json().await.unwrap();
+        let events = body["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| (e["digest"].as_str().unwrap().to_string(), e["event_index"].as_i64().unwrap()))
+            .collect();
+        (events, body["next_cursor"].as_str().map(str::to_string))
+    }
+
+    fn keys(events: &[(&str, i64)]) -> Vec<(String, i64)> {
+        events.iter().map(|(digest, index)| (digest.to_string(), *index)).collect()
+    }
+
+    #[tokio::test]
+    async fn events_are_filtered_and_paged_by_cursor() {
+        let db = TestDb::new().await;
+        db.insert_events(&[
+            stored_event("b", "dex", 10, 1_000),
+            UnxvEvent { event_index: 1, ..stored_event("b", "dex", 10, 1_000) },
+            stored_event("a", "dex", 11, 1_100),
+            stored_event("c", "lending", 11, 1_100),
+            stored_event("d", "dex", 12, 1_200),
+        ])
+        .await;
+        let base = serve_query(&db).await;
+
+        let (events, cursor) = page(&base, "module=dex&limit=2").await;
+        assert_eq!(events, keys(&[("b", 0), ("b", 1)]));
+        let cursor = cursor.expect("a next page");
+
+        // Rows indexed meanwhile, before and after the cursor, don't shift the next page.
+        db.insert_events(&[stored_event("0", "dex", 10, 1_000), stored_event("e", "dex", 13, 1_300)]).await;
+        let (events, cursor) = page(&base, &format!("module=dex&limit=2&cursor={cursor}")).await;
+        assert_eq!(events, keys(&[("a", 0), ("d", 0)]));
+        let (events, cursor) = page(&base, &format!("module=dex&limit=2&cursor={}", cursor.unwrap())).await;
+        assert_eq!(events, keys(&[("e", 0)]));
+        assert_eq!(cursor, None);
+
+        // A last page that is exactly full has no cursor either.
+        let (events, cursor) = page(&base, "module=dex&from_checkpoint=11&to_checkpoint=12&limit=2").await;
+        assert_eq!(events, keys(&[("a", 0), ("d", 0)]));
+        assert_eq!(cursor, None);
+        let (events, _) = page(&base, "module=lending").await;
+        assert_eq!(events, keys(&[("c", 0)]));
+        let (events, _) = page(&base, "event_type=Other").await;
+        assert!(events.is_empty());
+
+        for query in ["limit=0", "limit=101", "cursor=nope"] {
+            let response = reqwest::get(format!("{base}/events?{query}")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{query}");
+        }
+    }
+
+    #[test]
+    fn tokens_compare_whole() {
+        assert!(token_matches(b"s3cret", b"s3cret"));
+        assert!(!token_matches(b"s3creT", b"s3cret"));
+        assert!(!token_matches(b"s3cre", b"s3cret"));
+        assert!(!token_matches(b"s3crets", b"s3cret"));
+        assert!(!token_matches(b"", b"s3cret"));
+    }
+
+    #[tokio::test]
+    async fn admin_token_rejects_missing_and_invalid_credentials() {
+        let db = TestDb::new().await;
+        let id = open_alert(&db).await;
+        let base = serve_admin(&db, Some("s3cret")).await;
+        let client = reqwest::Client::new();
+        let url = format!("{base}/alerts/{id}/ack");
+        let status = |auth: Option<&'static str>| {
+            let request = client.post(&url);
+            let request = match auth {
+                Some(value) => request.header(header::AUTHORIZATION, value),
+                None => request,
+            };
+            async move { request.send().await.unwrap().status() }
+        };
+
+        assert_eq!(status(None).await, StatusCode::UNAUTHORIZED);
+        let invalid = ["s3cret", "Basic s3cret", "Bearer", "Bearer ", "Bearer s3cre", "Bearer s3cret2", "bearer s3cret"];
+        for invalid in invalid {
+            assert_eq!(status(Some(invalid)).await, StatusCode::UNAUTHORIZED, "{invalid:?}");
+        }
+        // Nothing was acknowledged by the rejected requests.
+        assert_eq!(status(Some("Bearer s3cret")).await, StatusCode::OK);
+    }
+
+    /// Serve the event stream on a free local port with a fast heartbeat, returning its base URL
+    /// and the sender committed events are published on.
+    async fn serve_stream(db: &TestDb) -> (String, broadcast::Sender<Arc<UnxvEvent>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (events_tx, _) = broadcast::channel(16);
+        let router = stream_router(db.db().clone(), events_tx.clone(), Duration::from_millis(300));
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        (format!("http://{address}"), events_tx)
+    }
+
+    /// Reads an SSE response one message at a time.
+    struct Messages {
+        response: reqwest::Response,
+        buffer: String,
+    }
+
+    impl Messages {
+        /// The next message other than a heartbeat, as `(event, id, data)`.
+        async fn next(&mut self) -> (String, Option<String>, serde_json::Value) {
+            loop {
+                let message = self.message().await;
+                if message.0 != "heartbeat" {
+                    return message;
+                }
+            }
+        }
+
+        /// The next message, which must be a heartbeat, by its data.
+        async fn heartbeat(&mut self) -> serde_json::Value {
+            let (kind, _, data) = self.message().await;
+            assert_eq!(kind, "heartbeat");
+            data
+        }
+
+        /// The next message's `(event, id, data)`; comments are skipped.
+        async fn message(&mut self) -> (String, Option<String>, serde_json::Value) {
+            loop {
+                if let Some(end) = self.buffer.find("\n\n") {
+                    let message: String = self.buffer.drain(..end + 2).collect();
+                    let (mut event, mut id, mut data) = (String::new(), None, None);
+                    for line in message.lines().filter(|line| !line.starts_with(':')) {
+                        match line.split_once(':').map(|(field, value)| (field, value.trim_start())) {
+                            Some(("event", value)) => event = value.to_string(),
+                            Some(("id", value)) => id = Some(value.to_string()),
+                            Some(("data", value)) => data = Some(serde_json::from_str(value).unwrap_or(value.into())),
+                            _ => {}
+                        }
+                    }
+                    if let Some(data) = data {
+                        return (event, id, data);
+                    }
+                    continue;
+                }
+                let chunk = tokio::time::timeout(Duration::from_secs(10), self.response.chunk())
+                    .await
+                    .expect("a message within 10s")
+                    .unwrap()
+                    .expect("the stream stays open");
+                self.buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+            }
+        }
+    }
+
+    async fn connect(base: &str, query: &str, last_event_id: Option<&str>) -> Messages {
+        let mut request = reqwest::Client::new().get(format!("{base}/events/stream?{query}"));
+        if let Some(id) = last_event_id {
+            request = request.header("last-event-id", id);
+        }
+        let response = request.send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{query}");
+        Messages { response, buffer: String::new() }
+    }
+
+    #[tokio::test]
+    async fn ordered_streams_resume_without_gaps_or_duplicates() {
+        let db = TestDb::new().await;
+        db.insert_events(&[stored_event("a", "dex", 1, 100)]).await;
+        let (base, events_tx) = serve_stream(&db).await;
+        let commit = |event: UnxvEvent| {
+            let (db, events_tx) = (&db, events_tx.clone());
+            async move {
+                db.insert_events(std::slice::from_ref(&event)).await;
+                let _ = events_tx.send(Arc::new(event));
+            }
+        };
+        let expect_event = |(kind, id, data): (String, Option<String>, serde_json::Value), digest: &str, seq: u64| {
+            assert_eq!(kind, "event");
+            assert_eq!(data["event"]["digest"], digest);
+            assert_eq!(data["seq"], seq, "{digest}");
+            assert_eq!(id.as_deref(), data["cursor"].as_str());
+            id.unwrap()
+        };
+
+        // Without a cursor nothing is replayed: the stream is live straight away.
+        let mut first = connect(&base, "sequenced=true", None).await;
+        let (kind, _, data) = first.next().await;
+        assert_eq!(kind, "live");
+        assert_eq!(data, serde_json::json!({ "live": true, "seq": 0, "cursor": null }));
+
+        commit(stored_event("d", "dex", 4, 400)).await;
+        commit(stored_event("e", "dex", 5, 500)).await;
+        expect_event(first.next().await, "d", 1);
+        let last_id = expect_event(first.next().await, "e", 2);
+        assert_eq!(first.heartbeat().await, serde_json::json!({ "seq": 2, "cursor": last_id }));
+        drop(first);
+
+        // Missed while disconnected, and one committed but not yet published when it reconnects.
+        commit(stored_event("f", "dex", 6, 600)).await;
+        commit(stored_event("g", "lending", 7, 700)).await;
+        let late = stored_event("i", "dex", 9, 900);
+        db.insert_events(std::slice::from_ref(&late)).await;
+
+        let mut resumed = connect(&base, "", Some(&last_id)).await;
+        expect_event(resumed.next().await, "f", 1);
+        expect_event(resumed.next().await, "g", 2);
+        let last_id = expect_event(resumed.next().await, "i", 3);
+        let (kind, _, data) = resumed.next().await;
+        assert_eq!(kind, "live");
+        assert_eq!(data, serde_json::json!({ "live": true, "seq": 3, "cursor": last_id }));
+
+        // Its publication arrives after the replay and is dropped rather than sent twice.
+        events_tx.send(Arc::new(late)).unwrap();
+        commit(stored_event("j", "dex", 10, 1_000)).await;
+        let last_id = expect_event(resumed.next().await, "j", 4);
+        assert_eq!(resumed.heartbeat().await, serde_json::json!({ "seq": 4, "cursor": last_id }));
+
+        // Filters apply to the replay as well.
+        let mut filtered = connect(&base, "module=lending&resume_from=1:a:0", None).await;
+        expect_event(filtered.next().await, "g", 1);
+        assert_eq!(filtered.next().await.0, "live");
+
+        let response = reqwest::get(format!("{base}/events/stream?resume_from=nope")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        // A plain stream's id resumes nothing.
+        let mut plain = connect(&base, "", Some("j:0")).await;
+        commit(stored_event("k", "dex", 11, 1_100)).await;
+        let (kind, id, _) = plain.next().await;
+        assert_eq!((kind.as_str(), id.as_deref()), ("event", Some("k:0")));
+    }
+}
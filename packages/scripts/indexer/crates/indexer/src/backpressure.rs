@@ -0,0 +1,169 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::metrics;
+
+/// Commits averaged into the rolling latency.
+const WINDOW: usize = 20;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct BackpressureArgs {
+    /// Slow checkpoint processing, and so fetching, while the rolling average commit latency
+    /// is above this many milliseconds (0 = disabled)
+    #[clap(env, long, default_value_t = 0)]
+    pub backpressure_commit_latency_ms: u64,
+    /// Milliseconds each checkpoint is held back while backpressure is on
+    #[clap(env, long, default_value_t = 200)]
+    pub backpressure_delay_ms: u64,
+}
+
+impl BackpressureArgs {
+    /// None when backpressure is disabled.
+    pub fn config(&self) -> Option<BackpressureConfig> {
+        (self.backpressure_commit_latency_ms > 0).then(|| BackpressureConfig {
+            threshold: Duration::from_millis(self.backpressure_commit_latency_ms),
+            delay: Duration::from_millis(self.backpressure_delay_ms),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BackpressureConfig {
+    /// Rolling commit latency above which backpressure turns on. It turns off again below 80%
+    /// of it, so a latency hovering at the threshold doesn't flap.
+    pub threshold: Duration,
+    /// Time each checkpoint waits before processing while backpressure is on.
+    pub delay: Duration,
+}
+
+/// Rolling commit latency and whether it is currently holding back ingestion.
+///
+/// The framework gives processors no handle on fetching, but it fetches into bounded
+/// channels: a processor that takes longer per checkpoint fills them, and ingestion waits
+/// for room instead of buffering more.
+pub struct Backpressure {
+    config: BackpressureConfig,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    latencies: VecDeque<Duration>,
+    total: Duration,
+    active: bool,
+}
+
+impl Backpressure {
+    pub fn new(config: BackpressureConfig) -> Self {
+        Self { config, state: Mutex::new(State::default()) }
+    }
+
+    /// Add one commit's latency to the window and update the state. Returns the state.
+    pub fn record(&self, latency: Duration) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.latencies.push_back(latency);
+        state.total += latency;
+        if state.latencies.len() > WINDOW {
+            let oldest = state.latencies.pop_front().unwrap_or_default();
+            state.total -= oldest;
+        }
+        let average = state.total / state.latencies.len() as u32;
+
+        let was_active = state.active;
+        if !was_active && average > self.config.threshold {
+            state.active = true;
+            warn!(?average, threshold = ?self.config.threshold, "Commit latency high, slowing ingestion");
+        } else if was_active && average < self.config.threshold.mul_f64(0.8) {
+            state.active = false;
+            info!(?average, "Commit latency recovered, resuming ingestion");
+        }
+        if let Some(metrics) = metrics::global() {
+            metrics.commit_latency_rolling_ms.set(average.as_secs_f64() * 1000.0);
+            metrics.backpressure_active.set(state.active as i64);
+        }
+        state.active
+    }
+
+    pub fn active(&self) -> bool {
+        self.state.lock().unwrap().active
+    }
+
+    /// Delay before processing the next checkpoint: `config.delay` while active, else zero.
+    pub fn delay(&self) -> Duration {
+        if self.active() {
+            self.config.delay
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+static BACKPRESSURE: OnceLock<Backpressure> = OnceLock::new();
+
+/// Turn on process-wide backpressure (`--backpressure-commit-latency-ms`). Later calls are
+/// ignored.
+pub fn install(config: BackpressureConfig) {
+    let _ = BACKPRESSURE.set(Backpressure::new(config));
+}
+
+/// Record a commit's latency, if backpressure is installed.
+pub fn record_commit(latency: Duration) {
+    if let Some(backpressure) = BACKPRESSURE.get() {
+        backpressure.record(latency);
+    }
+}
+
+/// Hold back the calling processor while backpressure is on. Processors are synchronous but
+/// run on the runtime's worker threads, so the thread is handed off for the wait.
+pub fn throttle() {
+    let Some(delay) = BACKPRESSURE.get().map(Backpressure::delay).filter(|d| !d.is_zero()) else {
+        return;
+    };
+    if let Some(metrics) = metrics::global() {
+        metrics.backpressure_delays.inc();
+    }
+    tokio::task::block_in_place(|| std::thread::sleep(delay));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backpressure() -> Backpressure {
+        Backpressure::new(BackpressureConfig {
+            threshold: Duration::from_millis(100),
+            delay: Duration::from_millis(200),
+        })
+    }
+
+    fn record_n(backpressure: &Backpressure, n: usize, ms: u64) -> bool {
+        (0..n).map(|_| backpressure.record(Duration::from_millis(ms))).last().unwrap()
+    }
+
+    #[test]
+    fn the_average_covers_the_last_window_of_commits() {
+        let backpressure = backpressure();
+        assert!(!record_n(&backpressure, WINDOW, 50));
+        assert_eq!(backpressure.delay(), Duration::ZERO);
+        // (19 * 50 + 1100) / 20 = 102.5ms once the oldest commit has left the window; averaged
+        // over all 21 commits it would be exactly 100ms, not above the threshold.
+        assert!(backpressure.record(Duration::from_millis(1100)));
+        assert_eq!(backpressure.delay(), Duration::from_millis(200));
+    }
+
+    #[test]
+    fn backpressure_turns_off_below_80_percent_of_the_threshold() {
+        let backpressure = backpressure();
+        assert!(record_n(&backpressure, WINDOW, 150));
+        // Below the threshold but above 80% of it, so it stays on.
+        assert!(record_n(&backpressure, WINDOW, 85));
+        assert_eq!(backpressure.delay(), Duration::from_millis(200));
+        assert!(!record_n(&backpressure, WINDOW, 70));
+        assert_eq!(backpressure.delay(), Duration::ZERO);
+        // Up to the threshold again, but not above it, so it stays off.
+        assert!(!record_n(&backpressure, WINDOW, 100));
+        assert!(backpressure.record(Duration::from_millis(121)));
+    }
+}
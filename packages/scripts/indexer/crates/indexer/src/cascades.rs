@@ -0,0 +1,344 @@
+use diesel::sql_types::BigInt;
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, QueryableByName};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+use sui_pg_db::Db;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::alerts::{Alert, AlertSender, Severity};
+use crate::metrics::UnxvMetrics;
+use unxv_events::{decode, UnxvDecodedEvent};
+use unxv_schema::models::LiquidationCascade;
+use unxv_schema::schema::{liquidation_cascades, unxv_events};
+
+/// Modules that emit `Liquidated`. Lending emits no liquidation event today.
+pub const LIQUIDATION_MODULES: &[&str] = &["futures", "gas_futures", "perpetuals", "xperps"];
+
+/// Most checkpoints scanned per round, so catching up over history stays in bounded batches.
+const ROUND_CHECKPOINTS: i64 = 100_000;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct CascadeArgs {
+    /// Seconds between liquidation cascade scans of unxv_events (0 = cascade job disabled)
+    #[clap(env, long, default_value_t = 0)]
+    pub liquidation_cascade_interval_secs: u64,
+    /// Liquidations at most this many checkpoints apart belong to the same cascade
+    #[clap(env, long, default_value_t = 2)]
+    pub liquidation_cascade_window: u64,
+}
+
+impl CascadeArgs {
+    /// None when the cascade job is disabled.
+    pub fn config(&self) -> Option<CascadeConfig> {
+        (self.liquidation_cascade_interval_secs > 0).then(|| CascadeConfig {
+            interval: Duration::from_secs(self.liquidation_cascade_interval_secs),
+            window: self.liquidation_cascade_window as i64,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CascadeConfig {
+    pub interval: Duration,
+    /// Largest checkpoint gap between consecutive liquidations of one cascade.
+    pub window: i64,
+}
+
+/// A decoded `Liquidated` event of one of the [`LIQUIDATION_MODULES`].
+#[derive(Debug, Clone)]
+pub struct Liquidation {
+    pub digest: String,
+    pub event_index: i64,
+    pub checkpoint: i64,
+    pub checkpoint_timestamp_ms: i64,
+    pub module: String,
+    pub qty_closed: u64,
+    pub exec_price_1e6: u64,
+    pub penalty_collat: u64,
+}
+
+impl Liquidation {
+    /// Decode a stored `module::Liquidated` row; None for other events.
+    pub fn decode(
+        digest: String,
+        event_index: i64,
+        checkpoint: i64,
+        checkpoint_timestamp_ms: i64,
+        module: String,
+        contents_bcs: &[u8],
+    ) -> anyhow::Result<Option<Self>> {
+        let (qty_closed, exec_price_1e6, penalty_collat) = match decode(&module, "Liquidated", contents_bcs)? {
+            UnxvDecodedEvent::Futures(unxv_events::futures::Event::Liquidated(l)) => {
+                (l.qty_closed, l.exec_price_1e6, l.penalty_collat)
+            }
+            UnxvDecodedEvent::GasFutures(unxv_events::gas_futures::Event::Liquidated(l)) => {
+                (l.qty_closed, l.exec_price_1e6, l.penalty_collat)
+            }
+            UnxvDecodedEvent::Perpetuals(unxv_events::perpetuals::Event::Liquidated(l)) => {
+                (l.qty_closed, l.exec_price_1e6, l.penalty_collat)
+            }
+            UnxvDecodedEvent::XPerps(unxv_events::xperps::Event::Liquidated(l)) => {
+                (l.qty_closed, l.exec_price_1e6, l.penalty_collat)
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(Self {
+            digest,
+            event_index,
+            checkpoint,
+            checkpoint_timestamp_ms,
+            module,
+            qty_closed,
+            exec_price_1e6,
+            penalty_collat,
+        }))
+    }
+}
+
+/// Group `liquidations`, sorted by checkpoint, into runs where consecutive liquidations are at
+/// most `window` checkpoints apart. Liquidations of one transaction share a checkpoint, so
+/// they always land in the same run.
+pub fn group(liquidations: &[Liquidation], window: i64) -> Vec<&[Liquidation]> {
+    let mut runs = vec![];
+    let mut start = 0;
+    for i in 1..=liquidations.len() {
+        if i == liquidations.len() || liquidations[i].checkpoint - liquidations[i - 1].checkpoint > window {
+            runs.push(&liquidations[start..i]);
+            start = i;
+        }
+    }
+    runs
+}
+
+/// The cascade row for `run`, or None if it stays within one module.
+pub fn cascade(run: &[Liquidation]) -> Option<LiquidationCascade> {
+    let (first, last) = (run.first()?, run.last()?);
+    let modules: BTreeSet<&str> = run.iter().map(|l| l.module.as_str()).collect();
+    if modules.len() < 2 {
+        return None;
+    }
+    let mut digests: Vec<String> = vec![];
+    for l in run {
+        if !digests.contains(&l.digest) {
+            digests.push(l.digest.clone());
+        }
+    }
+    let sum = |f: fn(&Liquidation) -> u128| i64::try_from(run.iter().map(f).sum::<u128>()).unwrap_or(i64::MAX);
+    Some(LiquidationCascade {
+        cascade_id: format!("{}:{}", first.digest, first.event_index),
+        first_checkpoint: first.checkpoint,
+        last_checkpoint: last.checkpoint,
+        first_timestamp_ms: first.checkpoint_timestamp_ms,
+        last_timestamp_ms: last.checkpoint_timestamp_ms,
+        modules: modules.into_iter().map(str::to_string).collect(),
+        digests,
+        liquidations: run.len() as i64,
+        qty_closed: sum(|l| l.qty_closed as u128),
+        notional: sum(|l| l.qty_closed as u128 * l.exec_price_1e6 as u128 / 1_000_000),
+        penalty_collat: sum(|l| l.penalty_collat as u128),
+    })
+}
+
+/// Scan for cascades on an interval until cancelled.
+pub async fn run(
+    db: Db,
+    config: CascadeConfig,
+    metrics: Arc<UnxvMetrics>,
+    alerts: AlertSender,
+    cancel: CancellationToken,
+) {
+    let mut interval = tokio::time::interval(config.interval);
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = interval.tick() => {}
+        }
+        match scan(&db, config.window).await {
+            Ok(0) => {}
+            Ok(n) => {
+                metrics.liquidation_cascades.inc_by(n as u64);
+                info!(cascades = n, "Recorded liquidation cascades");
+            }
+            Err(e) => alerts.send(Alert::new(
+                "cascades",
+                Severity::Warning,
+                "cascades:round_failed",
+                format!("Liquidation cascade scan failed: {e:#}"),
+            )),
+        }
+    }
+}
+
+#[derive(QueryableByName)]
+struct Checkpoint {
+    #[diesel(sql_type = BigInt)]
+    checkpoint: i64,
+}
+
+/// One round: group the liquidations committed since the last round and record the cascades
+/// that can't grow any more, i.e. whose last liquidation is at least `window` checkpoints
+/// below the highest checkpoint scanned. The rest are grouped again next round.
+async fn scan(db: &Db, window: i64) -> anyhow::Result<usize> {
+    let mut conn = db.connect().await?;
+    let done = diesel::sql_query("SELECT checkpoint FROM liquidation_cascade_progress")
+        .get_result::<Checkpoint>(&mut conn)
+        .await
+        .optional()?
+        .map_or(-1, |p| p.checkpoint);
+    // Every checkpoint up to the unxv_events watermark has been committed.
+    let Some(watermark) = diesel::sql_query(
+        "SELECT checkpoint_hi_inclusive AS checkpoint FROM watermarks WHERE pipeline = 'unxv_events'",
+    )
+    .get_result::<Checkpoint>(&mut conn)
+    .await
+    .optional()?
+    .map(|w| w.checkpoint) else {
+        return Ok(0);
+    };
+    let hi = watermark.min(done + ROUND_CHECKPOINTS);
+    if hi <= done {
+        return Ok(0);
+    }
+
+    let rows: Vec<(String, i64, i64, i64, String, Vec<u8>)> = unxv_events::table
+        .filter(unxv_events::module.eq_any(LIQUIDATION_MODULES.iter().copied()))
+        .filter(unxv_events::event_type.eq("Liquidated"))
+        .filter(unxv_events::checkpoint.gt(done))
+        .filter(unxv_events::checkpoint.le(hi))
+        .order((unxv_events::checkpoint, unxv_events::digest, unxv_events::event_index))
+        .select((
+            unxv_events::digest,
+            unxv_events::event_index,
+            unxv_events::checkpoint,
+            unxv_events::checkpoint_timestamp_ms,
+            unxv_events::module,
+            unxv_events::contents_bcs,
+        ))
+        .load(&mut conn)
+        .await?;
+    let mut liquidations = vec![];
+    for (digest, event_index, checkpoint, timestamp_ms, module, bcs) in rows {
+        let id = format!("{digest}:{event_index}");
+        match Liquidation::decode(digest, event_index, checkpoint, timestamp_ms, module, &bcs) {
+            Ok(Some(l)) => liquidations.push(l),
+            Ok(None) => {}
+            Err(e) => warn!("Skipping undecodable liquidation {id}: {e:#}"),
+        }
+    }
+
+    let mut progress = hi;
+    let mut cascades = vec![];
+    for run in group(&liquidations, window) {
+        let last = run[run.len() - 1].checkpoint;
+        if last > hi - window {
+            // May still chain with a liquidation above `hi`; regroup from its start next round.
+            progress = progress.min(run[0].checkpoint - 1);
+            break;
+        }
+        cascades.extend(cascade(run));
+    }
+
+    let cascades = &cascades;
+    conn.transaction(|conn| {
+        async move {
+            if !cascades.is_empty() {
+                diesel::insert_into(liquidation_cascades::table)
+                    .values(cascades)
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+                    .await?;
+            }
+            diesel::sql_query(
+                "INSERT INTO liquidation_cascade_progress (id, checkpoint) VALUES (TRUE, $1) \
+                 ON CONFLICT (id) DO UPDATE SET checkpoint = EXCLUDED.checkpoint",
+            )
+            .bind::<BigInt, _>(progress)
+            .execute(conn)
+            .await?;
+            Ok::<_, anyhow::Error>(())
+        }
+        .scope_boxed()
+    })
+    .await?;
+    Ok(cascades.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{stored_event, TestDb};
+    use diesel::SelectableHelper;
+    use move_core_types::account_address::AccountAddress;
+    use unxv_events::types::ID;
+    use unxv_schema::models::UnxvEvent;
+
+    /// A stored `module::Liquidated` closing `qty` contracts at `price`.
+    fn liquidated(digest: &str, event_index: i64, module: &str, checkpoint: i64, qty: u64, price: u64) -> UnxvEvent {
+        let (market_id, who) = (ID { bytes: AccountAddress::from_hex_literal("0xb0b").unwrap() }, AccountAddress::ONE);
+        macro_rules! liquidated {
+            ($module:ident) => {
+                bcs::to_bytes(&unxv_events::$module::Liquidated {
+                    market_id,
+                    who,
+                    qty_closed: qty,
+                    exec_price_1e6: price,
+                    penalty_collat: qty / 10,
+                    timestamp_ms: 0,
+                })
+            };
+        }
+        let contents_bcs = match module {
+            "futures" => liquidated!(futures),
+            "perpetuals" => liquidated!(perpetuals),
+            "xperps" => liquidated!(xperps),
+            _ => panic!("no Liquidated in {module}"),
+        }
+        .unwrap();
+        UnxvEvent {
+            event_index,
+            event_type: "Liquidated".to_string(),
+            contents_bcs,
+            ..stored_event(digest, module, checkpoint, checkpoint * 1_000)
+        }
+    }
+
+    #[tokio::test]
+    async fn a_transaction_liquidating_across_modules_starts_a_cascade() {
+        let db = TestDb::new().await;
+        db.insert_events(&[
+            // One transaction liquidating a futures and a perpetuals position...
+            liquidated("tx1", 0, "futures", 10, 100, 2_000_000),
+            liquidated("tx1", 1, "perpetuals", 10, 50, 3_000_000),
+            // ...chained to an xperps liquidation within the window...
+            liquidated("tx2", 0, "xperps", 12, 10, 1_000_000),
+            // ...and, far later, a run within one module, which is no cascade.
+            liquidated("tx3", 0, "futures", 40, 1, 1_000_000),
+            liquidated("tx4", 0, "futures", 41, 1, 1_000_000),
+        ])
+        .await;
+
+        // Nothing is scanned until the events are under the watermark.
+        assert_eq!(scan(db.db(), 2).await.unwrap(), 0);
+        db.set_watermark("unxv_events", 50).await;
+        assert_eq!(scan(db.db(), 2).await.unwrap(), 1);
+        assert_eq!(scan(db.db(), 2).await.unwrap(), 0);
+
+        let mut conn = db.db().connect().await.unwrap();
+        let cascades: Vec<LiquidationCascade> =
+            liquidation_cascades::table.select(LiquidationCascade::as_select()).load(&mut conn).await.unwrap();
+        let [cascade] = &cascades[..] else { panic!("expected one cascade, got {cascades:?}") };
+        assert_eq!(cascade.cascade_id, "tx1:0");
+        assert_eq!((cascade.first_checkpoint, cascade.last_checkpoint), (10, 12));
+        assert_eq!((cascade.first_timestamp_ms, cascade.last_timestamp_ms), (10_000, 12_000));
+        assert_eq!(cascade.modules, ["futures", "perpetuals", "xperps"]);
+        assert_eq!(cascade.digests, ["tx1", "tx2"]);
+        assert_eq!(cascade.liquidations, 3);
+        assert_eq!(cascade.qty_closed, 160);
+        assert_eq!(cascade.notional, 200 + 150 + 10);
+        assert_eq!(cascade.penalty_collat, 10 + 5 + 1);
+    }
+}
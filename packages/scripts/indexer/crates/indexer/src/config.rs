@@ -0,0 +1,409 @@
+use anyhow::Context;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+use crate::handlers::family_events_handler::Family;
+use crate::pipeline::CheckpointRange;
+use crate::{
+    normalize_address, normalize_type_tag, parse_event_types, redact_url, ModulesFilter, StructTagFilter, UnxvEnv,
+};
+
+/// Variable naming the config file when `--config` isn't given.
+pub const CONFIG_ENV: &str = "UNXV_CONFIG";
+
+/// An `--config` TOML file: the settings instances usually differ in. Every key is optional
+/// and unknown keys are rejected. Values become the defaults of the CLI options they stand in
+/// for (see [`UnxvConfig::apply_defaults`]), so a flag or variable always wins over the file and
+/// the file over built-in defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UnxvConfig {
+    /// `mainnet`, `testnet`, `localnet` or `custom`.
+    pub network: Option<String>,
+    pub database_url: Option<String>,
+    pub metrics_address: Option<SocketAddr>,
+    /// Pipelines to run; all registered pipelines when unset.
+    pub pipelines: Option<Vec<String>>,
+    /// One `unxv_events_<module>` pipeline per module (`--split-pipelines`).
+    pub split_pipelines: Option<bool>,
+    /// Package families with a pipeline each, `NAME=0xPKG1,0xPKG2`, in slot order (`--family`).
+    pub families: Option<Vec<String>>,
+    /// Settings of individual pipelines, keyed by pipeline name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub pipeline: BTreeMap<String, PipelineConfig>,
+    /// Struct tags recorded by `unxv_object_changes` (`--object-types`).
+    pub object_types: Option<Vec<String>>,
+    /// Event filters. Every pipeline built on `unxv_events` applies the same ones.
+    #[serde(default)]
+    pub filters: FilterConfig,
+}
+
+/// `[pipeline.<name>]`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineConfig {
+    /// Commit in checkpoint order (`--sequential-pipelines`).
+    pub sequential: Option<bool>,
+    /// Inclusive checkpoint bounds, `START..END` with either side open (`--pipeline-range`).
+    pub range: Option<String>,
+}
+
+/// `[filters]`, one key per filter option.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FilterConfig {
+    /// Module names, or `["all"]`.
+    pub modules: Option<Vec<String>>,
+    pub package_ids: Option<Vec<String>>,
+    /// `module::Struct` or `module::*`.
+    pub event_types: Option<Vec<String>>,
+    /// Full struct tags, `0xabc::module::Struct`.
+    pub event_struct_tags: Option<Vec<String>>,
+    pub sender_allowlist: Option<Vec<String>>,
+    pub sender_denylist: Option<Vec<String>>,
+    pub type_param_filter: Option<Vec<String>>,
+    pub type_param_filter_drop_untyped: Option<bool>,
+    pub balance_affecting_only: Option<bool>,
+    pub balance_affecting_events: Option<Vec<String>>,
+}
+
+impl UnxvConfig {
+    /// Read and validate `path`, so a bad value is reported against the file rather than the
+    /// variable it ends up in.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let config: UnxvConfig = toml::from_str(&text).with_context(|| format!("Invalid config {}", path.display()))?;
+        config.validate().with_context(|| format!("Invalid config {}", path.display()))?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if let Some(network) = &self.network {
+            <UnxvEnv as ValueEnum>::from_str(network, true).map_err(|e| anyhow::anyhow!("network: {e}"))?;
+        }
+        if let Some(url) = &self.database_url {
+            Url::parse(url).context("database_url")?;
+        }
+        for (name, pipeline) in &self.pipeline {
+            if let Some(range) = &pipeline.range {
+                range.parse::<CheckpointRange>().with_context(|| format!("pipeline.{name}.range"))?;
+            }
+        }
+        for family in self.families.iter().flatten() {
+            family.parse::<Family>().context("families")?;
+        }
+        if let Some(object_types) = &self.object_types {
+            object_types.join(",").parse::<StructTagFilter>().context("object_types")?;
+        }
+        let filters = &self.filters;
+        if let Some(modules) = &filters.modules {
+            modules.join(",").parse::<ModulesFilter>().context("filters.modules")?;
+        }
+        for package in filters.package_ids.iter().flatten() {
+            normalize_address(package).context("filters.package_ids")?;
+        }
+        if let Some(event_types) = &filters.event_types {
+            parse_event_types(&event_types.join(",")).context("filters.event_types")?;
+        }
+        if let Some(tags) = &filters.event_struct_tags {
+            tags.join(",").parse::<StructTagFilter>().context("filters.event_struct_tags")?;
+        }
+        if let Some(event_types) = &filters.balance_affecting_events {
+            parse_event_types(&event_types.join(",")).context("filters.balance_affecting_events")?;
+        }
+        for sender in filters.sender_allowlist.iter().flatten() {
+            normalize_address(sender).context("filters.sender_allowlist")?;
+        }
+        for sender in filters.sender_denylist.iter().flatten() {
+            normalize_address(sender).context("filters.sender_denylist")?;
+        }
+        for tag in filters.type_param_filter.iter().flatten() {
+            normalize_type_tag(tag).context("filters.type_param_filter")?;
+        }
+        Ok(())
+    }
+
+    /// The file's `network`, which `main` ranks below both `--env` and the positional network.
+    pub fn network(&self) -> Option<UnxvEnv> {
+        self.network.as_deref().and_then(|network| <UnxvEnv as ValueEnum>::from_str(network, true).ok())
+    }
+
+    /// `(option id, value)` for every key the file sets, with lists joined the way the option's
+    /// variable takes them. `network` is left out: it is also a positional argument, so `main`
+    /// merges it itself.
+    pub fn defaults(&self) -> Vec<(&'static str, String)> {
+        let mut vars = Vec::new();
+        let mut set = |name: &'static str, value: Option<String>| {
+            if let Some(value) = value {
+                vars.push((name, value));
+            }
+        };
+        let list = |values: &Option<Vec<String>>| values.as_ref().map(|v| v.join(","));
+        let flag = |value: Option<bool>| value.map(|v| v.to_string());
+
+        set("database_url", self.database_url.clone());
+        set("metrics_address", self.metrics_address.map(|a| a.to_string()));
+        set("pipelines", list(&self.pipelines));
+        set("split_pipelines", flag(self.split_pipelines));
+        set("families", self.families.as_ref().map(|f| f.join(";")));
+        let sequential: Vec<&str> = self
+            .pipeline
+            .iter()
+            .filter(|(_, p)| p.sequential == Some(true))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        set("sequential_pipelines", (!sequential.is_empty()).then(|| sequential.join(",")));
+        let ranges: Vec<String> = self
+            .pipeline
+            .iter()
+            .filter_map(|(name, p)| p.range.as_ref().map(|range| format!("{name}={range}")))
+            .collect();
+        set("pipeline_range", (!ranges.is_empty()).then(|| ranges.join(",")));
+        set("object_types", list(&self.object_types));
+
+        let filters = &self.filters;
+        set("modules", list(&filters.modules));
+        set("package_ids", list(&filters.package_ids));
+        set("event_types", list(&filters.event_types));
+        set("event_struct_tags", list(&filters.event_struct_tags));
+        set("sender_allowlist", list(&filters.sender_allowlist));
+        set("sender_denylist", list(&filters.sender_denylist));
+        set("type_param_filter", list(&filters.type_param_filter));
+        set("type_param_filter_drop_untyped", flag(filters.type_param_filter_drop_untyped));
+        set("balance_affecting_only", flag(filters.balance_affecting_only));
+        set("balance_affecting_events", list(&filters.balance_affecting_events));
+        vars
+    }
+
+    /// `command` with [`Self::defaults`] as the default values of the options they name, in
+    /// `command` and every subcommand that defines them. Clap ranks a flag over its variable
+    /// and both over a default, which gives the file its place without touching the
+    /// environment.
+    pub fn apply_defaults(&self, command: clap::Command) -> clap::Command {
+        fn apply(mut command: clap::Command, defaults: &[(&'static str, String)]) -> clap::Command {
+            for (id, value) in defaults {
+                if command.get_arguments().any(|arg| arg.get_id() == *id) {
+                    command = command.mut_arg(*id, |arg| arg.default_value(value.clone()));
+                }
+            }
+            let subcommands: Vec<String> = command.get_subcommands().map(|s| s.get_name().to_string()).collect();
+            for name in subcommands {
+                command = command.mut_subcommand(name, |subcommand| apply(subcommand, defaults));
+            }
+            command
+        }
+        apply(command, &self.defaults())
+    }
+
+    /// The config as TOML, with any database password replaced by `<redacted>`.
+    pub fn to_toml_redacted(&self) -> anyhow::Result<String> {
+        let mut config = self.clone();
+        config.database_url = config
+            .database_url
+            .map(|url| Url::parse(&url).map(|url| redact_url(&url)).unwrap_or_else(|_| "<redacted>".to_string()));
+        Ok(toml::to_string_pretty(&config)?)
+    }
+}
+
+/// The `--config` path in `args` (program name first), else [`CONFIG_ENV`]. Looked up before
+/// clap runs, because the file's values are part of the CLI's definition.
+pub fn config_path(args: impl IntoIterator<Item = OsString>) -> Option<PathBuf> {
+    let mut args = args.into_iter().skip(1);
+    while let Some(arg) = args.next() {
+        let Some(arg) = arg.to_str() else { continue };
+        if arg == "--" {
+            break;
+        }
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    std::env::var_os(CONFIG_ENV).map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+
+    #[derive(Parser)]
+    struct TestArgs {
+        // PATH is set in every test process, so it stands in for DATABASE_URL without
+        // changing the environment.
+        #[clap(env = "PATH", long)]
+        database_url: Option<String>,
+        #[clap(env = "UNXV_TEST_UNSET_METRICS_ADDRESS", long, default_value = "0.0.0.0:9184")]
+        metrics_address: SocketAddr,
+        #[clap(long, value_delimiter = ',')]
+        pipelines: Vec<String>,
+        #[clap(long)]
+        split_pipelines: bool,
+        #[clap(long, value_delimiter = ';')]
+        families: Vec<String>,
+        #[command(subcommand)]
+        command: Option<TestCommand>,
+    }
+
+    #[derive(Subcommand)]
+    enum TestCommand {
+        Index {
+            #[clap(long, value_delimiter = ',')]
+            sequential_pipelines: Vec<String>,
+        },
+    }
+
+    fn parse(config: &UnxvConfig, args: &[&str]) -> TestArgs {
+        let command = config.apply_defaults(TestArgs::command());
+        let argv = std::iter::once("test").chain(args.iter().copied());
+        TestArgs::from_arg_matches(&command.try_get_matches_from(argv).unwrap()).unwrap()
+    }
+
+    fn config() -> UnxvConfig {
+        toml::from_str(
+            r#"
+            database_url = "postgres://file/unxv"
+            metrics_address = "127.0.0.1:1000"
+            pipelines = ["unxv_events", "dex_trades"]
+            split_pipelines = true
+            families = ["v1=0xa,0xb", "v2=0xc"]
+
+            [pipeline.dex_trades]
+            sequential = true
+            range = "10.."
+
+            [filters]
+            modules = ["dex"]
+            package_ids = ["0xa"]
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn defaults_name_the_options() {
+        let defaults: BTreeMap<_, _> = config().defaults().into_iter().collect();
+        let expected: BTreeMap<_, _> = [
+            ("database_url", "postgres://file/unxv"),
+            ("metrics_address", "127.0.0.1:1000"),
+            ("pipelines", "unxv_events,dex_trades"),
+            ("split_pipelines", "true"),
+            ("families", "v1=0xa,0xb;v2=0xc"),
+            ("sequential_pipelines", "dex_trades"),
+            ("pipeline_range", "dex_trades=10.."),
+            ("modules", "dex"),
+            ("package_ids", "0xa"),
+        ]
+        .into_iter()
+        .map(|(id, value)| (id, value.to_string()))
+        .collect();
+        assert_eq!(defaults, expected);
+        assert!(UnxvConfig::default().defaults().is_empty());
+    }
+
+    #[test]
+    fn file_values_replace_built_in_defaults() {
+        let args = parse(&config(), &["index"]);
+        assert_eq!(args.metrics_address, "127.0.0.1:1000".parse().unwrap());
+        assert_eq!(args.pipelines, ["unxv_events", "dex_trades"]);
+        assert!(args.split_pipelines);
+        assert_eq!(args.families, ["v1=0xa,0xb", "v2=0xc"]);
+        let Some(TestCommand::Index { sequential_pipelines }) = args.command else { panic!("expected index") };
+        assert_eq!(sequential_pipelines, ["dex_trades"]);
+
+        let args = parse(&UnxvConfig::default(), &[]);
+        assert_eq!(args.metrics_address, "0.0.0.0:9184".parse().unwrap());
+        assert!(args.pipelines.is_empty() && !args.split_pipelines);
+    }
+
+    #[test]
+    fn flags_and_variables_win_over_the_file() {
+        let args = parse(&config(), &["--metrics-address", "127.0.0.1:2000", "--pipelines", "dex_trades"]);
+        assert_eq!(args.metrics_address, "127.0.0.1:2000".parse().unwrap());
+        assert_eq!(args.pipelines, ["dex_trades"]);
+
+        // Set everywhere: the variable wins over the file, and the flag over both.
+        let args = parse(&config(), &[]);
+        assert_eq!(args.database_url, std::env::var("PATH").ok());
+        let args = parse(&config(), &["--database-url", "postgres://flag/unxv"]);
+        assert_eq!(args.database_url.as_deref(), Some("postgres://flag/unxv"));
+    }
+
+    #[test]
+    fn config_path_comes_from_the_flag_or_the_variable() {
+        let path = |args: &[&str]| config_path(args.iter().map(OsString::from));
+        assert_eq!(path(&["unxv", "--config", "a.toml", "index"]), Some(PathBuf::from("a.toml")));
+        assert_eq!(path(&["unxv", "testnet", "--config=b.toml"]), Some(PathBuf::from("b.toml")));
+        let fallback = std::env::var_os(CONFIG_ENV).map(PathBuf::from);
+        assert_eq!(path(&["unxv", "--", "--config", "c.toml"]), fallback);
+        assert_eq!(path(&["--config"]), fallback);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        for text in [
+            "netwrok = \"testnet\"",
+            "[filters]\nmodule = [\"dex\"]",
+            "[pipeline.dex_trades]\nsequentail = true",
+        ] {
+            assert!(toml::from_str::<UnxvConfig>(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn invalid_values_name_their_key() {
+        for (text, key) in [
+            ("network = \"devnet\"", "network"),
+            ("database_url = \"not a url\"", "database_url"),
+            ("[pipeline.dex_trades]\nrange = \"9..1\"", "pipeline.dex_trades.range"),
+            ("families = [\"v1\"]", "families"),
+            ("families = [\"v1=0xnope\"]", "families"),
+            ("object_types = [\"0xabc::pool\"]", "object_types"),
+            ("[filters]\nmodules = [\"dex-v2\"]", "filters.modules"),
+            ("[filters]\npackage_ids = [\"0xa\", \"0xnope\"]", "filters.package_ids"),
+            ("[filters]\nevent_types = [\"ProtocolFeeTaken\"]", "filters.event_types"),
+            ("[filters]\nevent_struct_tags = [\"dex::Liquidated\"]", "filters.event_struct_tags"),
+            ("[filters]\nbalance_affecting_events = [\"dex\"]", "filters.balance_affecting_events"),
+            ("[filters]\nsender_allowlist = [\"0x2g\"]", "filters.sender_allowlist"),
+            ("[filters]\nsender_denylist = [\"0x\"]", "filters.sender_denylist"),
+            ("[filters]\ntype_param_filter = [\"not a type\"]", "filters.type_param_filter"),
+        ] {
+            let config: UnxvConfig = toml::from_str(text).unwrap();
+            let error = format!("{:#}", config.validate().unwrap_err());
+            assert!(error.starts_with(key), "{text}: {error}");
+        }
+        config().validate().unwrap();
+    }
+
+    #[test]
+    fn load_reports_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("unxv.toml");
+        std::fs::write(&path, "network = \"testnet\"\n[filters]\nmodules = [\"all\"]\n").unwrap();
+        let config = UnxvConfig::load(&path).unwrap();
+        assert_eq!(config.network(), Some(UnxvEnv::Testnet));
+        assert_eq!(config.filters.modules, Some(vec!["all".to_string()]));
+
+        std::fs::write(&path, "network = \"devnet\"\n").unwrap();
+        let error = format!("{:#}", UnxvConfig::load(&path).unwrap_err());
+        assert!(error.contains(&path.display().to_string()), "{error}");
+        assert!(UnxvConfig::load(&dir.path().join("missing.toml")).is_err());
+    }
+
+    #[test]
+    fn printed_configs_hide_the_database_password() {
+        let printed = config().to_toml_redacted().unwrap();
+        assert!(printed.contains("database_url = \"postgres://file/unxv\""), "{printed}");
+        let config = UnxvConfig { database_url: Some("postgres://u:secret@db/unxv".into()), ..config() };
+        let printed = config.to_toml_redacted().unwrap();
+        assert!(!printed.contains("secret") && printed.contains("%3Credacted%3E"), "{printed}");
+        assert_eq!(toml::from_str::<UnxvConfig>(&printed).unwrap().pipeline, config.pipeline);
+    }
+}
@@ -0,0 +1,341 @@
+use diesel_async::scoped_futures::ScopedBoxFuture;
+use diesel_async::AsyncPgConnection;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use sui_pg_db::{Db, DbArgs};
+use tracing::warn;
+use url::Url;
+
+use crate::{backpressure, metrics};
+
+/// Postgres caps the bind parameters of one statement at this many.
+pub const MAX_BIND_PARAMS: usize = u16::MAX as usize;
+
+/// Rows of `field_count` columns that fit in one multi-row insert.
+pub const fn max_insert_rows(field_count: usize) -> usize {
+    MAX_BIND_PARAMS / field_count
+}
+
+/// How commits write `unxv_events`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum InsertMethod {
+    /// Multi-row `INSERT ... VALUES`, chunked under [`MAX_BIND_PARAMS`].
+    #[default]
+    Rows,
+    /// One `INSERT ... SELECT FROM UNNEST(...)` binding an array per column, whatever the row
+    /// count. Stands in for `COPY ... FROM STDIN`, which `diesel-async` connections don't
+    /// support: a COPY would also need a staging table to keep `ON CONFLICT DO NOTHING`.
+    Unnest,
+}
+
+impl InsertMethod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InsertMethod::Rows => "rows",
+            InsertMethod::Unnest => "unnest",
+        }
+    }
+}
+
+static INSERT_METHOD: OnceLock<InsertMethod> = OnceLock::new();
+
+/// Set the process-wide [`InsertMethod`] (`--insert-method`). Later calls are ignored.
+pub fn set_insert_method(method: InsertMethod) {
+    let _ = INSERT_METHOD.set(method);
+}
+
+pub fn insert_method() -> InsertMethod {
+    INSERT_METHOD.get().copied().unwrap_or_default()
+}
+
+static REPROCESS: OnceLock<bool> = OnceLock::new();
+
+/// Have `unxv_events` commits overwrite the decoded columns of rows already stored
+/// (`--reprocess`) rather than leave them alone. Later calls are ignored.
+pub fn set_reprocess(reprocess: bool) {
+    let _ = REPROCESS.set(reprocess);
+}
+
+pub fn reprocess() -> bool {
+    REPROCESS.get().copied().unwrap_or_default()
+}
+
+/// Record an insert of `rows` rows into `table` that took `elapsed`.
+pub fn record_insert(table: &str, method: InsertMethod, rows: usize, elapsed: Duration) {
+    let Some(metrics) = metrics::global() else { return };
+    let labels = [table, method.as_str()];
+    metrics.inserted_rows.with_label_values(&labels).inc_by(rows as u64);
+    if rows > 0 && !elapsed.is_zero() {
+        metrics.insert_rows_per_second.with_label_values(&labels).observe(rows as f64 / elapsed.as_secs_f64());
+    }
+}
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Connect to the database for writing, retrying with exponential backoff until it accepts
+/// connections or `wait_timeout` elapses. A zero timeout makes a single attempt.
+pub async fn connect_with_retry(
+    database_url: Url,
+    db_args: DbArgs,
+    wait_timeout: Duration,
+) -> anyhow::Result<Db> {
+    let deadline = Instant::now() + wait_timeout;
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match try_connect(database_url.clone(), db_args.clone()).await {
+            Ok(db) => return Ok(db),
+            Err(e) if Instant::now() + backoff < deadline => {
+                warn!(attempt, ?backoff, "Database not ready, retrying: {e:#}");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Building the pool does not guarantee the server is up, so check out one connection.
+async fn try_connect(database_url: Url, db_args: DbArgs) -> anyhow::Result<Db> {
+    let db = Db::for_write(database_url, db_args).await?;
+    drop(db.connect().await?);
+    Ok(db)
+}
+
+const COMMIT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const COMMIT_MAX_BACKOFF: Duration = Duration::from_secs(5);
+const DEFAULT_COMMIT_MAX_ATTEMPTS: u32 = 5;
+
+static COMMIT_MAX_ATTEMPTS: OnceLock<u32> = OnceLock::new();
+
+/// Set the process-wide attempt cap of [`retry_commit`] (`--commit-max-attempts`). Later calls
+/// are ignored.
+pub fn set_commit_max_attempts(attempts: u32) {
+    let _ = COMMIT_MAX_ATTEMPTS.set(attempts.max(1));
+}
+
+/// Why a commit failed transiently, or None if retrying can't help. Diesel doesn't expose the
+/// SQLSTATE, so classes are recognized through its error kinds: serialization failures
+/// (`40001`), lost connections (`08xxx` and I/O errors), and deadlocks (`40P01`, which it
+/// reports as unknown, by message).
+pub fn transient_reason(err: &anyhow::Error) -> Option<&'static str> {
+    use diesel::result::{DatabaseErrorKind, Error};
+    err.chain().find_map(|cause| match cause.downcast_ref::<Error>()? {
+        Error::DatabaseError(DatabaseErrorKind::SerializationFailure, _) => Some("serialization_failure"),
+        Error::DatabaseError(DatabaseErrorKind::ClosedConnection | DatabaseErrorKind::UnableToSendCommand, _) => {
+            Some("connection")
+        }
+        Error::DatabaseError(DatabaseErrorKind::Unknown, info) if info.message().contains("deadlock detected") => {
+            Some("deadlock")
+        }
+        _ => None,
+    })
+}
+
+/// Messages of Postgres data exceptions (SQLSTATE class `22`) that Diesel reports as unknown.
+const DATA_ERROR_MESSAGES: &[&str] = &[
+    "invalid byte sequence",
+    "unsupported Unicode escape sequence",
+    "out of range",
+    "invalid input syntax",
+    "value too long",
+];
+
+/// Why a commit failed because of the rows it wrote, or None for any other failure. Such a
+/// commit fails the same way however often it's retried, and only the offending rows need to
+/// be set aside (see [`crate::quarantine`]): constraint violations, values Postgres rejects
+/// (such as a NUL in text), and binds Diesel couldn't serialize.
+pub fn data_error_reason(err: &anyhow::Error) -> Option<&'static str> {
+    use diesel::result::{DatabaseErrorKind, Error};
+    err.chain().find_map(|cause| match cause.downcast_ref::<Error>()? {
+        Error::DatabaseError(
+            DatabaseErrorKind::UniqueViolation
+            | DatabaseErrorKind::ForeignKeyViolation
+            | DatabaseErrorKind::NotNullViolation
+            | DatabaseErrorKind::CheckViolation,
+            _,
+        ) => Some("constraint"),
+        Error::DatabaseError(DatabaseErrorKind::Unknown, info)
+            if DATA_ERROR_MESSAGES.iter().any(|m| info.message().contains(m)) =>
+        {
+            Some("invalid_value")
+        }
+        Error::SerializationError(_) => Some("serialization"),
+        _ => None,
+    })
+}
+
+/// `backoff` scaled by a pseudo-random 50-100%, so commits that failed together don't retry
+/// in lockstep.
+fn jitter(backoff: Duration) -> Duration {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    backoff.mul_f64(0.5 + (nanos % 1000) as f64 / 2000.0)
+}
+
+/// Run a concurrent pipeline's commit, retrying [`transient_reason`] failures with exponential
+/// backoff and jitter up to the configured number of attempts; other errors propagate at once.
+/// `attempt` must be safe to repeat after a partial write, which `ON CONFLICT DO NOTHING`
+/// inserts are. A commit running inside a transaction can't continue after an error, so
+/// sequential pipelines leave retrying to the framework. Successful commits, retries included,
+/// feed [`backpressure`].
+pub async fn retry_commit<'a, T, F>(pipeline: &str, conn: &mut AsyncPgConnection, mut attempt: F) -> anyhow::Result<T>
+where
+    F: for<'r> FnMut(&'r mut AsyncPgConnection) -> ScopedBoxFuture<'a, 'r, anyhow::Result<T>>,
+{
+    let max_attempts = COMMIT_MAX_ATTEMPTS.get().copied().unwrap_or(DEFAULT_COMMIT_MAX_ATTEMPTS);
+    let mut backoff = COMMIT_INITIAL_BACKOFF;
+    let mut n = 1;
+    let started = Instant::now();
+    loop {
+        let err = match attempt(conn).await {
+            Ok(value) => {
+                backpressure::record_commit(started.elapsed());
+                return Ok(value);
+            }
+            Err(err) => err,
+        };
+        let Some(reason) = transient_reason(&err) else { return Err(err) };
+        if n >= max_attempts {
+            return Err(err.context(format!("{pipeline} commit failed after {n} attempts")));
+        }
+        if let Some(metrics) = metrics::global() {
+            metrics.commit_retries.with_label_values(&[pipeline, reason]).inc();
+        }
+        let delay = jitter(backoff);
+        warn!(pipeline, attempt = n, reason, ?delay, "Transient commit failure, retrying: {err:#}");
+        tokio::time::sleep(delay).await;
+        backoff = (backoff * 2).min(COMMIT_MAX_BACKOFF);
+        n += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestDb;
+    use diesel::result::{DatabaseErrorKind, Error};
+    use diesel_async::scoped_futures::ScopedFutureExt;
+    use sui_pg_db::temp::TempDb;
+    use tokio::net::TcpListener;
+
+    fn database_error(kind: DatabaseErrorKind, message: &str) -> anyhow::Error {
+        Error::DatabaseError(kind, Box::new(message.to_string())).into()
+    }
+
+    /// A local port nothing listens on.
+    async fn closed_port() -> u16 {
+        TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port()
+    }
+
+    fn url_on(url: &Url, port: u16) -> Url {
+        let mut url = url.clone();
+        url.set_host(Some("127.0.0.1")).unwrap();
+        url.set_port(Some(port)).unwrap();
+        url
+    }
+
+    #[tokio::test]
+    async fn connecting_waits_for_a_database_that_comes_up_in_time() {
+        let temp = TempDb::new().unwrap();
+        let target = temp.database().url().clone();
+        let port = closed_port().await;
+
+        // The database only becomes reachable on `port` after a second, through a proxy.
+        let proxy_target = format!("{}:{}", target.host_str().unwrap(), target.port().unwrap());
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            let listener = TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+            loop {
+                let (mut client, _) = listener.accept().await.unwrap();
+                let mut server = tokio::net::TcpStream::connect(&proxy_target).await.unwrap();
+                tokio::spawn(async move { tokio::io::copy_bidirectional(&mut client, &mut server).await });
+            }
+        });
+
+        let started = Instant::now();
+        let db = connect_with_retry(url_on(&target, port), DbArgs::default(), Duration::from_secs(10)).await.unwrap();
+        drop(db.connect().await.unwrap());
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn connecting_gives_up_at_the_deadline() {
+        let url = url_on(&Url::parse("postgres://postgres@localhost/postgres").unwrap(), closed_port().await);
+
+        let started = Instant::now();
+        assert!(connect_with_retry(url.clone(), DbArgs::default(), Duration::from_secs(2)).await.is_err());
+        // Attempts at 0s, 0.5s and 1.5s; the next backoff would end past the deadline.
+        assert!(started.elapsed() >= Duration::from_millis(1500));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        let started = Instant::now();
+        assert!(connect_with_retry(url, DbArgs::default(), Duration::ZERO).await.is_err());
+        assert!(started.elapsed() < INITIAL_BACKOFF);
+    }
+
+    #[test]
+    fn commit_errors_are_classified() {
+        use DatabaseErrorKind::*;
+        let transient = |kind, message| transient_reason(&database_error(kind, message));
+        let data = |kind, message| data_error_reason(&database_error(kind, message));
+
+        assert_eq!(transient(SerializationFailure, "could not serialize access"), Some("serialization_failure"));
+        assert_eq!(transient(ClosedConnection, "server closed the connection"), Some("connection"));
+        assert_eq!(transient(UnableToSendCommand, "broken pipe"), Some("connection"));
+        assert_eq!(transient(Unknown, "deadlock detected"), Some("deadlock"));
+        assert_eq!(transient(Unknown, "relation does not exist"), None);
+        assert_eq!(transient(UniqueViolation, "duplicate key"), None);
+        // Found through the context a caller adds, too.
+        let err = database_error(SerializationFailure, "could not serialize access").context("committing");
+        assert_eq!(transient_reason(&err), Some("serialization_failure"));
+        assert_eq!(transient_reason(&anyhow::anyhow!("deadlock detected")), None);
+
+        for kind in [UniqueViolation, ForeignKeyViolation, NotNullViolation, CheckViolation] {
+            assert_eq!(data(kind, "violates a constraint"), Some("constraint"));
+        }
+        assert_eq!(data(Unknown, "invalid byte sequence for encoding \"UTF8\": 0x00"), Some("invalid_value"));
+        assert_eq!(data(Unknown, "value \"99999999999\" is out of range for type integer"), Some("invalid_value"));
+        assert_eq!(data(Unknown, "deadlock detected"), None);
+        assert_eq!(data(SerializationFailure, "could not serialize access"), None);
+        assert_eq!(data_error_reason(&Error::SerializationError("bad bind".into()).into()), Some("serialization"));
+    }
+
+    #[tokio::test]
+    async fn commits_retry_transient_failures_only() {
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+
+        let mut attempts = 0;
+        let committed = retry_commit("test", &mut conn, |_| {
+            attempts += 1;
+            let attempt = attempts;
+            async move {
+                if attempt <= 2 {
+                    Err(database_error(DatabaseErrorKind::SerializationFailure, "could not serialize access"))
+                } else {
+                    Ok(attempt)
+                }
+            }
+            .scope_boxed()
+        })
+        .await
+        .unwrap();
+        assert_eq!(committed, 3);
+
+        let mut attempts = 0;
+        let err = retry_commit("test", &mut conn, |_| {
+            attempts += 1;
+            async { Err::<(), _>(database_error(DatabaseErrorKind::CheckViolation, "violates check")) }.scope_boxed()
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(attempts, 1);
+        assert_eq!(data_error_reason(&err), Some("constraint"));
+    }
+}
@@ -0,0 +1,203 @@
+use diesel::dsl::{now, sql};
+use diesel::sql_types::{BigInt, Bool, Text};
+use diesel::{ExpressionMethods, QueryDsl, SelectableHelper};
+use diesel_async::RunQueryDsl;
+use sui_indexer_alt_framework::pipeline::concurrent::Handler;
+use sui_indexer_alt_framework::pipeline::Processor;
+use sui_pg_db::Db;
+use tracing::{info, warn};
+
+use crate::handlers::unxv_events_handler::{EventRow, UnxvEventsHandler};
+use unxv_schema::models::UnxvDeadLetter;
+use unxv_schema::schema::unxv_dead_letters;
+
+/// Outcome of [`replay`].
+#[derive(Debug, Default)]
+pub struct ReplaySummary {
+    pub replayed: usize,
+    pub still_failing: usize,
+}
+
+/// Re-run `handler`'s decoder over `unxv_events` dead letters (of `module`, if given), in
+/// batches of `batch_size`. Events that now pass are written to `unxv_events` and removed from
+/// the dead letters; the rest get the new reason and one more attempt.
+///
+/// The insert and the delete are separate statements: a replay interrupted between them leaves
+/// rows that are already in `unxv_events`, and the next replay's insert skips them.
+pub async fn replay(
+    db: &Db,
+    handler: &UnxvEventsHandler,
+    module: Option<&str>,
+    batch_size: i64,
+) -> anyhow::Result<ReplaySummary> {
+    let mut conn = db.connect().await?;
+    let mut summary = ReplaySummary::default();
+    let mut after: Option<(String, i64)> = None;
+    loop {
+        let mut query = unxv_dead_letters::table
+            .select(UnxvDeadLetter::as_select())
+            .filter(unxv_dead_letters::pipeline.eq(UnxvEventsHandler::NAME))
+            .into_boxed();
+        if let Some(module) = module {
+            query = query.filter(unxv_dead_letters::module.eq(module.to_string()));
+        }
+        if let Some((digest, event_index)) = &after {
+            query = query.filter(
+                sql::<Bool>("(digest, event_index) > (")
+                    .bind::<Text, _>(digest.clone())
+                    .sql(", ")
+                    .bind::<BigInt, _>(*event_index)
+                    .sql(")"),
+            );
+        }
+        let batch: Vec<UnxvDeadLetter> = query
+            .order((unxv_dead_letters::digest, unxv_dead_letters::event_index))
+            .limit(batch_size)
+            .load(&mut conn)
+            .await?;
+        let Some(last) = batch.last() else { break };
+        after = Some((last.digest.clone(), last.event_index));
+
+        let (mut replayed, mut rows) = (vec![], vec![]);
+        for dead_letter in &batch {
+            match handler.replay(dead_letter) {
+                Ok(event) => {
+                    replayed.push(dead_letter);
+                    rows.push(EventRow::Event(event));
+                }
+                Err(reason) => {
+                    warn!(digest = %dead_letter.digest, event_index = dead_letter.event_index, "Still failing: {reason}");
+                    diesel::update(unxv_dead_letters::table)
+                        .filter(unxv_dead_letters::pipeline.eq(&dead_letter.pipeline))
+                        .filter(unxv_dead_letters::digest.eq(&dead_letter.digest))
+                        .filter(unxv_dead_letters::event_index.eq(dead_letter.event_index))
+                        .set((
+                            unxv_dead_letters::reason.eq(reason),
+                            unxv_dead_letters::attempts.eq(unxv_dead_letters::attempts + 1),
+                            unxv_dead_letters::last_attempt_at.eq(now),
+                        ))
+                        .execute(&mut conn)
+                        .await?;
+                    summary.still_failing += 1;
+                }
+            }
+        }
+        if replayed.is_empty() {
+            continue;
+        }
+
+        // The pipeline's own commit, so module schemas are honoured.
+        UnxvEventsHandler::commit(&rows, &mut conn).await?;
+        for dead_letter in &replayed {
+            diesel::delete(unxv_dead_letters::table)
+                .filter(unxv_dead_letters::pipeline.eq(&dead_letter.pipeline))
+                .filter(unxv_dead_letters::digest.eq(&dead_letter.digest))
+                .filter(unxv_dead_letters::event_index.eq(dead_letter.event_index))
+                .execute(&mut conn)
+                .await?;
+        }
+        summary.replayed += replayed.len();
+        info!(replayed = summary.replayed, still_failing = summary.still_failing, "Replaying dead letters");
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::builtin::unxversal_layouts;
+    use crate::decoder::JsonLimits;
+    use crate::test_utils::{event, sender_address, CheckpointBuilder, TestDb, PACKAGE};
+    use crate::ModulesFilter;
+    use move_core_types::account_address::AccountAddress;
+    use std::sync::Arc;
+    use unxv_events::dex::ProtocolFeeTaken;
+    use unxv_events::lending::DebtRepaid;
+    use unxv_events::types::{TypeName, ID};
+    use unxv_schema::schema::unxv_events;
+
+    fn handler(json_limits: JsonLimits) -> UnxvEventsHandler {
+        UnxvEventsHandler::new(ModulesFilter::default(), None)
+            .unwrap()
+            .with_layout_resolver(Arc::new(unxversal_layouts(None).unwrap()))
+            .with_json_limits(json_limits)
+    }
+
+    /// A fee and a repayment by sender 1.
+    fn rows(handler: &UnxvEventsHandler) -> Vec<EventRow> {
+        let fee = ProtocolFeeTaken {
+            payer: sender_address(1).into(),
+            base_fee_asset_unxv: false,
+            amount: 5,
+            asset: TypeName { name: format!("{}::sui::SUI", "0".repeat(63) + "2") },
+            timestamp_ms: 1_000,
+        };
+        let repaid = DebtRepaid {
+            market_id: ID { bytes: AccountAddress::from_hex_literal("0xb0b").unwrap() },
+            who: sender_address(1).into(),
+            amount: 10,
+            remaining_principal: 90,
+            timestamp_ms: 1_000,
+        };
+        let checkpoint = CheckpointBuilder::new(7)
+            .transaction(
+                1,
+                &[(PACKAGE, "dex", "swap")],
+                vec![
+                    event(1, &format!("{PACKAGE}::dex::ProtocolFeeTaken"), bcs::to_bytes(&fee).unwrap()),
+                    event(1, &format!("{PACKAGE}::lending::DebtRepaid"), bcs::to_bytes(&repaid).unwrap()),
+                ],
+            )
+            .build();
+        handler.process(&checkpoint).unwrap()
+    }
+
+    async fn attempts(db: &TestDb) -> Vec<(String, i32)> {
+        let mut conn = db.db().connect().await.unwrap();
+        unxv_dead_letters::table
+            .select((unxv_dead_letters::module, unxv_dead_letters::attempts))
+            .order(unxv_dead_letters::module)
+            .load(&mut conn)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn dead_letters_replay_once_they_decode_within_the_limits() {
+        let db = TestDb::new().await;
+        // Any object is too deep, so both events are dead-lettered.
+        let strict = handler(JsonLimits { contents_json_max_depth: 1, ..Default::default() });
+        let rows = rows(&strict);
+        assert!(rows.iter().all(|row| matches!(row, EventRow::DeadLetter(_))));
+        UnxvEventsHandler::commit(&rows, &mut db.db().connect().await.unwrap()).await.unwrap();
+        let before = attempts(&db).await;
+
+        // Under the same limits they still fail, and each attempt is counted.
+        let summary = replay(db.db(), &strict, None, 1).await.unwrap();
+        assert_eq!((summary.replayed, summary.still_failing), (0, 2));
+        let after: Vec<_> = before.iter().map(|(module, n)| (module.clone(), n + 1)).collect();
+        assert_eq!(attempts(&db).await, after);
+        assert_eq!(db.count("unxv_events").await, 0);
+
+        let lenient = handler(JsonLimits::default());
+        let summary = replay(db.db(), &lenient, Some("lending"), 1).await.unwrap();
+        assert_eq!((summary.replayed, summary.still_failing), (1, 0));
+        assert_eq!(attempts(&db).await.into_iter().map(|(module, _)| module).collect::<Vec<_>>(), ["dex"]);
+
+        let summary = replay(db.db(), &lenient, None, 1).await.unwrap();
+        assert_eq!((summary.replayed, summary.still_failing), (1, 0));
+        assert_eq!(db.count("unxv_dead_letters").await, 0);
+
+        let mut conn = db.db().connect().await.unwrap();
+        let replayed: Vec<(String, Option<serde_json::Value>)> = unxv_events::table
+            .select((unxv_events::module, unxv_events::contents_json))
+            .order(unxv_events::event_index)
+            .load(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(replayed.len(), 2);
+        assert_eq!((replayed[0].0.as_str(), replayed[1].0.as_str()), ("dex", "lending"));
+        assert_eq!(replayed[0].1.as_ref().unwrap()["amount"], "5");
+        assert_eq!(replayed[1].1.as_ref().unwrap()["remaining_principal"], "90");
+    }
+}
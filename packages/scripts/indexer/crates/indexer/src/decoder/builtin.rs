@@ -0,0 +1,158 @@
+//! Layouts for the events emitted by the Unxversal Move packages, parsed once at startup from
+//! the Move-like signatures of the typed definitions in `unxv-events`, so JSON decoding and the
+//! typed structs always agree.
+
+use anyhow::{anyhow, bail, Context};
+use move_core_types::account_address::AccountAddress;
+use move_core_types::annotated_value::{MoveFieldLayout, MoveStructLayout, MoveTypeLayout};
+use move_core_types::identifier::Identifier;
+use move_core_types::language_storage::StructTag;
+
+use super::StaticLayoutResolver;
+use crate::normalize_address;
+
+/// A resolver preloaded with the layouts of every Unxversal event, for events of `packages`
+/// (normalized addresses, as in `UNXV_PACKAGE_IDS`) or of any package when None.
+pub fn unxversal_layouts(packages: Option<&[String]>) -> anyhow::Result<StaticLayoutResolver> {
+    let packages = packages
+        .map(|packages| {
+            packages
+                .iter()
+                .map(|p| {
+                    let address = normalize_address(p).with_context(|| format!("Invalid package id {p}"))?;
+                    Ok(AccountAddress::from_hex_literal(&address)?)
+                })
+                .collect::<anyhow::Result<_>>()
+        })
+        .transpose()?;
+    let mut resolver = StaticLayoutResolver::default().with_packages(packages);
+    for sig in unxv_events::signatures() {
+        resolver.insert_any_package(parse_signature(sig).with_context(|| format!("Invalid event signature: {sig}"))?);
+    }
+    Ok(resolver)
+}
+
+// The address is a placeholder; the layouts are registered for any package.
+fn parse_signature(sig: &str) -> anyhow::Result<MoveStructLayout> {
+    let (head, body) = sig.split_once('{').ok_or_else(|| anyhow!("missing field list"))?;
+    let (module, name) = head.trim().split_once("::").ok_or_else(|| anyhow!("missing module"))?;
+    let body = body.trim().strip_suffix('}').ok_or_else(|| anyhow!("unterminated field list"))?;
+    let fields = body
+        .split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(|field| {
+            let (fname, ty) = field.split_once(':').ok_or_else(|| anyhow!("bad field `{field}`"))?;
+            let ty: String = ty.split_whitespace().collect();
+            Ok(MoveFieldLayout::new(Identifier::new(fname.trim())?, parse_type(&ty)?))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(MoveStructLayout { type_: struct_tag(AccountAddress::ZERO, module, name)?, fields })
+}
+
+fn parse_type(ty: &str) -> anyhow::Result<MoveTypeLayout> {
+    if let Some(inner) = ty.strip_prefix("vector<").and_then(|t| t.strip_suffix('>')) {
+        return Ok(MoveTypeLayout::Vector(Box::new(parse_type(inner)?)));
+    }
+    Ok(match ty {
+        "bool" => MoveTypeLayout::Bool,
+        "u8" => MoveTypeLayout::U8,
+        "u16" => MoveTypeLayout::U16,
+        "u32" => MoveTypeLayout::U32,
+        "u64" => MoveTypeLayout::U64,
+        "u128" => MoveTypeLayout::U128,
+        "u256" => MoveTypeLayout::U256,
+        "address" => MoveTypeLayout::Address,
+        "ID" => wrapper(AccountAddress::TWO, "object", "ID", "bytes", MoveTypeLayout::Address)?,
+        "String" => wrapper(AccountAddress::ONE, "string", "String", "bytes", bytes())?,
+        "TypeName" => {
+            let ascii = wrapper(AccountAddress::ONE, "ascii", "String", "bytes", bytes())?;
+            wrapper(AccountAddress::ONE, "type_name", "TypeName", "name", ascii)?
+        }
+        other => bail!("unsupported type `{other}`"),
+    })
+}
+
+fn wrapper(
+    address: AccountAddress,
+    module: &str,
+    name: &str,
+    field: &str,
+    layout: MoveTypeLayout,
+) -> anyhow::Result<MoveTypeLayout> {
+    Ok(MoveTypeLayout::Struct(Box::new(MoveStructLayout {
+        type_: struct_tag(address, module, name)?,
+        fields: vec![MoveFieldLayout::new(Identifier::new(field)?, layout)],
+    })))
+}
+
+fn bytes() -> MoveTypeLayout {
+    MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U8))
+}
+
+fn struct_tag(address: AccountAddress, module: &str, name: &str) -> anyhow::Result<StructTag> {
+    Ok(StructTag {
+        address,
+        module: Identifier::new(module)?,
+        name: Identifier::new(name)?,
+        type_params: vec![],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::LayoutResolver;
+
+    #[test]
+    fn parses_signatures() {
+        let sig = "dex::Filled { pool_id: ID, amounts: vector < u64 >, name: String, fee: TypeName, }";
+        let layout = parse_signature(sig).unwrap();
+        assert_eq!(layout.type_, struct_tag(AccountAddress::ZERO, "dex", "Filled").unwrap());
+        let fields: Vec<_> = layout.fields.iter().map(|f| (f.name.as_str(), &f.layout)).collect();
+        let id = wrapper(AccountAddress::TWO, "object", "ID", "bytes", MoveTypeLayout::Address).unwrap();
+        let string = wrapper(AccountAddress::ONE, "string", "String", "bytes", bytes()).unwrap();
+        assert_eq!(fields[0], ("pool_id", &id));
+        assert_eq!(fields[1], ("amounts", &MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U64))));
+        assert_eq!(fields[2], ("name", &string));
+        let MoveTypeLayout::Struct(fee) = fields[3].1 else { panic!("TypeName is a struct") };
+        assert_eq!(fee.type_, struct_tag(AccountAddress::ONE, "type_name", "TypeName").unwrap());
+
+        assert!(parse_signature("dex::Empty { }").unwrap().fields.is_empty());
+        assert_eq!(parse_type("vector<vector<u8>>").unwrap(), MoveTypeLayout::Vector(Box::new(bytes())));
+    }
+
+    #[test]
+    fn rejects_malformed_signatures() {
+        for sig in [
+            "dex::Filled",
+            "Filled { amount: u64 }",
+            "dex::Filled { amount: u64",
+            "dex::Filled { amount u64 }",
+            "dex::Filled { amount: Coin }",
+            "dex::Filled { amount: vector<u64 }",
+            "dex::Filled { 1amount: u64 }",
+        ] {
+            assert!(parse_signature(sig).is_err(), "{sig:?} parsed");
+        }
+    }
+
+    #[test]
+    fn every_event_signature_parses() {
+        let resolver = unxversal_layouts(None).unwrap();
+        let tag = crate::parse_struct_tag("0xe11e::lending::DebtRepaid").unwrap();
+        assert!(resolver.resolve(&tag).unwrap().is_some());
+    }
+
+    #[test]
+    fn layouts_follow_the_package_list() {
+        let resolver = unxversal_layouts(Some(&["0xA11CE".to_string()])).unwrap();
+        let resolve = |tag: &str| resolver.resolve(&crate::parse_struct_tag(tag).unwrap()).unwrap();
+        assert!(resolve("0xa11ce::dex::ProtocolFeeTaken").is_some());
+        assert!(resolve("0xe11e::dex::ProtocolFeeTaken").is_none());
+        for invalid in ["0xnope", "", &format!("0x1{}", "0".repeat(64))] {
+            let err = unxversal_layouts(Some(&[invalid.to_string()])).unwrap_err();
+            assert!(err.to_string().contains("Invalid package id"), "{invalid:?}: {err:#}");
+        }
+    }
+}
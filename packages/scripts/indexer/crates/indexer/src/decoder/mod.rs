@@ -0,0 +1,587 @@
+use move_core_types::account_address::AccountAddress;
+use move_core_types::annotated_value::{MoveStruct, MoveStructLayout, MoveTypeLayout, MoveValue};
+use move_core_types::language_storage::{StructTag, TypeTag};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+use std::sync::{Arc, RwLock};
+
+pub mod builtin;
+
+/// Decodes the BCS contents of one event type into JSON.
+pub trait EventDecoder: Send + Sync {
+    fn decode(&self, bcs: &[u8]) -> anyhow::Result<Value>;
+}
+
+/// Per-event-type decoders keyed by `(module, event_type)`. Decoders registered here take
+/// precedence over layout-based decoding; types with neither are stored with NULL JSON.
+#[derive(Default)]
+pub struct DecoderRegistry {
+    decoders: HashMap<(String, String), Arc<dyn EventDecoder>>,
+}
+
+impl DecoderRegistry {
+    pub fn register(&mut self, module: &str, event_type: &str, decoder: impl EventDecoder + 'static) {
+        self.decoders.insert((module.to_string(), event_type.to_string()), Arc::new(decoder));
+    }
+
+    pub fn get(&self, module: &str, event_type: &str) -> Option<&dyn EventDecoder> {
+        self.decoders
+            .get(&(module.to_string(), event_type.to_string()))
+            .map(|d| d.as_ref())
+    }
+}
+
+/// Decodes with a Rust type mirroring the Move struct, then serializes it with serde.
+pub struct BcsDecoder<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for BcsDecoder<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: DeserializeOwned + Serialize> EventDecoder for BcsDecoder<T> {
+    fn decode(&self, bcs: &[u8]) -> anyhow::Result<Value> {
+        Ok(serde_json::to_value(bcs::from_bytes::<T>(bcs)?)?)
+    }
+}
+
+/// Decodes with a fixed Move struct layout (see [`decode_to_json`]).
+pub struct LayoutDecoder(pub MoveStructLayout);
+
+impl EventDecoder for LayoutDecoder {
+    fn decode(&self, bcs: &[u8]) -> anyhow::Result<Value> {
+        decode_to_json(bcs, &self.0)
+    }
+}
+
+/// Resolves the Move struct layout needed to decode an event's BCS contents.
+/// Implementations must be cheap to call repeatedly or be wrapped in a [`CachingLayoutResolver`].
+pub trait LayoutResolver: Send + Sync {
+    /// `Ok(None)` means the type is unknown to this resolver.
+    fn resolve(&self, tag: &StructTag) -> anyhow::Result<Option<Arc<MoveStructLayout>>>;
+}
+
+/// Layouts registered up front, either for one package or by `(module, struct name)` for
+/// every package in [`Self::with_packages`]. The same modules are published under several
+/// package ids (and upgrades), so the built-in layouts can't name theirs; restricting them to
+/// the allowlisted packages keeps a foreign struct that happens to share a module and name
+/// from being decoded with the wrong layout.
+#[derive(Default)]
+pub struct StaticLayoutResolver {
+    /// Keyed by `(package, module, struct name)`; no package for layouts of any package.
+    layouts: HashMap<(Option<AccountAddress>, String, String), MoveStructLayout>,
+    /// Packages the package-less layouts apply to. None means any package.
+    packages: Option<HashSet<AccountAddress>>,
+}
+
+impl StaticLayoutResolver {
+    /// Register `layout` for its own type, package address included.
+    pub fn insert(&mut self, layout: MoveStructLayout) {
+        let key = (Some(layout.type_.address), layout.type_.module.to_string(), layout.type_.name.to_string());
+        self.layouts.insert(key, layout);
+    }
+
+    /// Register `layout` for its module and struct name in every package [`Self::with_packages`]
+    /// allows, whatever address its type carries.
+    pub fn insert_any_package(&mut self, layout: MoveStructLayout) {
+        let key = (None, layout.type_.module.to_string(), layout.type_.name.to_string());
+        self.layouts.insert(key, layout);
+    }
+
+    /// Limit the layouts registered with [`Self::insert_any_package`] to these packages.
+    pub fn with_packages(mut self, packages: Option<HashSet<AccountAddress>>) -> Self {
+        self.packages = packages;
+        self
+    }
+}
+
+impl LayoutResolver for StaticLayoutResolver {
+    fn resolve(&self, tag: &StructTag) -> anyhow::Result<Option<Arc<MoveStructLayout>>> {
+        let (module, name) = (tag.module.to_string(), tag.name.to_string());
+        let layout = self.layouts.get(&(Some(tag.address), module.clone(), name.clone())).or_else(|| {
+            let allowed = self.packages.as_ref().map_or(true, |packages| packages.contains(&tag.address));
+            allowed.then(|| self.layouts.get(&(None, module, name))).flatten()
+        });
+        Ok(layout.map(|layout| Arc::new(MoveStructLayout { type_: tag.clone(), fields: layout.fields.clone() })))
+    }
+}
+
+/// Memoizes another resolver per full `StructTag`, including negative results.
+pub struct CachingLayoutResolver<R> {
+    inner: R,
+    cache: RwLock<HashMap<StructTag, Option<Arc<MoveStructLayout>>>>,
+}
+
+impl<R: LayoutResolver> CachingLayoutResolver<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, cache: RwLock::new(HashMap::new()) }
+    }
+}
+
+impl<R: LayoutResolver> LayoutResolver for CachingLayoutResolver<R> {
+    fn resolve(&self, tag: &StructTag) -> anyhow::Result<Option<Arc<MoveStructLayout>>> {
+        if let Some(hit) = self.cache.read().unwrap().get(tag) {
+            return Ok(hit.clone());
+        }
+        // Errors are not cached so transient resolver failures can recover.
+        let layout = self.inner.resolve(tag)?;
+        self.cache.write().unwrap().insert(tag.clone(), layout.clone());
+        Ok(layout)
+    }
+}
+
+/// Bounds on decoded `contents_json`, so a maliciously nested or huge event can't bloat the
+/// table or break downstream parsers. Events over either limit are dead-lettered.
+#[derive(clap::Args, Debug, Clone, Copy)]
+pub struct JsonLimits {
+    /// Maximum nesting depth of a decoded contents_json value
+    #[clap(env, long, default_value_t = 64)]
+    pub contents_json_max_depth: usize,
+    /// Maximum serialized size of a decoded contents_json value, in bytes
+    #[clap(env, long, default_value_t = 1 << 20)]
+    pub contents_json_max_bytes: usize,
+}
+
+impl Default for JsonLimits {
+    fn default() -> Self {
+        Self { contents_json_max_depth: 64, contents_json_max_bytes: 1 << 20 }
+    }
+}
+
+impl JsonLimits {
+    /// On violation, returns a metric label and a description for the dead letter.
+    pub fn check(&self, json: &Value) -> Result<(), (&'static str, String)> {
+        let depth = json_depth(json);
+        if depth > self.contents_json_max_depth {
+            let max = self.contents_json_max_depth;
+            return Err(("json_depth", format!("contents_json depth {depth} exceeds {max}")));
+        }
+        let size = serde_json::to_vec(json).map_or(usize::MAX, |b| b.len());
+        if size > self.contents_json_max_bytes {
+            let max = self.contents_json_max_bytes;
+            return Err(("json_size", format!("contents_json size {size} bytes exceeds {max}")));
+        }
+        Ok(())
+    }
+}
+
+/// Nesting depth of `json` (scalars are 1), computed without recursion.
+fn json_depth(json: &Value) -> usize {
+    let mut max = 0;
+    let mut stack = vec![(json, 1)];
+    while let Some((value, depth)) = stack.pop() {
+        max = max.max(depth);
+        match value {
+            Value::Array(items) => stack.extend(items.iter().map(|v| (v, depth + 1))),
+            Value::Object(fields) => stack.extend(fields.values().map(|v| (v, depth + 1))),
+            _ => {}
+        }
+    }
+    max
+}
+
+/// Bound on the stored `contents_bcs`, so one huge event can't bloat a commit batch. Larger
+/// payloads are still decoded in full, then stored cut to the limit with
+/// `contents_bcs_truncated` set.
+#[derive(clap::Args, Debug, Clone, Copy)]
+pub struct BcsLimits {
+    /// Maximum stored size of an event's contents_bcs, in bytes
+    #[clap(env, long, default_value_t = 1 << 20)]
+    pub contents_bcs_max_bytes: usize,
+}
+
+impl Default for BcsLimits {
+    fn default() -> Self {
+        Self { contents_bcs_max_bytes: 1 << 20 }
+    }
+}
+
+impl BcsLimits {
+    /// `contents` cut to the limit, and whether anything was cut.
+    pub fn truncate(&self, contents: &[u8]) -> (Vec<u8>, bool) {
+        let len = contents.len().min(self.contents_bcs_max_bytes);
+        (contents[..len].to_vec(), len < contents.len())
+    }
+}
+
+/// Stands in for whatever was cut from a rendered type parameter, or for dropped trailing ones.
+pub const TYPE_PARAMS_TRUNCATED: &str = "...";
+
+/// Bounds on the `type_params` stored per event. Deeply nested generics are cut at the depth
+/// limit and the list at the size limit, both marked with [`TYPE_PARAMS_TRUNCATED`]; the full
+/// type is still findable through its hash.
+#[derive(clap::Args, Debug, Clone, Copy)]
+pub struct TypeParamLimits {
+    /// Maximum nesting depth of each stored type parameter
+    #[clap(env, long, default_value_t = 8)]
+    pub type_params_max_depth: usize,
+    /// Maximum serialized size of the stored type_params array, in bytes
+    #[clap(env, long, default_value_t = 4096)]
+    pub type_params_max_bytes: usize,
+}
+
+impl Default for TypeParamLimits {
+    fn default() -> Self {
+        Self { type_params_max_depth: 8, type_params_max_bytes: 4096 }
+    }
+}
+
+/// `type_params` as stored, with the hash of the full event type.
+pub struct TypeParams {
+    pub json: Value,
+    /// The same strings joined by [`type_params_key`].
+    pub key: String,
+    pub truncated: bool,
+    /// Hex SHA-256 of the event type's canonical string, type parameters included.
+    pub type_hash: String,
+}
+
+impl TypeParamLimits {
+    pub fn render(&self, type_tag: &StructTag) -> TypeParams {
+        let mut truncated = false;
+        let mut params = vec![];
+        // `[]` plus a comma per element after the first.
+        let mut size = 2;
+        for param in &type_tag.type_params {
+            let mut rendered = String::new();
+            truncated |= render_type_tag(param, self.type_params_max_depth, &mut rendered);
+            let len = serde_json::to_vec(&rendered).map_or(usize::MAX, |b| b.len()) + usize::from(!params.is_empty());
+            if size.saturating_add(len) > self.type_params_max_bytes {
+                truncated = true;
+                params.push(TYPE_PARAMS_TRUNCATED.to_string());
+                break;
+            }
+            size += len;
+            params.push(rendered);
+        }
+        TypeParams {
+            key: type_params_key(&params),
+            json: Value::from(params),
+            truncated,
+            type_hash: type_hash(type_tag),
+        }
+    }
+}
+
+/// `type_params` as one string for exact matches and grouping: the rendered parameters joined
+/// like a Move type argument list, e.g. `0x…::usdu::USDU, 0x…::sui::SUI`. Empty when there are
+/// none.
+pub fn type_params_key(params: &[String]) -> String {
+    params.join(", ")
+}
+
+/// Hex SHA-256 of `type_tag`'s canonical string, for exact-match lookups of truncated types.
+pub fn type_hash(type_tag: &StructTag) -> String {
+    Sha256::digest(type_tag.to_canonical_string(true).as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// The first two type parameters of `type_tag` as canonical strings (full 0x-prefixed
+/// addresses), for the `type_param_0`/`type_param_1` columns.
+pub fn leading_type_params(type_tag: &StructTag) -> (Option<String>, Option<String>) {
+    let mut params = type_tag.type_params.iter().map(|p| p.to_canonical_string(true));
+    (params.next(), params.next())
+}
+
+/// Render `tag` as its canonical string (full-length addresses, see
+/// [`crate::normalize_type_tag`]), replacing anything nested deeper than `depth` with
+/// [`TYPE_PARAMS_TRUNCATED`]. Returns whether anything was cut.
+fn render_type_tag(tag: &TypeTag, depth: usize, out: &mut String) -> bool {
+    if depth == 0 {
+        out.push_str(TYPE_PARAMS_TRUNCATED);
+        return true;
+    }
+    if type_depth(tag) <= depth {
+        out.push_str(&tag.to_canonical_string(true));
+        return false;
+    }
+    match tag {
+        TypeTag::Vector(inner) => {
+            out.push_str("vector<");
+            let truncated = render_type_tag(inner, depth - 1, out);
+            out.push('>');
+            truncated
+        }
+        TypeTag::Struct(s) => {
+            let head = StructTag {
+                address: s.address,
+                module: s.module.clone(),
+                name: s.name.clone(),
+                type_params: vec![],
+            };
+            out.push_str(&head.to_canonical_string(true));
+            let mut truncated = false;
+            out.push('<');
+            for (i, param) in s.type_params.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                truncated |= render_type_tag(param, depth - 1, out);
+            }
+            out.push('>');
+            truncated
+        }
+        primitive => {
+            out.push_str(&primitive.to_canonical_string(true));
+            false
+        }
+    }
+}
+
+/// Nesting depth of `tag` (primitives are 1), computed without recursion.
+fn type_depth(tag: &TypeTag) -> usize {
+    let mut max = 0;
+    let mut stack = vec![(tag, 1)];
+    while let Some((tag, depth)) = stack.pop() {
+        max = max.max(depth);
+        match tag {
+            TypeTag::Vector(inner) => stack.push((inner, depth + 1)),
+            TypeTag::Struct(s) => stack.extend(s.type_params.iter().map(|t| (t, depth + 1))),
+            _ => {}
+        }
+    }
+    max
+}
+
+/// Deserialize BCS `contents` with `layout` and render it as JSON.
+pub fn decode_to_json(contents: &[u8], layout: &MoveStructLayout) -> anyhow::Result<Value> {
+    let value = MoveStruct::simple_deserialize(contents, layout)?;
+    Ok(struct_to_json(value))
+}
+
+/// The object an event concerns: the value of the first top-level `0x2::object::ID` field of
+/// `json`, the event's contents decoded with `layout`. Sui events don't record an emitting
+/// object, but Unxversal events lead with the pool, market or vault they are about. None if the
+/// layout has no such field.
+pub fn emitter_object(layout: &MoveStructLayout, json: &Value) -> Option<String> {
+    let field = layout
+        .fields
+        .iter()
+        .find(|f| matches!(&f.layout, MoveTypeLayout::Struct(s) if is_object_id(&s.type_)))?;
+    json.get(field.name.as_str())?.as_str().map(str::to_string)
+}
+
+fn struct_to_json(s: MoveStruct) -> Value {
+    let tag = &s.type_;
+    // Well-known wrappers are flattened to their natural JSON form.
+    if is_std_string(tag) || is_type_name(tag) || is_object_id(tag) {
+        if let Some((_, inner)) = s.fields.into_iter().next() {
+            return match inner {
+                MoveValue::Vector(bytes) => Value::String(bytes_to_string(bytes)),
+                other => value_to_json(other),
+            };
+        }
+        return Value::Null;
+    }
+    let mut map = Map::new();
+    for (name, value) in s.fields {
+        map.insert(name.to_string(), value_to_json(value));
+    }
+    Value::Object(map)
+}
+
+/// Integers wider than 32 bits are rendered as strings to avoid JSON precision loss.
+fn value_to_json(value: MoveValue) -> Value {
+    match value {
+        MoveValue::Bool(b) => Value::Bool(b),
+        MoveValue::U8(n) => Value::from(n),
+        MoveValue::U16(n) => Value::from(n),
+        MoveValue::U32(n) => Value::from(n),
+        MoveValue::U64(n) => Value::String(n.to_string()),
+        MoveValue::U128(n) => Value::String(n.to_string()),
+        MoveValue::U256(n) => Value::String(n.to_string()),
+        MoveValue::Address(a) | MoveValue::Signer(a) => Value::String(a.to_canonical_string(true)),
+        MoveValue::Vector(values) => Value::Array(values.into_iter().map(value_to_json).collect()),
+        MoveValue::Struct(s) => struct_to_json(s),
+        MoveValue::Variant(v) => {
+            let mut map = Map::new();
+            map.insert("@variant".to_string(), Value::String(v.variant_name.to_string()));
+            for (name, value) in v.fields {
+                map.insert(name.to_string(), value_to_json(value));
+            }
+            Value::Object(map)
+        }
+    }
+}
+
+fn bytes_to_string(bytes: Vec<MoveValue>) -> String {
+    let bytes: Vec<u8> = bytes
+        .into_iter()
+        .filter_map(|b| match b {
+            MoveValue::U8(b) => Some(b),
+            _ => None,
+        })
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn is_std_string(tag: &StructTag) -> bool {
+    tag.address == AccountAddress::ONE
+        && (tag.module.as_str() == "string" || tag.module.as_str() == "ascii")
+        && tag.name.as_str() == "String"
+}
+
+fn is_type_name(tag: &StructTag) -> bool {
+    tag.address == AccountAddress::ONE && tag.module.as_str() == "type_name" && tag.name.as_str() == "TypeName"
+}
+
+fn is_object_id(tag: &StructTag) -> bool {
+    tag.address == AccountAddress::TWO && tag.module.as_str() == "object" && tag.name.as_str() == "ID"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use move_core_types::annotated_value::MoveFieldLayout;
+    use move_core_types::identifier::Identifier;
+    use serde_json::json;
+
+    fn address(hex: &str) -> AccountAddress {
+        AccountAddress::from_hex_literal(hex).unwrap()
+    }
+
+    fn tag(address: AccountAddress, module: &str, name: &str) -> StructTag {
+        StructTag {
+            address,
+            module: Identifier::new(module).unwrap(),
+            name: Identifier::new(name).unwrap(),
+            type_params: vec![],
+        }
+    }
+
+    /// `dex::Filled { pool_id: ID, amount: u64 }` of `package`.
+    fn filled_layout(package: AccountAddress) -> MoveStructLayout {
+        let id = MoveTypeLayout::Struct(Box::new(MoveStructLayout {
+            type_: tag(AccountAddress::TWO, "object", "ID"),
+            fields: vec![MoveFieldLayout::new(Identifier::new("bytes").unwrap(), MoveTypeLayout::Address)],
+        }));
+        MoveStructLayout {
+            type_: tag(package, "dex", "Filled"),
+            fields: vec![
+                MoveFieldLayout::new(Identifier::new("pool_id").unwrap(), id),
+                MoveFieldLayout::new(Identifier::new("amount").unwrap(), MoveTypeLayout::U64),
+            ],
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Filled {
+        pool_id: AccountAddress,
+        amount: u64,
+    }
+
+    #[test]
+    fn decodes_with_an_injected_layout() {
+        let package = address("0xa11ce");
+        let mut resolver = StaticLayoutResolver::default();
+        resolver.insert(filled_layout(package));
+        let resolver = CachingLayoutResolver::new(resolver);
+
+        let layout = resolver.resolve(&tag(package, "dex", "Filled")).unwrap().unwrap();
+        let pool = address("0xb0b");
+        let bcs = bcs::to_bytes(&Filled { pool_id: pool, amount: u64::MAX }).unwrap();
+        let json = decode_to_json(&bcs, &layout).unwrap();
+        assert_eq!(json, json!({ "pool_id": pool.to_canonical_string(true), "amount": u64::MAX.to_string() }));
+        assert_eq!(emitter_object(&layout, &json), Some(pool.to_canonical_string(true)));
+    }
+
+    #[test]
+    fn package_layouts_only_resolve_for_their_package() {
+        let mut resolver = StaticLayoutResolver::default();
+        resolver.insert(filled_layout(address("0xa11ce")));
+        assert!(resolver.resolve(&tag(address("0xa11ce"), "dex", "Filled")).unwrap().is_some());
+        assert!(resolver.resolve(&tag(address("0xe11e"), "dex", "Filled")).unwrap().is_none());
+        assert!(resolver.resolve(&tag(address("0xa11ce"), "dex", "Other")).unwrap().is_none());
+    }
+
+    #[test]
+    fn any_package_layouts_follow_the_allowlist() {
+        let (allowed, foreign) = (address("0xa11ce"), address("0xe11e"));
+        let mut resolver = StaticLayoutResolver::default().with_packages(Some(HashSet::from([allowed])));
+        resolver.insert_any_package(filled_layout(AccountAddress::ZERO));
+        let layout = resolver.resolve(&tag(allowed, "dex", "Filled")).unwrap().unwrap();
+        assert_eq!(layout.type_.address, allowed);
+        assert!(resolver.resolve(&tag(foreign, "dex", "Filled")).unwrap().is_none());
+
+        let mut resolver = StaticLayoutResolver::default();
+        resolver.insert_any_package(filled_layout(AccountAddress::ZERO));
+        assert!(resolver.resolve(&tag(foreign, "dex", "Filled")).unwrap().is_some());
+    }
+
+    #[test]
+    fn package_layouts_take_precedence() {
+        let package = address("0xa11ce");
+        let mut resolver = StaticLayoutResolver::default().with_packages(Some(HashSet::new()));
+        resolver.insert_any_package(filled_layout(AccountAddress::ZERO));
+        let mut own = filled_layout(package);
+        own.fields.pop();
+        resolver.insert(own);
+        let layout = resolver.resolve(&tag(package, "dex", "Filled")).unwrap().unwrap();
+        assert_eq!(layout.fields.len(), 1);
+    }
+
+    #[test]
+    fn json_limits_bound_depth_then_size() {
+        let limits = JsonLimits { contents_json_max_depth: 2, contents_json_max_bytes: 20 };
+        assert_eq!(limits.check(&json!("scalar")), Ok(()));
+        assert_eq!(limits.check(&json!({ "a": 1 })), Ok(()));
+        assert_eq!(
+            limits.check(&json!({ "a": [1] })),
+            Err(("json_depth", "contents_json depth 3 exceeds 2".to_string()))
+        );
+        assert_eq!(
+            limits.check(&json!({ "a": "x".repeat(20) })),
+            Err(("json_size", "contents_json size 28 bytes exceeds 20".to_string()))
+        );
+    }
+
+    #[test]
+    fn bcs_limits_truncate_only_oversized_contents() {
+        let limits = BcsLimits { contents_bcs_max_bytes: 4 };
+        assert_eq!(limits.truncate(&[]), (vec![], false));
+        assert_eq!(limits.truncate(&[1, 2, 3, 4]), (vec![1, 2, 3, 4], false));
+        assert_eq!(limits.truncate(&[1, 2, 3, 4, 5]), (vec![1, 2, 3, 4], true));
+    }
+
+    #[test]
+    fn type_params_render_canonically() {
+        let tag = crate::parse_struct_tag("0xa::pool::Pool<0x2::sui::SUI, u64>").unwrap();
+        let rendered = TypeParamLimits::default().render(&tag);
+        let sui = format!("{}::sui::SUI", AccountAddress::TWO.to_canonical_string(true));
+        assert_eq!(rendered.json, json!([sui, "u64"]));
+        assert_eq!(rendered.key, format!("{sui}, u64"));
+        assert!(!rendered.truncated);
+        assert_eq!(rendered.type_hash, type_hash(&tag));
+
+        let untyped = TypeParamLimits::default().render(&crate::parse_struct_tag("0xa::pool::Pool").unwrap());
+        assert_eq!((untyped.json, untyped.key, untyped.truncated), (json!([]), String::new(), false));
+    }
+
+    #[test]
+    fn type_params_are_cut_at_the_depth_limit() {
+        let tag = crate::parse_struct_tag("0xa::pool::Pool<vector<vector<u8>>, u8>").unwrap();
+        let limits = TypeParamLimits { type_params_max_depth: 2, ..TypeParamLimits::default() };
+        let rendered = limits.render(&tag);
+        assert_eq!(rendered.json, json!(["vector<vector<...>>", "u8"]));
+        assert!(rendered.truncated);
+        // The hash still identifies the full type.
+        assert_eq!(rendered.type_hash, type_hash(&tag));
+    }
+
+    #[test]
+    fn type_params_are_cut_at_the_size_limit() {
+        let tag = crate::parse_struct_tag("0xa::pool::Pool<0x2::sui::SUI, 0x2::sui::SUI, 0x2::sui::SUI>").unwrap();
+        let limits = TypeParamLimits { type_params_max_bytes: 100, ..TypeParamLimits::default() };
+        let rendered = limits.render(&tag);
+        let sui = format!("{}::sui::SUI", AccountAddress::TWO.to_canonical_string(true));
+        assert_eq!(rendered.json, json!([sui, TYPE_PARAMS_TRUNCATED]));
+        assert!(rendered.truncated);
+    }
+}
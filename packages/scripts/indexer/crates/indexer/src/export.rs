@@ -0,0 +1,384 @@
+use arrow_array::{ArrayRef, BinaryArray, BooleanArray, Int64Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use clap::ValueEnum;
+use diesel::{ExpressionMethods, QueryDsl, SelectableHelper};
+use diesel_async::RunQueryDsl;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::io::BufWriter;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use sui_pg_db::Db;
+use tracing::info;
+
+use crate::handlers::unxv_events_handler::UnxvEventsHandler;
+use crate::ModulesFilter;
+use unxv_schema::models::UnxvEvent;
+use unxv_schema::query::{after, EventCursor};
+use unxv_schema::schema::unxv_events;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ExportFormat {
+    /// One row per event; `contents_bcs` as hex, JSON columns as JSON text
+    Csv,
+    /// Snappy-compressed; `contents_bcs` as binary, JSON columns as JSON text
+    Parquet,
+}
+
+/// Outcome of [`run`].
+#[derive(Debug, Default)]
+pub struct ExportSummary {
+    /// Rows read in the checkpoint range.
+    pub scanned: usize,
+    /// Rows that passed the filters and were written.
+    pub exported: usize,
+}
+
+/// Column order of every export, matching the `unxv_events` columns.
+pub const COLUMNS: &[&str] = &[
+    "digest",
+    "event_index",
+    "sender",
+    "checkpoint",
+    "checkpoint_timestamp_ms",
+    "tx_timestamp_ms",
+    "package",
+    "module",
+    "event_type",
+    "type_params",
+    "type_params_key",
+    "type_params_truncated",
+    "type_hash",
+    "type_param_0",
+    "type_param_1",
+    "contents_bcs",
+    "contents_json",
+    "gas_used",
+    "tx_success",
+    "call_packages",
+    "is_new_sender",
+    "indexer_version",
+    "emitter_object",
+    "contents_bcs_truncated",
+    "event_type_full",
+];
+
+/// Write the `unxv_events` rows of the `checkpoints` that pass `handler`'s filters to
+/// `output`, oldest first. Rows are read `batch_size` at a time, so memory stays bounded
+/// whatever the range. The file is written next to `output` and renamed over it once
+/// complete, so a failed export never leaves a truncated file behind.
+///
+/// `modules` narrows the query itself; the handler's other filters are applied to each row.
+/// Only the default schema is read, not `--module-schemas` targets.
+pub async fn run(
+    db: &Db,
+    handler: &UnxvEventsHandler,
+    modules: &ModulesFilter,
+    checkpoints: RangeInclusive<u64>,
+    output: &Path,
+    format: ExportFormat,
+    batch_size: usize,
+) -> anyhow::Result<ExportSummary> {
+    let partial = partial_path(output);
+    let mut sink: Box<dyn Sink> = match format {
+        ExportFormat::Csv => Box::new(CsvSink::create(&partial)?),
+        ExportFormat::Parquet => Box::new(ParquetSink::create(&partial)?),
+    };
+
+    let mut conn = db.connect().await?;
+    let mut summary = ExportSummary::default();
+    let mut cursor: Option<EventCursor> = None;
+    loop {
+        let mut query = unxv_events::table
+            .filter(unxv_events::checkpoint.between(*checkpoints.start() as i64, *checkpoints.end() as i64))
+            .into_boxed();
+        if let ModulesFilter::Only(modules) = modules {
+            query = query.filter(unxv_events::module.eq_any(modules.iter().cloned().collect::<Vec<_>>()));
+        }
+        if let Some(cursor) = &cursor {
+            query = after(query, cursor);
+        }
+        let batch: Vec<UnxvEvent> = query
+            .order((unxv_events::checkpoint, unxv_events::digest, unxv_events::event_index))
+            .limit(batch_size as i64)
+            .select(UnxvEvent::as_select())
+            .load(&mut conn)
+            .await?;
+        let Some(tail) = batch.last() else { break };
+        cursor = Some(EventCursor::from(tail));
+        summary.scanned += batch.len();
+
+        let accepted: Vec<UnxvEvent> = batch.into_iter().filter(|e| handler.accepts(e)).collect();
+        sink.write(&accepted)?;
+        summary.exported += accepted.len();
+        info!(scanned = summary.scanned, exported = summary.exported, "Exporting events");
+    }
+
+    sink.finish()?;
+    std::fs::rename(&partial, output)?;
+    Ok(summary)
+}
+
+fn partial_path(output: &Path) -> PathBuf {
+    let mut name = output.file_name().unwrap_or_default().to_os_string();
+    name.push(".partial");
+    output.with_file_name(name)
+}
+
+trait Sink {
+    fn write(&mut self, events: &[UnxvEvent]) -> anyhow::Result<()>;
+    fn finish(self: Box<Self>) -> anyhow::Result<()>;
+}
+
+struct CsvSink {
+    writer: csv::Writer<BufWriter<File>>,
+}
+
+impl CsvSink {
+    fn create(path: &Path) -> anyhow::Result<Self> {
+        let mut writer = csv::Writer::from_writer(BufWriter::new(File::create(path)?));
+        writer.write_record(COLUMNS)?;
+        Ok(Self { writer })
+    }
+}
+
+impl Sink for CsvSink {
+    fn write(&mut self, events: &[UnxvEvent]) -> anyhow::Result<()> {
+        for e in events {
+            let opt = |v: Option<String>| v.unwrap_or_default();
+            self.writer.write_record([
+                e.digest.clone(),
+                e.event_index.to_string(),
+                e.sender.clone(),
+                e.checkpoint.to_string(),
+                e.checkpoint_timestamp_ms.to_string(),
+                opt(e.tx_timestamp_ms.map(|v| v.to_string())),
+                e.package.clone(),
+                e.module.clone(),
+                e.event_type.clone(),
+                e.type_params.to_string(),
+                opt(e.type_params_key.clone()),
+                e.type_params_truncated.to_string(),
+                opt(e.type_hash.clone()),
+                opt(e.type_param_0.clone()),
+                opt(e.type_param_1.clone()),
+                hex(&e.contents_bcs),
+                opt(e.contents_json.as_ref().map(|v| v.to_string())),
+                opt(e.gas_used.map(|v| v.to_string())),
+                opt(e.tx_success.map(|v| v.to_string())),
+                opt(e.call_packages.as_ref().map(|v| v.to_string())),
+                opt(e.is_new_sender.map(|v| v.to_string())),
+                opt(e.indexer_version.clone()),
+                opt(e.emitter_object.clone()),
+                e.contents_bcs_truncated.to_string(),
+                opt(e.event_type_full.clone()),
+            ])?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+struct ParquetSink {
+    writer: ArrowWriter<File>,
+    schema: SchemaRef,
+}
+
+impl ParquetSink {
+    fn create(path: &Path) -> anyhow::Result<Self> {
+        let schema = parquet_schema();
+        let props = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+        let writer = ArrowWriter::try_new(File::create(path)?, schema.clone(), Some(props))?;
+        Ok(Self { writer, schema })
+    }
+}
+
+impl Sink for ParquetSink {
+    fn write(&mut self, events: &[UnxvEvent]) -> anyhow::Result<()> {
+        if !events.is_empty() {
+            self.writer.write(&record_batch(&self.schema, events)?)?;
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> anyhow::Result<()> {
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+/// Arrow schema of [`COLUMNS`]: JSON columns are strings, `contents_bcs` is binary.
+pub fn parquet_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("digest", DataType::Utf8, false),
+        Field::new("event_index", DataType::Int64, false),
+        Field::new("sender", DataType::Utf8, false),
+        Field::new("checkpoint", DataType::Int64, false),
+        Field::new("checkpoint_timestamp_ms", DataType::Int64, false),
+        Field::new("tx_timestamp_ms", DataType::Int64, true),
+        Field::new("package", DataType::Utf8, false),
+        Field::new("module", DataType::Utf8, false),
+        Field::new("event_type", DataType::Utf8, false),
+        Field::new("type_params", DataType::Utf8, false),
+        Field::new("type_params_key", DataType::Utf8, true),
+        Field::new("type_params_truncated", DataType::Boolean, false),
+        Field::new("type_hash", DataType::Utf8, true),
+        Field::new("type_param_0", DataType::Utf8, true),
+        Field::new("type_param_1", DataType::Utf8, true),
+        Field::new("contents_bcs", DataType::Binary, false),
+        Field::new("contents_json", DataType::Utf8, true),
+        Field::new("gas_used", DataType::Int64, true),
+        Field::new("tx_success", DataType::Boolean, true),
+        Field::new("call_packages", DataType::Utf8, true),
+        Field::new("is_new_sender", DataType::Boolean, true),
+        Field::new("indexer_version", DataType::Utf8, true),
+        Field::new("emitter_object", DataType::Utf8, true),
+        Field::new("contents_bcs_truncated", DataType::Boolean, false),
+        Field::new("event_type_full", DataType::Utf8, true),
+    ]))
+}
+
+/// `events` as one Arrow batch of [`parquet_schema`].
+pub fn record_batch(schema: &SchemaRef, events: &[UnxvEvent]) -> anyhow::Result<RecordBatch> {
+    let text = |f: fn(&UnxvEvent) -> String| -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(events.iter().map(f)))
+    };
+    let opt_text = |f: fn(&UnxvEvent) -> Option<String>| -> ArrayRef {
+        Arc::new(events.iter().map(f).collect::<StringArray>())
+    };
+    let int = |f: fn(&UnxvEvent) -> i64| -> ArrayRef { Arc::new(Int64Array::from_iter_values(events.iter().map(f))) };
+    let opt_int = |f: fn(&UnxvEvent) -> Option<i64>| -> ArrayRef {
+        Arc::new(events.iter().map(f).collect::<Int64Array>())
+    };
+    let opt_bool = |f: fn(&UnxvEvent) -> Option<bool>| -> ArrayRef {
+        Arc::new(events.iter().map(f).collect::<BooleanArray>())
+    };
+    let columns: Vec<ArrayRef> = vec![
+        text(|e| e.digest.clone()),
+        int(|e| e.event_index),
+        text(|e| e.sender.clone()),
+        int(|e| e.checkpoint),
+        int(|e| e.checkpoint_timestamp_ms),
+        opt_int(|e| e.tx_timestamp_ms),
+        text(|e| e.package.clone()),
+        text(|e| e.module.clone()),
+        text(|e| e.event_type.clone()),
+        text(|e| e.type_params.to_string()),
+        opt_text(|e| e.type_params_key.clone()),
+        opt_bool(|e| Some(e.type_params_truncated)),
+        opt_text(|e| e.type_hash.clone()),
+        opt_text(|e| e.type_param_0.clone()),
+        opt_text(|e| e.type_param_1.clone()),
+        Arc::new(BinaryArray::from_iter_values(events.iter().map(|e| e.contents_bcs.as_slice()))),
+        opt_text(|e| e.contents_json.as_ref().map(|v| v.to_string())),
+        opt_int(|e| e.gas_used),
+        opt_bool(|e| e.tx_success),
+        opt_text(|e| e.call_packages.as_ref().map(|v| v.to_string())),
+        opt_bool(|e| e.is_new_sender),
+        opt_text(|e| e.indexer_version.clone()),
+        opt_text(|e| e.emitter_object.clone()),
+        opt_bool(|e| Some(e.contents_bcs_truncated)),
+        opt_text(|e| e.event_type_full.clone()),
+    ];
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{stored_event, TestDb};
+    use arrow_array::Array;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    /// Three checkpoints of dex and lending events; the range exported is the first two.
+    async fn seeded() -> (TestDb, Vec<UnxvEvent>) {
+        let db = TestDb::new().await;
+        let events = vec![
+            UnxvEvent {
+                contents_bcs: vec![0xde, 0xad],
+                contents_json: Some(serde_json::json!({ "amount": "5" })),
+                type_params: serde_json::json!(["0x2::sui::SUI"]),
+                gas_used: Some(-3),
+                ..stored_event("a", "dex", 10, 10_000)
+            },
+            UnxvEvent { event_index: 1, ..stored_event("a", "lending", 10, 10_000) },
+            stored_event("b", "dex", 11, 11_000),
+            stored_event("c", "dex", 12, 12_000),
+        ];
+        db.insert_events(&events).await;
+        (db, events[..3].to_vec())
+    }
+
+    async fn export(db: &TestDb, output: &Path, format: ExportFormat) -> ExportSummary {
+        let handler = UnxvEventsHandler::new(ModulesFilter::All, None).unwrap();
+        // Batches of one, so the export pages through the range.
+        run(db.db(), &handler, &ModulesFilter::All, 10..=11, output, format, 1).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn parquet_exports_read_back() {
+        let (db, expected) = seeded().await;
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("events.parquet");
+        let summary = export(&db, &output, ExportFormat::Parquet).await;
+        assert_eq!((summary.scanned, summary.exported), (3, 3));
+        assert!(!partial_path(&output).exists());
+
+        // One batch holding every row, across the row groups the export wrote.
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&output).unwrap())
+            .unwrap()
+            .with_batch_size(expected.len())
+            .build()
+            .unwrap();
+        let read = reader.next().unwrap().unwrap();
+        assert!(reader.next().is_none());
+        let names: Vec<&str> = read.schema_ref().fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(names, COLUMNS);
+        assert_eq!(read.columns(), record_batch(&parquet_schema(), &expected).unwrap().columns());
+
+        let bcs = read.column_by_name("contents_bcs").unwrap().as_any().downcast_ref::<BinaryArray>().unwrap();
+        assert_eq!(bcs.value(0), [0xde, 0xad]);
+        let gas = read.column_by_name("gas_used").unwrap().as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!((gas.value(0), gas.is_null(1)), (-3, true));
+    }
+
+    #[tokio::test]
+    async fn csv_exports_read_back() {
+        let (db, expected) = seeded().await;
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("events.csv");
+        let summary = export(&db, &output, ExportFormat::Csv).await;
+        assert_eq!((summary.scanned, summary.exported), (3, 3));
+
+        let mut reader = csv::Reader::from_path(&output).unwrap();
+        assert_eq!(reader.headers().unwrap().iter().collect::<Vec<_>>(), COLUMNS);
+        let records: Vec<csv::StringRecord> = reader.records().collect::<Result<_, _>>().unwrap();
+        assert_eq!(records.len(), expected.len());
+        let field = |record: &csv::StringRecord, column: &str| {
+            record[COLUMNS.iter().position(|c| *c == column).unwrap()].to_string()
+        };
+        for (record, event) in records.iter().zip(&expected) {
+            assert_eq!(field(record, "digest"), event.digest);
+            assert_eq!(field(record, "event_index"), event.event_index.to_string());
+            assert_eq!(field(record, "module"), event.module);
+            assert_eq!(field(record, "checkpoint"), event.checkpoint.to_string());
+        }
+        let first = &records[0];
+        assert_eq!(field(first, "contents_bcs"), "dead");
+        let json: serde_json::Value = serde_json::from_str(&field(first, "contents_json")).unwrap();
+        assert_eq!(json, serde_json::json!({ "amount": "5" }));
+        assert_eq!(field(first, "type_params"), r#"["0x2::sui::SUI"]"#);
+        assert_eq!((field(first, "gas_used").as_str(), field(&records[1], "gas_used").as_str()), ("-3", ""));
+    }
+}
@@ -0,0 +1,281 @@
+use anyhow::Context;
+use diesel::sql_types::{BigInt, Nullable};
+use diesel::QueryableByName;
+use diesel_async::RunQueryDsl;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use sui_indexer_alt_framework::pipeline::concurrent::Handler;
+use sui_indexer_alt_framework::pipeline::Processor;
+use sui_pg_db::Db;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::alerts::{Alert, AlertSender, Severity};
+use crate::handlers::unxv_events_handler::UnxvEventsHandler;
+use crate::inspect::CheckpointSource;
+use crate::metrics::UnxvMetrics;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct GapArgs {
+    /// Seconds between checks of unxv_checkpoints for checkpoints below the unxv_events
+    /// watermark that were never processed (0 = disabled)
+    #[clap(env, long, default_value_t = 0)]
+    pub gap_check_interval_secs: u64,
+}
+
+impl GapArgs {
+    /// None when the check is disabled.
+    pub fn config(&self) -> Option<GapConfig> {
+        (self.gap_check_interval_secs > 0).then(|| GapConfig {
+            interval: Duration::from_secs(self.gap_check_interval_secs),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GapConfig {
+    pub interval: Duration,
+}
+
+/// Inclusive run of checkpoints missing from `unxv_checkpoints`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, QueryableByName)]
+pub struct Gap {
+    #[diesel(sql_type = BigInt)]
+    pub first: i64,
+    #[diesel(sql_type = BigInt)]
+    pub last: i64,
+}
+
+impl Gap {
+    pub fn checkpoints(&self) -> u64 {
+        (self.last - self.first + 1) as u64
+    }
+}
+
+/// Result of [`find`]: the range that was checked and the gaps in it, in order.
+#[derive(Debug, Default)]
+pub struct GapReport {
+    /// None when there was nothing to check: no bookkeeping rows or no watermark yet.
+    pub range: Option<(u64, u64)>,
+    pub gaps: Vec<Gap>,
+}
+
+impl GapReport {
+    pub fn missing(&self) -> u64 {
+        self.gaps.iter().map(Gap::checkpoints).sum()
+    }
+}
+
+#[derive(QueryableByName)]
+struct Bounds {
+    #[diesel(sql_type = Nullable<BigInt>)]
+    first: Option<i64>,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    last: Option<i64>,
+}
+
+/// The default range: from the first recorded checkpoint (the table only covers what was
+/// processed since it was added) to the `unxv_events` watermark.
+const BOUNDS: &str = "\
+    SELECT (SELECT min(checkpoint) FROM unxv_checkpoints) AS first, \
+           (SELECT checkpoint_hi_inclusive FROM watermarks WHERE pipeline = 'unxv_events') AS last";
+
+/// Runs of `$1..=$2` without a row: after each row up to the next one (or past `$2`), plus the
+/// stretch before the first row.
+const FIND_GAPS: &str = "\
+    SELECT first, last FROM ( \
+        SELECT checkpoint + 1 AS first, \
+               lead(checkpoint, 1, $2 + 1) OVER (ORDER BY checkpoint) - 1 AS last \
+        FROM unxv_checkpoints WHERE checkpoint BETWEEN $1 AND $2 \
+        UNION ALL \
+        SELECT $1, coalesce(min(checkpoint), $2 + 1) - 1 \
+        FROM unxv_checkpoints WHERE checkpoint BETWEEN $1 AND $2 \
+    ) g \
+    WHERE first <= last \
+    ORDER BY first";
+
+/// Checkpoints in `first..=last` that `unxv_events` never recorded in `unxv_checkpoints`. Either
+/// bound defaults to [`BOUNDS`]. Checkpoints skipped on purpose (`--pipeline-range`,
+/// `--out-of-order-policy skip`) show up as gaps too.
+pub async fn find(db: &Db, first: Option<u64>, last: Option<u64>) -> anyhow::Result<GapReport> {
+    let mut conn = db.connect().await?;
+    let bounds: Bounds = diesel::sql_query(BOUNDS).get_result(&mut conn).await?;
+    let first = first.or(bounds.first.map(|c| c as u64));
+    let last = last.or(bounds.last.map(|c| c.max(0) as u64));
+    let (Some(first), Some(last)) = (first, last) else {
+        return Ok(GapReport::default());
+    };
+    anyhow::ensure!(first <= last, "Empty checkpoint range {first}..={last}");
+    let gaps = diesel::sql_query(FIND_GAPS)
+        .bind::<BigInt, _>(first as i64)
+        .bind::<BigInt, _>(last as i64)
+        .load(&mut conn)
+        .await?;
+    Ok(GapReport { range: Some((first, last)), gaps })
+}
+
+/// Outcome of [`repair`].
+#[derive(Debug, Default)]
+pub struct RepairSummary {
+    pub checkpoints: u64,
+    pub rows: usize,
+}
+
+/// Re-ingest the checkpoints of `gaps` from `source` with `handler`, which should emit
+/// checkpoint rows, committing each through the pipeline's own commit. Watermarks are left
+/// alone.
+pub async fn repair(
+    db: &Db,
+    source: &CheckpointSource,
+    handler: &UnxvEventsHandler,
+    gaps: &[Gap],
+) -> anyhow::Result<RepairSummary> {
+    let client = reqwest::Client::new();
+    let mut conn = db.connect().await?;
+    let mut summary = RepairSummary::default();
+    for gap in gaps {
+        for sequence_number in gap.first as u64..=gap.last as u64 {
+            let checkpoint = source
+                .fetch(&client, sequence_number)
+                .await
+                .with_context(|| format!("Failed to fetch checkpoint {sequence_number}"))?;
+            let rows = handler.process(&Arc::new(checkpoint))?;
+            summary.rows += UnxvEventsHandler::commit(&rows, &mut conn).await?;
+            summary.checkpoints += 1;
+        }
+        info!(first = gap.first, last = gap.last, "Repaired gap");
+    }
+    Ok(summary)
+}
+
+/// Count gaps below the watermark on an interval until cancelled.
+pub async fn run(
+    db: Db,
+    config: GapConfig,
+    metrics: Arc<UnxvMetrics>,
+    alerts: AlertSender,
+    cancel: CancellationToken,
+) {
+    let mut interval = tokio::time::interval(config.interval);
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = interval.tick() => {}
+        }
+        match find(&db, None, None).await {
+            Ok(report) => {
+                metrics.checkpoint_gaps.set(report.gaps.len() as i64);
+                if let Some(gap) = report.gaps.first() {
+                    warn!(
+                        gaps = report.gaps.len(),
+                        missing = report.missing(),
+                        "Checkpoints missing from unxv_checkpoints, the first {}..={}; see `verify` and `repair`",
+                        gap.first,
+                        gap.last
+                    );
+                }
+            }
+            Err(e) => alerts.send(Alert::new(
+                "gaps",
+                Severity::Warning,
+                "gaps:check_failed",
+                format!("Checkpoint gap check failed: {e:#}"),
+            )),
+        }
+    }
+}
+
+/// How far past a checkpoint the stream may get before that checkpoint counts as missing
+/// rather than late. Checkpoints are processed several at a time, so they reach `process`
+/// slightly out of order.
+pub(crate) const REORDER_WINDOW: u64 = 1_000;
+
+/// Watches the checkpoints one pipeline's `process` receives for ones the stream skipped,
+/// without touching the database. A checkpoint is reported once one `REORDER_WINDOW` beyond it
+/// has arrived in its place. Checkpoints before the first one received aren't tracked.
+#[derive(Default)]
+pub struct StreamGaps {
+    seen: Mutex<Option<Seen>>,
+}
+
+struct Seen {
+    /// First checkpoint not received yet.
+    next: u64,
+    /// Checkpoints received past `next`.
+    ahead: BTreeSet<u64>,
+}
+
+impl StreamGaps {
+    /// Record `checkpoint` and return the inclusive runs it shows to be missing, in order.
+    pub fn observe(&self, checkpoint: u64) -> Vec<(u64, u64)> {
+        let mut seen = self.seen.lock().unwrap();
+        let Some(seen) = seen.as_mut() else {
+            *seen = Some(Seen { next: checkpoint + 1, ahead: BTreeSet::new() });
+            return vec![];
+        };
+        // Replays below the frontier were either seen already or predate tracking.
+        if checkpoint < seen.next {
+            return vec![];
+        }
+        seen.ahead.insert(checkpoint);
+        let mut gaps = vec![];
+        loop {
+            while seen.ahead.first() == Some(&seen.next) {
+                seen.ahead.pop_first();
+                seen.next += 1;
+            }
+            match (seen.ahead.first(), seen.ahead.last()) {
+                (Some(&first), Some(&last)) if last - seen.next >= REORDER_WINDOW => {
+                    gaps.push((seen.next, first - 1));
+                    seen.next = first;
+                }
+                _ => return gaps,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_order_streams_have_no_gaps() {
+        let gaps = StreamGaps::default();
+        for checkpoint in 100..2_200 {
+            assert!(gaps.observe(checkpoint).is_empty());
+        }
+    }
+
+    #[test]
+    fn late_checkpoints_within_the_window_are_not_gaps() {
+        let gaps = StreamGaps::default();
+        for checkpoint in [10, 13, 12, 11, 1_010] {
+            assert!(gaps.observe(checkpoint).is_empty());
+        }
+        // Before the first checkpoint received, so not tracked.
+        assert!(gaps.observe(5).is_empty());
+    }
+
+    #[test]
+    fn gaps_are_reported_once_past_the_window() {
+        let gaps = StreamGaps::default();
+        gaps.observe(10);
+        assert!(gaps.observe(13).is_empty());
+        assert!(gaps.observe(1_010).is_empty());
+        assert_eq!(gaps.observe(1_011), [(11, 12)]);
+        // Arriving after being reported doesn't report or reopen anything.
+        assert!(gaps.observe(11).is_empty());
+        assert!(gaps.observe(1_012).is_empty());
+    }
+
+    #[test]
+    fn one_checkpoint_can_reveal_several_gaps() {
+        let gaps = StreamGaps::default();
+        for checkpoint in [0, 5, 8] {
+            assert!(gaps.observe(checkpoint).is_empty());
+        }
+        assert_eq!(gaps.observe(2_000), [(1, 4), (6, 7), (9, 1_999)]);
+    }
+}
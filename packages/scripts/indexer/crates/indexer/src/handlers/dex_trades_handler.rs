@@ -0,0 +1,319 @@
+use async_trait::async_trait;
+use diesel::sql_types::{Array, BigInt, Text};
+use diesel::QueryableByName;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::RunQueryDsl;
+use move_core_types::account_address::AccountAddress;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashSet};
+use std::sync::Arc;
+use sui_indexer_alt_framework::pipeline::concurrent::Handler;
+use sui_indexer_alt_framework::pipeline::Processor;
+use sui_pg_db::{Connection, Db};
+use sui_types::full_checkpoint_content::CheckpointData;
+use tracing::{debug, warn};
+
+use crate::db;
+use crate::normalize_address;
+use crate::pipeline::{pipeline_entry, PipelineEntry};
+use unxv_schema::models::DexTrade;
+use unxv_schema::schema::dex_trades;
+
+pub const PIPELINE: PipelineEntry = pipeline_entry!(
+    DexTradesHandler,
+    tables: ["dex_trades", "dex_market_participants"],
+    events: ["order_info::OrderFilled"],
+    decoder_version: 1,
+    backfill_name: "dex_trades_backfill",
+    build: |ctx| Ok(Some(DexTradesHandler::new(ctx.package_allowlist.clone())?)),
+);
+
+/// Module of the Unxversal entry functions that route orders into DeepBook.
+const DEX_MODULE: &str = "dex";
+const FILL_MODULE: &str = "order_info";
+const FILL_EVENT: &str = "OrderFilled";
+
+/// Stage each fill's maker and taker under its UTC day. Only genuinely new rows are returned.
+const STAGE_PARTICIPANTS: &str = "\
+    INSERT INTO dex_market_participants (pool_id, day, role, account) \
+    SELECT p, (to_timestamp(ms / 1000.0) AT TIME ZONE 'UTC')::date, r, a \
+    FROM unnest($1::TEXT[], $2::BIGINT[], $3::TEXT[], $4::TEXT[]) AS t(p, ms, r, a) \
+    ON CONFLICT DO NOTHING \
+    RETURNING pool_id, day::TEXT AS day";
+
+/// Recount days that were already finalized when new participants arrived for them. Bounded to
+/// the touched (pool_id, day) keys, each an index range scan of the staging table.
+const RECOUNT_FINALIZED: &str = "\
+    UPDATE dex_daily_participants d SET \
+        unique_makers = (SELECT count(*) FROM dex_market_participants m \
+                         WHERE m.pool_id = d.pool_id AND m.day = d.day AND m.role = 'maker'), \
+        unique_takers = (SELECT count(*) FROM dex_market_participants m \
+                         WHERE m.pool_id = d.pool_id AND m.day = d.day AND m.role = 'taker'), \
+        recounted_at = now() \
+    FROM unnest($1::TEXT[], $2::TEXT[]) AS t(p, day) \
+    WHERE d.pool_id = t.p AND d.day = t.day::DATE";
+
+#[derive(QueryableByName)]
+struct StagedDay {
+    #[diesel(sql_type = Text)]
+    pool_id: String,
+    #[diesel(sql_type = Text)]
+    day: String,
+}
+
+/// BCS layout of DeepBook v3 `order_info::OrderFilled`.
+#[derive(Deserialize)]
+struct OrderFilled {
+    pool_id: AccountAddress,
+    _maker_order_id: u128,
+    _taker_order_id: u128,
+    _maker_client_order_id: u64,
+    _taker_client_order_id: u64,
+    price: u64,
+    taker_is_bid: bool,
+    _taker_fee: u64,
+    _taker_fee_is_deep: bool,
+    _maker_fee: u64,
+    _maker_fee_is_deep: bool,
+    base_quantity: u64,
+    quote_quantity: u64,
+    maker_balance_manager_id: AccountAddress,
+    taker_balance_manager_id: AccountAddress,
+    timestamp: u64,
+}
+
+/// Typed DEX trades. `unxvdex::dex` is a thin layer over DeepBook, so fills are DeepBook
+/// `order_info::OrderFilled` events emitted by calls made through the `dex` module; the
+/// package allowlist is checked against that calling package.
+pub struct DexTradesHandler {
+    /// Normalized 0x package addresses to accept. Empty => accept any.
+    package_allowlist: Option<HashSet<String>>,
+}
+
+impl DexTradesHandler {
+    /// Fails on an invalid address in `package_allowlist`.
+    pub fn new(package_allowlist: Option<Vec<String>>) -> anyhow::Result<Self> {
+        let package_allowlist = package_allowlist
+            .map(|v| v.iter().map(|s| normalize_address(s)).collect::<anyhow::Result<_>>())
+            .transpose()?;
+        Ok(Self { package_allowlist })
+    }
+}
+
+impl Processor for DexTradesHandler {
+    const NAME: &'static str = "dex_trades";
+    type Value = DexTrade;
+
+    fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<Self::Value>> {
+        let mut out = Vec::new();
+        for tx in &checkpoint.transactions {
+            let Some(events) = &tx.events else { continue; };
+            let checkpoint_timestamp_ms = checkpoint.checkpoint_summary.timestamp_ms as i64;
+            let checkpoint_no = checkpoint.checkpoint_summary.sequence_number as i64;
+            let digest = tx.transaction.digest().to_string();
+
+            for (idx, ev) in events.data.iter().enumerate() {
+                if ev.transaction_module.as_str() != DEX_MODULE { continue; }
+                if ev.type_.module.as_str() != FILL_MODULE || ev.type_.name.as_str() != FILL_EVENT { continue; }
+                let package = ev.package_id.to_canonical_string(true);
+                if let Some(allow) = &self.package_allowlist {
+                    if !allow.contains(&package) { continue; }
+                }
+                let fill: OrderFilled = match bcs::from_bytes(&ev.contents) {
+                    Ok(f) => f,
+                    Err(e) => {
+                        warn!("Skipping undecodable {FILL_MODULE}::{FILL_EVENT} in {digest}: {e}");
+                        continue;
+                    }
+                };
+                let row = DexTrade {
+                    event_digest: format!("{digest}:{idx}"),
+                    digest: digest.clone(),
+                    checkpoint: checkpoint_no,
+                    checkpoint_timestamp_ms,
+                    package,
+                    pool_id: fill.pool_id.to_canonical_string(true),
+                    maker: fill.maker_balance_manager_id.to_canonical_string(true),
+                    taker: fill.taker_balance_manager_id.to_canonical_string(true),
+                    price: fill.price as i64,
+                    base_quantity: fill.base_quantity as i64,
+                    quote_quantity: fill.quote_quantity as i64,
+                    is_bid: fill.taker_is_bid,
+                    timestamp_ms: fill.timestamp as i64,
+                };
+                debug!("Observed dex trade {:?}", row);
+                out.push(row);
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[async_trait]
+impl Handler for DexTradesHandler {
+    type Store = Db;
+
+    async fn commit<'a>(values: &[Self::Value], conn: &mut Connection<'a>) -> anyhow::Result<usize> {
+        db::retry_commit(Self::NAME, conn, |conn| {
+            async move {
+                let inserted = diesel::insert_into(dex_trades::table)
+                    .values(values)
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+                    .await?;
+
+                let (mut pools, mut times, mut roles, mut accounts) = (vec![], vec![], vec![], vec![]);
+                for t in values {
+                    for (role, account) in [("maker", &t.maker), ("taker", &t.taker)] {
+                        pools.push(t.pool_id.clone());
+                        times.push(t.checkpoint_timestamp_ms);
+                        roles.push(role);
+                        accounts.push(account.clone());
+                    }
+                }
+                let staged: Vec<StagedDay> = diesel::sql_query(STAGE_PARTICIPANTS)
+                    .bind::<Array<Text>, _>(&pools)
+                    .bind::<Array<BigInt>, _>(&times)
+                    .bind::<Array<Text>, _>(&roles)
+                    .bind::<Array<Text>, _>(&accounts)
+                    .load(conn)
+                    .await?;
+
+                // Late arrivals: a no-op unless one of these days has already been rolled up.
+                let days: BTreeSet<(String, String)> = staged.into_iter().map(|s| (s.pool_id, s.day)).collect();
+                if !days.is_empty() {
+                    let (pools, days): (Vec<_>, Vec<_>) = days.into_iter().unzip();
+                    diesel::sql_query(RECOUNT_FINALIZED)
+                        .bind::<Array<Text>, _>(&pools)
+                        .bind::<Array<Text>, _>(&days)
+                        .execute(conn)
+                        .await?;
+                }
+                Ok(inserted)
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{event, CheckpointBuilder, TestDb, PACKAGE};
+    use move_core_types::identifier::Identifier;
+    use serde::Serialize;
+    use sui_types::base_types::ObjectID;
+    use sui_types::event::Event;
+
+    const DEEPBOOK: &str = "0xdee9";
+    const FOREIGN: &str = "0xe11e";
+
+    /// [`OrderFilled`]'s layout, to encode fixtures with.
+    #[derive(Serialize)]
+    struct Fill {
+        pool_id: AccountAddress,
+        maker_order_id: u128,
+        taker_order_id: u128,
+        maker_client_order_id: u64,
+        taker_client_order_id: u64,
+        price: u64,
+        taker_is_bid: bool,
+        taker_fee: u64,
+        taker_fee_is_deep: bool,
+        maker_fee: u64,
+        maker_fee_is_deep: bool,
+        base_quantity: u64,
+        quote_quantity: u64,
+        maker_balance_manager_id: AccountAddress,
+        taker_balance_manager_id: AccountAddress,
+        timestamp: u64,
+    }
+
+    fn address(hex: &str) -> AccountAddress {
+        AccountAddress::from_hex_literal(hex).unwrap()
+    }
+
+    /// A DeepBook fill emitted by a call into `module` of `package`.
+    fn order_filled(package: &str, module: &str) -> Event {
+        let fill = Fill {
+            pool_id: address("0xb001"),
+            maker_order_id: 1,
+            taker_order_id: 2,
+            maker_client_order_id: 3,
+            taker_client_order_id: 4,
+            price: 1_500,
+            taker_is_bid: true,
+            taker_fee: 5,
+            taker_fee_is_deep: false,
+            maker_fee: 6,
+            maker_fee_is_deep: false,
+            base_quantity: 20,
+            quote_quantity: 30_000,
+            maker_balance_manager_id: address("0x3a7e"),
+            taker_balance_manager_id: address("0x7a7e"),
+            timestamp: 1_234,
+        };
+        Event {
+            package_id: ObjectID::from_hex_literal(package).unwrap(),
+            transaction_module: Identifier::new(module).unwrap(),
+            ..event(1, &format!("{DEEPBOOK}::order_info::OrderFilled"), bcs::to_bytes(&fill).unwrap())
+        }
+    }
+
+    #[test]
+    fn fills_routed_through_the_dex_become_trades() {
+        let checkpoint = CheckpointBuilder::new(7)
+            .transaction(
+                1,
+                &[(PACKAGE, "dex", "place_market_order")],
+                vec![event(1, &format!("{PACKAGE}::dex::ProtocolFeeTaken"), vec![]), order_filled(PACKAGE, "dex")],
+            )
+            // A fill not routed through the dex module, and one through a package outside the allowlist.
+            .transaction(2, &[(DEEPBOOK, "pool", "place_market_order")], vec![order_filled(DEEPBOOK, "pool")])
+            .transaction(3, &[(FOREIGN, "dex", "place_market_order")], vec![order_filled(FOREIGN, "dex")])
+            .build();
+        let digest = checkpoint.transactions[0].transaction.digest().to_string();
+
+        let handler = DexTradesHandler::new(Some(vec![PACKAGE.to_string()])).unwrap();
+        let trades = handler.process(&checkpoint).unwrap();
+        assert_eq!(trades.len(), 1);
+        let trade = &trades[0];
+        assert_eq!(trade.event_digest, format!("{digest}:1"));
+        assert_eq!(trade.digest, digest);
+        assert_eq!(trade.checkpoint, 7);
+        assert_eq!(trade.package, normalize_address(PACKAGE).unwrap());
+        assert_eq!(trade.pool_id, normalize_address("0xb001").unwrap());
+        assert_eq!(trade.maker, normalize_address("0x3a7e").unwrap());
+        assert_eq!(trade.taker, normalize_address("0x7a7e").unwrap());
+        assert_eq!((trade.price, trade.base_quantity, trade.quote_quantity), (1_500, 20, 30_000));
+        assert!(trade.is_bid);
+        assert_eq!(trade.timestamp_ms, 1_234);
+
+        // Without an allowlist, fills through any package's dex module count.
+        let trades = DexTradesHandler::new(None).unwrap().process(&checkpoint).unwrap();
+        assert_eq!(trades.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn trades_are_stored_once_with_their_participants() {
+        use diesel::{QueryDsl, SelectableHelper};
+
+        let checkpoint = CheckpointBuilder::new(7)
+            .transaction(1, &[(PACKAGE, "dex", "place_market_order")], vec![order_filled(PACKAGE, "dex")])
+            .build();
+        let trades = DexTradesHandler::new(None).unwrap().process(&checkpoint).unwrap();
+
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        assert_eq!(DexTradesHandler::commit(&trades, &mut conn).await.unwrap(), 1);
+        assert_eq!(DexTradesHandler::commit(&trades, &mut conn).await.unwrap(), 0);
+
+        let stored: Vec<DexTrade> = dex_trades::table.select(DexTrade::as_select()).load(&mut conn).await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].event_digest, trades[0].event_digest);
+        assert_eq!((stored[0].price, stored[0].base_quantity, stored[0].quote_quantity), (1_500, 20, 30_000));
+        // The maker and the taker, each once.
+        assert_eq!(db.count("dex_market_participants").await, 2);
+    }
+}
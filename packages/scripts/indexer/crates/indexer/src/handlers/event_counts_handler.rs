@@ -0,0 +1,98 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate};
+use diesel::upsert::excluded;
+use diesel::ExpressionMethods;
+use diesel_async::RunQueryDsl;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use sui_indexer_alt_framework::pipeline::sequential::Handler;
+use sui_indexer_alt_framework::pipeline::Processor;
+use sui_pg_db::{Connection, Db};
+use sui_types::full_checkpoint_content::CheckpointData;
+
+use crate::handlers::unxv_events_handler::{EventRow, UnxvEventsHandler};
+use crate::pipeline::{pipeline_entry, PipelineEntry};
+use unxv_schema::models::UnxvEventCount;
+use unxv_schema::schema::unxv_event_counts;
+
+pub const PIPELINE: PipelineEntry = pipeline_entry!(
+    sequential,
+    EventCountsHandler,
+    tables: ["unxv_event_counts"],
+    events: [],
+    decoder_version: 1,
+    backfill_name: "unxv_event_counts_backfill",
+    build: |ctx| Ok(Some(EventCountsHandler::new(ctx.events_handler()?))),
+);
+
+/// `(module, event_type, UTC day)`.
+type CountKey = (String, String, NaiveDate);
+
+/// Daily event counts per module and struct. Sequential, so each checkpoint is added exactly
+/// once, in order, and the running counts stay consistent with the watermark. Wraps an
+/// [`UnxvEventsHandler`] so the same events as `unxv_events` are counted.
+pub struct EventCountsHandler {
+    events: UnxvEventsHandler,
+}
+
+impl EventCountsHandler {
+    /// `events` should not carry decoders: only the event types are used here.
+    pub fn new(events: UnxvEventsHandler) -> Self {
+        Self { events }
+    }
+}
+
+impl Processor for EventCountsHandler {
+    const NAME: &'static str = "unxv_event_counts";
+    type Value = UnxvEventCount;
+
+    fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<Self::Value>> {
+        // The day comes from the checkpoint's own timestamp, never from when it is processed.
+        let ms = checkpoint.checkpoint_summary.timestamp_ms as i64;
+        let day = DateTime::from_timestamp_millis(ms)
+            .ok_or_else(|| anyhow::anyhow!("Checkpoint timestamp {ms} out of range"))?
+            .date_naive();
+        let mut counts: BTreeMap<CountKey, i64> = BTreeMap::new();
+        for row in self.events.process(checkpoint)? {
+            let EventRow::Event(ev) = row else { continue };
+            *counts.entry((ev.module, ev.event_type, day)).or_default() += 1;
+        }
+        Ok(counts
+            .into_iter()
+            .map(|((module, event_type, day), count)| UnxvEventCount { module, event_type, day, count })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Handler for EventCountsHandler {
+    type Store = Db;
+    /// Summed per key, so a batch spanning several checkpoints writes each key once; Postgres
+    /// rejects an upsert that touches the same row twice.
+    type Batch = BTreeMap<CountKey, i64>;
+
+    fn batch(batch: &mut Self::Batch, values: Vec<Self::Value>) {
+        for v in values {
+            *batch.entry((v.module, v.event_type, v.day)).or_default() += v.count;
+        }
+    }
+
+    async fn commit<'a>(batch: &Self::Batch, conn: &mut Connection<'a>) -> anyhow::Result<usize> {
+        let rows: Vec<UnxvEventCount> = batch
+            .iter()
+            .map(|((module, event_type, day), count)| UnxvEventCount {
+                module: module.clone(),
+                event_type: event_type.clone(),
+                day: *day,
+                count: *count,
+            })
+            .collect();
+        Ok(diesel::insert_into(unxv_event_counts::table)
+            .values(&rows)
+            .on_conflict((unxv_event_counts::module, unxv_event_counts::event_type, unxv_event_counts::day))
+            .do_update()
+            .set(unxv_event_counts::count.eq(unxv_event_counts::count + excluded(unxv_event_counts::count)))
+            .execute(conn)
+            .await?)
+    }
+}
@@ -0,0 +1,159 @@
+use async_trait::async_trait;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::RunQueryDsl;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use sui_field_count::FieldCount;
+use sui_indexer_alt_framework::pipeline::concurrent::Handler;
+use sui_indexer_alt_framework::pipeline::Processor;
+use sui_pg_db::{Connection, Db};
+use sui_types::full_checkpoint_content::CheckpointData;
+
+use crate::db::{self, max_insert_rows};
+use crate::handlers::unxv_events_handler::{EventRow, UnxvEventsHandler};
+use crate::pipeline::{pipeline_entry, PipelineEntry};
+use unxv_schema::models::UnxvEventSampled;
+use unxv_schema::schema::unxv_events_sampled;
+
+pub const PIPELINE: PipelineEntry = pipeline_entry!(
+    EventsSampledHandler,
+    tables: ["unxv_events_sampled"],
+    events: [],
+    decoder_version: 1,
+    backfill_name: "unxv_events_sampled_backfill",
+    build: |ctx| {
+        let Some(rate) = ctx.event_sample_rate else { return Ok(None) };
+        let events =
+            ctx.events_handler()?.with_layout_resolver(ctx.layout_resolver.clone()).with_json_limits(ctx.json_limits);
+        Ok(Some(EventsSampledHandler::new(events, rate)))
+    },
+);
+
+/// Whether the event `{digest}:{event_index}` is in a sample of `rate` (0 to 1): the first 8
+/// bytes of its SHA-256 fall below `rate` of the u64 range. The same event is always in or out
+/// of the sample, whichever process or backfill sees it, and raising the rate keeps every event
+/// already sampled.
+pub fn is_sampled(event_digest: &str, rate: f64) -> bool {
+    let hash = Sha256::digest(event_digest.as_bytes());
+    let bucket = u64::from_be_bytes(hash[..8].try_into().expect("8 bytes"));
+    (bucket as f64) < rate * u64::MAX as f64
+}
+
+/// Writes the events accepted by the wrapped [`UnxvEventsHandler`] that fall in the
+/// [`is_sampled`] sample to `unxv_events_sampled`. Dead-lettered events are left out.
+pub struct EventsSampledHandler {
+    events: UnxvEventsHandler,
+    rate: f64,
+}
+
+impl EventsSampledHandler {
+    pub fn new(events: UnxvEventsHandler, rate: f64) -> Self {
+        Self { events, rate }
+    }
+}
+
+impl Processor for EventsSampledHandler {
+    const NAME: &'static str = "unxv_events_sampled";
+    type Value = UnxvEventSampled;
+
+    fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<Self::Value>> {
+        Ok(self
+            .events
+            .process(checkpoint)?
+            .into_iter()
+            .filter_map(|row| match row {
+                EventRow::Event(ev) => Some(ev),
+                EventRow::DeadLetter(_) | EventRow::Checkpoint(_) => None,
+            })
+            .filter(|ev| is_sampled(&format!("{}:{}", ev.digest, ev.event_index), self.rate))
+            .map(|ev| UnxvEventSampled {
+                digest: ev.digest,
+                event_index: ev.event_index,
+                sender: ev.sender,
+                checkpoint: ev.checkpoint,
+                checkpoint_timestamp_ms: ev.checkpoint_timestamp_ms,
+                package: ev.package,
+                module: ev.module,
+                event_type: ev.event_type,
+                contents_json: ev.contents_json,
+                tx_success: ev.tx_success,
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Handler for EventsSampledHandler {
+    type Store = Db;
+
+    async fn commit<'a>(values: &[Self::Value], conn: &mut Connection<'a>) -> anyhow::Result<usize> {
+        db::retry_commit(Self::NAME, conn, |conn| {
+            async move {
+                let mut rows = 0;
+                for chunk in values.chunks(max_insert_rows(UnxvEventSampled::FIELD_COUNT)) {
+                    rows += diesel::insert_into(unxv_events_sampled::table)
+                        .values(chunk)
+                        .on_conflict_do_nothing()
+                        .execute(conn)
+                        .await?;
+                }
+                Ok(rows)
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{event, CheckpointBuilder, PACKAGE};
+    use crate::ModulesFilter;
+
+    fn keys(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("digest{i}:{}", i % 3)).collect()
+    }
+
+    fn sampled(keys: &[String], rate: f64) -> Vec<&String> {
+        keys.iter().filter(|key| is_sampled(key, rate)).collect()
+    }
+
+    #[test]
+    fn sampling_is_deterministic_and_nested() {
+        let keys = keys(10_000);
+        assert!(sampled(&keys, 0.0).is_empty());
+        assert_eq!(sampled(&keys, 1.0).len(), keys.len());
+
+        let quarter = sampled(&keys, 0.25);
+        assert_eq!(sampled(&keys, 0.25), quarter);
+        assert!((2_200..2_800).contains(&quarter.len()), "{} sampled", quarter.len());
+        // Raising the rate keeps every event already sampled.
+        let half = sampled(&keys, 0.5);
+        assert!(quarter.iter().all(|key| half.contains(key)));
+        assert!(half.len() > quarter.len());
+    }
+
+    #[test]
+    fn processes_agree_on_the_sample() {
+        let mut checkpoint = CheckpointBuilder::new(7);
+        for sender in 1..=20 {
+            let events = (0..5).map(|_| event(sender, &format!("{PACKAGE}::dex::Swapped"), vec![])).collect();
+            checkpoint = checkpoint.transaction(sender, &[], events);
+        }
+        let checkpoint = checkpoint.build();
+        let handler = |rate| EventsSampledHandler::new(UnxvEventsHandler::new(ModulesFilter::All, None).unwrap(), rate);
+        let keys = |rows: Vec<UnxvEventSampled>| -> Vec<(String, i64)> {
+            rows.into_iter().map(|row| (row.digest, row.event_index)).collect()
+        };
+
+        assert!(handler(0.0).process(&checkpoint).unwrap().is_empty());
+        assert_eq!(handler(1.0).process(&checkpoint).unwrap().len(), 100);
+        let sample = keys(handler(0.3).process(&checkpoint).unwrap());
+        assert!(!sample.is_empty() && sample.len() < 100);
+        assert_eq!(keys(handler(0.3).process(&checkpoint).unwrap()), sample);
+        for (digest, event_index) in &sample {
+            assert!(is_sampled(&format!("{digest}:{event_index}"), 0.3));
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use std::sync::Arc;
+use sui_indexer_alt_framework::pipeline::concurrent::Handler;
+use sui_indexer_alt_framework::pipeline::Processor;
+use sui_pg_db::{Connection, Db};
+use sui_types::full_checkpoint_content::CheckpointData;
+
+use crate::handlers::unxv_events_handler::{commit_rows, pipeline_handler, EventRow, UnxvEventsHandler};
+use crate::normalize_address;
+use crate::pipeline::{pipeline_entry, EventsPipeline, PipelineContext, PipelineEntry};
+
+/// A `--family`: packages, typically the versions of one deployment, indexed by a pipeline of
+/// their own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Family {
+    pub name: String,
+    /// Normalized package addresses.
+    pub packages: Vec<String>,
+}
+
+impl FromStr for Family {
+    type Err = anyhow::Error;
+
+    /// `NAME=0xPKG1,0xPKG2`, NAME made of lowercase letters, digits and underscores.
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (name, packages) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Expected NAME=0xPKG1,0xPKG2, got {s:?}"))?;
+        let name = name.trim();
+        anyhow::ensure!(
+            !name.is_empty() && name.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_'),
+            "Family name must be lowercase letters, digits and underscores, got {name:?}"
+        );
+        let packages = packages
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(|p| normalize_address(p).with_context(|| format!("Family {name}")))
+            .collect::<anyhow::Result<Vec<String>>>()?;
+        anyhow::ensure!(!packages.is_empty(), "Family {name} lists no packages");
+        Ok(Self { name: name.to_string(), packages })
+    }
+}
+
+/// A pipeline slot for the family at `SLOT` in `--family` order. The framework names
+/// pipelines by a `Processor::NAME` constant, so families can't name their pipelines: they
+/// take numbered slots instead.
+pub trait FamilySlot: Send + Sync + 'static {
+    const SLOT: usize;
+    /// `unxv_events_family_<SLOT + 1>`.
+    const NAME: &'static str;
+}
+
+/// Declare a marker type and a [`PipelineEntry`] per slot, and [`FAMILIES`].
+macro_rules! family_pipelines {
+    ($($entry:ident: $marker:ident => $slot:literal),* $(,)?) => {
+        $(
+            pub struct $marker;
+
+            impl FamilySlot for $marker {
+                const SLOT: usize = $slot - 1;
+                const NAME: &'static str = concat!("unxv_events_family_", $slot);
+            }
+
+            pub const $entry: PipelineEntry = PipelineEntry {
+                events_pipeline: Some(EventsPipeline::Family($slot - 1)),
+                ..pipeline_entry!(
+                    FamilyEventsHandler<$marker>,
+                    tables: ["unxv_events", "unxv_dead_letters", "unxv_events_quarantine", "sender_first_seen"],
+                    events: [],
+                    decoder_version: 1,
+                    backfill_name: concat!("unxv_events_family_", $slot, "_backfill"),
+                    build: |ctx| build::<$marker>(ctx),
+                )
+            };
+        )*
+
+        /// Family pipelines, one per slot; `--family` may be given at most this many times.
+        pub const FAMILIES: &[&str] = &[$(concat!("unxv_events_family_", $slot)),*];
+    };
+}
+
+family_pipelines! {
+    FAMILY_1: Family1 => 1,
+    FAMILY_2: Family2 => 2,
+    FAMILY_3: Family3 => 3,
+    FAMILY_4: Family4 => 4,
+    FAMILY_5: Family5 => 5,
+    FAMILY_6: Family6 => 6,
+    FAMILY_7: Family7 => 7,
+    FAMILY_8: Family8 => 8,
+}
+
+/// The `unxv_events` handler restricted to the packages of `S`'s family, in place of
+/// `UNXV_PACKAGE_IDS`, with no checkpoint rows. Live stats count each checkpoint once, so only
+/// the first family's pipeline feeds them.
+fn build<S: FamilySlot>(ctx: &PipelineContext) -> anyhow::Result<Option<FamilyEventsHandler<S>>> {
+    let Some(family) = ctx.families.get(S::SLOT) else { return Ok(None) };
+    let events = pipeline_handler(ctx)?
+        .with_package_allowlist(Some(family.packages.clone()))?
+        .with_live_stats(S::SLOT == 0);
+    Ok(Some(FamilyEventsHandler::new(events)))
+}
+
+/// Reject more families than there are slots, and families sharing a name or a package, which
+/// would have two pipelines write the same events.
+pub fn check_families(families: &[Family]) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        families.len() <= FAMILIES.len(),
+        "At most {} --family options are supported, got {}",
+        FAMILIES.len(),
+        families.len()
+    );
+    for (i, family) in families.iter().enumerate() {
+        for other in &families[..i] {
+            anyhow::ensure!(other.name != family.name, "Family {} is given twice", family.name);
+            if let Some(package) = family.packages.iter().find(|p| other.packages.contains(p)) {
+                anyhow::bail!("Package {package} is in both families {} and {}", other.name, family.name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// [`UnxvEventsHandler`] for one package family, run as pipeline `unxv_events_family_<n>` with
+/// its own watermark, so one family can be paused or backfilled without touching the others.
+/// Rows go to the same tables as `unxv_events` and conflict on the same keys. Dead letters are
+/// still filed under `unxv_events`, so `replay-dead-letters` picks them up as before.
+pub struct FamilyEventsHandler<S> {
+    events: UnxvEventsHandler,
+    slot: PhantomData<S>,
+}
+
+impl<S: FamilySlot> FamilyEventsHandler<S> {
+    pub fn new(events: UnxvEventsHandler) -> Self {
+        Self { events, slot: PhantomData }
+    }
+}
+
+impl<S: FamilySlot> Processor for FamilyEventsHandler<S> {
+    const NAME: &'static str = S::NAME;
+    type Value = EventRow;
+
+    fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<Self::Value>> {
+        self.events.process(checkpoint)
+    }
+}
+
+#[async_trait]
+impl<S: FamilySlot> Handler for FamilyEventsHandler<S> {
+    type Store = Db;
+
+    async fn commit<'a>(values: &[Self::Value], conn: &mut Connection<'a>) -> anyhow::Result<usize> {
+        commit_rows(Self::NAME, values, conn).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::PipelineArgs;
+    use crate::test_utils::{event, CheckpointBuilder, PACKAGE};
+    use crate::ModulesFilter;
+    use clap::Parser;
+
+    const FOREIGN: &str = "0xe11e";
+
+    #[derive(Parser)]
+    struct Cli {
+        #[clap(flatten)]
+        pipeline: PipelineArgs,
+    }
+
+    fn families(args: &[&str]) -> Vec<Family> {
+        Cli::try_parse_from(std::iter::once("unxversalindexer").chain(args.iter().copied())).unwrap().pipeline.families
+    }
+
+    #[test]
+    fn families_are_parsed_in_order() {
+        let expected = vec![
+            Family { name: "v1".into(), packages: vec![normalize_address(PACKAGE).unwrap()] },
+            Family {
+                name: "v2_upgrades".into(),
+                packages: vec![normalize_address("0xb0b").unwrap(), normalize_address(FOREIGN).unwrap()],
+            },
+        ];
+        assert_eq!(families(&["--family", "v1=0xa11ce", "--family", "v2_upgrades=0xb0b, 0xe11e"]), expected);
+        // The environment variable separates families with semicolons.
+        assert_eq!(families(&["--family", "v1=0xa11ce;v2_upgrades=0xb0b,0xe11e"]), expected);
+        assert!(families(&[]).is_empty());
+        check_families(&expected).unwrap();
+
+        for invalid in ["0xa11ce", "V1=0xa11ce", "=0xa11ce", "v1=", "v1=0xnope"] {
+            assert!(invalid.parse::<Family>().is_err(), "{invalid} parsed");
+        }
+    }
+
+    #[test]
+    fn families_may_not_overlap() {
+        let family = |spec: &str| spec.parse::<Family>().unwrap();
+        let twice = check_families(&[family("v1=0xa11ce"), family("v1=0xb0b")]).unwrap_err();
+        assert_eq!(twice.to_string(), "Family v1 is given twice");
+        let shared = check_families(&[family("v1=0xa11ce"), family("v2=0xb0b,0xa11ce")]).unwrap_err();
+        assert!(shared.to_string().ends_with("is in both families v1 and v2"), "{shared}");
+        let too_many: Vec<Family> = (0..=FAMILIES.len()).map(|i| family(&format!("f{i}=0x{:x}", i + 1))).collect();
+        assert!(check_families(&too_many).is_err());
+    }
+
+    /// `family`'s handler, restricted to its packages as [`build`] does.
+    fn handler<S: FamilySlot>(family: &Family) -> FamilyEventsHandler<S> {
+        let events = UnxvEventsHandler::new(ModulesFilter::All, None).unwrap();
+        FamilyEventsHandler::new(events.with_package_allowlist(Some(family.packages.clone())).unwrap())
+    }
+
+    fn packages<S: FamilySlot>(handler: &FamilyEventsHandler<S>, checkpoint: &Arc<CheckpointData>) -> Vec<String> {
+        handler
+            .process(checkpoint)
+            .unwrap()
+            .into_iter()
+            .filter_map(|row| match row {
+                EventRow::Event(event) => Some(event.package),
+                EventRow::DeadLetter(_) | EventRow::Checkpoint(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn each_family_keeps_only_its_packages() {
+        let checkpoint = CheckpointBuilder::new(3)
+            .transaction(1, &[(PACKAGE, "dex", "swap")], vec![event(1, &format!("{PACKAGE}::dex::Swapped"), vec![])])
+            .transaction(2, &[(FOREIGN, "dex", "swap")], vec![event(2, &format!("{FOREIGN}::dex::Swapped"), vec![])])
+            .transaction(3, &[("0xb0b", "dex", "swap")], vec![event(3, "0xb0b::dex::Swapped", vec![])])
+            .build();
+        let v1: Family = "v1=0xa11ce".parse().unwrap();
+        let v2: Family = "v2=0xe11e,0xb0b".parse().unwrap();
+
+        let first = handler::<Family1>(&v1);
+        let second = handler::<Family2>(&v2);
+        assert_eq!(packages(&first, &checkpoint), v1.packages);
+        assert_eq!(packages(&second, &checkpoint), v2.packages);
+        // Each under its own pipeline, and so its own watermark.
+        assert_eq!(
+            (FamilyEventsHandler::<Family1>::NAME, FamilyEventsHandler::<Family2>::NAME),
+            ("unxv_events_family_1", "unxv_events_family_2")
+        );
+    }
+}
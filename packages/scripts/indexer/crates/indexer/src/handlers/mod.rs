@@ -1,15 +1,50 @@
-use sui_types::full_checkpoint_content::CheckpointTransaction;
-use sui_types::transaction::{Command, TransactionDataAPI};
+use crate::pipeline::PipelineEntry;
 
+pub mod dex_trades_handler;
+pub mod event_counts_handler;
+pub mod events_sampled_handler;
+pub mod family_events_handler;
+pub mod module_events_handler;
+pub mod object_changes_handler;
+pub mod referrals_handler;
+pub mod transactions_handler;
+pub mod tx_event_bitmap_handler;
 pub mod unxv_events_handler;
+pub mod usdu_flows_handler;
 
-pub(crate) fn try_extract_move_call_package(tx: &CheckpointTransaction) -> Option<String> {
-    let txn_kind = tx.transaction.transaction_data().kind();
-    let first_command = txn_kind.iter_commands().next()?;
-    if let Command::MoveCall(move_call) = first_command {
-        Some(move_call.package.to_string())
-    } else {
-        None
-    }
-}
-
+/// Every pipeline the indexer can run, in registration order. A new pipeline adds its module
+/// above and its `PIPELINE` entry here.
+pub const PIPELINES: &[PipelineEntry] = &[
+    unxv_events_handler::PIPELINE,
+    module_events_handler::ADMIN,
+    module_events_handler::FEES,
+    module_events_handler::ORACLE,
+    module_events_handler::STAKING,
+    module_events_handler::REWARDS,
+    module_events_handler::USDU,
+    module_events_handler::BOOK,
+    module_events_handler::BIG_VECTOR,
+    module_events_handler::DEX,
+    module_events_handler::FUTURES,
+    module_events_handler::GAS_FUTURES,
+    module_events_handler::PERPETUALS,
+    module_events_handler::OPTIONS,
+    module_events_handler::LENDING,
+    module_events_handler::XPERPS,
+    family_events_handler::FAMILY_1,
+    family_events_handler::FAMILY_2,
+    family_events_handler::FAMILY_3,
+    family_events_handler::FAMILY_4,
+    family_events_handler::FAMILY_5,
+    family_events_handler::FAMILY_6,
+    family_events_handler::FAMILY_7,
+    family_events_handler::FAMILY_8,
+    events_sampled_handler::PIPELINE,
+    tx_event_bitmap_handler::PIPELINE,
+    transactions_handler::PIPELINE,
+    referrals_handler::PIPELINE,
+    dex_trades_handler::PIPELINE,
+    event_counts_handler::PIPELINE,
+    usdu_flows_handler::PIPELINE,
+    object_changes_handler::PIPELINE,
+];
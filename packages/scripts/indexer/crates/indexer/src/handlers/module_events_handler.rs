@@ -0,0 +1,181 @@
+use async_trait::async_trait;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use sui_indexer_alt_framework::pipeline::concurrent::Handler;
+use sui_indexer_alt_framework::pipeline::Processor;
+use sui_pg_db::{Connection, Db};
+use sui_types::full_checkpoint_content::CheckpointData;
+
+use crate::handlers::unxv_events_handler::{commit_rows, pipeline_handler, EventRow, UnxvEventsHandler};
+use crate::pipeline::{pipeline_entry, EventsPipeline, PipelineContext, PipelineEntry};
+use crate::ModulesFilter;
+
+/// A module indexed by a pipeline of its own under `--split-pipelines`. The framework names
+/// pipelines by a `Processor::NAME` constant, so each module is a type.
+pub trait EventsModule: Send + Sync + 'static {
+    const MODULE: &'static str;
+    /// `unxv_events_<module>`.
+    const NAME: &'static str;
+}
+
+/// Declare a marker type and a [`PipelineEntry`] per module, and [`MODULES`].
+macro_rules! module_pipelines {
+    ($($entry:ident: $marker:ident => $module:literal),* $(,)?) => {
+        $(
+            pub struct $marker;
+
+            impl EventsModule for $marker {
+                const MODULE: &'static str = $module;
+                const NAME: &'static str = concat!("unxv_events_", $module);
+            }
+
+            pub const $entry: PipelineEntry = PipelineEntry {
+                events_pipeline: Some(EventsPipeline::Module),
+                ..pipeline_entry!(
+                    ModuleEventsHandler<$marker>,
+                    tables: ["unxv_events", "unxv_dead_letters", "unxv_events_quarantine", "sender_first_seen"],
+                    events: [],
+                    decoder_version: 1,
+                    backfill_name: concat!("unxv_events_", $module, "_backfill"),
+                    build: |ctx| build::<$marker>(ctx),
+                )
+            };
+        )*
+
+        /// Modules with a pipeline of their own under `--split-pipelines`, in registration order.
+        pub const MODULES: &[&str] = &[$($module),*];
+    };
+}
+
+module_pipelines! {
+    ADMIN: Admin => "admin",
+    FEES: Fees => "fees",
+    ORACLE: Oracle => "oracle",
+    STAKING: Staking => "staking",
+    REWARDS: Rewards => "rewards",
+    USDU: Usdu => "usdu",
+    BOOK: Book => "book",
+    BIG_VECTOR: BigVector => "big_vector",
+    DEX: Dex => "dex",
+    FUTURES: Futures => "futures",
+    GAS_FUTURES: GasFutures => "gas_futures",
+    PERPETUALS: Perpetuals => "perpetuals",
+    OPTIONS: Options => "options",
+    LENDING: Lending => "lending",
+    XPERPS: Xperps => "xperps",
+}
+
+/// The `unxv_events` handler restricted to `M`'s module, with no checkpoint rows. Live stats
+/// count each checkpoint once, so only the first enabled module's pipeline feeds them.
+fn build<M: EventsModule>(ctx: &PipelineContext) -> anyhow::Result<Option<ModuleEventsHandler<M>>> {
+    if !ctx.modules.allows(M::MODULE) {
+        return Ok(None);
+    }
+    let live_stats = MODULES.iter().find(|m| ctx.modules.allows(m)) == Some(&M::MODULE);
+    let events = pipeline_handler(ctx)?
+        .with_modules(ModulesFilter::Only([M::MODULE.to_string()].into()))
+        .with_live_stats(live_stats);
+    Ok(Some(ModuleEventsHandler::new(events)))
+}
+
+/// Under `--split-pipelines` only [`MODULES`] are indexed, so `--modules` has to list modules
+/// among them rather than `all`.
+pub fn check_modules(modules: &ModulesFilter) -> anyhow::Result<()> {
+    let ModulesFilter::Only(modules) = modules else {
+        anyhow::bail!("--split-pipelines needs an explicit --modules list, not `all`");
+    };
+    for module in modules {
+        anyhow::ensure!(
+            MODULES.contains(&module.as_str()),
+            "Module {module} has no pipeline of its own under --split-pipelines"
+        );
+    }
+    Ok(())
+}
+
+/// [`UnxvEventsHandler`] for a single module, run as pipeline `unxv_events_<module>` with its
+/// own watermark, so a burst in one module doesn't hold back the others. Rows go to the same
+/// tables as `unxv_events` and conflict on the same keys, so moving a module between pipelines
+/// never duplicates an event. Dead letters are still filed under `unxv_events`, so
+/// `replay-dead-letters` picks them up as before.
+pub struct ModuleEventsHandler<M> {
+    events: UnxvEventsHandler,
+    module: PhantomData<M>,
+}
+
+impl<M: EventsModule> ModuleEventsHandler<M> {
+    pub fn new(events: UnxvEventsHandler) -> Self {
+        Self { events, module: PhantomData }
+    }
+}
+
+impl<M: EventsModule> Processor for ModuleEventsHandler<M> {
+    const NAME: &'static str = M::NAME;
+    type Value = EventRow;
+
+    fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<Self::Value>> {
+        self.events.process(checkpoint)
+    }
+}
+
+#[async_trait]
+impl<M: EventsModule> Handler for ModuleEventsHandler<M> {
+    type Store = Db;
+
+    async fn commit<'a>(values: &[Self::Value], conn: &mut Connection<'a>) -> anyhow::Result<usize> {
+        commit_rows(Self::NAME, values, conn).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{event, CheckpointBuilder, TestDb, PACKAGE};
+    use std::collections::HashSet;
+
+    fn handler<M: EventsModule>() -> ModuleEventsHandler<M> {
+        let modules = ModulesFilter::Only([M::MODULE.to_string()].into());
+        ModuleEventsHandler::new(UnxvEventsHandler::new(modules, None).unwrap())
+    }
+
+    fn keys(rows: &[EventRow]) -> HashSet<(String, i64)> {
+        rows.iter()
+            .filter_map(|row| match row {
+                EventRow::Event(e) => Some((e.digest.clone(), e.event_index)),
+                EventRow::DeadLetter(_) | EventRow::Checkpoint(_) => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn module_pipelines_split_a_checkpoint_into_disjoint_rows() {
+        let checkpoint = CheckpointBuilder::new(7)
+            .transaction(
+                1,
+                &[(PACKAGE, "dex", "swap")],
+                vec![
+                    event(1, &format!("{PACKAGE}::dex::OrderPlaced"), vec![]),
+                    event(1, &format!("{PACKAGE}::lending::DebtRepaid"), vec![]),
+                ],
+            )
+            .transaction(
+                2,
+                &[(PACKAGE, "lending", "borrow")],
+                vec![event(2, &format!("{PACKAGE}::lending::Borrowed"), vec![])],
+            )
+            .transaction(3, &[(PACKAGE, "vault", "open")], vec![event(3, &format!("{PACKAGE}::vault::Opened"), vec![])])
+            .build();
+
+        let dex = handler::<Dex>().process(&checkpoint).unwrap();
+        let lending = handler::<Lending>().process(&checkpoint).unwrap();
+        let (dex_keys, lending_keys) = (keys(&dex), keys(&lending));
+        assert_eq!((dex_keys.len(), lending_keys.len()), (1, 2));
+        assert!(dex_keys.is_disjoint(&lending_keys));
+
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        ModuleEventsHandler::<Dex>::commit(&dex, &mut conn).await.unwrap();
+        ModuleEventsHandler::<Lending>::commit(&lending, &mut conn).await.unwrap();
+        assert_eq!(db.count("unxv_events").await, 3);
+    }
+}
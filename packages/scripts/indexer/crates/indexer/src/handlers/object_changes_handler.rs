@@ -0,0 +1,126 @@
+use async_trait::async_trait;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::RunQueryDsl;
+use std::sync::Arc;
+use sui_field_count::FieldCount;
+use sui_indexer_alt_framework::pipeline::concurrent::Handler;
+use sui_indexer_alt_framework::pipeline::Processor;
+use sui_pg_db::{Connection, Db};
+use sui_types::full_checkpoint_content::CheckpointData;
+use tracing::debug;
+
+use crate::db::{self, max_insert_rows};
+use crate::pipeline::{pipeline_entry, PipelineEntry};
+use crate::StructTagFilter;
+use unxv_schema::models::UnxvObjectChange;
+use unxv_schema::schema::unxv_object_changes;
+
+pub const PIPELINE: PipelineEntry = pipeline_entry!(
+    ObjectChangesHandler,
+    tables: ["unxv_object_changes"],
+    events: [],
+    decoder_version: 1,
+    backfill_name: "unxv_object_changes_backfill",
+    build: |ctx| Ok(ctx.object_types.clone().map(ObjectChangesHandler::new)),
+);
+
+/// Records every object a transaction created or mutated whose type is in the `--object-types`
+/// [`StructTagFilter`], one row per new version, so shared state such as pools and vaults can be followed
+/// without an event for each change. Deleted and wrapped objects have no new version and are
+/// not recorded.
+pub struct ObjectChangesHandler {
+    object_types: StructTagFilter,
+}
+
+impl ObjectChangesHandler {
+    pub fn new(object_types: StructTagFilter) -> Self {
+        Self { object_types }
+    }
+}
+
+impl Processor for ObjectChangesHandler {
+    const NAME: &'static str = "unxv_object_changes";
+    type Value = UnxvObjectChange;
+
+    fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<Self::Value>> {
+        let checkpoint_no = checkpoint.checkpoint_summary.sequence_number as i64;
+        let timestamp_ms = checkpoint.checkpoint_summary.timestamp_ms as i64;
+        let mut out = Vec::new();
+        for tx in &checkpoint.transactions {
+            let digest = tx.transaction.digest().to_string();
+            for object in &tx.output_objects {
+                let Some(move_object) = object.data.try_as_move() else { continue; };
+                let Some(tag) = object.struct_tag() else { continue; };
+                if !self.object_types.matches(&tag) {
+                    continue;
+                }
+                let row = UnxvObjectChange {
+                    object_id: object.id().to_canonical_string(true),
+                    version: object.version().value() as i64,
+                    object_type: tag.to_canonical_string(true),
+                    digest: digest.clone(),
+                    checkpoint: checkpoint_no,
+                    timestamp_ms,
+                    contents_bcs: move_object.contents().to_vec(),
+                };
+                debug!("Observed {} version {} of {}", row.object_id, row.version, row.object_type);
+                out.push(row);
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[async_trait]
+impl Handler for ObjectChangesHandler {
+    type Store = Db;
+
+    async fn commit<'a>(values: &[Self::Value], conn: &mut Connection<'a>) -> anyhow::Result<usize> {
+        db::retry_commit(Self::NAME, conn, |conn| {
+            async move {
+                let mut rows = 0;
+                for chunk in values.chunks(max_insert_rows(UnxvObjectChange::FIELD_COUNT)) {
+                    rows += diesel::insert_into(unxv_object_changes::table)
+                        .values(chunk)
+                        .on_conflict_do_nothing()
+                        .execute(conn)
+                        .await?;
+                }
+                Ok(rows)
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{CheckpointBuilder, TestDb};
+    use sui_types::test_checkpoint_data_builder::TestCheckpointDataBuilder;
+
+    #[tokio::test]
+    async fn only_allowlisted_object_types_are_recorded() {
+        let handler = ObjectChangesHandler::new("0x2::coin::Coin<0xa11ce::usdu::USDU>".parse().unwrap());
+        let checkpoint =
+            CheckpointBuilder::new(5).coins(1, &[(0, "0xa11ce::usdu::USDU", 250), (1, "0x2::sui::SUI", 9)]).build();
+
+        let changes = handler.process(&checkpoint).unwrap();
+        let [change] = &changes[..] else { panic!("one matching object, got {changes:?}") };
+        assert_eq!(change.object_id, TestCheckpointDataBuilder::derive_object_id(0).to_canonical_string(true));
+        assert_eq!(change.object_type, format!("0x{:0>64}::coin::Coin<0x{:0>64}::usdu::USDU>", "2", "a11ce"));
+        let digest = checkpoint.transactions[0].transaction.digest().to_string();
+        assert_eq!((change.checkpoint, &change.digest), (5, &digest));
+        // A coin is its id followed by its balance.
+        assert_eq!(change.contents_bcs.len(), 40);
+        assert_eq!(change.contents_bcs[32..], 250u64.to_le_bytes());
+
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        assert_eq!(ObjectChangesHandler::commit(&changes, &mut conn).await.unwrap(), 1);
+        // The same version again is a no-op.
+        assert_eq!(ObjectChangesHandler::commit(&changes, &mut conn).await.unwrap(), 0);
+        assert_eq!(db.count("unxv_object_changes").await, 1);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::db;
+use crate::normalize_address;
+use crate::pipeline::{pipeline_entry, PipelineEntry};
+use async_trait::async_trait;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::RunQueryDsl;
+use std::collections::HashSet;
+use std::sync::Arc;
+use sui_indexer_alt_framework::pipeline::concurrent::Handler;
+use sui_indexer_alt_framework::pipeline::Processor;
+use sui_pg_db::{Connection, Db};
+use sui_types::full_checkpoint_content::CheckpointData;
+use tracing::{debug, warn};
+
+use unxv_events::rewards::ReferralSet;
+use unxv_schema::models::UnxvReferral;
+use unxv_schema::schema::unxv_referrals;
+
+pub const PIPELINE: PipelineEntry = pipeline_entry!(
+    ReferralsHandler,
+    tables: ["unxv_referrals"],
+    events: ["rewards::ReferralSet"],
+    decoder_version: 1,
+    backfill_name: "unxv_referrals_backfill",
+    build: |ctx| Ok(Some(ReferralsHandler::new(ctx.package_allowlist.clone())?)),
+);
+
+const MODULE: &str = "rewards";
+const EVENT_TYPE: &str = "ReferralSet";
+
+/// Referral attribution: decodes `rewards::ReferralSet` (emitted once when a user binds a
+/// parent) into `unxv_referrals`, with the parent as referrer and the child as referee.
+pub struct ReferralsHandler {
+    /// Normalized 0x package addresses to accept. Empty => accept any.
+    package_allowlist: Option<HashSet<String>>,
+}
+
+impl ReferralsHandler {
+    /// Fails on an invalid address in `package_allowlist`.
+    pub fn new(package_allowlist: Option<Vec<String>>) -> anyhow::Result<Self> {
+        let package_allowlist = package_allowlist
+            .map(|v| v.iter().map(|s| normalize_address(s)).collect::<anyhow::Result<_>>())
+            .transpose()?;
+        Ok(Self { package_allowlist })
+    }
+}
+
+impl Processor for ReferralsHandler {
+    const NAME: &'static str = "unxv_referrals";
+    type Value = UnxvReferral;
+
+    fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<Self::Value>> {
+        let mut out = Vec::new();
+        for tx in &checkpoint.transactions {
+            let Some(events) = &tx.events else { continue; };
+            let checkpoint_timestamp_ms = checkpoint.checkpoint_summary.timestamp_ms as i64;
+            let checkpoint_no = checkpoint.checkpoint_summary.sequence_number as i64;
+            let digest = tx.transaction.digest().to_string();
+
+            for (idx, ev) in events.data.iter().enumerate() {
+                let type_tag = &ev.type_;
+                if type_tag.module.as_str() != MODULE || type_tag.name.as_str() != EVENT_TYPE { continue; }
+                let package = type_tag.address.to_canonical_string(true);
+                if let Some(allow) = &self.package_allowlist {
+                    if !allow.contains(&package) { continue; }
+                }
+                let referral: ReferralSet = match bcs::from_bytes(&ev.contents) {
+                    Ok(r) => r,
+                    Err(e) => {
+                        warn!("Skipping undecodable {MODULE}::{EVENT_TYPE} in {digest}: {e}");
+                        continue;
+                    }
+                };
+                let row = UnxvReferral {
+                    event_digest: format!("{digest}:{idx}"),
+                    digest: digest.clone(),
+                    checkpoint: checkpoint_no,
+                    checkpoint_timestamp_ms,
+                    package,
+                    referrer: referral.parent.to_canonical_string(true),
+                    referee: referral.child.to_canonical_string(true),
+                    reward_amount: None,
+                };
+                debug!("Observed Unxv referral {:?}", row);
+                out.push(row);
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[async_trait]
+impl Handler for ReferralsHandler {
+    type Store = Db;
+
+    async fn commit<'a>(values: &[Self::Value], conn: &mut Connection<'a>) -> anyhow::Result<usize> {
+        db::retry_commit(Self::NAME, conn, |conn| {
+            async move {
+                Ok(diesel::insert_into(unxv_referrals::table)
+                    .values(values)
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+                    .await?)
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+}
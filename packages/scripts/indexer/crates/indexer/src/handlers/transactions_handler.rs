@@ -0,0 +1,139 @@
+use async_trait::async_trait;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::RunQueryDsl;
+use std::collections::HashSet;
+use std::sync::Arc;
+use sui_indexer_alt_framework::pipeline::concurrent::Handler;
+use sui_indexer_alt_framework::pipeline::Processor;
+use sui_pg_db::{Connection, Db};
+use sui_types::effects::TransactionEffectsAPI;
+use sui_types::execution_status::{ExecutionFailureStatus, ExecutionStatus};
+use sui_types::full_checkpoint_content::CheckpointData;
+use sui_types::transaction::{Command, TransactionDataAPI, TransactionKind};
+
+use crate::db;
+use crate::handlers::unxv_events_handler::{EventRow, UnxvEventsHandler};
+use crate::pipeline::{pipeline_entry, PipelineEntry};
+use unxv_schema::models::UnxvTransaction;
+use unxv_schema::schema::unxv_transactions;
+
+pub const PIPELINE: PipelineEntry = pipeline_entry!(
+    TransactionsHandler,
+    tables: ["unxv_transactions"],
+    events: [],
+    decoder_version: 1,
+    backfill_name: "unxv_transactions_backfill",
+    build: |ctx| Ok(Some(TransactionsHandler::new(ctx.events_handler()?))),
+);
+
+/// Transaction-level context (status, gas, Move calls) for every transaction that emitted an
+/// event accepted by the wrapped [`UnxvEventsHandler`]'s filters. Failed transactions are
+/// included: their events are still in the checkpoint and indexed.
+pub struct TransactionsHandler {
+    events: UnxvEventsHandler,
+}
+
+impl TransactionsHandler {
+    /// `events` should not carry decoders: only which transactions emitted events is used here.
+    pub fn new(events: UnxvEventsHandler) -> Self {
+        Self { events }
+    }
+}
+
+/// `[{"package", "module", "function"}]` for the Move call commands of a programmable
+/// transaction; empty for system transactions.
+fn move_calls(kind: &TransactionKind) -> serde_json::Value {
+    let TransactionKind::ProgrammableTransaction(pt) = kind else {
+        return serde_json::Value::Array(vec![]);
+    };
+    pt.commands
+        .iter()
+        .filter_map(|command| match command {
+            Command::MoveCall(call) => Some(serde_json::json!({
+                "package": call.package.to_canonical_string(true),
+                "module": call.module.to_string(),
+                "function": call.function.to_string(),
+            })),
+            _ => None,
+        })
+        .collect()
+}
+
+impl Processor for TransactionsHandler {
+    const NAME: &'static str = "unxv_transactions";
+    type Value = UnxvTransaction;
+
+    fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<Self::Value>> {
+        // Dead-lettered events were accepted too; they just failed the JSON limits.
+        let digests: HashSet<String> = self
+            .events
+            .process(checkpoint)?
+            .into_iter()
+            .filter_map(|row| match row {
+                EventRow::Event(ev) => Some(ev.digest),
+                EventRow::DeadLetter(dl) => Some(dl.digest),
+                EventRow::Checkpoint(_) => None,
+            })
+            .collect();
+        if digests.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let summary = &checkpoint.checkpoint_summary;
+        let mut out = Vec::with_capacity(digests.len());
+        for tx in &checkpoint.transactions {
+            let digest = tx.transaction.digest().to_string();
+            if !digests.contains(&digest) {
+                continue;
+            }
+            let data = tx.transaction.transaction_data();
+            let (success, abort_code, error) = match tx.effects.status() {
+                ExecutionStatus::Success => (true, None, None),
+                ExecutionStatus::Failure { error, command } => {
+                    let abort_code = match error {
+                        ExecutionFailureStatus::MoveAbort(_, code) => Some(*code as i64),
+                        _ => None,
+                    };
+                    let at = command.map(|c| format!(" in command {c}")).unwrap_or_default();
+                    (false, abort_code, Some(format!("{error:?}{at}")))
+                }
+            };
+            let gas = tx.effects.gas_cost_summary();
+            out.push(UnxvTransaction {
+                digest,
+                // `SuiAddress` displays in the padded form already.
+                sender: data.sender().to_string(),
+                checkpoint: summary.sequence_number as i64,
+                checkpoint_timestamp_ms: summary.timestamp_ms as i64,
+                success,
+                abort_code,
+                error,
+                computation_cost: gas.computation_cost as i64,
+                storage_cost: gas.storage_cost as i64,
+                storage_rebate: gas.storage_rebate as i64,
+                move_calls: move_calls(data.kind()),
+            });
+        }
+        Ok(out)
+    }
+}
+
+#[async_trait]
+impl Handler for TransactionsHandler {
+    type Store = Db;
+
+    async fn commit<'a>(values: &[Self::Value], conn: &mut Connection<'a>) -> anyhow::Result<usize> {
+        // Keyed by digest, so reprocessing a checkpoint writes nothing new.
+        db::retry_commit(Self::NAME, conn, |conn| {
+            async move {
+                Ok(diesel::insert_into(unxv_transactions::table)
+                    .values(values)
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+                    .await?)
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+}
@@ -0,0 +1,176 @@
+use async_trait::async_trait;
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::RunQueryDsl;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use sui_field_count::FieldCount;
+use sui_indexer_alt_framework::pipeline::concurrent::Handler;
+use sui_indexer_alt_framework::pipeline::Processor;
+use sui_pg_db::{Connection, Db};
+use sui_types::full_checkpoint_content::CheckpointData;
+
+use crate::db;
+use crate::handlers::unxv_events_handler::{EventRow, UnxvEventsHandler};
+use crate::pipeline::{pipeline_entry, PipelineEntry};
+use unxv_schema::models::UnxvTxEventBitmap;
+use unxv_schema::schema::{unxv_event_types, unxv_tx_event_bitmap};
+
+pub const PIPELINE: PipelineEntry = pipeline_entry!(
+    TxEventBitmapHandler,
+    tables: ["unxv_tx_event_bitmap", "unxv_event_types"],
+    events: [],
+    decoder_version: 1,
+    backfill_name: "unxv_tx_event_bitmap_backfill",
+    build: |ctx| {
+        if !ctx.tx_event_bitmap {
+            return Ok(None);
+        }
+        Ok(Some(TxEventBitmapHandler::new(ctx.events_handler()?)))
+    },
+);
+
+/// Distinct `module::Struct` event types emitted by one transaction.
+#[derive(Debug, FieldCount)]
+pub struct TxEventTypes {
+    pub digest: String,
+    pub checkpoint: i64,
+    pub event_types: BTreeSet<String>,
+}
+
+/// Per-transaction event-type membership. Wraps an [`UnxvEventsHandler`] so exactly the events
+/// accepted by its filters are counted; type names are interned into `unxv_event_types` at
+/// commit time and stored as an id array per transaction.
+pub struct TxEventBitmapHandler {
+    events: UnxvEventsHandler,
+}
+
+impl TxEventBitmapHandler {
+    /// `events` should not carry decoders: only the event types are used here.
+    pub fn new(events: UnxvEventsHandler) -> Self {
+        Self { events }
+    }
+}
+
+impl Processor for TxEventBitmapHandler {
+    const NAME: &'static str = "unxv_tx_event_bitmap";
+    type Value = TxEventTypes;
+
+    fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<Self::Value>> {
+        let mut out: Vec<TxEventTypes> = Vec::new();
+        // Rows arrive grouped by transaction, in checkpoint order.
+        for row in self.events.process(checkpoint)? {
+            let EventRow::Event(ev) = row else { continue };
+            let event_type = format!("{}::{}", ev.module, ev.event_type);
+            match out.last_mut() {
+                Some(last) if last.digest == ev.digest => {
+                    last.event_types.insert(event_type);
+                }
+                _ => out.push(TxEventTypes {
+                    digest: ev.digest,
+                    checkpoint: ev.checkpoint,
+                    event_types: BTreeSet::from([event_type]),
+                }),
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[async_trait]
+impl Handler for TxEventBitmapHandler {
+    type Store = Db;
+
+    async fn commit<'a>(values: &[Self::Value], conn: &mut Connection<'a>) -> anyhow::Result<usize> {
+        db::retry_commit(Self::NAME, conn, |conn| {
+            async move {
+                let names: Vec<String> = values
+                    .iter()
+                    .flat_map(|v| v.event_types.iter().cloned())
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect();
+
+                diesel::insert_into(unxv_event_types::table)
+                    .values(names.iter().map(|n| unxv_event_types::event_type.eq(n.as_str())).collect::<Vec<_>>())
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+                    .await?;
+                let ids: HashMap<String, i32> = unxv_event_types::table
+                    .select((unxv_event_types::event_type, unxv_event_types::id))
+                    .filter(unxv_event_types::event_type.eq_any(names))
+                    .load::<(String, i32)>(conn)
+                    .await?
+                    .into_iter()
+                    .collect();
+
+                let rows: Vec<UnxvTxEventBitmap> = values
+                    .iter()
+                    .map(|v| UnxvTxEventBitmap {
+                        digest: v.digest.clone(),
+                        checkpoint: v.checkpoint,
+                        event_type_ids: v.event_types.iter().filter_map(|t| ids.get(t).copied()).collect(),
+                    })
+                    .collect();
+                Ok(diesel::insert_into(unxv_tx_event_bitmap::table)
+                    .values(&rows)
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+                    .await?)
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{event, CheckpointBuilder, TestDb, PACKAGE};
+    use crate::ModulesFilter;
+    use diesel::SelectableHelper;
+
+    #[tokio::test]
+    async fn bitmaps_hold_each_transactions_event_types() {
+        let fill = || event(1, &format!("{PACKAGE}::dex::OrderFilled"), vec![]);
+        let checkpoint = CheckpointBuilder::new(7)
+            .transaction(1, &[], vec![fill(), fill(), event(1, &format!("{PACKAGE}::lending::Borrowed"), vec![])])
+            .transaction(2, &[], vec![fill()])
+            // Filtered out by the default modules, so it gets no bitmap.
+            .transaction(3, &[], vec![event(3, &format!("{PACKAGE}::vault::Opened"), vec![])])
+            .build();
+        let digest = |i: usize| checkpoint.transactions[i].transaction.digest().to_string();
+
+        let handler = TxEventBitmapHandler::new(UnxvEventsHandler::new(ModulesFilter::default(), None).unwrap());
+        let values = handler.process(&checkpoint).unwrap();
+        let types: Vec<_> = values.iter().map(|v| (v.digest.clone(), v.event_types.clone())).collect();
+        assert_eq!(
+            types,
+            [
+                (digest(0), BTreeSet::from(["dex::OrderFilled".to_string(), "lending::Borrowed".to_string()])),
+                (digest(1), BTreeSet::from(["dex::OrderFilled".to_string()])),
+            ]
+        );
+
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        assert_eq!(TxEventBitmapHandler::commit(&values, &mut conn).await.unwrap(), 2);
+        let names: HashMap<i32, String> = unxv_event_types::table
+            .select((unxv_event_types::id, unxv_event_types::event_type))
+            .load::<(i32, String)>(&mut conn)
+            .await
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(names.len(), 2);
+        let stored: Vec<UnxvTxEventBitmap> =
+            unxv_tx_event_bitmap::table.select(UnxvTxEventBitmap::as_select()).load(&mut conn).await.unwrap();
+        for (digest, event_types) in &types {
+            let row = stored.iter().find(|r| &r.digest == digest).unwrap();
+            let stored_types: BTreeSet<String> = row.event_type_ids.iter().map(|id| names[id].clone()).collect();
+            assert_eq!(&stored_types, event_types);
+        }
+        assert_eq!(stored.len(), 2);
+    }
+}
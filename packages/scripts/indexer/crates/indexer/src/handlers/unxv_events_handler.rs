@@ -1,83 +1,643 @@
-use crate::handlers::try_extract_move_call_package;
+use crate::decoder::{
+    decode_to_json, emitter_object, leading_type_params, type_hash, type_params_key, BcsLimits, DecoderRegistry,
+    JsonLimits, LayoutResolver, TypeParamLimits, TYPE_PARAMS_TRUNCATED,
+};
+use crate::db::{self, max_insert_rows, InsertMethod};
+use crate::metrics::UnxvMetrics;
+use crate::pipeline::{pipeline_entry, EventsPipeline, PipelineContext, PipelineEntry};
+use crate::schemas::ModuleSchemas;
+use crate::{
+    kafka, move_call_packages, normalize_address, normalize_type_tag, notify, parse_struct_tag, quarantine, schemas,
+    stats, stream, tx_commit_timestamp_ms, ModulesFilter, StructTagFilter,
+};
 use async_trait::async_trait;
-use diesel_async::RunQueryDsl;
-use std::collections::HashSet;
+use chrono::Utc;
+use diesel::sql_types::{Array, BigInt, Bool, Bytea, Jsonb, Nullable, Text};
+use diesel::upsert::excluded;
+use diesel::{ExpressionMethods, QueryableByName};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use move_core_types::language_storage::StructTag;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Instant;
+use sui_field_count::FieldCount;
 use sui_indexer_alt_framework::pipeline::concurrent::Handler;
 use sui_indexer_alt_framework::pipeline::Processor;
 use sui_pg_db::{Connection, Db};
+use sui_types::effects::TransactionEffectsAPI;
 use sui_types::full_checkpoint_content::CheckpointData;
-use tracing::debug;
+use sui_types::gas::GasCostSummary;
+use tracing::{debug, warn};
 
-use unxv_schema::models::UnxvEvent;
-use unxv_schema::schema::unxv_events;
+use unxv_schema::models::{UnxvCheckpoint, UnxvDeadLetter, UnxvEvent, UnxvQuarantinedRow};
+use unxv_schema::schema::{unxv_checkpoints, unxv_dead_letters, unxv_events};
+
+/// Replaced by the per-module pipelines of [`super::module_events_handler`] under
+/// `--split-pipelines`.
+pub const PIPELINE: PipelineEntry = PipelineEntry {
+    events_pipeline: Some(EventsPipeline::All),
+    ..pipeline_entry!(
+        UnxvEventsHandler,
+        tables: ["unxv_events", "unxv_dead_letters", "unxv_events_quarantine", "sender_first_seen", "unxv_checkpoints"],
+        events: [],
+        decoder_version: 1,
+        backfill_name: "unxv_events_backfill",
+        build: |ctx| Ok(Some(pipeline_handler(ctx)?.with_live_stats(true).with_checkpoint_rows(true))),
+    )
+};
+
+/// The handler an `unxv_events` pipeline runs: the configured filters, decoding and row
+/// options, without live stats or checkpoint rows.
+pub fn pipeline_handler(ctx: &PipelineContext) -> anyhow::Result<UnxvEventsHandler> {
+    Ok(ctx
+        .events_handler()?
+        .with_layout_resolver(ctx.layout_resolver.clone())
+        .with_json_limits(ctx.json_limits)
+        .with_type_param_limits(ctx.type_param_limits)
+        .with_bcs_limits(ctx.bcs_limits)
+        .with_metrics(ctx.metrics.clone())
+        .with_indexer_version(ctx.indexer_version.clone())
+        .with_tx_timestamps(ctx.tx_timestamps)
+        .with_sender_first_seen(ctx.sender_first_seen))
+}
+
+/// One processed event: written to `unxv_events`, or set aside in `unxv_dead_letters`. With
+/// [`UnxvEventsHandler::with_checkpoint_rows`], each checkpoint also yields its
+/// `unxv_checkpoints` row.
+#[derive(Debug)]
+pub enum EventRow {
+    Event(UnxvEvent),
+    DeadLetter(UnxvDeadLetter),
+    Checkpoint(UnxvCheckpoint),
+}
+
+impl FieldCount for EventRow {
+    const FIELD_COUNT: usize = if UnxvEvent::FIELD_COUNT > UnxvDeadLetter::FIELD_COUNT {
+        UnxvEvent::FIELD_COUNT
+    } else {
+        UnxvDeadLetter::FIELD_COUNT
+    };
+}
 
 /// Generic Unxversal event catcher. Inserts raw events for modules in the
 /// unxversal package family (modules: futures, gas_futures, perpetuals, x* variants, staking, lending, rewards, dex, options).
 pub struct UnxvEventsHandler {
-    /// Lowercased module names to accept (e.g. "futures", "perpetuals"). Empty => accept all modules under unxversal.
-    modules_filter: Option<HashSet<String>>,
-    /// Lowercased 0x package addresses to accept (comma-separated allowlist). Empty => accept any.
+    /// Modules to accept (e.g. "futures", "perpetuals").
+    modules_filter: ModulesFilter,
+    /// Lowercased module name => struct names to accept within it. An empty set accepts the
+    /// whole module; modules without an entry are only subject to `modules_filter`.
+    event_types_filter: Option<HashMap<String, HashSet<String>>>,
+    /// Strict allowlist in the same shape as `event_types_filter`: modules without an entry are
+    /// rejected. Set by `--balance-affecting-only`.
+    balance_filter: Option<HashMap<String, HashSet<String>>>,
+    /// Full event struct tags to accept, on top of the module and event type filters. None =>
+    /// accept any.
+    struct_tags: Option<StructTagFilter>,
+    /// Normalized 0x package addresses (see [`normalize_address`]) to accept. Empty => accept any.
     package_allowlist: Option<HashSet<String>>,
+    /// Normalized transaction senders to accept. None => accept any.
+    senders_allow: Option<HashSet<String>>,
+    /// Normalized transaction senders to reject; takes precedence over `senders_allow`.
+    senders_deny: Option<HashSet<String>>,
+    /// Canonical types (see [`crate::normalize_type_tag`]) of which an event must have at
+    /// least one among its type parameters. None => accept any.
+    type_param_filter: Option<HashSet<String>>,
+    /// Also reject events without type parameters while `type_param_filter` is set.
+    type_param_filter_drop_untyped: bool,
+    /// Per-type decoders for `contents_json`, consulted before the layout resolver.
+    decoders: Option<Arc<DecoderRegistry>>,
+    /// Resolves event layouts for `contents_json`. With neither this nor a matching decoder,
+    /// `contents_json` is NULL.
+    layout_resolver: Option<Arc<dyn LayoutResolver>>,
+    /// Decoded contents over these limits send the event to `unxv_dead_letters`.
+    json_limits: JsonLimits,
+    /// Caps on the stored `type_params`; the decoder registry still matches on the outer type.
+    type_param_limits: TypeParamLimits,
+    /// Payloads over this size are stored truncated, flagged in `contents_bcs_truncated`.
+    bcs_limits: BcsLimits,
+    metrics: Option<Arc<UnxvMetrics>>,
+    /// Stamped into `indexer_version` on every row when set.
+    indexer_version: Option<String>,
+    /// Fill `tx_timestamp_ms`; otherwise it is left NULL.
+    tx_timestamps: bool,
+    /// Fill `is_new_sender`, maintaining `sender_first_seen` at commit; otherwise it is left NULL.
+    sender_first_seen: bool,
+    /// Feed processed checkpoints to [`crate::stats`]. Only the `unxv_events` pipeline itself
+    /// (or one of its per-module replacements) does, not the handlers wrapping this one.
+    live_stats: bool,
+    /// Emit an [`EventRow::Checkpoint`] per processed checkpoint. Like `live_stats`, only the
+    /// `unxv_events` pipeline itself (and `repair`) does.
+    checkpoint_rows: bool,
 }
 
 impl UnxvEventsHandler {
-    pub fn new(modules_filter: Option<Vec<&str>>, package_allowlist: Option<Vec<String>>) -> Self {
-        let modules_filter = modules_filter.map(|v| v.into_iter().map(|s| s.to_ascii_lowercase()).collect());
-        let package_allowlist = package_allowlist.map(|v| v.into_iter().map(|s| s.to_ascii_lowercase()).collect());
-        Self { modules_filter, package_allowlist }
+    /// Fails on an invalid address in `package_allowlist`.
+    pub fn new(modules_filter: ModulesFilter, package_allowlist: Option<Vec<String>>) -> anyhow::Result<Self> {
+        Self {
+            modules_filter,
+            event_types_filter: None,
+            balance_filter: None,
+            struct_tags: None,
+            package_allowlist: None,
+            senders_allow: None,
+            senders_deny: None,
+            type_param_filter: None,
+            type_param_filter_drop_untyped: false,
+            decoders: None,
+            layout_resolver: None,
+            json_limits: JsonLimits::default(),
+            type_param_limits: TypeParamLimits::default(),
+            bcs_limits: BcsLimits::default(),
+            metrics: None,
+            indexer_version: None,
+            tx_timestamps: false,
+            sender_first_seen: false,
+            live_stats: false,
+            checkpoint_rows: false,
+        }
+        .with_package_allowlist(package_allowlist)
+    }
+
+    /// Replace the module filter given to [`Self::new`].
+    pub fn with_modules(mut self, modules_filter: ModulesFilter) -> Self {
+        self.modules_filter = modules_filter;
+        self
+    }
+
+    /// Replace the package allowlist given to [`Self::new`]. Fails on an invalid address.
+    pub fn with_package_allowlist(mut self, package_allowlist: Option<Vec<String>>) -> anyhow::Result<Self> {
+        self.package_allowlist = package_allowlist
+            .map(|v| v.iter().map(|s| normalize_address(s)).collect::<anyhow::Result<_>>())
+            .transpose()?;
+        Ok(self)
+    }
+
+    /// Narrow filtered modules to specific event structs, e.g. `dex => {"ProtocolFeeTaken"}`.
+    pub fn with_event_types(mut self, event_types: Option<HashMap<String, HashSet<String>>>) -> Self {
+        self.event_types_filter = event_types
+            .map(|filter| filter.into_iter().map(|(m, names)| (m.to_ascii_lowercase(), names)).collect());
+        self
+    }
+
+    /// Only keep events whose struct tag matches `tags`, e.g. one `Liquidated` struct of one
+    /// package rather than every `perpetuals::Liquidated`.
+    pub fn with_struct_tags(mut self, tags: Option<StructTagFilter>) -> Self {
+        self.struct_tags = tags;
+        self
+    }
+
+    /// Only keep the listed events (see [`crate::BALANCE_AFFECTING_EVENTS`]); unlike
+    /// [`Self::with_event_types`], unlisted modules are dropped entirely.
+    pub fn with_balance_filter(mut self, events: Option<HashMap<String, HashSet<String>>>) -> Self {
+        self.balance_filter = events
+            .map(|filter| filter.into_iter().map(|(m, names)| (m.to_ascii_lowercase(), names)).collect());
+        self
+    }
+
+    /// Only keep events from transactions sent by `senders_allow` (if given) and not by
+    /// `senders_deny`. Addresses are normalized, so short or uppercase forms match, and an
+    /// invalid one is an error. The CLI accepts only one of the two; if both are given here, deny
+    /// wins.
+    pub fn with_sender_filter(
+        mut self,
+        senders_allow: Option<HashSet<String>>,
+        senders_deny: Option<HashSet<String>>,
+    ) -> anyhow::Result<Self> {
+        let normalize = |set: HashSet<String>| set.iter().map(|s| normalize_address(s)).collect::<anyhow::Result<_>>();
+        self.senders_allow = senders_allow.map(normalize).transpose()?;
+        self.senders_deny = senders_deny.map(normalize).transpose()?;
+        Ok(self)
+    }
+
+    /// Only keep events with at least one type parameter in `types`, e.g. USDU-denominated
+    /// markets. Entries must already be canonical (see [`crate::normalize_type_tag`]), the
+    /// form event type parameters are compared and stored in. Events without type parameters
+    /// are kept unless `drop_untyped` is set.
+    pub fn with_type_param_filter(mut self, types: Option<HashSet<String>>, drop_untyped: bool) -> Self {
+        self.type_param_filter = types;
+        self.type_param_filter_drop_untyped = drop_untyped;
+        self
+    }
+
+    pub fn with_decoders(mut self, decoders: Arc<DecoderRegistry>) -> Self {
+        self.decoders = Some(decoders);
+        self
+    }
+
+    pub fn with_layout_resolver(mut self, resolver: Arc<dyn LayoutResolver>) -> Self {
+        self.layout_resolver = Some(resolver);
+        self
+    }
+
+    pub fn with_json_limits(mut self, limits: JsonLimits) -> Self {
+        self.json_limits = limits;
+        self
+    }
+
+    pub fn with_type_param_limits(mut self, limits: TypeParamLimits) -> Self {
+        self.type_param_limits = limits;
+        self
+    }
+
+    pub fn with_bcs_limits(mut self, limits: BcsLimits) -> Self {
+        self.bcs_limits = limits;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<UnxvMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn with_indexer_version(mut self, version: Option<String>) -> Self {
+        self.indexer_version = version;
+        self
+    }
+
+    /// Also store each event's transaction time in `tx_timestamp_ms`: the commit time of the
+    /// consensus commit prologue preceding the transaction in its checkpoint, falling back to the
+    /// checkpoint time when there is none.
+    pub fn with_tx_timestamps(mut self, enabled: bool) -> Self {
+        self.tx_timestamps = enabled;
+        self
+    }
+
+    /// Track each sender's first checkpoint in `sender_first_seen` and flag the events of that
+    /// checkpoint with `is_new_sender`. Rows are emitted with `Some(false)` and stamped at
+    /// commit, which has no access to the handler.
+    pub fn with_sender_first_seen(mut self, enabled: bool) -> Self {
+        self.sender_first_seen = enabled;
+        self
+    }
+
+    pub fn with_live_stats(mut self, enabled: bool) -> Self {
+        self.live_stats = enabled;
+        self
+    }
+
+    pub fn with_checkpoint_rows(mut self, enabled: bool) -> Self {
+        self.checkpoint_rows = enabled;
+        self
     }
 
     fn allow_module(&self, module: &str) -> bool {
-        match &self.modules_filter {
+        self.modules_filter.allows(module)
+    }
+
+    fn allow_event(&self, module: &str, event_type: &str) -> bool {
+        let Some(filter) = &self.event_types_filter else { return true };
+        match filter.get(&module.to_ascii_lowercase()) {
             None => true,
-            Some(set) => set.contains(&module.to_ascii_lowercase()),
+            Some(names) => names.is_empty() || names.contains(event_type),
+        }
+    }
+
+    /// The filter that rejects this event, as a metric label; None if it is accepted.
+    fn filter_reason(&self, module: &str, event_type: &str, package: &str) -> Option<&'static str> {
+        if !self.allow_module(module) {
+            Some("module")
+        } else if !self.allow_event(module, event_type) {
+            Some("event_type")
+        } else if !self.allow_balance(module, event_type) {
+            Some("balance")
+        } else if self.package_allowlist.as_ref().is_some_and(|allow| !allow.contains(package)) {
+            Some("package")
+        } else {
+            None
+        }
+    }
+
+    fn count_skipped(&self, reason: &str, events: usize) {
+        if let Some(metrics) = &self.metrics {
+            metrics.events_skipped.with_label_values(&[reason]).inc_by(events as u64);
+        }
+    }
+
+    fn allow_struct_tag(&self, tag: &StructTag) -> bool {
+        self.struct_tags.as_ref().map_or(true, |filter| filter.matches(tag))
+    }
+
+    fn allow_balance(&self, module: &str, event_type: &str) -> bool {
+        let Some(filter) = &self.balance_filter else { return true };
+        filter
+            .get(&module.to_ascii_lowercase())
+            .is_some_and(|names| names.is_empty() || names.contains(event_type))
+    }
+
+    fn allow_sender(&self, sender: &str) -> bool {
+        if self.senders_deny.as_ref().is_some_and(|deny| deny.contains(sender)) {
+            return false;
+        }
+        self.senders_allow.as_ref().map_or(true, |allow| allow.contains(sender))
+    }
+
+    /// Whether an event with these canonical type parameters passes `type_param_filter`.
+    fn allow_type_params(&self, params: impl IntoIterator<Item = String>) -> bool {
+        let Some(filter) = &self.type_param_filter else { return true };
+        let mut untyped = true;
+        for param in params {
+            if filter.contains(&param) {
+                return true;
+            }
+            untyped = false;
+        }
+        untyped && !self.type_param_filter_drop_untyped
+    }
+
+    /// Whether a stored row passes the same filters as ingestion, for reads of `unxv_events`
+    /// such as `export`. Stored type parameters are normalized again, since rows written before
+    /// they were canonical have short addresses. A row whose package or sender isn't a valid
+    /// address matches nothing.
+    pub fn accepts(&self, event: &UnxvEvent) -> bool {
+        let params: Vec<String> = serde_json::from_value(event.type_params.clone()).unwrap_or_default();
+        normalize_address(&event.package)
+            .is_ok_and(|package| self.filter_reason(&event.module, &event.event_type, &package).is_none())
+            && (self.struct_tags.is_none() || stored_struct_tag(event).is_some_and(|tag| self.allow_struct_tag(&tag)))
+            && normalize_address(&event.sender).is_ok_and(|sender| self.allow_sender(&sender))
+            && self.allow_type_params(params.into_iter().map(|p| normalize_type_tag(&p).unwrap_or(p)))
+    }
+
+    /// Best-effort decode of the event contents. Failures are logged and counted, never fatal.
+    fn decode_contents(&self, type_tag: &StructTag, contents: &[u8]) -> Option<serde_json::Value> {
+        let registered = self
+            .decoders
+            .as_ref()
+            .and_then(|r| r.get(type_tag.module.as_str(), type_tag.name.as_str()));
+        let (reason, err) = if let Some(decoder) = registered {
+            match decoder.decode(contents) {
+                Ok(json) => return Some(json),
+                Err(e) => ("decode", e),
+            }
+        } else {
+            let resolver = self.layout_resolver.as_ref()?;
+            match resolver.resolve(type_tag) {
+                Ok(Some(layout)) => match decode_to_json(contents, &layout) {
+                    Ok(json) => return Some(json),
+                    Err(e) => ("decode", e),
+                },
+                Ok(None) => ("unknown_layout", anyhow::anyhow!("no layout registered")),
+                Err(e) => ("resolve", e),
+            }
+        };
+        warn!("Failed to decode contents of {type_tag}: {err:#}");
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .contents_decode_failures
+                .with_label_values(&[type_tag.module.as_str(), type_tag.name.as_str(), reason])
+                .inc();
+        }
+        None
+    }
+
+    /// `contents` as stored in `unxv_events`: cut to `--contents-bcs-max-bytes`, with a warning
+    /// and a count when that loses data.
+    fn stored_contents(&self, digest: &str, event_index: i64, tag: &StructTag, contents: &[u8]) -> (Vec<u8>, bool) {
+        let (stored, truncated) = self.bcs_limits.truncate(contents);
+        if truncated {
+            warn!(digest, event_index, size = contents.len(), "Truncated contents_bcs of {tag}");
+            if let Some(metrics) = &self.metrics {
+                metrics
+                    .truncated_payloads
+                    .with_label_values(&[tag.module.as_str(), tag.name.as_str()])
+                    .inc();
+            }
+        }
+        (stored, truncated)
+    }
+
+    /// See [`emitter_object`]. None without decoded contents or a layout for the type, even
+    /// when a registered decoder produced the contents.
+    fn emitter_object(&self, type_tag: &StructTag, contents_json: Option<&serde_json::Value>) -> Option<String> {
+        let layout = self.layout_resolver.as_ref()?.resolve(type_tag).ok()??;
+        emitter_object(&layout, contents_json?)
+    }
+}
+
+impl UnxvEventsHandler {
+    /// Rebuild the `unxv_events` row of a dead-lettered event with the current decoder and
+    /// limits, or return why it still fails. Transaction-level columns (`tx_timestamp_ms`,
+    /// `gas_used`, `tx_success`, `call_packages`) aren't kept in dead letters and stay NULL.
+    pub fn replay(&self, dead_letter: &UnxvDeadLetter) -> Result<UnxvEvent, String> {
+        let params: Vec<String> = serde_json::from_value(dead_letter.type_params.clone()).unwrap_or_default();
+        let base = format!("{}::{}::{}", dead_letter.package, dead_letter.module, dead_letter.event_type);
+        // The full type can be rebuilt unless its parameters were truncated; layouts are looked
+        // up by module and name, so the bare struct still decodes.
+        let full = (!params.iter().any(|p| p.contains(TYPE_PARAMS_TRUNCATED)))
+            .then(|| if params.is_empty() { base.clone() } else { format!("{base}<{}>", params.join(", ")) })
+            .and_then(|tag| parse_struct_tag(&tag).ok());
+        let type_tag = match &full {
+            Some(tag) => tag.clone(),
+            None => parse_struct_tag(&base).map_err(|e| format!("{e:#}"))?,
+        };
+
+        let (type_param_0, type_param_1) = full.as_ref().map(leading_type_params).unwrap_or_default();
+        let contents_json = self.decode_contents(&type_tag, &dead_letter.contents_bcs);
+        let emitter_object = self.emitter_object(&type_tag, contents_json.as_ref());
+        let (contents_bcs, contents_bcs_truncated) =
+            self.stored_contents(&dead_letter.digest, dead_letter.event_index, &type_tag, &dead_letter.contents_bcs);
+        if let Some((_, reason)) = contents_json.as_ref().and_then(|json| self.json_limits.check(json).err()) {
+            return Err(reason);
         }
+        Ok(UnxvEvent {
+            digest: dead_letter.digest.clone(),
+            event_index: dead_letter.event_index,
+            sender: dead_letter.sender.clone(),
+            checkpoint: dead_letter.checkpoint,
+            checkpoint_timestamp_ms: dead_letter.checkpoint_timestamp_ms,
+            package: dead_letter.package.clone(),
+            module: dead_letter.module.clone(),
+            event_type: dead_letter.event_type.clone(),
+            type_params: dead_letter.type_params.clone(),
+            type_params_key: Some(type_params_key(&params)),
+            type_params_truncated: full.is_none(),
+            type_hash: full.as_ref().map(type_hash),
+            event_type_full: full.as_ref().map(|tag| tag.to_canonical_string(true)),
+            contents_bcs,
+            contents_bcs_truncated,
+            contents_json,
+            indexer_version: self.indexer_version.clone(),
+            tx_timestamp_ms: None,
+            gas_used: None,
+            tx_success: None,
+            call_packages: None,
+            is_new_sender: None,
+            type_param_0,
+            type_param_1,
+            emitter_object,
+        })
     }
 }
 
+impl UnxvEventsHandler {
+    /// Rebuild the decoded columns of a stored event from its `contents_bcs` with the current
+    /// decoder and limits, or return why it still doesn't decode. The type parameter columns
+    /// are only rebuilt for rows with `event_type_full`, since older rows don't keep the
+    /// parameters in a form that parses back.
+    pub fn redecode(&self, event: &UnxvEvent) -> Result<UnxvEvent, String> {
+        if event.contents_bcs_truncated {
+            return Err("contents_bcs was stored truncated".to_string());
+        }
+        let type_tag = stored_struct_tag(event)
+            .ok_or_else(|| format!("Unparsable event type {}::{}::{}", event.package, event.module, event.event_type))?;
+        let contents_json = self
+            .decode_contents(&type_tag, &event.contents_bcs)
+            .ok_or_else(|| format!("No decoder or layout decodes {type_tag}"))?;
+        if let Err((_, reason)) = self.json_limits.check(&contents_json) {
+            return Err(reason);
+        }
+        let mut row = UnxvEvent {
+            emitter_object: self.emitter_object(&type_tag, Some(&contents_json)),
+            contents_json: Some(contents_json),
+            ..event.clone()
+        };
+        if event.event_type_full.is_some() {
+            let type_params = self.type_param_limits.render(&type_tag);
+            (row.type_param_0, row.type_param_1) = leading_type_params(&type_tag);
+            row.type_params = type_params.json;
+            row.type_params_key = Some(type_params.key);
+            row.type_params_truncated = type_params.truncated;
+            row.type_hash = Some(type_params.type_hash);
+        }
+        Ok(row)
+    }
+}
+
+/// Gas the transaction actually paid for: computation + storage - storage rebate. Negative when
+/// the rebate outweighs the charges (e.g. a transaction that mostly deletes objects).
+pub fn net_gas_used(summary: &GasCostSummary) -> i64 {
+    summary.computation_cost as i64 + summary.storage_cost as i64 - summary.storage_rebate as i64
+}
+
 impl Processor for UnxvEventsHandler {
     const NAME: &'static str = "unxv_events";
-    type Value = UnxvEvent;
+    type Value = EventRow;
 
     fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<Self::Value>> {
         let mut out = Vec::new();
+        let mut tx_timestamp_ms = checkpoint.checkpoint_summary.timestamp_ms as i64;
         for tx in &checkpoint.transactions {
+            if let Some(ms) = tx_commit_timestamp_ms(&tx.transaction) {
+                tx_timestamp_ms = ms as i64;
+            }
             let Some(events) = &tx.events else { continue; };
-            let package = try_extract_move_call_package(tx).unwrap_or_default();
             let checkpoint_timestamp_ms = checkpoint.checkpoint_summary.timestamp_ms as i64;
             let checkpoint_no = checkpoint.checkpoint_summary.sequence_number as i64;
             let digest = tx.transaction.digest().to_string();
+            // `SuiAddress` displays in the padded form already.
+            let sender = tx.transaction.sender_address().to_string();
+            let gas_used = net_gas_used(tx.effects.gas_cost_summary());
+            let tx_success = tx.effects.status().is_ok();
+            let call_packages = serde_json::Value::from(move_call_packages(&tx.transaction));
+            if !self.allow_sender(&sender) {
+                self.count_skipped("sender", events.data.len());
+                continue;
+            }
 
             for (idx, ev) in events.data.iter().enumerate() {
                 let type_tag = &ev.type_;
                 let module_name = type_tag.module.to_string();
                 let struct_name = type_tag.name.to_string();
-                // package address as 0x string
-                let addr_hex = type_tag.address.to_string().to_ascii_lowercase();
-                if !self.allow_module(&module_name) { continue; }
-                if let Some(allow) = &self.package_allowlist {
-                    if !allow.contains(&addr_hex) { continue; }
+                // Originating package: the address that defines the event struct, as a
+                // 0x-prefixed, lowercased, 32-byte hex string.
+                let package = type_tag.address.to_canonical_string(true);
+                if let Some(reason) = self.filter_reason(&module_name, &struct_name, &package) {
+                    self.count_skipped(reason, 1);
+                    continue;
+                }
+                if !self.allow_struct_tag(type_tag) {
+                    self.count_skipped("struct_tag", 1);
+                    continue;
+                }
+                if !self.allow_type_params(type_tag.type_params.iter().map(|p| p.to_canonical_string(true))) {
+                    self.count_skipped("type_param", 1);
+                    continue;
                 }
 
-                let type_params = serde_json::json!(type_tag.type_params.iter().map(|t| t.to_string()).collect::<Vec<_>>());
-                let event_digest = format!("{digest}{idx}");
+                let type_params = self.type_param_limits.render(type_tag);
+                if type_params.truncated {
+                    debug!(%digest, event_index = idx, "Truncated type_params of {module_name}::{struct_name}");
+                }
+                let (type_param_0, type_param_1) = leading_type_params(type_tag);
+                let contents_json = self.decode_contents(type_tag, &ev.contents);
+                let emitter_object = self.emitter_object(type_tag, contents_json.as_ref());
+                let (contents_bcs, contents_bcs_truncated) =
+                    self.stored_contents(&digest, idx as i64, type_tag, &ev.contents);
                 let row = UnxvEvent {
-                    event_digest,
                     digest: digest.clone(),
-                    sender: tx.transaction.sender_address().to_string(),
+                    event_index: idx as i64,
+                    sender: sender.clone(),
                     checkpoint: checkpoint_no,
                     checkpoint_timestamp_ms,
-                    package: package.clone(),
+                    tx_timestamp_ms: self.tx_timestamps.then_some(tx_timestamp_ms),
+                    gas_used: Some(gas_used),
+                    tx_success: Some(tx_success),
+                    package,
                     module: module_name,
                     event_type: struct_name,
-                    type_params,
-                    contents_bcs: ev.contents.clone(),
+                    type_params: type_params.json,
+                    type_params_key: Some(type_params.key),
+                    type_params_truncated: type_params.truncated,
+                    type_hash: Some(type_params.type_hash),
+                    event_type_full: Some(type_tag.to_canonical_string(true)),
+                    type_param_0,
+                    type_param_1,
+                    call_packages: Some(call_packages.clone()),
+                    is_new_sender: self.sender_first_seen.then_some(false),
+                    contents_bcs,
+                    contents_bcs_truncated,
+                    contents_json,
+                    indexer_version: self.indexer_version.clone(),
+                    emitter_object,
                 };
                 debug!("Observed Unxv event {:?}", row);
-                out.push(row);
+                if let Some(metrics) = &self.metrics {
+                    metrics.events_processed.with_label_values(&[&row.module, &row.event_type]).inc();
+                }
+                let violation = row.contents_json.as_ref().and_then(|json| self.json_limits.check(json).err());
+                match violation {
+                    None => out.push(EventRow::Event(row)),
+                    Some((label, reason)) => {
+                        warn!(digest = %row.digest, event_index = row.event_index, "Dead-lettering event: {reason}");
+                        if let Some(metrics) = &self.metrics {
+                            metrics.dead_letters.with_label_values(&[Self::NAME, label]).inc();
+                        }
+                        out.push(EventRow::DeadLetter(UnxvDeadLetter {
+                            pipeline: Self::NAME.to_string(),
+                            digest: row.digest,
+                            event_index: row.event_index,
+                            sender: row.sender,
+                            checkpoint: row.checkpoint,
+                            checkpoint_timestamp_ms: row.checkpoint_timestamp_ms,
+                            package: row.package,
+                            module: row.module,
+                            event_type: row.event_type,
+                            type_params: row.type_params,
+                            // Kept whole, so a replay can decode it.
+                            contents_bcs: ev.contents.clone(),
+                            reason,
+                        }));
+                    }
+                }
             }
         }
+        let summary = &checkpoint.checkpoint_summary;
+        if self.checkpoint_rows {
+            let event_count = out.iter().filter(|row| matches!(row, EventRow::Event(_))).count();
+            out.push(EventRow::Checkpoint(UnxvCheckpoint {
+                checkpoint: summary.sequence_number as i64,
+                tx_count: checkpoint.transactions.len() as i64,
+                event_count: event_count as i64,
+            }));
+        }
+        if let Some(metrics) = &self.metrics {
+            let events = out.iter().filter(|row| !matches!(row, EventRow::Checkpoint(_))).count();
+            metrics.events_per_checkpoint.observe(events as f64);
+            metrics.record_checkpoint(summary.sequence_number, summary.timestamp_ms as i64, Utc::now().timestamp_millis());
+        }
+        if self.live_stats {
+            stats::global().record_checkpoint(summary.sequence_number, summary.timestamp_ms as i64, Utc::now());
+        }
         Ok(out)
     }
 }
@@ -87,11 +647,1205 @@ impl Handler for UnxvEventsHandler {
     type Store = Db;
 
     async fn commit<'a>(values: &[Self::Value], conn: &mut Connection<'a>) -> anyhow::Result<usize> {
-        Ok(diesel::insert_into(unxv_events::table)
-            .values(values)
+        commit_rows(Self::NAME, values, conn).await
+    }
+}
+
+/// The commit of every pipeline writing [`EventRow`]s, `pipeline` naming it in retries and
+/// quarantine logs. Rows land in the same tables whichever pipeline commits them. Committed
+/// events go to [`kafka`], then to [`stream`] subscribers.
+pub async fn commit_rows(pipeline: &str, values: &[EventRow], conn: &mut AsyncPgConnection) -> anyhow::Result<usize> {
+    let (rows, committed) = write_batch(pipeline, values, conn).await?;
+    let events: Vec<&UnxvEvent> = committed
+        .iter()
+        .filter_map(|v| match v {
+            EventRow::Event(event) => Some(event),
+            EventRow::DeadLetter(_) | EventRow::Checkpoint(_) => None,
+        })
+        .collect();
+    // After the commit, so Kafka never sees an event before Postgres has it. A failure fails the
+    // commit and the framework retries it, republishing the batch over rows already written. Of
+    // a batch that needed quarantining, only the rows that committed are published.
+    kafka::publish(pipeline, events.iter().copied()).await?;
+    // After Kafka, so a retried commit doesn't stream the batch twice.
+    stream::publish(events.iter().copied());
+    stats::global().record_events(events.iter().map(|e| e.module.as_str()), Utc::now());
+    Ok(rows)
+}
+
+/// Write `values`, setting aside the rows the database rejects (see [`quarantine::isolate`]).
+/// Returns the rows written and the values that committed.
+async fn write_batch<'v>(
+    pipeline: &str,
+    values: &'v [EventRow],
+    conn: &mut AsyncPgConnection,
+) -> anyhow::Result<(usize, Vec<&'v EventRow>)> {
+    match db::retry_commit(pipeline, conn, |conn| write_rows(values, conn).scope_boxed()).await {
+        Ok(rows) => Ok((rows, values.iter().collect())),
+        Err(err) if db::data_error_reason(&err).is_some() => {
+            warn!(pipeline, rows = values.len(), "Commit rejected by the database, isolating the offending rows: {err:#}");
+            quarantine::isolate(
+                pipeline,
+                values,
+                conn,
+                err,
+                |part, conn| write_rows(part, conn).scope_boxed(),
+                |value, error| quarantined_row(pipeline, value, error),
+            )
+            .await
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Write one commit's rows: events to their schemas, dead letters, checkpoint rows and the
+/// batch notification. Safe to repeat after a partial write.
+async fn write_rows(values: &[EventRow], conn: &mut AsyncPgConnection) -> anyhow::Result<usize> {
+    let mut events = vec![];
+    let mut dead_letters = vec![];
+    let mut checkpoints = vec![];
+    for value in values {
+        match value {
+            EventRow::Event(event) => events.push(event),
+            EventRow::DeadLetter(dead_letter) => dead_letters.push(dead_letter),
+            EventRow::Checkpoint(checkpoint) => checkpoints.push(checkpoint),
+        }
+    }
+
+    let events = &events;
+    let mut rows = if events.iter().any(|e| e.is_new_sender.is_some()) {
+        // One transaction, so the sender_first_seen rows stay locked until these events are
+        // visible to a concurrent commit that moves a sender's first checkpoint earlier.
+        conn.transaction(|conn| {
+            async move {
+                let stamped = stamp_new_senders(events, conn).await?;
+                insert_by_schema(schemas::global(), &stamped.iter().collect::<Vec<_>>(), conn).await
+            }
+            .scope_boxed()
+        })
+        .await?
+    } else {
+        insert_by_schema(schemas::global(), events, conn).await?
+    };
+    if let Some(payload) = notify::Payload::summarize(events) {
+        notify::send(&payload, conn).await?;
+    }
+    for chunk in dead_letters.chunks(max_insert_rows(UnxvDeadLetter::FIELD_COUNT)) {
+        rows += diesel::insert_into(unxv_dead_letters::table)
+            .values(chunk.to_vec())
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await?;
+    }
+    for chunk in checkpoints.chunks(max_insert_rows(UnxvCheckpoint::FIELD_COUNT)) {
+        rows += diesel::insert_into(unxv_checkpoints::table)
+            .values(chunk.to_vec())
             .on_conflict_do_nothing()
             .execute(conn)
-            .await?)
+            .await?;
+    }
+    Ok(rows)
+}
+
+/// The struct tag of a stored event: `event_type_full`, or for rows written before it existed
+/// the tag without type parameters, which only tags without type parameters match.
+fn stored_struct_tag(event: &UnxvEvent) -> Option<StructTag> {
+    match &event.event_type_full {
+        Some(full) => parse_struct_tag(full).ok(),
+        None => parse_struct_tag(&format!("{}::{}::{}", event.package, event.module, event.event_type)).ok(),
+    }
+}
+
+/// The quarantine row for `value`, attributed to `pipeline`, the one whose commit rejected it.
+fn quarantined_row(pipeline: &str, value: &EventRow, error: &str) -> anyhow::Result<UnxvQuarantinedRow> {
+    match value {
+        EventRow::Event(e) => {
+            quarantine::quarantined(pipeline, "unxv_events", &e.digest, e.event_index, e.checkpoint, e, error)
+        }
+        EventRow::DeadLetter(d) => {
+            quarantine::quarantined(pipeline, "unxv_dead_letters", &d.digest, d.event_index, d.checkpoint, d, error)
+        }
+        // Keyed by checkpoint, so each rejected checkpoint row keeps its own quarantine row.
+        EventRow::Checkpoint(c) => {
+            quarantine::quarantined(pipeline, "unxv_checkpoints", &c.checkpoint.to_string(), 0, c.checkpoint, c, error)
+        }
+    }
+}
+
+/// Events of modules mapped to their own schema in `module_schemas` go there; the rest to the
+/// default one.
+async fn insert_by_schema(
+    module_schemas: &ModuleSchemas,
+    events: &[&UnxvEvent],
+    conn: &mut AsyncPgConnection,
+) -> anyhow::Result<usize> {
+    let mut by_schema: BTreeMap<Option<&str>, Vec<&UnxvEvent>> = BTreeMap::new();
+    for event in events {
+        by_schema.entry(module_schemas.schema(&event.module)).or_default().push(event);
+    }
+    let mut rows = 0;
+    for (schema, events) in by_schema {
+        rows += match schema {
+            None => insert_events(&events, conn).await?,
+            Some(schema) => {
+                // `unxv_events` is unqualified in the generated SQL, so a transaction-local
+                // search_path of just `schema` points it at `schema.unxv_events`.
+                conn.transaction(|conn| {
+                    async move {
+                        diesel::sql_query(format!("SET LOCAL search_path TO {schema}")).execute(conn).await?;
+                        insert_events(&events, conn).await
+                    }
+                    .scope_boxed()
+                })
+                .await?
+            }
+        };
+    }
+    Ok(rows)
+}
+
+/// Record each sender's earliest checkpoint. Only moves earlier, so reprocessing or a later
+/// checkpoint committing first can't push it forward.
+const UPSERT_FIRST_SEEN: &str = "\
+    INSERT INTO sender_first_seen (sender, first_checkpoint, first_timestamp_ms) \
+    SELECT * FROM unnest($1::TEXT[], $2::BIGINT[], $3::BIGINT[]) \
+    ON CONFLICT (sender) DO UPDATE SET \
+        first_checkpoint = excluded.first_checkpoint, \
+        first_timestamp_ms = excluded.first_timestamp_ms \
+    WHERE excluded.first_checkpoint < sender_first_seen.first_checkpoint";
+
+/// Events committed earlier and stamped new whose sender has since been seen in an earlier
+/// checkpoint (concurrent commits land out of order).
+const UNSTAMP_MOVED: &str = "\
+    UPDATE unxv_events e SET is_new_sender = false \
+    FROM sender_first_seen f \
+    WHERE f.sender = ANY($1) AND e.sender = f.sender \
+      AND e.is_new_sender AND e.checkpoint > f.first_checkpoint";
+
+#[derive(QueryableByName)]
+struct FirstSeen {
+    #[diesel(sql_type = Text)]
+    sender: String,
+    #[diesel(sql_type = BigInt)]
+    first_checkpoint: i64,
+}
+
+/// Update `sender_first_seen` from `events` and return them with `is_new_sender` set: true for
+/// every event in its sender's first checkpoint.
+async fn stamp_new_senders(events: &[&UnxvEvent], conn: &mut AsyncPgConnection) -> anyhow::Result<Vec<UnxvEvent>> {
+    let mut earliest: BTreeMap<&str, (i64, i64)> = BTreeMap::new();
+    for e in events {
+        let entry = earliest.entry(&e.sender).or_insert((e.checkpoint, e.checkpoint_timestamp_ms));
+        if e.checkpoint < entry.0 {
+            *entry = (e.checkpoint, e.checkpoint_timestamp_ms);
+        }
+    }
+    let senders: Vec<&str> = earliest.keys().copied().collect();
+    let (checkpoints, timestamps): (Vec<i64>, Vec<i64>) = earliest.values().copied().unzip();
+    diesel::sql_query(UPSERT_FIRST_SEEN)
+        .bind::<Array<Text>, _>(&senders)
+        .bind::<Array<BigInt>, _>(&checkpoints)
+        .bind::<Array<BigInt>, _>(&timestamps)
+        .execute(conn)
+        .await?;
+    diesel::sql_query(UNSTAMP_MOVED).bind::<Array<Text>, _>(&senders).execute(conn).await?;
+    let first: HashMap<String, i64> = diesel::sql_query("SELECT sender, first_checkpoint FROM sender_first_seen WHERE sender = ANY($1)")
+        .bind::<Array<Text>, _>(&senders)
+        .load::<FirstSeen>(conn)
+        .await?
+        .into_iter()
+        .map(|f| (f.sender, f.first_checkpoint))
+        .collect();
+
+    Ok(events
+        .iter()
+        .map(|e| UnxvEvent {
+            is_new_sender: Some(first.get(&e.sender) == Some(&e.checkpoint)),
+            ..(*e).clone()
+        })
+        .collect())
+}
+
+/// Insert with the configured [`InsertMethod`], recording its throughput. Returns the rows
+/// actually inserted or, under [`db::reprocess`], updated.
+async fn insert_events(events: &[&UnxvEvent], conn: &mut AsyncPgConnection) -> anyhow::Result<usize> {
+    let method = db::insert_method();
+    let start = Instant::now();
+    let inserted = match method {
+        InsertMethod::Rows => insert_event_rows(events, db::reprocess(), conn).await?,
+        InsertMethod::Unnest => insert_events_unnest(events, db::reprocess(), conn).await?,
+    };
+    db::record_insert("unxv_events", method, events.len(), start.elapsed());
+    Ok(inserted)
+}
+
+/// Insert in chunks that stay under the bind parameter cap: sequential batches and replays
+/// aren't bounded by the framework's concurrent chunking. With `reprocess`, rows already
+/// stored get their [`DECODED_COLUMNS`] overwritten.
+async fn insert_event_rows(
+    events: &[&UnxvEvent],
+    reprocess: bool,
+    conn: &mut AsyncPgConnection,
+) -> anyhow::Result<usize> {
+    let mut inserted = 0;
+    for chunk in events.chunks(max_insert_rows(UnxvEvent::FIELD_COUNT)) {
+        let insert = diesel::insert_into(unxv_events::table)
+            .values(chunk.to_vec())
+            .on_conflict((unxv_events::digest, unxv_events::event_index));
+        inserted += if reprocess {
+            // Keep in step with DECODED_COLUMNS.
+            insert
+                .do_update()
+                .set((
+                    unxv_events::contents_json.eq(excluded(unxv_events::contents_json)),
+                    unxv_events::emitter_object.eq(excluded(unxv_events::emitter_object)),
+                    unxv_events::type_params.eq(excluded(unxv_events::type_params)),
+                    unxv_events::type_params_truncated.eq(excluded(unxv_events::type_params_truncated)),
+                    unxv_events::type_param_0.eq(excluded(unxv_events::type_param_0)),
+                    unxv_events::type_param_1.eq(excluded(unxv_events::type_param_1)),
+                    unxv_events::type_params_key.eq(excluded(unxv_events::type_params_key)),
+                    unxv_events::type_hash.eq(excluded(unxv_events::type_hash)),
+                    unxv_events::event_type_full.eq(excluded(unxv_events::event_type_full)),
+                ))
+                .execute(conn)
+                .await?
+        } else {
+            insert.do_nothing().execute(conn).await?
+        };
+    }
+    Ok(inserted)
+}
+
+/// Columns derived from decoding an event's type and contents, which [`db::reprocess`]
+/// overwrites on rows already stored. The others record the transaction and don't change.
+const DECODED_COLUMNS: &[&str] = &[
+    "contents_json",
+    "emitter_object",
+    "type_params",
+    "type_params_truncated",
+    "type_param_0",
+    "type_param_1",
+    "type_params_key",
+    "type_hash",
+    "event_type_full",
+];
+
+/// Column order of the [`UnxvEvent`] binds in [`insert_events_unnest`], without the conflict
+/// clause.
+const INSERT_EVENTS_UNNEST: &str = "\
+    INSERT INTO unxv_events (
+        digest, event_index, sender, checkpoint, checkpoint_timestamp_ms, package, module, event_type,
+        type_params, contents_bcs, contents_json, indexer_version, tx_timestamp_ms, gas_used, tx_success,
+        type_params_truncated, type_hash, call_packages, is_new_sender, type_param_0, type_param_1,
+        type_params_key, emitter_object, contents_bcs_truncated, event_type_full
+    )
+    SELECT * FROM UNNEST(
+        $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22,
+        $23, $24, $25
+    )
+    ON CONFLICT (digest, event_index)";
+
+/// One statement for the whole batch: each column is bound as a single array, so the bind
+/// parameter cap doesn't apply and Postgres parses and plans once. `reprocess` as for
+/// [`insert_event_rows`].
+async fn insert_events_unnest(
+    events: &[&UnxvEvent],
+    reprocess: bool,
+    conn: &mut AsyncPgConnection,
+) -> anyhow::Result<usize> {
+    if events.is_empty() {
+        return Ok(0);
+    }
+    macro_rules! column {
+        ($field:ident) => {
+            events.iter().map(|e| e.$field.clone()).collect::<Vec<_>>()
+        };
     }
+    let on_conflict = if reprocess {
+        let set: Vec<String> = DECODED_COLUMNS.iter().map(|c| format!("{c} = EXCLUDED.{c}")).collect();
+        format!("DO UPDATE SET {}", set.join(", "))
+    } else {
+        "DO NOTHING".to_string()
+    };
+    Ok(diesel::sql_query(format!("{INSERT_EVENTS_UNNEST} {on_conflict}"))
+        .bind::<Array<Text>, _>(column!(digest))
+        .bind::<Array<BigInt>, _>(column!(event_index))
+        .bind::<Array<Text>, _>(column!(sender))
+        .bind::<Array<BigInt>, _>(column!(checkpoint))
+        .bind::<Array<BigInt>, _>(column!(checkpoint_timestamp_ms))
+        .bind::<Array<Text>, _>(column!(package))
+        .bind::<Array<Text>, _>(column!(module))
+        .bind::<Array<Text>, _>(column!(event_type))
+        .bind::<Array<Jsonb>, _>(column!(type_params))
+        .bind::<Array<Bytea>, _>(column!(contents_bcs))
+        .bind::<Array<Nullable<Jsonb>>, _>(column!(contents_json))
+        .bind::<Array<Nullable<Text>>, _>(column!(indexer_version))
+        .bind::<Array<Nullable<BigInt>>, _>(column!(tx_timestamp_ms))
+        .bind::<Array<Nullable<BigInt>>, _>(column!(gas_used))
+        .bind::<Array<Nullable<Bool>>, _>(column!(tx_success))
+        .bind::<Array<Bool>, _>(column!(type_params_truncated))
+        .bind::<Array<Nullable<Text>>, _>(column!(type_hash))
+        .bind::<Array<Nullable<Jsonb>>, _>(column!(call_packages))
+        .bind::<Array<Nullable<Bool>>, _>(column!(is_new_sender))
+        .bind::<Array<Nullable<Text>>, _>(column!(type_param_0))
+        .bind::<Array<Nullable<Text>>, _>(column!(type_param_1))
+        .bind::<Array<Nullable<Text>>, _>(column!(type_params_key))
+        .bind::<Array<Nullable<Text>>, _>(column!(emitter_object))
+        .bind::<Array<Bool>, _>(column!(contents_bcs_truncated))
+        .bind::<Array<Nullable<Text>>, _>(column!(event_type_full))
+        .execute(conn)
+        .await?)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::builtin::unxversal_layouts;
+    use crate::test_utils::{
+        event, fixtures, fixtures_dir, load_checkpoint, sender_address, write_checkpoint, CheckpointBuilder, TestDb,
+        PACKAGE,
+    };
+    use move_core_types::account_address::AccountAddress;
+    use sui_types::base_types::ObjectID;
+    use unxv_events::dex::ProtocolFeeTaken;
+    use unxv_events::lending::DebtRepaid;
+    use unxv_events::types::{TypeName, ID};
+
+    const FOREIGN: &str = "0xe11e";
+
+    fn fee_taken(sender: u8, amount: u64) -> Vec<u8> {
+        bcs::to_bytes(&ProtocolFeeTaken {
+            payer: sender_address(sender).into(),
+            base_fee_asset_unxv: false,
+            amount,
+            asset: TypeName { name: format!("{}::sui::SUI", "0".repeat(63) + "2") },
+            timestamp_ms: 1_000,
+        })
+        .unwrap()
+    }
+
+    fn debt_repaid(sender: u8) -> Vec<u8> {
+        bcs::to_bytes(&DebtRepaid {
+            market_id: ID { bytes: AccountAddress::from_hex_literal("0xb0b").unwrap() },
+            who: sender_address(sender).into(),
+            amount: 10,
+            remaining_principal: 90,
+            timestamp_ms: 1_000,
+        })
+        .unwrap()
+    }
+
+    /// Sender 1 pays a fee and repays debt through the Unxversal package, sender 2 pays a fee to
+    /// a foreign package with the same module, and sender 3 emits an event of a module outside
+    /// the Unxversal family.
+    fn checkpoint(sequence_number: u64) -> Arc<CheckpointData> {
+        CheckpointBuilder::new(sequence_number)
+            .transaction(
+                1,
+                &[(PACKAGE, "dex", "swap")],
+                vec![
+                    event(1, &format!("{PACKAGE}::dex::ProtocolFeeTaken"), fee_taken(1, 5)),
+                    event(1, &format!("{PACKAGE}::lending::DebtRepaid"), debt_repaid(1)),
+                ],
+            )
+            .transaction(
+                2,
+                &[(FOREIGN, "dex", "swap")],
+                vec![event(2, &format!("{FOREIGN}::dex::ProtocolFeeTaken"), fee_taken(2, 7))],
+            )
+            .transaction(3, &[], vec![event(3, &format!("{PACKAGE}::vault::Opened"), vec![])])
+            .build()
+    }
+
+    fn handler() -> UnxvEventsHandler {
+        UnxvEventsHandler::new(ModulesFilter::default(), None)
+            .unwrap()
+            .with_layout_resolver(Arc::new(unxversal_layouts(None).unwrap()))
+            .with_checkpoint_rows(true)
+    }
+
+    fn events(rows: &[EventRow]) -> Vec<&UnxvEvent> {
+        rows.iter()
+            .filter_map(|row| match row {
+                EventRow::Event(event) => Some(event),
+                EventRow::DeadLetter(_) | EventRow::Checkpoint(_) => None,
+            })
+            .collect()
+    }
+
+    fn names(rows: &[EventRow]) -> Vec<(String, String, String)> {
+        events(rows)
+            .into_iter()
+            .map(|e| (e.package.clone(), e.module.clone(), e.event_type.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn processes_unxversal_events() {
+        let rows = handler().process(&checkpoint(7)).unwrap();
+        let package = normalize_address(PACKAGE).unwrap();
+        assert_eq!(
+            names(&rows),
+            [
+                (package.clone(), "dex".into(), "ProtocolFeeTaken".into()),
+                (package.clone(), "lending".into(), "DebtRepaid".into()),
+                (normalize_address(FOREIGN).unwrap(), "dex".into(), "ProtocolFeeTaken".into()),
+            ]
+        );
+
+        let fee = events(&rows)[0];
+        assert_eq!(fee.checkpoint, 7);
+        assert_eq!(fee.event_index, 0);
+        assert_eq!(fee.sender, normalize_address(&sender_address(1).to_string()).unwrap());
+        assert_eq!(fee.call_packages, Some(serde_json::json!([package])));
+        assert_eq!(fee.contents_json.as_ref().unwrap()["amount"], "5");
+        let repaid = events(&rows)[1];
+        assert_eq!(repaid.event_index, 1);
+        assert_eq!(repaid.emitter_object, Some(normalize_address("0xb0b").unwrap()));
+
+        let Some(EventRow::Checkpoint(row)) = rows.last() else { panic!("no checkpoint row") };
+        assert_eq!((row.checkpoint, row.tx_count, row.event_count), (7, 3, 3));
+    }
+
+    #[test]
+    fn gas_used_is_net_of_the_storage_rebate() {
+        // Computation, storage, storage rebate and non-refundable storage fee.
+        assert_eq!(net_gas_used(&GasCostSummary::new(1_000_000, 2_500_000, 1_800_000, 20_000)), 1_700_000);
+        assert_eq!(net_gas_used(&GasCostSummary::new(1_000_000, 0, 4_000_000, 40_000)), -3_000_000);
+        assert_eq!(net_gas_used(&GasCostSummary::default()), 0);
+
+        let checkpoint = checkpoint(7);
+        let rows = handler().process(&checkpoint).unwrap();
+        for (event, tx) in events(&rows).iter().zip([0, 0, 1]) {
+            let effects = &checkpoint.transactions[tx].effects;
+            assert_eq!(event.gas_used, Some(net_gas_used(effects.gas_cost_summary())));
+            assert_eq!(event.tx_success, Some(true));
+        }
+    }
+
+    #[tokio::test]
+    async fn the_first_two_type_parameters_are_stored_normalized() {
+        use diesel::QueryDsl;
+
+        let sui = format!("0x{}2::sui::SUI", "0".repeat(63));
+        let wrapped = format!("0x{}dee9::coin::Wrapped<{sui}>", "0".repeat(60));
+        let checkpoint = CheckpointBuilder::new(7)
+            .transaction(
+                1,
+                &[(PACKAGE, "dex", "create_pool")],
+                vec![
+                    event(
+                        1,
+                        &format!("{PACKAGE}::dex::PoolCreated<0x2::sui::SUI, 0xdee9::coin::Wrapped<0x2::sui::SUI>>"),
+                        vec![],
+                    ),
+                    event(1, &format!("{PACKAGE}::dex::Deposited<0x2::sui::SUI>"), vec![]),
+                    event(1, &format!("{PACKAGE}::dex::Paused"), vec![]),
+                ],
+            )
+            .build();
+        let rows = UnxvEventsHandler::new(ModulesFilter::All, None).unwrap().process(&checkpoint).unwrap();
+        let params: Vec<_> = events(&rows).iter().map(|e| (e.type_param_0.clone(), e.type_param_1.clone())).collect();
+        let expected = [(Some(sui.clone()), Some(wrapped)), (Some(sui), None), (None, None)];
+        assert_eq!(params, expected);
+
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        commit_rows(UnxvEventsHandler::NAME, &rows, &mut conn).await.unwrap();
+        let stored: Vec<(Option<String>, Option<String>)> = unxv_events::table
+            .select((unxv_events::type_param_0, unxv_events::type_param_1))
+            .order(unxv_events::event_index)
+            .load(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(stored, expected);
+    }
+
+    #[tokio::test]
+    async fn the_full_event_type_is_stored_canonical() {
+        use diesel::{ExpressionMethods, QueryDsl};
+
+        let checkpoint = CheckpointBuilder::new(7)
+            .transaction(
+                1,
+                &[(PACKAGE, "dex", "create_pool")],
+                vec![
+                    event(
+                        1,
+                        &format!("{PACKAGE}::dex::PoolCreated<0x2::sui::SUI, vector<0xdee9::coin::Wrapped<u64>>>"),
+                        vec![],
+                    ),
+                    event(1, &format!("{PACKAGE}::dex::Paused"), vec![]),
+                ],
+            )
+            .build();
+        let rows = UnxvEventsHandler::new(ModulesFilter::All, None).unwrap().process(&checkpoint).unwrap();
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        commit_rows(UnxvEventsHandler::NAME, &rows, &mut conn).await.unwrap();
+
+        // Every address in full, parameters separated by a bare comma.
+        let package = format!("0x{:0>64}", "a11ce");
+        let pool_created =
+            format!("{package}::dex::PoolCreated<0x{:0>64}::sui::SUI,vector<0x{:0>64}::coin::Wrapped<u64>>>", "2", "dee9");
+        let full: Vec<(String, Option<String>)> = unxv_events::table
+            .select((unxv_events::event_type, unxv_events::event_type_full))
+            .order(unxv_events::event_index)
+            .load(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(
+            full,
+            [
+                ("PoolCreated".to_string(), Some(pool_created.clone())),
+                ("Paused".to_string(), Some(format!("{package}::dex::Paused")))
+            ]
+        );
+
+        let found: i64 = unxv_events::table
+            .filter(unxv_events::event_type_full.eq(&pool_created))
+            .select(unxv_events::event_index)
+            .first(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(found, 0);
+    }
+
+    #[tokio::test]
+    async fn type_params_match_whole_by_key_and_per_parameter_by_containment() {
+        #[derive(QueryableByName)]
+        struct EventType {
+            #[diesel(sql_type = Text)]
+            event_type: String,
+        }
+        #[derive(QueryableByName)]
+        struct Plan {
+            #[diesel(sql_type = Text, column_name = "QUERY PLAN")]
+            line: String,
+        }
+
+        /// Event types of the rows `sql` selects, sorted.
+        async fn matches(conn: &mut AsyncPgConnection, sql: &str) -> Vec<String> {
+            let rows: Vec<EventType> = diesel::sql_query(sql).load(conn).await.unwrap();
+            let mut types: Vec<String> = rows.into_iter().map(|r| r.event_type).collect();
+            types.sort();
+            types
+        }
+
+        let sui = format!("0x{}2::sui::SUI", "0".repeat(63));
+        let usdc = format!("0x{}dee9::usdc::USDC", "0".repeat(60));
+        let checkpoint = CheckpointBuilder::new(7)
+            .transaction(
+                1,
+                &[(PACKAGE, "dex", "create_pool")],
+                vec![
+                    event(1, &format!("{PACKAGE}::dex::PoolCreated<0x2::sui::SUI, 0xdee9::usdc::USDC>"), vec![]),
+                    event(1, &format!("{PACKAGE}::dex::Deposited<0x2::sui::SUI>"), vec![]),
+                    event(1, &format!("{PACKAGE}::dex::Paused"), vec![]),
+                ],
+            )
+            .build();
+        let rows = UnxvEventsHandler::new(ModulesFilter::All, None).unwrap().process(&checkpoint).unwrap();
+        // Both forms are filled from the same canonical parameters.
+        for e in events(&rows) {
+            let params: Vec<String> = serde_json::from_value(e.type_params.clone()).unwrap();
+            assert_eq!(e.type_params_key.as_deref(), Some(params.join(", ").as_str()));
+        }
+        assert_eq!(events(&rows)[0].type_params, serde_json::json!([sui, usdc]));
+
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        commit_rows(UnxvEventsHandler::NAME, &rows, &mut conn).await.unwrap();
+        let exact = format!("SELECT event_type FROM unxv_events WHERE type_params_key = '{sui}, {usdc}'");
+        let contains = format!(
+            "SELECT event_type FROM unxv_events WHERE type_params @> '[\"{sui}\"]' AND NOT type_params_truncated"
+        );
+        assert_eq!(matches(&mut conn, &exact).await, ["PoolCreated"]);
+        assert_eq!(matches(&mut conn, &contains).await, ["Deposited", "PoolCreated"]);
+
+        // Each style has an index to use once the table is too big to scan.
+        diesel::sql_query("SET enable_seqscan = off").execute(&mut conn).await.unwrap();
+        for (sql, index) in [(exact, "idx_unxv_events_type_params_key"), (contains, "idx_unxv_events_type_params")] {
+            let plan: Vec<Plan> = diesel::sql_query(format!("EXPLAIN {sql}")).load(&mut conn).await.unwrap();
+            let plan: Vec<String> = plan.into_iter().map(|p| p.line).collect();
+            assert!(plan.iter().any(|line| line.split_whitespace().any(|word| word == index)), "{sql}: {plan:#?}");
+        }
+    }
+
+    #[test]
+    fn events_of_a_multi_package_transaction_keep_their_own_package() {
+        // A PTB calling into both packages. The foreign event is emitted while `PACKAGE` runs, so
+        // only its struct tag tells the packages apart.
+        let foreign_fee = sui_types::event::Event {
+            package_id: ObjectID::from_hex_literal(PACKAGE).unwrap(),
+            ..event(1, &format!("{FOREIGN}::dex::ProtocolFeeTaken"), fee_taken(1, 7))
+        };
+        let checkpoint = CheckpointBuilder::new(7)
+            .transaction(
+                1,
+                &[(PACKAGE, "dex", "swap"), (FOREIGN, "dex", "swap")],
+                vec![event(1, &format!("{PACKAGE}::dex::ProtocolFeeTaken"), fee_taken(1, 5)), foreign_fee],
+            )
+            .build();
+        let (package, foreign) = (normalize_address(PACKAGE).unwrap(), normalize_address(FOREIGN).unwrap());
+
+        let rows = handler().process(&checkpoint).unwrap();
+        let packages: Vec<_> = events(&rows).iter().map(|e| (e.event_index, e.package.clone())).collect();
+        assert_eq!(packages, [(0, package.clone()), (1, foreign.clone())]);
+        for e in events(&rows) {
+            assert_eq!(e.call_packages, Some(serde_json::json!([package, foreign])));
+        }
+    }
+
+    #[test]
+    fn filters_drop_events_before_they_are_stored() {
+        let checkpoint = checkpoint(7);
+        let package = normalize_address(PACKAGE).unwrap();
+        let foreign = normalize_address(FOREIGN).unwrap();
+
+        let rows = handler().with_package_allowlist(Some(vec![PACKAGE.into()])).unwrap().process(&checkpoint).unwrap();
+        assert_eq!(
+            names(&rows),
+            [
+                (package.clone(), "dex".into(), "ProtocolFeeTaken".into()),
+                (package.clone(), "lending".into(), "DebtRepaid".into()),
+            ]
+        );
+
+        let rows = handler().with_modules("lending".parse().unwrap()).process(&checkpoint).unwrap();
+        assert_eq!(names(&rows), [(package.clone(), "lending".into(), "DebtRepaid".into())]);
+
+        let deny = HashSet::from([sender_address(1).to_string()]);
+        let rows = handler().with_sender_filter(None, Some(deny)).unwrap().process(&checkpoint).unwrap();
+        assert_eq!(names(&rows), [(foreign.clone(), "dex".into(), "ProtocolFeeTaken".into())]);
+
+        let tags = format!("{FOREIGN}::dex::ProtocolFeeTaken").parse().unwrap();
+        let rows = handler().with_struct_tags(Some(tags)).process(&checkpoint).unwrap();
+        assert_eq!(names(&rows), [(foreign, "dex".into(), "ProtocolFeeTaken".into())]);
+
+        let rows = handler().with_modules(ModulesFilter::All).process(&checkpoint).unwrap();
+        assert_eq!(names(&rows).last().unwrap(), &(package, "vault".into(), "Opened".into()));
+    }
+
+    #[test]
+    fn sender_filters_allow_deny_and_combine() {
+        let checkpoint = checkpoint(7);
+        let senders = |handler: UnxvEventsHandler| -> Vec<String> {
+            let rows = handler.process(&checkpoint).unwrap();
+            let mut senders: Vec<String> = events(&rows).iter().map(|e| e.sender.clone()).collect();
+            senders.dedup();
+            senders
+        };
+        let sender = |n: u8| normalize_address(&sender_address(n).to_string()).unwrap();
+        // Short and uppercase, as a caller might write it.
+        let written = |n: u8| {
+            let address = sender(n);
+            format!("0X{}", address[2..].trim_start_matches('0').to_uppercase())
+        };
+        let set = |ns: &[u8]| Some(ns.iter().map(|n| written(*n)).collect::<HashSet<_>>());
+
+        assert_eq!(senders(handler()), [sender(1), sender(2)]);
+        assert_eq!(senders(handler().with_sender_filter(set(&[2]), None).unwrap()), [sender(2)]);
+        assert_eq!(senders(handler().with_sender_filter(None, set(&[2])).unwrap()), [sender(1)]);
+        // Deny wins over allow.
+        assert_eq!(senders(handler().with_sender_filter(set(&[1, 2]), set(&[2])).unwrap()), [sender(1)]);
+        assert!(senders(handler().with_sender_filter(set(&[1]), set(&[1])).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn metrics_count_processed_and_skipped_events() {
+        let metrics = UnxvMetrics::new(&prometheus::Registry::new());
+        let deny = HashSet::from([sender_address(3).to_string()]);
+        let handler = handler()
+            .with_metrics(metrics.clone())
+            .with_package_allowlist(Some(vec![PACKAGE.into()]))
+            .unwrap()
+            .with_sender_filter(None, Some(deny))
+            .unwrap();
+        handler.process(&checkpoint(7)).unwrap();
+
+        let processed =
+            |module: &str, event_type: &str| metrics.events_processed.with_label_values(&[module, event_type]).get();
+        let skipped = |reason: &str| metrics.events_skipped.with_label_values(&[reason]).get();
+        assert_eq!(processed("dex", "ProtocolFeeTaken"), 1);
+        assert_eq!(processed("lending", "DebtRepaid"), 1);
+        assert_eq!(processed("vault", "Opened"), 0);
+        // Sender 2's fee goes to a foreign package, and sender 3 is denied.
+        assert_eq!((skipped("package"), skipped("sender"), skipped("module")), (1, 1, 0));
+    }
+
+    #[test]
+    fn balance_filter_keeps_only_listed_events() {
+        let events = crate::parse_event_types(crate::BALANCE_AFFECTING_EVENTS).unwrap();
+        let handler = handler().with_balance_filter(Some(events));
+        assert!(handler.allow_balance("lending", "CollateralDeposited2"));
+        assert!(handler.allow_balance("Lending", "DebtRepaid"));
+        assert!(!handler.allow_balance("lending", "MarketInitialized2"));
+        // Unlisted modules are dropped entirely.
+        assert!(!handler.allow_balance("book", "OrderPlaced"));
+
+        let handler = handler.with_balance_filter(Some(crate::parse_event_types("staking::*").unwrap()));
+        assert!(handler.allow_balance("staking", "Anything"));
+        assert!(!handler.allow_balance("dex", "ProtocolFeeTaken"));
+        assert!(handler.with_balance_filter(None).allow_balance("book", "OrderPlaced"));
+    }
+
+    #[test]
+    fn filters_reject_invalid_addresses() {
+        assert!(UnxvEventsHandler::new(ModulesFilter::All, Some(vec!["0x".into()])).is_err());
+        assert!(handler().with_package_allowlist(Some(vec![PACKAGE.into(), "0xnope".into()])).is_err());
+        assert!(handler().with_sender_filter(Some(HashSet::from(["0x2g".into()])), None).is_err());
+        assert!(handler().with_sender_filter(None, Some(HashSet::from([format!("0x1{}", "0".repeat(64))]))).is_err());
+    }
+
+    #[test]
+    fn stored_rows_with_invalid_addresses_are_not_accepted() {
+        let rows = handler().process(&checkpoint(7)).unwrap();
+        let mut event = events(&rows)[0].clone();
+        assert!(handler().accepts(&event));
+        event.sender = "nope".into();
+        assert!(!handler().accepts(&event));
+        let mut event = events(&rows)[0].clone();
+        event.package = "0x".into();
+        assert!(!handler().accepts(&event));
+    }
+
+    #[tokio::test]
+    async fn checkpoint_files_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let written = checkpoint(7);
+        write_checkpoint(dir.path(), &written);
+        let loaded = load_checkpoint(dir.path(), 7).await;
+        let (written, loaded) = (handler().process(&written).unwrap(), handler().process(&loaded).unwrap());
+        assert_eq!(format!("{written:?}"), format!("{loaded:?}"));
+    }
+
+    #[tokio::test]
+    async fn tx_timestamps_fall_back_to_the_checkpoint_until_a_commit_prologue() {
+        use sui_types::base_types::{random_object_ref, SuiAddress};
+        use sui_types::messages_consensus::ConsensusCommitPrologue;
+        use sui_types::transaction::{Transaction, TransactionData, TransactionKind};
+
+        // Sender 1's transaction comes before any consensus commit prologue, sender 2's after it.
+        let mut data = (*CheckpointBuilder::new(7)
+            .transaction(1, &[], vec![event(1, &format!("{PACKAGE}::dex::ProtocolFeeTaken"), fee_taken(1, 5))])
+            .transaction(9, &[], vec![])
+            .transaction(2, &[], vec![event(2, &format!("{PACKAGE}::dex::ProtocolFeeTaken"), fee_taken(2, 7))])
+            .build())
+        .clone();
+        let prologue = ConsensusCommitPrologue { epoch: 0, round: 1, commit_timestamp_ms: 1_700_000_000_123 };
+        let kind = TransactionKind::ConsensusCommitPrologue(prologue);
+        let prologue = TransactionData::new(kind, SuiAddress::ZERO, random_object_ref(), 1_000_000, 1_000);
+        data.transactions[1].transaction = Transaction::from_data(prologue, vec![]);
+
+        // Through a checkpoint file, as fixtures are read.
+        let dir = tempfile::tempdir().unwrap();
+        write_checkpoint(dir.path(), &data);
+        let checkpoint = load_checkpoint(dir.path(), 7).await;
+        let rows = handler().with_tx_timestamps(true).process(&checkpoint).unwrap();
+        let timestamps: Vec<_> = events(&rows).iter().map(|e| e.tx_timestamp_ms).collect();
+        let checkpoint_timestamp_ms = checkpoint.checkpoint_summary.timestamp_ms as i64;
+        assert_eq!(timestamps, [Some(checkpoint_timestamp_ms), Some(1_700_000_000_123)]);
+
+        let rows = handler().process(&checkpoint).unwrap();
+        assert!(events(&rows).iter().all(|e| e.tx_timestamp_ms.is_none()));
+    }
+
+    #[tokio::test]
+    async fn the_emitter_object_is_captured_when_the_event_names_one() {
+        use diesel::QueryDsl;
+
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        // Through a checkpoint file, as fixtures are read.
+        let dir = tempfile::tempdir().unwrap();
+        write_checkpoint(dir.path(), &checkpoint(7));
+        let checkpoint = load_checkpoint(dir.path(), 7).await;
+        commit_rows(UnxvEventsHandler::NAME, &handler().process(&checkpoint).unwrap(), &mut conn).await.unwrap();
+
+        let stored: Vec<(String, Option<String>)> = unxv_events::table
+            .select((unxv_events::event_type, unxv_events::emitter_object))
+            .order_by((unxv_events::digest, unxv_events::event_index))
+            .load(&mut conn)
+            .await
+            .unwrap();
+        let mut stored: Vec<(&str, Option<&str>)> =
+            stored.iter().map(|(event_type, emitter)| (event_type.as_str(), emitter.as_deref())).collect();
+        stored.sort();
+        // DebtRepaid leads with its market's ID; ProtocolFeeTaken has no ID field.
+        let market = normalize_address("0xb0b").unwrap();
+        assert_eq!(
+            stored,
+            [("DebtRepaid", Some(market.as_str())), ("ProtocolFeeTaken", None), ("ProtocolFeeTaken", None)]
+        );
+
+        // Without a layout the contents aren't decoded, and the object isn't known.
+        let rows = UnxvEventsHandler::new(ModulesFilter::default(), None).unwrap().process(&checkpoint).unwrap();
+        assert!(events(&rows).iter().all(|e| e.emitter_object.is_none()));
+    }
+
+    /// Every Unxversal event in the recorded fixtures decodes with the built-in layouts.
+    #[tokio::test]
+    async fn fixtures_decode() {
+        for sequence_number in fixtures() {
+            let rows = handler().process(&load_checkpoint(&fixtures_dir(), sequence_number).await).unwrap();
+            for event in events(&rows) {
+                assert!(
+                    event.contents_json.is_some(),
+                    "{sequence_number}.chk: {}::{} didn't decode",
+                    event.module,
+                    event.event_type
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn recommitting_a_checkpoint_is_idempotent() {
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        let rows = handler().process(&checkpoint(7)).unwrap();
+
+        assert_eq!(commit_rows("unxv_events", &rows, &mut conn).await.unwrap(), 4);
+        assert_eq!(db.count("unxv_events").await, 3);
+        assert_eq!(db.count("unxv_checkpoints").await, 1);
+
+        assert_eq!(commit_rows("unxv_events", &rows, &mut conn).await.unwrap(), 0);
+        assert_eq!(db.count("unxv_events").await, 3);
+        assert_eq!(db.count("unxv_checkpoints").await, 1);
+    }
+
+    #[tokio::test]
+    async fn duplicate_events_in_a_batch_are_stored_once() {
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        let mut rows = handler().with_checkpoint_rows(false).process(&checkpoint(7)).unwrap();
+        let duplicate = match &rows[0] {
+            EventRow::Event(event) => EventRow::Event(UnxvEvent { contents_json: None, ..event.clone() }),
+            row => panic!("expected an event, got {row:?}"),
+        };
+        rows.push(duplicate);
+
+        assert_eq!(commit_rows("unxv_events", &rows, &mut conn).await.unwrap(), 3);
+        assert_eq!(db.count("unxv_events").await, 3);
+    }
+
+    #[tokio::test]
+    async fn only_events_of_a_senders_first_checkpoint_are_flagged_new() {
+        use diesel::QueryDsl;
+
+        /// `(checkpoint, is_new_sender)` of each stored event.
+        async fn flags(conn: &mut AsyncPgConnection) -> Vec<(i64, Option<bool>)> {
+            unxv_events::table
+                .select((unxv_events::checkpoint, unxv_events::is_new_sender))
+                .order((unxv_events::checkpoint, unxv_events::sender, unxv_events::event_index))
+                .load(conn)
+                .await
+                .unwrap()
+        }
+
+        /// The same events as committed in `checkpoint`, as other transactions.
+        fn at(rows: &[EventRow], checkpoint: i64) -> Vec<EventRow> {
+            events(rows)
+                .into_iter()
+                .map(|e| {
+                    EventRow::Event(UnxvEvent { digest: format!("{}-{checkpoint}", e.digest), checkpoint, ..e.clone() })
+                })
+                .collect()
+        }
+
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        let handler = handler().with_checkpoint_rows(false).with_sender_first_seen(true);
+        let rows = handler.process(&checkpoint(7)).unwrap();
+        assert!(events(&rows).iter().all(|e| e.is_new_sender == Some(false)));
+
+        commit_rows(UnxvEventsHandler::NAME, &rows, &mut conn).await.unwrap();
+        assert_eq!(flags(&mut conn).await, [(7, Some(true)); 3]);
+        assert_eq!(db.count("sender_first_seen").await, 2);
+
+        // Seen once: later checkpoints and a recommit don't flag them again.
+        commit_rows(UnxvEventsHandler::NAME, &at(&rows, 8), &mut conn).await.unwrap();
+        commit_rows(UnxvEventsHandler::NAME, &rows, &mut conn).await.unwrap();
+        assert_eq!(flags(&mut conn).await, [[(7, Some(true)); 3], [(8, Some(false)); 3]].concat());
+
+        // An earlier checkpoint committing late takes the flag over.
+        commit_rows(UnxvEventsHandler::NAME, &at(&rows, 5), &mut conn).await.unwrap();
+        assert_eq!(
+            flags(&mut conn).await,
+            [[(5, Some(true)); 3], [(7, Some(false)); 3], [(8, Some(false)); 3]].concat()
+        );
+        let first: Vec<FirstSeen> =
+            diesel::sql_query("SELECT sender, first_checkpoint FROM sender_first_seen").load(&mut conn).await.unwrap();
+        assert_eq!(first.iter().map(|f| f.first_checkpoint).collect::<Vec<_>>(), [5, 5]);
+    }
+
+    #[tokio::test]
+    async fn events_of_mapped_modules_land_in_their_schema() {
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        let module_schemas = schemas::ModuleSchemaArgs {
+            module_schemas: vec![("dex".to_string(), "market".to_string())],
+            create_module_schemas: true,
+        }
+        .schemas()
+        .unwrap();
+        schemas::prepare(db.db(), &module_schemas, true).await.unwrap();
+
+        let rows = handler().with_checkpoint_rows(false).process(&checkpoint(7)).unwrap();
+        assert_eq!(insert_by_schema(&module_schemas, &events(&rows), &mut conn).await.unwrap(), 3);
+        // Both fees, whichever package emitted them, and the repayment in the default schema.
+        assert_eq!(db.count("market.unxv_events").await, 2);
+        assert_eq!(db.count("unxv_events").await, 1);
+    }
+
+    /// 20k rows is far past the bind parameter cap of one multi-row INSERT. Both insert methods
+    /// take them in a single call and store the same rows.
+    #[tokio::test]
+    async fn the_indexer_version_is_stamped_on_stored_rows() {
+        use diesel::QueryDsl;
+
+        async fn versions(conn: &mut AsyncPgConnection) -> Vec<Option<String>> {
+            unxv_events::table.select(unxv_events::indexer_version).load(conn).await.unwrap()
+        }
+
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        let stamped = handler().with_indexer_version(Some("1.2.3-abcdef".to_string()));
+        commit_rows(UnxvEventsHandler::NAME, &stamped.process(&checkpoint(7)).unwrap(), &mut conn).await.unwrap();
+        assert_eq!(versions(&mut conn).await, vec![Some("1.2.3-abcdef".to_string()); 3]);
+
+        diesel::sql_query("DELETE FROM unxv_events").execute(&mut conn).await.unwrap();
+        let rows = handler().with_checkpoint_rows(false).process(&checkpoint(7)).unwrap();
+        commit_rows(UnxvEventsHandler::NAME, &rows, &mut conn).await.unwrap();
+        assert_eq!(versions(&mut conn).await, [None, None, None]);
+    }
+
+    #[tokio::test]
+    async fn reprocessing_fills_in_the_decoded_columns_of_stored_rows() {
+        use diesel::QueryDsl;
+
+        type Stored = (String, i64, Option<serde_json::Value>, i64);
+
+        async fn insert(
+            method: InsertMethod,
+            events: &[UnxvEvent],
+            reprocess: bool,
+            conn: &mut AsyncPgConnection,
+        ) -> usize {
+            let events: Vec<&UnxvEvent> = events.iter().collect();
+            match method {
+                InsertMethod::Rows => insert_event_rows(&events, reprocess, conn).await.unwrap(),
+                InsertMethod::Unnest => insert_events_unnest(&events, reprocess, conn).await.unwrap(),
+            }
+        }
+
+        async fn stored(conn: &mut AsyncPgConnection) -> Vec<Stored> {
+            unxv_events::table
+                .select((
+                    unxv_events::digest,
+                    unxv_events::event_index,
+                    unxv_events::contents_json,
+                    unxv_events::checkpoint_timestamp_ms,
+                ))
+                .order_by((unxv_events::digest, unxv_events::event_index))
+                .load(conn)
+                .await
+                .unwrap()
+        }
+
+        // Stored before a decoder for the events was available.
+        let rows = UnxvEventsHandler::new(ModulesFilter::default(), None).unwrap().process(&checkpoint(7)).unwrap();
+        let undecoded: Vec<UnxvEvent> = events(&rows).into_iter().cloned().collect();
+        assert!(undecoded.iter().all(|e| e.contents_json.is_none()));
+        // Seen again with one. A transaction fact that differs is left as first stored.
+        let rows = handler().process(&checkpoint(7)).unwrap();
+        let decoded: Vec<UnxvEvent> = events(&rows)
+            .into_iter()
+            .map(|e| UnxvEvent { checkpoint_timestamp_ms: e.checkpoint_timestamp_ms + 1, ..e.clone() })
+            .collect();
+        assert!(decoded.iter().all(|e| e.contents_json.is_some()));
+        let mut expected: Vec<Stored> = decoded
+            .iter()
+            .map(|e| (e.digest.clone(), e.event_index, e.contents_json.clone(), e.checkpoint_timestamp_ms - 1))
+            .collect();
+        expected.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+
+        for method in [InsertMethod::Rows, InsertMethod::Unnest] {
+            let db = TestDb::new().await;
+            let mut conn = db.db().connect().await.unwrap();
+            assert_eq!(insert(method, &undecoded, false, &mut conn).await, 3);
+            let before = stored(&mut conn).await;
+
+            // Without --reprocess the stored rows are left alone.
+            assert_eq!(insert(method, &decoded, false, &mut conn).await, 0);
+            assert_eq!(stored(&mut conn).await, before);
+
+            assert_eq!(insert(method, &decoded, true, &mut conn).await, 3);
+            assert_eq!(stored(&mut conn).await, expected, "{method:?}");
+            assert_eq!(db.count("unxv_events").await, 3);
+        }
+    }
+
+    #[tokio::test]
+    async fn insert_methods_store_the_same_rows() {
+        #[derive(QueryableByName)]
+        struct Md5 {
+            #[diesel(sql_type = Text)]
+            md5: String,
+        }
+        async fn table_md5(conn: &mut AsyncPgConnection) -> String {
+            let query = "SELECT md5(string_agg(e::text, ',' ORDER BY digest, event_index)) AS md5 FROM unxv_events e";
+            diesel::sql_query(query)
+                .get_result::<Md5>(conn)
+                .await
+                .unwrap()
+                .md5
+        }
+
+        let rows = handler().process(&checkpoint(7)).unwrap();
+        let template = events(&rows)[1];
+        let synthetic: Vec<UnxvEvent> = (0..20_000i64)
+            .map(|i| UnxvEvent {
+                digest: format!("digest{}", i / 4),
+                event_index: i % 4,
+                checkpoint: 7 + i / 100,
+                ..template.clone()
+            })
+            .collect();
+        let synthetic: Vec<&UnxvEvent> = synthetic.iter().collect();
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+
+        assert_eq!(insert_event_rows(&synthetic, false, &mut conn).await.unwrap(), 20_000);
+        assert_eq!(insert_event_rows(&synthetic, false, &mut conn).await.unwrap(), 0);
+        let by_rows = table_md5(&mut conn).await;
+
+        diesel::sql_query("DELETE FROM unxv_events").execute(&mut conn).await.unwrap();
+        assert_eq!(insert_events_unnest(&synthetic, false, &mut conn).await.unwrap(), 20_000);
+        assert_eq!(insert_events_unnest(&synthetic, false, &mut conn).await.unwrap(), 0);
+        assert_eq!(table_md5(&mut conn).await, by_rows);
+        assert_eq!(db.count("unxv_events").await, 20_000);
+    }
+
+    #[tokio::test]
+    async fn rejected_rows_are_not_published() {
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        let mut rows = handler().with_checkpoint_rows(false).process(&checkpoint(7)).unwrap();
+        let mut rejected = events(&rows)[1].clone();
+        // Postgres rejects NUL characters in text.
+        rejected.event_type.push('\0');
+        rows[1] = EventRow::Event(rejected);
+
+        let key = |event: &UnxvEvent| (event.digest.clone(), event.event_index);
+        let (_, committed) = write_batch("unxv_events", &rows, &mut conn).await.unwrap();
+        let published: Vec<_> = committed
+            .into_iter()
+            .filter_map(|row| match row {
+                EventRow::Event(event) => Some(key(event)),
+                EventRow::DeadLetter(_) | EventRow::Checkpoint(_) => None,
+            })
+            .collect();
+        let events = events(&rows);
+        assert_eq!(published, [key(events[0]), key(events[2])]);
+        assert_eq!(db.count("unxv_events").await, 2);
+        assert_eq!(db.count("unxv_events_quarantine").await, 1);
+    }
+
+    #[tokio::test]
+    async fn quarantined_rows_name_the_pipeline_that_rejected_them() {
+        use diesel::QueryDsl;
+        use unxv_schema::schema::unxv_events_quarantine;
+
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        let mut rows = handler().with_checkpoint_rows(false).process(&checkpoint(7)).unwrap();
+        let mut rejected = events(&rows)[0].clone();
+        rejected.event_type.push('\0');
+        rows[0] = EventRow::Event(rejected);
+
+        commit_rows("unxv_events_dex", &rows, &mut conn).await.unwrap();
+        let pipelines: Vec<String> =
+            unxv_events_quarantine::table.select(unxv_events_quarantine::pipeline).load(&mut conn).await.unwrap();
+        assert_eq!(pipelines, ["unxv_events_dex"]);
+    }
+
+    #[tokio::test]
+    async fn subscribers_receive_the_committed_events_they_filter_for() {
+        // The stream is process-wide, so only look at this test's checkpoint.
+        const CHECKPOINT: u64 = 278_000;
+        stream::enable(1024);
+        let mut subscriber = stream::sender().unwrap().subscribe();
+        let filter = stream::Filter::new(Some("dex"), None);
+
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        let mut rows = handler().with_checkpoint_rows(false).process(&checkpoint(CHECKPOINT)).unwrap();
+        let mut rejected = events(&rows)[0].clone();
+        rejected.event_type.push('\0');
+        rows[0] = EventRow::Event(rejected);
+        commit_rows(UnxvEventsHandler::NAME, &rows, &mut conn).await.unwrap();
+
+        let mut received = vec![];
+        while let Ok(event) = subscriber.try_recv() {
+            if event.checkpoint == CHECKPOINT as i64 && filter.matches(&event) {
+                received.push((event.package.clone(), event.event_type.clone()));
+            }
+        }
+        // The rejected dex event and the unfiltered lending one never arrive.
+        assert_eq!(received, [(normalize_address(FOREIGN).unwrap(), "ProtocolFeeTaken".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn rejected_checkpoint_rows_are_quarantined_separately() {
+        use diesel::QueryDsl;
+        use unxv_schema::schema::unxv_events_quarantine;
+
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        let mut rows = handler().process(&checkpoint(7)).unwrap();
+        rows.extend(handler().process(&checkpoint(8)).unwrap());
+        let rejected = || {
+            anyhow::Error::from(diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::CheckViolation,
+                Box::new("checkpoint row rejected".to_string()),
+            ))
+        };
+
+        quarantine::isolate(
+            "unxv_events",
+            &rows,
+            &mut conn,
+            rejected(),
+            |part, conn| {
+                async move {
+                    if part.iter().any(|row| matches!(row, EventRow::Checkpoint(_))) {
+                        return Err(rejected());
+                    }
+                    write_rows(part, conn).await
+                }
+                .scope_boxed()
+            },
+            |value, error| quarantined_row("unxv_events", value, error),
+        )
+        .await
+        .unwrap();
+
+        let quarantined: Vec<(String, String, i64)> = unxv_events_quarantine::table
+            .select((
+                unxv_events_quarantine::target_table,
+                unxv_events_quarantine::digest,
+                unxv_events_quarantine::checkpoint,
+            ))
+            .order_by(unxv_events_quarantine::checkpoint)
+            .load(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(
+            quarantined,
+            [("unxv_checkpoints".to_string(), "7".to_string(), 7), ("unxv_checkpoints".to_string(), "8".to_string(), 8)]
+        );
+        assert_eq!(db.count("unxv_checkpoints").await, 0);
+    }
+}
@@ -0,0 +1,273 @@
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use diesel::sql_types::{Array, BigInt, Nullable, Numeric, Text};
+use diesel_async::RunQueryDsl;
+use std::collections::HashSet;
+use std::sync::Arc;
+use sui_indexer_alt_framework::pipeline::sequential::Handler;
+use sui_indexer_alt_framework::pipeline::Processor;
+use sui_pg_db::{Connection, Db};
+use sui_types::full_checkpoint_content::CheckpointData;
+use tracing::{debug, warn};
+
+use crate::normalize_address;
+use crate::pipeline::{pipeline_entry, PipelineEntry};
+use unxv_events::usdu::Claimed;
+use unxv_schema::models::UsduFlow;
+
+pub const PIPELINE: PipelineEntry = pipeline_entry!(
+    sequential,
+    UsduFlowsHandler,
+    tables: ["usdu_flows", "usdu_supply"],
+    events: ["usdu::Claimed"],
+    decoder_version: 1,
+    backfill_name: "usdu_flows_backfill",
+    build: |ctx| Ok(Some(UsduFlowsHandler::new(ctx.package_allowlist.clone())?)),
+);
+
+const MODULE: &str = "usdu";
+
+/// Insert the batch's flows and add the ones not already stored to the supply row. Counting
+/// only rows the insert returns keeps the totals exact if a checkpoint is ever committed twice.
+const INSERT_FLOWS: &str = "\
+    WITH new AS ( \
+        INSERT INTO usdu_flows ( \
+            event_digest, digest, checkpoint, checkpoint_timestamp_ms, package, flow_type, amount, \
+            from_address, to_address, timestamp_ms \
+        ) \
+        SELECT * FROM UNNEST($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) \
+        ON CONFLICT DO NOTHING \
+        RETURNING flow_type, amount \
+    ), totals AS ( \
+        SELECT coalesce(sum(amount) FILTER (WHERE flow_type = 'mint'), 0) AS minted, \
+               coalesce(sum(amount) FILTER (WHERE flow_type = 'burn'), 0) AS burned \
+        FROM new \
+    ) \
+    INSERT INTO usdu_supply (id, minted, burned, supply) \
+    SELECT TRUE, minted, burned, minted - burned FROM totals \
+    ON CONFLICT (id) DO UPDATE SET \
+        minted = usdu_supply.minted + excluded.minted, \
+        burned = usdu_supply.burned + excluded.burned, \
+        supply = usdu_supply.supply + excluded.supply";
+
+/// USDU supply changes: decodes the `usdu` module's events into `usdu_flows` and keeps the
+/// running totals in `usdu_supply`. Sequential, so the totals are written in the same
+/// transaction as the watermark and always match it.
+///
+/// The module only emits `Claimed` when its faucet mints; it has no burn or transfer events,
+/// and coin transfers emit none, so every flow is a `mint` today.
+pub struct UsduFlowsHandler {
+    /// Normalized 0x package addresses to accept. Empty => accept any.
+    package_allowlist: Option<HashSet<String>>,
+}
+
+impl UsduFlowsHandler {
+    /// Fails on an invalid address in `package_allowlist`.
+    pub fn new(package_allowlist: Option<Vec<String>>) -> anyhow::Result<Self> {
+        let package_allowlist = package_allowlist
+            .map(|v| v.iter().map(|s| normalize_address(s)).collect::<anyhow::Result<_>>())
+            .transpose()?;
+        Ok(Self { package_allowlist })
+    }
+}
+
+/// `(flow_type, amount, from, to, timestamp_ms)` of a `usdu` event, if it moves supply.
+type Flow = (&'static str, u64, Option<String>, Option<String>, u64);
+
+fn decode(event_type: &str, contents: &[u8]) -> anyhow::Result<Option<Flow>> {
+    Ok(match event_type {
+        "Claimed" => {
+            let claimed: Claimed = bcs::from_bytes(contents)?;
+            Some(("mint", claimed.amount, None, Some(claimed.who.to_canonical_string(true)), claimed.timestamp_ms))
+        }
+        _ => None,
+    })
+}
+
+impl Processor for UsduFlowsHandler {
+    const NAME: &'static str = "usdu_flows";
+    type Value = UsduFlow;
+
+    fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<Self::Value>> {
+        let mut out = Vec::new();
+        for tx in &checkpoint.transactions {
+            let Some(events) = &tx.events else { continue; };
+            let checkpoint_timestamp_ms = checkpoint.checkpoint_summary.timestamp_ms as i64;
+            let checkpoint_no = checkpoint.checkpoint_summary.sequence_number as i64;
+            let digest = tx.transaction.digest().to_string();
+
+            for (idx, ev) in events.data.iter().enumerate() {
+                let type_tag = &ev.type_;
+                if type_tag.module.as_str() != MODULE { continue; }
+                let package = type_tag.address.to_canonical_string(true);
+                if let Some(allow) = &self.package_allowlist {
+                    if !allow.contains(&package) { continue; }
+                }
+                let event_type = type_tag.name.as_str();
+                let (flow_type, amount, from_address, to_address, timestamp_ms) = match decode(event_type, &ev.contents) {
+                    Ok(Some(flow)) => flow,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        warn!("Skipping undecodable {MODULE}::{event_type} in {digest}: {e}");
+                        continue;
+                    }
+                };
+                let row = UsduFlow {
+                    event_digest: format!("{digest}:{idx}"),
+                    digest: digest.clone(),
+                    checkpoint: checkpoint_no,
+                    checkpoint_timestamp_ms,
+                    package,
+                    flow_type: flow_type.to_string(),
+                    amount: BigDecimal::from(amount),
+                    from_address,
+                    to_address,
+                    timestamp_ms: timestamp_ms as i64,
+                };
+                debug!("Observed USDU flow {:?}", row);
+                out.push(row);
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[async_trait]
+impl Handler for UsduFlowsHandler {
+    type Store = Db;
+    type Batch = Vec<UsduFlow>;
+
+    fn batch(batch: &mut Self::Batch, values: Vec<Self::Value>) {
+        batch.extend(values);
+    }
+
+    async fn commit<'a>(batch: &Self::Batch, conn: &mut Connection<'a>) -> anyhow::Result<usize> {
+        if batch.is_empty() {
+            return Ok(0);
+        }
+        macro_rules! column {
+            ($field:ident) => {
+                batch.iter().map(|f| f.$field.clone()).collect::<Vec<_>>()
+            };
+        }
+        diesel::sql_query(INSERT_FLOWS)
+            .bind::<Array<Text>, _>(column!(event_digest))
+            .bind::<Array<Text>, _>(column!(digest))
+            .bind::<Array<BigInt>, _>(column!(checkpoint))
+            .bind::<Array<BigInt>, _>(column!(checkpoint_timestamp_ms))
+            .bind::<Array<Text>, _>(column!(package))
+            .bind::<Array<Text>, _>(column!(flow_type))
+            .bind::<Array<Numeric>, _>(column!(amount))
+            .bind::<Array<Nullable<Text>>, _>(column!(from_address))
+            .bind::<Array<Nullable<Text>>, _>(column!(to_address))
+            .bind::<Array<BigInt>, _>(column!(timestamp_ms))
+            .execute(conn)
+            .await?;
+        Ok(batch.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{event, CheckpointBuilder, TestDb, PACKAGE};
+    use diesel::QueryDsl;
+    use unxv_schema::schema::usdu_supply;
+
+    /// A `usdu::Claimed` as the faucet emits it: `who` 0xabab..ab, `amount` 1_000_000,
+    /// `total_claimed` 3_000_000, `timestamp_ms` 1_700_000_000_000.
+    const CLAIMED: &[u8] = &[
+        0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, //
+        0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, //
+        0x40, 0x42, 0x0f, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0xc0, 0xc6, 0x2d, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x68, 0xe5, 0xcf, 0x8b, 0x01, 0x00, 0x00,
+    ];
+
+    const FOREIGN: &str = "0xe11e";
+
+    fn claimed(amount: u64) -> Vec<u8> {
+        let mut bytes = CLAIMED.to_vec();
+        bytes[32..40].copy_from_slice(&amount.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn claims_decode_as_mints() {
+        let who = format!("0x{}", "ab".repeat(32));
+        assert_eq!(decode("Claimed", CLAIMED).unwrap(), Some(("mint", 1_000_000, None, Some(who), 1_700_000_000_000)));
+        // The module's other events don't move supply.
+        let paused = bcs::to_bytes(&unxv_events::usdu::Paused {
+            paused: true,
+            by: sui_types::base_types::SuiAddress::ZERO.into(),
+            timestamp_ms: 1,
+        })
+        .unwrap();
+        assert_eq!(decode("Paused", &paused).unwrap(), None);
+        assert!(decode("Claimed", &CLAIMED[..40]).is_err());
+    }
+
+    #[test]
+    fn only_allowlisted_usdu_flows_are_processed() {
+        let checkpoint = CheckpointBuilder::new(4)
+            .transaction(
+                1,
+                &[(PACKAGE, "usdu", "claim")],
+                vec![
+                    event(1, &format!("{PACKAGE}::usdu::Claimed"), CLAIMED.to_vec()),
+                    // Truncated, so it's skipped.
+                    event(1, &format!("{PACKAGE}::usdu::Claimed"), CLAIMED[..40].to_vec()),
+                    event(1, &format!("{PACKAGE}::usdu::Claimed"), claimed(u64::MAX)),
+                ],
+            )
+            .transaction(
+                2,
+                &[(FOREIGN, "usdu", "claim")],
+                vec![event(2, &format!("{FOREIGN}::usdu::Claimed"), claimed(7))],
+            )
+            .build();
+
+        let handler = UsduFlowsHandler::new(Some(vec![PACKAGE.to_string()])).unwrap();
+        let flows = handler.process(&checkpoint).unwrap();
+        let amounts: Vec<_> = flows.iter().map(|f| (f.event_digest.ends_with(":0"), f.amount.clone())).collect();
+        assert_eq!(amounts, [(true, BigDecimal::from(1_000_000)), (false, BigDecimal::from(u64::MAX))]);
+        let flow = &flows[0];
+        assert_eq!(flow.package, normalize_address(PACKAGE).unwrap());
+        assert_eq!((flow.flow_type.as_str(), flow.from_address.as_deref()), ("mint", None));
+        assert_eq!((flow.checkpoint, flow.timestamp_ms), (4, 1_700_000_000_000));
+
+        // Without an allowlist the foreign package's claim counts too.
+        assert_eq!(UsduFlowsHandler::new(None).unwrap().process(&checkpoint).unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn supply_counts_each_flow_once() {
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        let checkpoint = CheckpointBuilder::new(4)
+            .transaction(
+                1,
+                &[(PACKAGE, "usdu", "claim")],
+                vec![
+                    event(1, &format!("{PACKAGE}::usdu::Claimed"), CLAIMED.to_vec()),
+                    event(1, &format!("{PACKAGE}::usdu::Claimed"), claimed(u64::MAX)),
+                ],
+            )
+            .build();
+        let flows = UsduFlowsHandler::new(None).unwrap().process(&checkpoint).unwrap();
+
+        // Committing the checkpoint again leaves the totals as they were.
+        for _ in 0..2 {
+            assert_eq!(UsduFlowsHandler::commit(&flows, &mut conn).await.unwrap(), 2);
+        }
+        assert_eq!(db.count("usdu_flows").await, 2);
+        let (minted, burned, supply): (BigDecimal, BigDecimal, BigDecimal) = usdu_supply::table
+            .select((usdu_supply::minted, usdu_supply::burned, usdu_supply::supply))
+            .first(&mut conn)
+            .await
+            .unwrap();
+        // Past u64::MAX, which NUMERIC holds.
+        let total = BigDecimal::from(u64::MAX) + BigDecimal::from(1_000_000);
+        assert_eq!((minted, burned, supply), (total.clone(), BigDecimal::from(0), total));
+    }
+}
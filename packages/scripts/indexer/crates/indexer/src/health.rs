@@ -0,0 +1,102 @@
+use chrono::Utc;
+use diesel::sql_types::{Array, BigInt, Text};
+use diesel::QueryableByName;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use sui_pg_db::Db;
+
+/// Highest checkpoint handed to any pipeline by ingestion, plus one (0 = none yet).
+static LATEST_OBSERVED: AtomicU64 = AtomicU64::new(0);
+
+/// Note a checkpoint delivered by ingestion. Called by every pipeline, before any filtering.
+pub fn observe_checkpoint(sequence_number: u64) {
+    LATEST_OBSERVED.fetch_max(sequence_number + 1, Ordering::Relaxed);
+}
+
+pub fn latest_observed_checkpoint() -> Option<u64> {
+    LATEST_OBSERVED.load(Ordering::Relaxed).checked_sub(1)
+}
+
+#[derive(QueryableByName)]
+struct Watermark {
+    #[diesel(sql_type = Text)]
+    pipeline: String,
+    #[diesel(sql_type = BigInt)]
+    checkpoint_hi_inclusive: i64,
+    #[diesel(sql_type = BigInt)]
+    timestamp_ms_hi_inclusive: i64,
+}
+
+/// Progress of one pipeline. Lags are None until it has committed a watermark.
+#[derive(Debug, Serialize)]
+pub struct PipelineHealth {
+    pub pipeline: String,
+    pub watermark_checkpoint: Option<u64>,
+    pub watermark_timestamp_ms: Option<i64>,
+    /// Checkpoints ingested but not yet committed.
+    pub lag_checkpoints: Option<u64>,
+    /// Wall-clock time since the watermark checkpoint was produced.
+    pub lag_ms: Option<i64>,
+}
+
+/// What `GET /health` returns: the slowest pipeline's figures at the top level, then each
+/// pipeline's.
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub watermark_checkpoint: Option<u64>,
+    pub latest_checkpoint: Option<u64>,
+    pub lag_checkpoints: Option<u64>,
+    pub lag_ms: Option<i64>,
+    pub max_lag_ms: u64,
+    pub pipelines: Vec<PipelineHealth>,
+}
+
+/// Lag of `pipelines` from the framework's watermarks table against the latest checkpoint
+/// ingestion has delivered. Unhealthy once any pipeline lags more than `max_lag_ms`.
+pub async fn check(db: &Db, pipelines: &[String], max_lag_ms: u64) -> anyhow::Result<HealthReport> {
+    let mut conn = db.connect().await?;
+    let watermarks: Vec<Watermark> = diesel::sql_query(
+        "SELECT pipeline, checkpoint_hi_inclusive, timestamp_ms_hi_inclusive FROM watermarks WHERE pipeline = ANY($1)",
+    )
+    .bind::<Array<Text>, _>(pipelines)
+    .load(&mut conn)
+    .await?;
+
+    let now_ms = Utc::now().timestamp_millis();
+    let latest = latest_observed_checkpoint();
+    let pipelines: Vec<PipelineHealth> = pipelines
+        .iter()
+        .map(|name| {
+            let watermark = watermarks.iter().find(|w| &w.pipeline == name);
+            let checkpoint = watermark.map(|w| w.checkpoint_hi_inclusive.max(0) as u64);
+            PipelineHealth {
+                pipeline: name.clone(),
+                watermark_checkpoint: checkpoint,
+                watermark_timestamp_ms: watermark.map(|w| w.timestamp_ms_hi_inclusive),
+                lag_checkpoints: checkpoint.zip(latest).map(|(c, l)| l.saturating_sub(c)),
+                lag_ms: watermark.map(|w| (now_ms - w.timestamp_ms_hi_inclusive).max(0)),
+            }
+        })
+        .collect();
+
+    let lag_ms = pipelines.iter().filter_map(|p| p.lag_ms).max();
+    Ok(HealthReport {
+        healthy: lag_ms.map_or(true, |lag| lag as u64 <= max_lag_ms),
+        watermark_checkpoint: pipelines.iter().filter_map(|p| p.watermark_checkpoint).min(),
+        latest_checkpoint: latest,
+        lag_checkpoints: pipelines.iter().filter_map(|p| p.lag_checkpoints).max(),
+        lag_ms,
+        max_lag_ms,
+        pipelines,
+    })
+}
+
+/// Whether the database answers. The health server starts after migrations, so that is all
+/// readiness still depends on.
+pub async fn ready(db: &Db) -> anyhow::Result<()> {
+    let mut conn = db.connect().await?;
+    diesel::sql_query("SELECT 1").execute(&mut conn).await?;
+    Ok(())
+}
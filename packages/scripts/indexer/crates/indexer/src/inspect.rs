@@ -0,0 +1,177 @@
+use anyhow::Context;
+use base64::Engine;
+use std::io::Write;
+use std::path::PathBuf;
+use sui_indexer_alt_framework::ingestion::ClientArgs;
+use sui_indexer_alt_framework::pipeline::Processor;
+use sui_types::full_checkpoint_content::CheckpointData;
+use url::Url;
+
+use crate::handlers::unxv_events_handler::{EventRow, UnxvEventsHandler};
+
+/// Checkpoint blobs are a one-byte encoding tag followed by the payload; 1 is BCS.
+pub(crate) const BLOB_ENCODING_BCS: u8 = 1;
+
+/// Where `inspect` and the parquet sink read checkpoint files from. Fullnode RPC ingestion goes
+/// through the framework's client, which needs a database, so it isn't supported.
+#[derive(Debug, Clone)]
+pub enum CheckpointSource {
+    Remote(Url),
+    Local(PathBuf),
+}
+
+impl CheckpointSource {
+    pub fn from_client_args(args: &ClientArgs) -> anyhow::Result<Self> {
+        match (&args.local_ingestion_path, &args.remote_store_url) {
+            (Some(path), _) => Ok(CheckpointSource::Local(path.clone())),
+            (None, Some(url)) => Ok(CheckpointSource::Remote(url.clone())),
+            (None, None) => {
+                anyhow::bail!("Reading checkpoint files directly needs --remote-store-url or --local-ingestion-path")
+            },
+        }
+    }
+
+    /// `{sequence_number}.chk`, decoded.
+    pub async fn fetch(&self, client: &reqwest::Client, sequence_number: u64) -> anyhow::Result<CheckpointData> {
+        let file = format!("{sequence_number}.chk");
+        let bytes = match self {
+            CheckpointSource::Local(dir) => {
+                let path = dir.join(&file);
+                tokio::fs::read(&path).await.with_context(|| format!("Failed to read {}", path.display()))?
+            }
+            CheckpointSource::Remote(url) => {
+                let url = url.join(&file)?;
+                let response = client.get(url.clone()).send().await?.error_for_status()?;
+                response.bytes().await.with_context(|| format!("Failed to download {url}"))?.to_vec()
+            }
+        };
+        let (&encoding, payload) = bytes.split_first().context("Empty checkpoint file")?;
+        anyhow::ensure!(encoding == BLOB_ENCODING_BCS, "Unsupported checkpoint encoding {encoding}");
+        Ok(bcs::from_bytes(payload)?)
+    }
+}
+
+/// Rows printed by [`run`].
+#[derive(Debug, Default)]
+pub struct InspectSummary {
+    pub checkpoints: u64,
+    pub events: usize,
+    pub dead_letters: usize,
+}
+
+/// A row as one JSON object, tagged with the table it would go to, with `contents_bcs` in
+/// base64 instead of an array of numbers.
+fn to_json_line(row: &EventRow) -> anyhow::Result<String> {
+    let (table, mut value, bcs) = match row {
+        EventRow::Event(event) => ("unxv_events", serde_json::to_value(event)?, Some(&event.contents_bcs)),
+        EventRow::DeadLetter(dead_letter) => {
+            ("unxv_dead_letters", serde_json::to_value(dead_letter)?, Some(&dead_letter.contents_bcs))
+        }
+        EventRow::Checkpoint(checkpoint) => ("unxv_checkpoints", serde_json::to_value(checkpoint)?, None),
+    };
+    let object = value.as_object_mut().context("Row is not a JSON object")?;
+    object.insert("table".into(), table.into());
+    if let Some(bcs) = bcs {
+        object.insert("contents_bcs".into(), base64::engine::general_purpose::STANDARD.encode(bcs).into());
+    }
+    Ok(serde_json::to_string(&value)?)
+}
+
+/// Run `handler`'s `process` over checkpoints `first..=last` from `source` and write every
+/// row it would insert to `out`, one JSON object per line, flushing after each. Rows filled in
+/// at commit (`is_new_sender`) are left as processed.
+pub async fn run(
+    source: &CheckpointSource,
+    handler: &UnxvEventsHandler,
+    first: u64,
+    last: u64,
+    out: &mut impl Write,
+) -> anyhow::Result<InspectSummary> {
+    let client = reqwest::Client::new();
+    let mut summary = InspectSummary::default();
+    for sequence_number in first..=last {
+        let checkpoint = std::sync::Arc::new(
+            source
+                .fetch(&client, sequence_number)
+                .await
+                .with_context(|| format!("Failed to fetch checkpoint {sequence_number}"))?,
+        );
+        for row in handler.process(&checkpoint)? {
+            match row {
+                EventRow::Event(_) => summary.events += 1,
+                EventRow::DeadLetter(_) => summary.dead_letters += 1,
+                EventRow::Checkpoint(_) => {}
+            }
+            writeln!(out, "{}", to_json_line(&row)?)?;
+            out.flush()?;
+        }
+        summary.checkpoints += 1;
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{event, write_checkpoint, CheckpointBuilder, PACKAGE};
+    use crate::ModulesFilter;
+
+    /// `first..=last`, each with a dex event and a lending event, as checkpoint files in `dir`.
+    fn write_range(dir: &std::path::Path, first: u64, last: u64) {
+        for sequence_number in first..=last {
+            let checkpoint = CheckpointBuilder::new(sequence_number)
+                .transaction(
+                    1,
+                    &[(PACKAGE, "dex", "swap")],
+                    vec![
+                        event(1, &format!("{PACKAGE}::dex::Swapped"), vec![1, 2, 3]),
+                        event(1, &format!("{PACKAGE}::lending::Borrowed"), vec![4]),
+                    ],
+                )
+                .build();
+            write_checkpoint(dir, &checkpoint);
+        }
+    }
+
+    fn lines(out: &[u8]) -> Vec<serde_json::Value> {
+        std::str::from_utf8(out).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn rows_are_printed_as_json_lines_without_a_database() {
+        let dir = tempfile::tempdir().unwrap();
+        write_range(dir.path(), 7, 8);
+        let source = CheckpointSource::Local(dir.path().to_path_buf());
+        let handler = UnxvEventsHandler::new(ModulesFilter::All, None).unwrap().with_checkpoint_rows(true);
+
+        let mut out = vec![];
+        let summary = run(&source, &handler, 7, 8, &mut out).await.unwrap();
+        assert_eq!((summary.checkpoints, summary.events, summary.dead_letters), (2, 4, 0));
+
+        let lines = lines(&out);
+        let tables: Vec<_> = lines.iter().map(|line| line["table"].as_str().unwrap()).collect();
+        assert_eq!(tables, ["unxv_events", "unxv_events", "unxv_checkpoints"].repeat(2));
+        let swapped = &lines[0];
+        assert_eq!((swapped["checkpoint"].as_i64(), swapped["module"].as_str()), (Some(7), Some("dex")));
+        assert_eq!(swapped["contents_bcs"], "AQID");
+        assert_eq!(lines[1]["contents_bcs"], "BA==");
+        assert_eq!(lines[5]["checkpoint"], 8);
+    }
+
+    #[tokio::test]
+    async fn filters_apply_as_in_the_pipeline() {
+        let dir = tempfile::tempdir().unwrap();
+        write_range(dir.path(), 7, 7);
+        let source = CheckpointSource::Local(dir.path().to_path_buf());
+        let handler = UnxvEventsHandler::new("lending".parse().unwrap(), None).unwrap();
+
+        let mut out = vec![];
+        run(&source, &handler, 7, 7, &mut out).await.unwrap();
+        let modules: Vec<_> = lines(&out).iter().map(|line| line["module"].as_str().unwrap().to_string()).collect();
+        assert_eq!(modules, ["lending"]);
+
+        // The range must exist in full.
+        let err = run(&source, &handler, 7, 8, &mut vec![]).await.unwrap_err();
+        assert!(format!("{err:#}").contains("checkpoint 8"), "{err:#}");
+    }
+}
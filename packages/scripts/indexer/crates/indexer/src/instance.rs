@@ -0,0 +1,196 @@
+use diesel::sql_types::{Array, Double, Text};
+use diesel::QueryableByName;
+use diesel_async::RunQueryDsl;
+use std::sync::Arc;
+use std::time::Duration;
+use sui_pg_db::Db;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+use url::Url;
+
+use crate::alerts::{Alert, AlertSender, Severity};
+use crate::metrics::UnxvMetrics;
+
+/// Postgres truncates `application_name` beyond this many bytes.
+const MAX_APPLICATION_NAME: usize = 63;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct InstanceArgs {
+    /// Identifies this process in `watermarks.instance_id` (default: `{hostname}-{pid}`)
+    #[clap(env, long)]
+    pub instance_id: Option<String>,
+    /// Seconds between checks for another instance writing this instance's pipelines (0 = off)
+    #[clap(env, long, default_value_t = 60)]
+    pub instance_check_interval_secs: u64,
+}
+
+impl InstanceArgs {
+    pub fn instance_id(&self) -> String {
+        let id = self.instance_id.clone().unwrap_or_else(|| {
+            let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "unknown".to_string());
+            format!("{host}-{}", std::process::id())
+        });
+        let mut end = id.len().min(MAX_APPLICATION_NAME);
+        while !id.is_char_boundary(end) {
+            end -= 1;
+        }
+        id[..end].to_string()
+    }
+
+    /// None when the conflict check is disabled.
+    pub fn config(&self, instance_id: String, pipelines: Vec<String>) -> Option<InstanceConfig> {
+        (self.instance_check_interval_secs > 0).then(|| InstanceConfig {
+            instance_id,
+            interval: Duration::from_secs(self.instance_check_interval_secs),
+            pipelines,
+        })
+    }
+}
+
+/// Tag every connection with `instance_id` as its `application_name`, which the watermarks
+/// trigger records as the writer. An explicit `application_name` in the URL is kept and
+/// becomes the effective instance id, which is returned with the URL.
+pub fn tag_database_url(mut url: Url, instance_id: String) -> (Url, String) {
+    let existing = url.query_pairs().find(|(k, _)| k == "application_name").map(|(_, v)| v.into_owned());
+    if let Some(name) = existing {
+        return (url, name);
+    }
+    url.query_pairs_mut().append_pair("application_name", &instance_id);
+    (url, instance_id)
+}
+
+#[derive(Debug, Clone)]
+pub struct InstanceConfig {
+    pub instance_id: String,
+    pub interval: Duration,
+    /// Watermark rows this instance writes.
+    pub pipelines: Vec<String>,
+}
+
+#[derive(QueryableByName)]
+struct Conflict {
+    #[diesel(sql_type = Text)]
+    pipeline: String,
+    #[diesel(sql_type = Text)]
+    instance_id: String,
+}
+
+/// Another writer seen within two check intervals.
+const CONFLICTS_QUERY: &str = "\
+    SELECT pipeline, instance_id FROM watermarks \
+    WHERE pipeline = ANY($1) AND instance_id IS NOT NULL AND instance_id <> $2 \
+      AND instance_seen_at > now() - make_interval(secs => $3)";
+
+/// Warn and alert, until cancelled, whenever a different instance has recently advanced one of
+/// this instance's watermarks. The watermarks trigger already keeps them from regressing; two
+/// writers still duplicate work and usually mean a misdeployment.
+pub async fn run(
+    db: Db,
+    config: InstanceConfig,
+    metrics: Arc<UnxvMetrics>,
+    alerts: AlertSender,
+    cancel: CancellationToken,
+) {
+    let mut interval = tokio::time::interval(config.interval);
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = interval.tick() => {}
+        }
+        match conflicts(&db, &config).await {
+            Ok(conflicts) => {
+                for c in conflicts {
+                    warn!(pipeline = %c.pipeline, other = %c.instance_id, "Another indexer instance is writing this pipeline");
+                    metrics.watermark_conflicts.with_label_values(&[&c.pipeline]).inc();
+                    alerts.send(
+                        Alert::new(
+                            "instance",
+                            Severity::Critical,
+                            format!("instance:conflict:{}", c.pipeline),
+                            format!(
+                                "Instance {} is also writing pipeline {} (this instance: {})",
+                                c.instance_id, c.pipeline, config.instance_id
+                            ),
+                        )
+                        .with_context(serde_json::json!({
+                            "pipeline": c.pipeline,
+                            "other_instance": c.instance_id,
+                            "instance": config.instance_id,
+                        })),
+                    );
+                }
+            }
+            Err(e) => warn!("Instance conflict check failed: {e:#}"),
+        }
+    }
+}
+
+async fn conflicts(db: &Db, config: &InstanceConfig) -> anyhow::Result<Vec<Conflict>> {
+    let mut conn = db.connect().await?;
+    Ok(diesel::sql_query(CONFLICTS_QUERY)
+        .bind::<Array<Text>, _>(&config.pipelines)
+        .bind::<Text, _>(&config.instance_id)
+        .bind::<Double, _>(2.0 * config.interval.as_secs_f64())
+        .load(&mut conn)
+        .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestDb;
+    use diesel::sql_types::BigInt;
+    use sui_pg_db::Connection;
+
+    #[derive(QueryableByName)]
+    struct Watermark {
+        #[diesel(sql_type = BigInt)]
+        checkpoint_hi_inclusive: i64,
+        #[diesel(sql_type = Text)]
+        instance_id: String,
+    }
+
+    /// A connection writing as `instance`, as [`tag_database_url`] arranges.
+    async fn writer<'a>(db: &'a TestDb, instance: &str) -> Connection<'a> {
+        let mut conn = db.db().connect().await.unwrap();
+        diesel::sql_query(format!("SET application_name = '{instance}'")).execute(&mut conn).await.unwrap();
+        conn
+    }
+
+    async fn commit(conn: &mut Connection<'_>, checkpoint: i64) -> (i64, String) {
+        let row: Watermark = diesel::sql_query(
+            "UPDATE watermarks SET checkpoint_hi_inclusive = $1 WHERE pipeline = 'unxv_events' \
+             RETURNING checkpoint_hi_inclusive, instance_id",
+        )
+        .bind::<BigInt, _>(checkpoint)
+        .get_result(conn)
+        .await
+        .unwrap();
+        (row.checkpoint_hi_inclusive, row.instance_id)
+    }
+
+    #[tokio::test]
+    async fn watermarks_never_regress_between_two_writers() {
+        let db = TestDb::new().await;
+        db.set_watermark("unxv_events", 0).await;
+        let (mut a, mut b) = (writer(&db, "indexer-a").await, writer(&db, "indexer-b").await);
+
+        assert_eq!(commit(&mut a, 10).await, (10, "indexer-a".to_string()));
+        // B lags behind A: its lower watermark is ignored, but it is recorded as the writer.
+        assert_eq!(commit(&mut b, 8).await, (10, "indexer-b".to_string()));
+        assert_eq!(commit(&mut b, 12).await, (12, "indexer-b".to_string()));
+        assert_eq!(commit(&mut a, 11).await, (12, "indexer-a".to_string()));
+        assert_eq!(commit(&mut a, 13).await, (13, "indexer-a".to_string()));
+
+        // Each instance sees the other as a conflicting writer.
+        let config = |instance: &str| InstanceConfig {
+            instance_id: instance.to_string(),
+            interval: Duration::from_secs(60),
+            pipelines: vec!["unxv_events".to_string()],
+        };
+        let others: Vec<String> =
+            conflicts(db.db(), &config("indexer-b")).await.unwrap().into_iter().map(|c| c.instance_id).collect();
+        assert_eq!(others, ["indexer-a"]);
+        assert!(conflicts(db.db(), &config("indexer-a")).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+//! Optional Kafka sink: events committed to `unxv_events` are also published to a topic, one
+//! JSON message per event keyed by transaction digest, so downstream consumers don't have to
+//! poll Postgres. Postgres stays the source of truth: an event is published only once its
+//! commit has succeeded, and a commit whose events the broker doesn't acknowledge fails and is
+//! retried by the framework, so every event is delivered at least once.
+
+use std::time::Duration;
+
+use unxv_schema::models::UnxvEvent;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct KafkaArgs {
+    /// Comma-separated Kafka bootstrap servers to publish committed events to (unset =
+    /// disabled). Needs a build with the `kafka` feature
+    #[clap(env, long)]
+    pub kafka_brokers: Option<String>,
+    /// Topic committed events are published to
+    #[clap(env, long, default_value = "unxv_events")]
+    pub kafka_topic: String,
+    /// Messages buffered in memory while the broker is slow or unreachable. Commits wait for
+    /// room once the buffer is full
+    #[clap(env, long, default_value_t = 100_000)]
+    pub kafka_buffer_messages: u32,
+    /// Milliseconds a message may wait for the broker before the commit that produced it fails
+    /// and is retried, holding back the pipeline
+    #[clap(env, long, default_value_t = 30_000)]
+    pub kafka_delivery_timeout_ms: u64,
+}
+
+impl KafkaArgs {
+    /// None when no brokers are configured.
+    pub fn config(&self) -> anyhow::Result<Option<KafkaConfig>> {
+        let Some(brokers) = self.kafka_brokers.clone() else {
+            return Ok(None);
+        };
+        anyhow::ensure!(cfg!(feature = "kafka"), "--kafka-brokers needs a build with the `kafka` feature");
+        anyhow::ensure!(!self.kafka_topic.is_empty(), "--kafka-topic must not be empty");
+        anyhow::ensure!(self.kafka_buffer_messages > 0, "--kafka-buffer-messages must be positive");
+        Ok(Some(KafkaConfig {
+            brokers,
+            topic: self.kafka_topic.clone(),
+            buffer_messages: self.kafka_buffer_messages,
+            delivery_timeout: Duration::from_millis(self.kafka_delivery_timeout_ms),
+        }))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KafkaConfig {
+    pub brokers: String,
+    pub topic: String,
+    pub buffer_messages: u32,
+    pub delivery_timeout: Duration,
+}
+
+#[cfg(feature = "kafka")]
+mod producer {
+    use anyhow::Context;
+    use base64::Engine;
+    use rdkafka::config::ClientConfig;
+    use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+    use rdkafka::producer::{FutureProducer, FutureRecord};
+    use std::sync::OnceLock;
+    use std::time::Duration;
+    use tracing::warn;
+
+    use super::KafkaConfig;
+    use crate::metrics;
+    use unxv_schema::models::UnxvEvent;
+
+    /// How long to wait before retrying a message the full buffer turned away.
+    const QUEUE_FULL_BACKOFF: Duration = Duration::from_millis(50);
+
+    struct Producer {
+        inner: FutureProducer,
+        topic: String,
+    }
+
+    /// Commits are associated functions without access to the handler, so they look the
+    /// producer up here.
+    static PRODUCER: OnceLock<Producer> = OnceLock::new();
+
+    /// Create the producer commits publish through. Delivery is asynchronous, so an
+    /// unreachable broker only shows up once the first commit times out.
+    pub fn install(config: &KafkaConfig) -> anyhow::Result<()> {
+        let inner: FutureProducer = ClientConfig::new()
+            .set("bootstrap.servers", &config.brokers)
+            // Retries neither duplicate nor reorder messages, so each transaction's events stay
+            // in order on their partition.
+            .set("enable.idempotence", "true")
+            .set("queue.buffering.max.messages", config.buffer_messages.to_string())
+            .set("message.timeout.ms", config.delivery_timeout.as_millis().to_string())
+            .create()
+            .context("Failed to create the Kafka producer")?;
+        let _ = PRODUCER.set(Producer { inner, topic: config.topic.clone() });
+        Ok(())
+    }
+
+    /// Publish committed events and wait until the broker has acknowledged all of them. An
+    /// error means some may not have been delivered, so the caller should fail its commit to
+    /// have it retried; the events already delivered are then published again.
+    pub async fn publish<'a>(pipeline: &str, events: impl IntoIterator<Item = &'a UnxvEvent>) -> anyhow::Result<()> {
+        let Some(producer) = PRODUCER.get() else { return Ok(()) };
+        let events: Vec<&UnxvEvent> = events.into_iter().collect();
+        let payloads = events.iter().map(|e| message(e)).collect::<anyhow::Result<Vec<_>>>()?;
+
+        // Enqueue in order, then wait for every acknowledgement.
+        let mut deliveries = Vec::with_capacity(events.len());
+        for (event, payload) in events.iter().zip(&payloads) {
+            let mut record = FutureRecord::to(&producer.topic).key(event.digest.as_str()).payload(payload.as_str());
+            loop {
+                match producer.inner.send_result(record) {
+                    Ok(delivery) => {
+                        deliveries.push(delivery);
+                        break;
+                    }
+                    Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), returned)) => {
+                        record = returned;
+                        tokio::time::sleep(QUEUE_FULL_BACKOFF).await;
+                    }
+                    Err((err, _)) => {
+                        record_failed((events.len() - deliveries.len()) as u64);
+                        return Err(err).context("Failed to enqueue an event for Kafka");
+                    }
+                }
+            }
+        }
+
+        let mut failed = 0;
+        let mut first_error = None;
+        for delivery in deliveries {
+            match delivery.await {
+                Ok(Ok(_)) => {}
+                Ok(Err((err, _))) => {
+                    failed += 1;
+                    first_error.get_or_insert_with(|| err.to_string());
+                }
+                Err(_) => {
+                    failed += 1;
+                    first_error.get_or_insert_with(|| "producer dropped the message".to_string());
+                }
+            }
+        }
+        let published = events.len() as u64 - failed;
+        if let Some(metrics) = metrics::global() {
+            metrics.kafka_published.inc_by(published);
+        }
+        record_failed(failed);
+        match first_error {
+            None => Ok(()),
+            Some(err) => {
+                warn!(pipeline, failed, "Kafka did not acknowledge every event, the commit will be retried: {err}");
+                anyhow::bail!("{failed} events were not delivered to Kafka: {err}")
+            }
+        }
+    }
+
+    /// An event as one JSON object, with `contents_bcs` in base64 instead of an array of numbers.
+    fn message(event: &UnxvEvent) -> anyhow::Result<String> {
+        let mut value = serde_json::to_value(event)?;
+        if let Some(object) = value.as_object_mut() {
+            object.insert(
+                "contents_bcs".into(),
+                base64::engine::general_purpose::STANDARD.encode(&event.contents_bcs).into(),
+            );
+        }
+        Ok(serde_json::to_string(&value)?)
+    }
+
+    fn record_failed(failed: u64) {
+        if let Some(metrics) = metrics::global() {
+            metrics.kafka_failed.inc_by(failed);
+        }
+    }
+}
+
+#[cfg(feature = "kafka")]
+pub use producer::{install, publish};
+
+#[cfg(not(feature = "kafka"))]
+pub fn install(_config: &KafkaConfig) -> anyhow::Result<()> {
+    anyhow::bail!("Built without the `kafka` feature")
+}
+
+#[cfg(not(feature = "kafka"))]
+pub async fn publish<'a>(_pipeline: &str, _events: impl IntoIterator<Item = &'a UnxvEvent>) -> anyhow::Result<()> {
+    Ok(())
+}
@@ -1,29 +1,567 @@
-use move_core_types::language_storage::StructTag;
+use anyhow::Context;
+use move_core_types::language_storage::{StructTag, TypeTag};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::str::FromStr;
+use sui_indexer_alt_framework::ingestion::ClientArgs;
+use sui_types::transaction::{Command, TransactionDataAPI, TransactionKind};
 use url::Url;
 
+pub mod alerts;
+pub mod api;
+pub mod backpressure;
+pub mod cascades;
+pub mod config;
+pub mod db;
+pub mod dead_letters;
+pub mod decoder;
+pub mod export;
+pub mod gaps;
 pub mod handlers;
+pub mod health;
+pub mod inspect;
+pub mod instance;
+pub mod kafka;
+pub mod maintenance;
+pub mod metrics;
+pub mod notify;
+pub mod pipeline;
+pub mod pruner;
+pub mod quarantine;
+pub mod reprocess;
+pub mod rollups;
+pub mod schemas;
+pub mod shutdown;
+pub mod sink;
+pub mod stats;
+pub mod stream;
+#[cfg(test)]
+pub(crate) mod test_utils;
+pub mod views;
+
+/// `{crate version}-{git revision}` of this build, stamped on rows with `--stamp-indexer-version`.
+pub const INDEXER_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "-", env!("GIT_REVISION"));
 
 pub const MAINNET_REMOTE_STORE_URL: &str = "https://checkpoints.mainnet.sui.io";
 pub const TESTNET_REMOTE_STORE_URL: &str = "https://checkpoints.testnet.sui.io";
+/// Fullnode started by `sui start`; local networks have no public checkpoint store.
+pub const LOCALNET_RPC_API_URL: &str = "http://127.0.0.1:9000";
 
 #[derive(Debug, Clone, Copy, clap::ValueEnum)]
 pub enum UnxvEnv {
     Mainnet,
     Testnet,
+    /// A `sui start` network on this machine, ingested from its fullnode RPC.
+    Localnet,
+    /// Devnets and private networks: the checkpoint source must be given explicitly.
+    Custom,
 }
 
 impl UnxvEnv {
-    pub fn remote_store_url(&self) -> Url {
+    /// The public checkpoint store for known networks; None for `Localnet` and `Custom`.
+    pub fn remote_store_url(&self) -> Option<Url> {
         let remote_store_url = match self {
             UnxvEnv::Mainnet => MAINNET_REMOTE_STORE_URL,
             UnxvEnv::Testnet => TESTNET_REMOTE_STORE_URL,
+            UnxvEnv::Localnet | UnxvEnv::Custom => return None,
+        };
+        Some(Url::parse(remote_store_url).unwrap())
+    }
+
+    /// The default fullnode RPC to ingest from, for networks without a checkpoint store.
+    pub fn rpc_api_url(&self) -> Option<Url> {
+        match self {
+            UnxvEnv::Localnet => Some(Url::parse(LOCALNET_RPC_API_URL).unwrap()),
+            UnxvEnv::Mainnet | UnxvEnv::Testnet | UnxvEnv::Custom => None,
+        }
+    }
+}
+
+/// Where checkpoints are ingested from, on top of the network's default store.
+#[derive(clap::Args, Clone, Default)]
+pub struct SourceArgs {
+    /// Checkpoint store URL, overriding the network's default
+    #[clap(env, long)]
+    pub remote_store_url: Option<Url>,
+    /// Directory of checkpoint files to ingest from instead of the network
+    #[clap(env, long)]
+    pub local_ingestion_path: Option<PathBuf>,
+    /// Fullnode RPC URL to ingest checkpoints from
+    #[clap(env, long)]
+    pub rpc_api_url: Option<Url>,
+    /// Basic-auth username for --rpc-api-url
+    #[clap(env, long)]
+    pub rpc_username: Option<String>,
+    /// Basic-auth password for --rpc-api-url
+    #[clap(env, long)]
+    pub rpc_password: Option<String>,
+}
+
+// Manual so the RPC password never ends up in logs.
+impl std::fmt::Debug for SourceArgs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SourceArgs")
+            .field("remote_store_url", &self.remote_store_url.as_ref().map(redact_url))
+            .field("local_ingestion_path", &self.local_ingestion_path)
+            .field("rpc_api_url", &self.rpc_api_url.as_ref().map(redact_url))
+            .field("rpc_username", &self.rpc_username)
+            .field("rpc_password", &self.rpc_password.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+/// `url` for display, with any password replaced by `<redacted>`.
+pub fn redact_url(url: &Url) -> String {
+    let mut url = url.clone();
+    if url.password().is_some() {
+        let _ = url.set_password(Some("<redacted>"));
+    }
+    url.to_string()
+}
+
+impl SourceArgs {
+    /// Build the framework's ingestion config, failing if `env` has no default source and no
+    /// custom source was given. Explicit flags win over the network's defaults, and the
+    /// framework reads from the remote store when one is set, so the network's default store
+    /// is only used when no RPC URL was given either.
+    pub fn client_args(&self, env: UnxvEnv) -> anyhow::Result<ClientArgs> {
+        if let Some(path) = &self.local_ingestion_path {
+            anyhow::ensure!(
+                self.remote_store_url.is_none() && self.rpc_api_url.is_none(),
+                "--local-ingestion-path cannot be combined with --remote-store-url or --rpc-api-url",
+            );
+            return Ok(ClientArgs {
+                remote_store_url: None,
+                local_ingestion_path: Some(path.clone()),
+                rpc_api_url: None,
+                rpc_username: None,
+                rpc_password: None,
+            });
+        }
+        let (remote_store_url, rpc_api_url) = match (&self.remote_store_url, &self.rpc_api_url) {
+            (Some(url), rpc) => (Some(url.clone()), rpc.clone()),
+            (None, Some(rpc)) => (None, Some(rpc.clone())),
+            (None, None) => (env.remote_store_url(), env.rpc_api_url()),
         };
-        Url::parse(remote_store_url).unwrap()
+        anyhow::ensure!(
+            remote_store_url.is_some() || rpc_api_url.is_some(),
+            "Network {env:?} has no default checkpoint source: pass --remote-store-url or --rpc-api-url",
+        );
+        Ok(ClientArgs {
+            remote_store_url,
+            local_ingestion_path: None,
+            rpc_api_url,
+            rpc_username: self.rpc_username.clone(),
+            rpc_password: self.rpc_password.clone(),
+        })
+    }
+}
+
+/// Modules indexed when `--modules` isn't given: every module of the Unxversal packages.
+pub const DEFAULT_MODULES: &str =
+    "admin,fees,oracle,staking,rewards,usdu,book,big_vector,dex,futures,gas_futures,perpetuals,options,lending,xperps";
+
+/// Events that move funds (mints, burns, transfers, fills, fees, settlements), kept by
+/// `--balance-affecting-only`. Informational events (config, order book, initialization) are not.
+pub const BALANCE_AFFECTING_EVENTS: &str = "\
+    dex::PoolCreationFeePaid,dex::ProtocolFeeTaken,\
+    fees::FeeAccrued,fees::FeeWithdrawn,fees::FeesSwept,fees::UnxvFeeSplit,\
+    futures::CollateralDeposited,futures::CollateralWithdrawn,futures::FeeCharged,futures::Liquidated,\
+    futures::OrderFilled,futures::PnlCreditPaid,futures::Settled,futures::TraderRewardsClaimed,\
+    futures::TraderRewardsDeposited,\
+    gas_futures::CollateralDeposited,gas_futures::CollateralWithdrawn,gas_futures::FeeCharged,\
+    gas_futures::Liquidated,gas_futures::OrderFilled,gas_futures::PnlCreditPaid,\
+    lending::CollateralDeposited2,lending::CollateralWithdrawn2,lending::DebtBorrowed,lending::DebtRepaid,\
+    lending::DebtSupplied,lending::DebtWithdrawn,\
+    options::CollateralLocked,options::CollateralUnlocked,options::Exercised,options::Matched,\
+    options::OrderFilled,options::WriterClaimed,\
+    perpetuals::CollateralDeposited,perpetuals::CollateralWithdrawn,perpetuals::FeeCharged,\
+    perpetuals::FundingSettled,perpetuals::Liquidated,perpetuals::TraderRewardsClaimed,\
+    perpetuals::TraderRewardsDeposited,\
+    rewards::FaucetClaimed,\
+    staking::RewardAdded,staking::RewardBuyback,staking::RewardsClaimed,staking::Staked,staking::Unstaked,\
+    unxv::UNXVBurned,unxv::UNXVMinted,\
+    usdu::Claimed,\
+    xperps::CollateralDeposited,xperps::CollateralWithdrawn,xperps::FeeCharged,xperps::FundingSettled,\
+    xperps::Liquidated,xperps::OrderFilled,xperps::TraderRewardsClaimed,xperps::TraderRewardsDeposited";
+
+/// Which event modules to index: `all`, or a comma-separated list of lowercased module names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModulesFilter {
+    All,
+    Only(HashSet<String>),
+}
+
+impl ModulesFilter {
+    pub fn allows(&self, module: &str) -> bool {
+        match self {
+            ModulesFilter::All => true,
+            ModulesFilter::Only(modules) => modules.contains(&module.to_ascii_lowercase()),
+        }
     }
 }
 
-// Helper: parse a StructTag string into move_core_types::language_storage::StructTag
-pub fn parse_struct_tag(tag: &str) -> StructTag {
-    tag.parse().expect("valid struct tag")
+impl Default for ModulesFilter {
+    fn default() -> Self {
+        DEFAULT_MODULES.parse().expect("valid default modules")
+    }
 }
 
+impl FromStr for ModulesFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        if s.trim().eq_ignore_ascii_case("all") {
+            return Ok(ModulesFilter::All);
+        }
+        let mut modules = HashSet::new();
+        for module in s.split(',').map(str::trim) {
+            let valid = module.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && module.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            anyhow::ensure!(valid, "Invalid module name {module:?} in {s:?}");
+            modules.insert(module.to_ascii_lowercase());
+        }
+        Ok(ModulesFilter::Only(modules))
+    }
+}
+
+/// Parse a comma-separated event type filter such as `dex::ProtocolFeeTaken,staking::*`
+/// into module => struct names; `module::*` keeps the whole module (empty set).
+pub fn parse_event_types(spec: &str) -> anyhow::Result<HashMap<String, HashSet<String>>> {
+    let mut filter: HashMap<String, HashSet<String>> = HashMap::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (module, name) = entry
+            .split_once("::")
+            .ok_or_else(|| anyhow::anyhow!("Expected MODULE::Struct or MODULE::*, got {entry:?}"))?;
+        let names = filter.entry(module.trim().to_ascii_lowercase()).or_default();
+        if name.trim() != "*" {
+            names.insert(name.trim().to_string());
+        }
+    }
+    Ok(filter)
+}
+
+/// Parse a `package::module::Struct<...>` string into a [`StructTag`]. Tags come from
+/// configuration and stored rows as well as the chain, so a malformed one is an error for the
+/// caller to report, not a reason to abort.
+pub fn parse_struct_tag(tag: &str) -> anyhow::Result<StructTag> {
+    tag.parse().with_context(|| format!("Invalid struct tag {tag:?}"))
+}
+
+/// Comma-separated struct tags such as `0xabc::perpetuals::Liquidated`, matched against event or
+/// object types. Addresses are compared parsed, so short and padded forms match, as in the
+/// package allowlist. A tag without type parameters matches every instantiation of the struct;
+/// one with them matches only that instantiation. Commas inside `<...>` belong to the tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructTagFilter(Vec<StructTag>);
+
+impl StructTagFilter {
+    pub fn matches(&self, tag: &StructTag) -> bool {
+        self.0.iter().any(|allowed| {
+            allowed.address == tag.address
+                && allowed.module == tag.module
+                && allowed.name == tag.name
+                && (allowed.type_params.is_empty() || allowed.type_params == tag.type_params)
+        })
+    }
+
+    /// The tags in canonical form, in the order given.
+    pub fn canonical(&self) -> Vec<String> {
+        self.0.iter().map(|tag| tag.to_canonical_string(true)).collect()
+    }
+}
+
+impl FromStr for StructTagFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> anyhow::Result<Self> {
+        let mut tags = Vec::new();
+        let (mut depth, mut start) = (0usize, 0);
+        for (i, c) in spec.char_indices().chain([(spec.len(), ',')]) {
+            match c {
+                '<' => depth += 1,
+                '>' => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => {
+                    let tag = spec[start..i].trim();
+                    if !tag.is_empty() {
+                        tags.push(parse_struct_tag(tag)?);
+                    }
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        anyhow::ensure!(!tags.is_empty(), "Expected at least one struct tag, got {spec:?}");
+        Ok(Self(tags))
+    }
+}
+
+/// Normalize a Move type such as `0x2::sui::SUI` to its canonical string: every address
+/// 0x-prefixed, lowercased and zero-padded to 32 bytes. This is the form stored in
+/// `type_params` and matched by the type parameter filter.
+pub fn normalize_type_tag(tag: &str) -> anyhow::Result<String> {
+    let parsed: TypeTag = tag.trim().parse().with_context(|| format!("Invalid type {tag:?}"))?;
+    Ok(parsed.to_canonical_string(true))
+}
+
+/// Normalize a Sui address or package id to its canonical form: `0x`-prefixed,
+/// lowercased and zero-padded to 32 bytes. Accepts short forms such as `0x2`; anything
+/// other than 1 to 64 hex digits is an error.
+pub fn normalize_address(addr: &str) -> anyhow::Result<String> {
+    let trimmed = addr.trim();
+    let hex = trimmed
+        .strip_prefix("0x")
+        .or_else(|| trimmed.strip_prefix("0X"))
+        .unwrap_or(trimmed);
+    anyhow::ensure!(
+        (1..=64).contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        "Invalid address {addr:?}: expected up to 64 hex digits"
+    );
+    Ok(format!("0x{:0>64}", hex.to_ascii_lowercase()))
+}
+
+/// Consensus commit time carried by a consensus commit prologue transaction. Sui transactions
+/// have no timestamp of their own; the prologue's applies to the transactions that follow it
+/// in the checkpoint.
+pub fn consensus_commit_timestamp_ms(kind: &TransactionKind) -> Option<u64> {
+    match kind {
+        TransactionKind::ConsensusCommitPrologue(p) => Some(p.commit_timestamp_ms),
+        TransactionKind::ConsensusCommitPrologueV2(p) => Some(p.commit_timestamp_ms),
+        TransactionKind::ConsensusCommitPrologueV3(p) => Some(p.commit_timestamp_ms),
+        TransactionKind::ConsensusCommitPrologueV4(p) => Some(p.commit_timestamp_ms),
+        _ => None,
+    }
+}
+
+/// Distinct packages called by a transaction's Move call commands, in first-call order, as
+/// canonical 0x addresses. Empty for transactions without Move calls.
+pub fn move_call_packages(tx: &sui_types::transaction::Transaction) -> Vec<String> {
+    let TransactionKind::ProgrammableTransaction(pt) = tx.transaction_data().kind() else {
+        return vec![];
+    };
+    let mut packages: Vec<String> = vec![];
+    for command in &pt.commands {
+        if let Command::MoveCall(call) = command {
+            let package = call.package.to_canonical_string(true);
+            if !packages.contains(&package) {
+                packages.push(package);
+            }
+        }
+    }
+    packages
+}
+
+/// [`consensus_commit_timestamp_ms`] of a checkpoint transaction.
+pub fn tx_commit_timestamp_ms(tx: &sui_types::transaction::Transaction) -> Option<u64> {
+    consensus_commit_timestamp_ms(tx.transaction_data().kind())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(items: &[&str]) -> HashSet<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn urls_are_redacted() {
+        let redacted = redact_url(&url("postgres://indexer:hunter2@db:5432/unxv"));
+        assert_eq!(redacted, "postgres://indexer:%3Credacted%3E@db:5432/unxv");
+        assert_eq!(redact_url(&url("https://user@rpc.example/")), "https://user@rpc.example/");
+
+        let args = SourceArgs { rpc_password: Some("hunter2".into()), ..Default::default() };
+        assert!(!format!("{args:?}").contains("hunter2"));
+    }
+
+    #[test]
+    fn client_args_default_to_the_network() {
+        let mainnet = SourceArgs::default().client_args(UnxvEnv::Mainnet).unwrap();
+        assert_eq!(mainnet.remote_store_url, UnxvEnv::Mainnet.remote_store_url());
+        assert_eq!(mainnet.rpc_api_url, None);
+
+        let localnet = SourceArgs::default().client_args(UnxvEnv::Localnet).unwrap();
+        assert_eq!(localnet.remote_store_url, None);
+        assert_eq!(localnet.rpc_api_url, Some(url(LOCALNET_RPC_API_URL)));
+
+        let error = SourceArgs::default().client_args(UnxvEnv::Custom).unwrap_err();
+        assert!(error.to_string().contains("no default checkpoint source"), "{error}");
+    }
+
+    #[test]
+    fn client_args_prefer_explicit_sources() {
+        let store = SourceArgs { remote_store_url: Some(url("https://store.example/")), ..Default::default() };
+        let args = store.client_args(UnxvEnv::Testnet).unwrap();
+        assert_eq!(args.remote_store_url, Some(url("https://store.example/")));
+        assert_eq!(args.rpc_api_url, None);
+
+        let rpc = SourceArgs {
+            rpc_api_url: Some(url("https://rpc.example/")),
+            rpc_username: Some("user".into()),
+            rpc_password: Some("secret".into()),
+            ..Default::default()
+        };
+        let args = rpc.client_args(UnxvEnv::Mainnet).unwrap();
+        assert_eq!(args.remote_store_url, None);
+        assert_eq!(args.rpc_api_url, Some(url("https://rpc.example/")));
+        assert_eq!((args.rpc_username.as_deref(), args.rpc_password.as_deref()), (Some("user"), Some("secret")));
+
+        let local = SourceArgs { local_ingestion_path: Some("/checkpoints".into()), ..Default::default() };
+        let args = local.client_args(UnxvEnv::Custom).unwrap();
+        assert_eq!(args.local_ingestion_path, Some(PathBuf::from("/checkpoints")));
+        assert_eq!((args.remote_store_url, args.rpc_api_url), (None, None));
+
+        let both = SourceArgs { rpc_api_url: Some(url("https://rpc.example/")), ..local };
+        assert!(both.client_args(UnxvEnv::Custom).is_err());
+    }
+
+    #[test]
+    fn modules_filter_parses_lists_and_all() {
+        assert_eq!(" ALL ".parse::<ModulesFilter>().unwrap(), ModulesFilter::All);
+        let filter: ModulesFilter = "Dex, gas_futures,_private".parse().unwrap();
+        assert_eq!(filter, ModulesFilter::Only(set(&["dex", "gas_futures", "_private"])));
+        assert!(filter.allows("DEX"));
+        assert!(!filter.allows("lending"));
+        assert!(ModulesFilter::default().allows("xperps"));
+        for invalid in ["", "dex,", "9lives", "dex::Filled", "d-ex"] {
+            assert!(invalid.parse::<ModulesFilter>().is_err(), "{invalid:?} parsed");
+        }
+    }
+
+    #[test]
+    fn event_types_group_by_module() {
+        let filter = parse_event_types(" Dex::ProtocolFeeTaken, dex::PoolCreationFeePaid,staking::*,, ").unwrap();
+        assert_eq!(
+            filter,
+            HashMap::from([
+                ("dex".to_string(), set(&["ProtocolFeeTaken", "PoolCreationFeePaid"])),
+                ("staking".to_string(), HashSet::new()),
+            ])
+        );
+        assert!(parse_event_types("").unwrap().is_empty());
+        assert!(parse_event_types("ProtocolFeeTaken").is_err());
+    }
+
+    #[test]
+    fn balance_affecting_events_are_defined() {
+        let defined: HashSet<&str> =
+            unxv_events::signatures().map(|sig| sig.split_once(' ').expect("signature has fields").0).collect();
+        for (module, names) in parse_event_types(BALANCE_AFFECTING_EVENTS).unwrap() {
+            assert!(!names.is_empty(), "{module}::* isn't a list of events");
+            for name in names {
+                assert!(defined.contains(format!("{module}::{name}").as_str()), "{module}::{name} isn't an event");
+            }
+        }
+    }
+
+    #[test]
+    fn addresses_normalize_to_the_padded_form() {
+        let padded = format!("0x{}2", "0".repeat(63));
+        for (input, expected) in [
+            ("0x2", padded.as_str()),
+            (" 0x02 ", padded.as_str()),
+            ("2", padded.as_str()),
+            (padded.as_str(), padded.as_str()),
+            ("0XAbC", "0x0000000000000000000000000000000000000000000000000000000000000abc"),
+            (
+                "0xA11CEA11CEA11CEA11CEA11CEA11CEA11CEA11CEA11CEA11CEA11CEA11CE0000",
+                "0xa11cea11cea11cea11cea11cea11cea11cea11cea11cea11cea11cea11ce0000",
+            ),
+        ] {
+            assert_eq!(normalize_address(input).unwrap(), expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_addresses_are_rejected() {
+        let too_long = format!("0x1{}", "0".repeat(64));
+        for invalid in ["", "0x", " ", "0xnope", "0x2g", "0x-2", "0x 2", "0x0x2", too_long.as_str()] {
+            assert!(normalize_address(invalid).is_err(), "{invalid:?} normalized");
+        }
+    }
+
+    #[test]
+    fn struct_tags_parse_with_type_parameters() {
+        let tag = parse_struct_tag("0x2::coin::Coin<0x2::sui::SUI>").unwrap();
+        assert_eq!(tag.address, move_core_types::account_address::AccountAddress::TWO);
+        assert_eq!((tag.module.as_str(), tag.name.as_str()), ("coin", "Coin"));
+        assert_eq!(tag.type_params.len(), 1);
+        for invalid in ["", "0x2::coin", "coin::Coin", "0x2::coin::Coin<"] {
+            assert!(parse_struct_tag(invalid).is_err(), "{invalid:?} parsed");
+        }
+    }
+
+    #[test]
+    fn struct_tag_filter_splits_outside_type_parameters() {
+        let spec = "0xa::pool::Pool<0x2::sui::SUI, 0xb::usdu::USDU>, ,0xa::dex::Filled";
+        let filter: StructTagFilter = spec.parse().unwrap();
+        assert_eq!(
+            filter.canonical(),
+            [
+                format!(
+                    "{}::pool::Pool<{}::sui::SUI, {}::usdu::USDU>",
+                    normalize_address("0xa").unwrap(),
+                    normalize_address("0x2").unwrap(),
+                    normalize_address("0xb").unwrap()
+                ),
+                format!("{}::dex::Filled", normalize_address("0xa").unwrap()),
+            ]
+        );
+        assert!(" , ".parse::<StructTagFilter>().is_err());
+        assert!("0xa::dex".parse::<StructTagFilter>().is_err());
+    }
+
+    #[test]
+    fn struct_tag_filter_matches() {
+        let filter: StructTagFilter = "0xa::pool::Pool<0x2::sui::SUI>,0x00a::dex::Filled".parse().unwrap();
+        let matches = |tag: &str| filter.matches(&parse_struct_tag(tag).unwrap());
+        assert!(matches("0xa::dex::Filled"));
+        // Without type parameters in the filter, every instantiation matches.
+        assert!(matches("0xa::dex::Filled<0x2::sui::SUI>"));
+        assert!(matches("0x000a::pool::Pool<0x2::sui::SUI>"));
+        assert!(!matches("0xa::pool::Pool<0xb::usdu::USDU>"));
+        assert!(!matches("0xa::pool::Pool"));
+        assert!(!matches("0xb::dex::Filled"));
+        assert!(!matches("0xa::dex::Cancelled"));
+    }
+
+    #[test]
+    fn move_call_packages_are_distinct_in_first_call_order() {
+        use move_core_types::identifier::Identifier;
+        use sui_types::base_types::{random_object_ref, ObjectID, SuiAddress};
+        use sui_types::programmable_transaction_builder::ProgrammableTransactionBuilder as Builder;
+        use sui_types::transaction::{Transaction, TransactionData};
+
+        fn call(ptb: &mut Builder, package: &str, module: &str) {
+            let package = ObjectID::from_hex_literal(package).unwrap();
+            let (module, function) = (Identifier::new(module).unwrap(), Identifier::new("f").unwrap());
+            ptb.programmable_move_call(package, module, function, vec![], vec![]);
+        }
+        fn transaction(ptb: Builder) -> Transaction {
+            let (sender, gas) = (SuiAddress::ZERO, vec![random_object_ref()]);
+            Transaction::from_data(
+                TransactionData::new_programmable(sender, gas, ptb.finish(), 1_000_000, 1_000),
+                vec![],
+            )
+        }
+
+        let mut ptb = Builder::new();
+        call(&mut ptb, "0xb", "dex");
+        call(&mut ptb, "0xa", "lending");
+        call(&mut ptb, "0xb", "oracle");
+        ptb.transfer_sui(SuiAddress::ZERO, Some(1));
+        ptb.command(Command::MakeMoveVec(None, vec![]));
+        call(&mut ptb, "0xc", "dex");
+        let expected: Vec<String> = ["0xb", "0xa", "0xc"].iter().map(|p| normalize_address(p).unwrap()).collect();
+        assert_eq!(move_call_packages(&transaction(ptb)), expected);
+
+        // Transfers alone call no packages.
+        let mut ptb = Builder::new();
+        ptb.transfer_sui(SuiAddress::ZERO, Some(1));
+        assert!(move_call_packages(&transaction(ptb)).is_empty());
+    }
+}
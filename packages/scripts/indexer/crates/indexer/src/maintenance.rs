@@ -0,0 +1,177 @@
+use diesel::sql_types::{BigInt, Text};
+use diesel::QueryableByName;
+use diesel_async::RunQueryDsl;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sui_pg_db::Db;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::alerts::{Alert, AlertSender, Severity};
+use crate::metrics::UnxvMetrics;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct MaintenanceArgs {
+    /// Seconds between table bloat samples (0 = maintenance task disabled)
+    #[clap(env, long, default_value_t = 0)]
+    pub maintenance_interval_secs: u64,
+    /// Run VACUUM (ANALYZE) on the most bloated tables instead of only reporting
+    #[clap(env, long)]
+    pub maintenance_vacuum: bool,
+    /// Dead tuple ratio above which a table is vacuumed
+    #[clap(env, long, default_value_t = 0.2)]
+    pub maintenance_dead_ratio: f64,
+    /// Minimum dead tuples before a table is considered for vacuum
+    #[clap(env, long, default_value_t = 10_000)]
+    pub maintenance_min_dead_tuples: i64,
+    /// Maximum tables vacuumed per round
+    #[clap(env, long, default_value_t = 2)]
+    pub maintenance_max_tables: usize,
+    /// UTC hour window START-END in which vacuums may run, e.g. `2-5` (default: any time)
+    #[clap(env, long, value_parser = parse_window)]
+    pub maintenance_window: Option<(u32, u32)>,
+}
+
+impl MaintenanceArgs {
+    /// None when the maintenance task is disabled.
+    pub fn config(&self) -> Option<MaintenanceConfig> {
+        (self.maintenance_interval_secs > 0).then(|| MaintenanceConfig {
+            interval: Duration::from_secs(self.maintenance_interval_secs),
+            vacuum: self.maintenance_vacuum,
+            dead_ratio_threshold: self.maintenance_dead_ratio,
+            min_dead_tuples: self.maintenance_min_dead_tuples,
+            max_tables_per_round: self.maintenance_max_tables,
+            window: self.maintenance_window,
+        })
+    }
+}
+
+/// Table bloat monitoring and optional targeted `VACUUM (ANALYZE)`.
+#[derive(Debug, Clone)]
+pub struct MaintenanceConfig {
+    /// How often to sample `pg_stat_user_tables`.
+    pub interval: Duration,
+    /// Run `VACUUM (ANALYZE)` on offenders; otherwise only report.
+    pub vacuum: bool,
+    /// Dead/(live+dead) tuple ratio above which a table is a vacuum candidate.
+    pub dead_ratio_threshold: f64,
+    /// Tables with fewer dead tuples than this are never vacuumed, whatever the ratio.
+    pub min_dead_tuples: i64,
+    /// At most this many tables are vacuumed per round, worst first.
+    pub max_tables_per_round: usize,
+    /// UTC hour range `[start, end)` in which vacuums may run. None => any time.
+    pub window: Option<(u32, u32)>,
+}
+
+#[derive(QueryableByName, Debug, Clone)]
+pub struct TableStats {
+    #[diesel(sql_type = Text)]
+    pub relname: String,
+    #[diesel(sql_type = BigInt)]
+    pub n_live_tup: i64,
+    #[diesel(sql_type = BigInt)]
+    pub n_dead_tup: i64,
+    #[diesel(sql_type = BigInt)]
+    pub table_bytes: i64,
+    #[diesel(sql_type = BigInt)]
+    pub index_bytes: i64,
+}
+
+impl TableStats {
+    pub fn dead_ratio(&self) -> f64 {
+        let total = self.n_live_tup + self.n_dead_tup;
+        if total == 0 { 0.0 } else { self.n_dead_tup as f64 / total as f64 }
+    }
+}
+
+const TABLE_STATS_QUERY: &str = "\
+    SELECT relname::TEXT AS relname, n_live_tup, n_dead_tup, \
+           pg_table_size(relid) AS table_bytes, pg_indexes_size(relid) AS index_bytes \
+    FROM pg_stat_user_tables";
+
+/// Parse a `START-END` UTC hour window such as `2-5`. `END` is exclusive and may wrap past midnight.
+pub fn parse_window(s: &str) -> anyhow::Result<(u32, u32)> {
+    let (start, end) = s
+        .split_once('-')
+        .ok_or_else(|| anyhow::anyhow!("Expected START-END hours, got {s:?}"))?;
+    let (start, end): (u32, u32) = (start.trim().parse()?, end.trim().parse()?);
+    anyhow::ensure!(start < 24 && end <= 24 && start != end, "Invalid maintenance window {s:?}");
+    Ok((start, end))
+}
+
+fn in_window(window: Option<(u32, u32)>, hour: u32) -> bool {
+    match window {
+        None => true,
+        Some((start, end)) if start < end => (start..end).contains(&hour),
+        Some((start, end)) => hour >= start || hour < end,
+    }
+}
+
+/// Tables to vacuum this round: over both thresholds, ordered by dead ratio, capped.
+pub fn pick_vacuum_targets(stats: &[TableStats], config: &MaintenanceConfig) -> Vec<String> {
+    let mut candidates: Vec<&TableStats> = stats
+        .iter()
+        .filter(|t| t.n_dead_tup >= config.min_dead_tuples && t.dead_ratio() > config.dead_ratio_threshold)
+        .collect();
+    candidates.sort_by(|a, b| b.dead_ratio().total_cmp(&a.dead_ratio()));
+    candidates
+        .into_iter()
+        .take(config.max_tables_per_round)
+        .map(|t| t.relname.clone())
+        .collect()
+}
+
+/// Sample table stats on an interval until cancelled, exporting them as gauges and vacuuming
+/// the worst offenders inside the configured window.
+pub async fn run(
+    db: Db,
+    config: MaintenanceConfig,
+    metrics: Arc<UnxvMetrics>,
+    alerts: AlertSender,
+    cancel: CancellationToken,
+) {
+    let mut interval = tokio::time::interval(config.interval);
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = interval.tick() => {}
+        }
+        if let Err(e) = round(&db, &config, &metrics).await {
+            alerts.send(Alert::new(
+                "maintenance",
+                Severity::Warning,
+                "maintenance:round_failed",
+                format!("Maintenance round failed: {e:#}"),
+            ));
+        }
+    }
+}
+
+async fn round(db: &Db, config: &MaintenanceConfig, metrics: &UnxvMetrics) -> anyhow::Result<()> {
+    let mut conn = db.connect().await?;
+    let stats: Vec<TableStats> = diesel::sql_query(TABLE_STATS_QUERY).load(&mut conn).await?;
+    for t in &stats {
+        let table = [t.relname.as_str()];
+        metrics.table_live_tuples.with_label_values(&table).set(t.n_live_tup);
+        metrics.table_dead_tuples.with_label_values(&table).set(t.n_dead_tup);
+        metrics.table_dead_ratio.with_label_values(&table).set(t.dead_ratio());
+        metrics.table_bytes.with_label_values(&table).set(t.table_bytes);
+        metrics.table_index_bytes.with_label_values(&table).set(t.index_bytes);
+    }
+
+    if !config.vacuum || !in_window(config.window, current_utc_hour()) {
+        return Ok(());
+    }
+    for table in pick_vacuum_targets(&stats, config) {
+        info!(table, "Running VACUUM (ANALYZE)");
+        let stmt = format!("VACUUM (ANALYZE) \"{}\"", table.replace('"', "\"\""));
+        diesel::sql_query(stmt).execute(&mut conn).await?;
+        metrics.table_vacuums.with_label_values(&[table.as_str()]).inc();
+    }
+    Ok(())
+}
+
+fn current_utc_hour() -> u32 {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    ((secs % 86_400) / 3_600) as u32
+}
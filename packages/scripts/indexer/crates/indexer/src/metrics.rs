@@ -0,0 +1,381 @@
+use prometheus::{
+    exponential_buckets, register_gauge_vec_with_registry, register_gauge_with_registry,
+    register_histogram_vec_with_registry, register_histogram_with_registry, register_int_counter_vec_with_registry,
+    register_int_counter_with_registry, register_int_gauge_vec_with_registry, register_int_gauge_with_registry, Gauge,
+    GaugeVec, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Registry,
+};
+use prometheus::core::Collector;
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock};
+
+/// Indexer-specific metrics, registered on the `unxv`-prefixed registry created in main.rs.
+pub struct UnxvMetrics {
+    // unxv_events pipeline throughput.
+    pub events_processed: IntCounterVec,
+    pub events_skipped: IntCounterVec,
+    pub events_per_checkpoint: Histogram,
+    /// Highest checkpoint `unxv_events` has processed.
+    pub latest_checkpoint: IntGauge,
+    /// Wall clock minus the timestamp of [`Self::latest_checkpoint`] when it was processed.
+    pub ingestion_lag_ms: IntGauge,
+
+    /// Events whose `contents_bcs` could not be decoded into `contents_json`.
+    pub contents_decode_failures: IntCounterVec,
+    /// Events stored with `contents_bcs` cut to `--contents-bcs-max-bytes`.
+    pub truncated_payloads: IntCounterVec,
+    /// Events written to `unxv_dead_letters` instead of their pipeline's table.
+    pub dead_letters: IntCounterVec,
+
+    // Table maintenance (see `maintenance`).
+    pub table_live_tuples: IntGaugeVec,
+    pub table_dead_tuples: IntGaugeVec,
+    pub table_dead_ratio: GaugeVec,
+    pub table_bytes: IntGaugeVec,
+    pub table_index_bytes: IntGaugeVec,
+    pub table_vacuums: IntCounterVec,
+
+    /// Checkpoints a pipeline skipped because they're outside its `--pipeline-range`.
+    pub checkpoints_skipped: IntCounterVec,
+    /// Checkpoints delivered at or below the pipeline's stored watermark, by `--out-of-order-policy`.
+    pub checkpoints_out_of_order: IntCounterVec,
+    /// Runs of checkpoints below the `unxv_events` watermark missing from `unxv_checkpoints`,
+    /// as of the last check (see `gaps`).
+    pub checkpoint_gaps: IntGauge,
+    /// Checkpoints a pipeline's processor never received (see `gaps::StreamGaps`).
+    pub checkpoints_missed: IntCounterVec,
+
+    /// Rows deleted by the pruner (see `pruner`).
+    pub pruned_rows: IntCounterVec,
+    /// `unxv_events` rows deleted by per-module retention, by event module.
+    pub pruned_module_rows: IntCounterVec,
+
+    /// Days finalized by rollup tasks (see `rollups`), by rollup table.
+    pub rollup_days_finalized: IntCounterVec,
+    /// Cross-module liquidation cascades recorded (see `cascades`).
+    pub liquidation_cascades: IntCounter,
+
+    /// Checks that found another instance writing one of this instance's watermarks.
+    pub watermark_conflicts: IntCounterVec,
+
+    // Commit inserts, by table and `--insert-method`.
+    pub inserted_rows: IntCounterVec,
+    pub insert_rows_per_second: HistogramVec,
+    /// Commits retried after a transient database error, by pipeline and error class.
+    pub commit_retries: IntCounterVec,
+    /// Rows set aside in `unxv_events_quarantine` because the database rejected them.
+    pub quarantined_rows: IntCounterVec,
+
+    // Backpressure from commit latency (see `backpressure`).
+    pub commit_latency_rolling_ms: Gauge,
+    pub backpressure_active: IntGauge,
+    pub backpressure_delays: IntCounter,
+
+    // Kafka sink (see `kafka`).
+    pub kafka_published: IntCounter,
+    pub kafka_failed: IntCounter,
+}
+
+static METRICS: OnceLock<Arc<UnxvMetrics>> = OnceLock::new();
+
+/// Make `metrics` reachable from commits, which are associated functions without access to
+/// the handler. Later calls are ignored.
+pub fn install(metrics: Arc<UnxvMetrics>) {
+    let _ = METRICS.set(metrics);
+}
+
+/// The metrics given to [`install`], if any (commands and replays run without them).
+pub fn global() -> Option<&'static UnxvMetrics> {
+    METRICS.get().map(|m| m.as_ref())
+}
+
+impl UnxvMetrics {
+    pub fn new(registry: &Registry) -> Arc<Self> {
+        Arc::new(Self {
+            events_processed: register_int_counter_vec_with_registry!(
+                "indexer_events_processed",
+                "Events accepted by the unxv_events pipeline",
+                &["module", "event_type"],
+                registry,
+            )
+            .unwrap(),
+            events_skipped: register_int_counter_vec_with_registry!(
+                "indexer_events_skipped",
+                "Events rejected by the unxv_events filters, by filter",
+                &["reason"],
+                registry,
+            )
+            .unwrap(),
+            events_per_checkpoint: register_histogram_with_registry!(
+                "indexer_events_per_checkpoint",
+                "Events accepted by the unxv_events pipeline per checkpoint",
+                vec![0.0, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0],
+                registry,
+            )
+            .unwrap(),
+            latest_checkpoint: register_int_gauge_with_registry!(
+                "indexer_latest_checkpoint",
+                "Sequence number of the highest checkpoint processed by unxv_events",
+                registry,
+            )
+            .unwrap(),
+            ingestion_lag_ms: register_int_gauge_with_registry!(
+                "indexer_ingestion_lag_ms",
+                "Milliseconds between the highest processed checkpoint's timestamp and when it was processed",
+                registry,
+            )
+            .unwrap(),
+            contents_decode_failures: register_int_counter_vec_with_registry!(
+                "indexer_contents_decode_failures",
+                "Events stored without contents_json because decoding failed",
+                &["module", "event_type", "reason"],
+                registry,
+            )
+            .unwrap(),
+            truncated_payloads: register_int_counter_vec_with_registry!(
+                "indexer_truncated_payloads",
+                "Events stored with contents_bcs truncated to --contents-bcs-max-bytes",
+                &["module", "event_type"],
+                registry,
+            )
+            .unwrap(),
+            dead_letters: register_int_counter_vec_with_registry!(
+                "indexer_dead_letters",
+                "Events set aside in unxv_dead_letters, by pipeline and reason",
+                &["pipeline", "reason"],
+                registry,
+            )
+            .unwrap(),
+            table_live_tuples: register_int_gauge_vec_with_registry!(
+                "indexer_table_live_tuples",
+                "Estimated live tuples per table (pg_stat_user_tables)",
+                &["table"],
+                registry,
+            )
+            .unwrap(),
+            table_dead_tuples: register_int_gauge_vec_with_registry!(
+                "indexer_table_dead_tuples",
+                "Estimated dead tuples per table (pg_stat_user_tables)",
+                &["table"],
+                registry,
+            )
+            .unwrap(),
+            table_dead_ratio: register_gauge_vec_with_registry!(
+                "indexer_table_dead_ratio",
+                "Dead tuples as a fraction of live plus dead tuples per table",
+                &["table"],
+                registry,
+            )
+            .unwrap(),
+            table_bytes: register_int_gauge_vec_with_registry!(
+                "indexer_table_bytes",
+                "On-disk size of each table's heap, including bloat",
+                &["table"],
+                registry,
+            )
+            .unwrap(),
+            table_index_bytes: register_int_gauge_vec_with_registry!(
+                "indexer_table_index_bytes",
+                "On-disk size of all indexes of each table, including bloat",
+                &["table"],
+                registry,
+            )
+            .unwrap(),
+            table_vacuums: register_int_counter_vec_with_registry!(
+                "indexer_table_vacuums",
+                "VACUUM (ANALYZE) runs triggered by the maintenance task",
+                &["table"],
+                registry,
+            )
+            .unwrap(),
+            checkpoints_skipped: register_int_counter_vec_with_registry!(
+                "indexer_checkpoints_skipped",
+                "Checkpoints skipped before processing because they're outside the pipeline's range",
+                &["pipeline", "reason"],
+                registry,
+            )
+            .unwrap(),
+            checkpoints_out_of_order: register_int_counter_vec_with_registry!(
+                "indexer_checkpoints_out_of_order",
+                "Checkpoints delivered at or below the pipeline's stored watermark",
+                &["pipeline", "policy"],
+                registry,
+            )
+            .unwrap(),
+            checkpoint_gaps: register_int_gauge_with_registry!(
+                "indexer_checkpoint_gaps",
+                "Runs of checkpoints below the unxv_events watermark missing from unxv_checkpoints",
+                registry,
+            )
+            .unwrap(),
+            checkpoints_missed: register_int_counter_vec_with_registry!(
+                "indexer_checkpoints_missed",
+                "Checkpoints skipped by the stream a pipeline processes",
+                &["pipeline"],
+                registry,
+            )
+            .unwrap(),
+            pruned_rows: register_int_counter_vec_with_registry!(
+                "indexer_pruned_rows",
+                "Rows deleted by the retention pruner",
+                &["table"],
+                registry,
+            )
+            .unwrap(),
+            pruned_module_rows: register_int_counter_vec_with_registry!(
+                "indexer_pruned_module_rows",
+                "unxv_events rows deleted by per-module retention",
+                &["module"],
+                registry,
+            )
+            .unwrap(),
+            rollup_days_finalized: register_int_counter_vec_with_registry!(
+                "indexer_rollup_days_finalized",
+                "(market, day) rows finalized by rollup tasks",
+                &["rollup"],
+                registry,
+            )
+            .unwrap(),
+            liquidation_cascades: register_int_counter_with_registry!(
+                "indexer_liquidation_cascades",
+                "Liquidation cascades across modules recorded in liquidation_cascades",
+                registry,
+            )
+            .unwrap(),
+            watermark_conflicts: register_int_counter_vec_with_registry!(
+                "indexer_watermark_conflicts",
+                "Checks that found another instance writing this pipeline's watermark",
+                &["pipeline"],
+                registry,
+            )
+            .unwrap(),
+            inserted_rows: register_int_counter_vec_with_registry!(
+                "indexer_inserted_rows",
+                "Rows sent to insert statements by commits, including ones skipped as duplicates",
+                &["table", "method"],
+                registry,
+            )
+            .unwrap(),
+            insert_rows_per_second: register_histogram_vec_with_registry!(
+                "indexer_insert_rows_per_second",
+                "Throughput of each commit's insert into a table",
+                &["table", "method"],
+                exponential_buckets(100.0, 2.0, 14).unwrap(),
+                registry,
+            )
+            .unwrap(),
+            commit_retries: register_int_counter_vec_with_registry!(
+                "indexer_commit_retries",
+                "Commits retried after a transient database error",
+                &["pipeline", "reason"],
+                registry,
+            )
+            .unwrap(),
+            quarantined_rows: register_int_counter_vec_with_registry!(
+                "indexer_quarantined_rows",
+                "Rows written to unxv_events_quarantine after the database rejected them at commit",
+                &["pipeline"],
+                registry,
+            )
+            .unwrap(),
+            commit_latency_rolling_ms: register_gauge_with_registry!(
+                "indexer_commit_latency_rolling_ms",
+                "Average latency of the most recent commits, as seen by backpressure",
+                registry,
+            )
+            .unwrap(),
+            backpressure_active: register_int_gauge_with_registry!(
+                "indexer_backpressure_active",
+                "1 while commit latency is holding back checkpoint processing, else 0",
+                registry,
+            )
+            .unwrap(),
+            backpressure_delays: register_int_counter_with_registry!(
+                "indexer_backpressure_delays",
+                "Checkpoints held back by backpressure",
+                registry,
+            )
+            .unwrap(),
+            kafka_published: register_int_counter_with_registry!(
+                "indexer_kafka_messages_published",
+                "Committed events acknowledged by Kafka",
+                registry,
+            )
+            .unwrap(),
+            kafka_failed: register_int_counter_with_registry!(
+                "indexer_kafka_messages_failed",
+                "Committed events Kafka did not acknowledge; their commit is retried",
+                registry,
+            )
+            .unwrap(),
+        })
+    }
+
+    /// Record a processed checkpoint. Checkpoints are processed concurrently and out of order,
+    /// so one below the latest leaves both gauges alone.
+    pub fn record_checkpoint(&self, sequence_number: u64, timestamp_ms: i64, now_ms: i64) {
+        let sequence_number = sequence_number as i64;
+        if sequence_number >= self.latest_checkpoint.get() {
+            self.latest_checkpoint.set(sequence_number);
+            self.ingestion_lag_ms.set(now_ms - timestamp_ms);
+        }
+    }
+
+    /// Totals of `events_processed` per module, summed over event types.
+    pub fn events_processed_by_module(&self) -> BTreeMap<String, u64> {
+        let mut totals = BTreeMap::new();
+        for family in self.events_processed.collect() {
+            for metric in family.get_metric() {
+                let module = metric.get_label().iter().find(|l| l.get_name() == "module");
+                if let Some(module) = module {
+                    *totals.entry(module.get_value().to_string()).or_default() += metric.get_counter().get_value() as u64;
+                }
+            }
+        }
+        totals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::unxv_events_handler::UnxvEventsHandler;
+    use crate::test_utils::CheckpointBuilder;
+    use crate::ModulesFilter;
+    use sui_indexer_alt_framework::pipeline::Processor;
+
+    /// `name`'s gauge value in `registry`.
+    fn gauge(registry: &Registry, name: &str) -> Option<f64> {
+        let family = registry.gather().into_iter().find(|f| f.get_name() == name)?;
+        Some(family.get_metric()[0].get_gauge().get_value())
+    }
+
+    #[test]
+    fn only_the_highest_checkpoint_sets_the_lag() {
+        let registry = Registry::new_custom(Some("unxv".into()), None).unwrap();
+        let metrics = UnxvMetrics::new(&registry);
+        metrics.record_checkpoint(10, 1_000, 1_250);
+        // Processed late, but below the latest.
+        metrics.record_checkpoint(9, 500, 5_000);
+        assert_eq!(gauge(&registry, "unxv_indexer_latest_checkpoint"), Some(10.0));
+        assert_eq!(gauge(&registry, "unxv_indexer_ingestion_lag_ms"), Some(250.0));
+
+        metrics.record_checkpoint(11, 2_000, 2_100);
+        assert_eq!((metrics.latest_checkpoint.get(), metrics.ingestion_lag_ms.get()), (11, 100));
+    }
+
+    #[test]
+    fn processing_a_checkpoint_sets_the_lag_from_its_timestamp() {
+        let metrics = UnxvMetrics::new(&Registry::new());
+        let handler = UnxvEventsHandler::new(ModulesFilter::All, None).unwrap().with_metrics(metrics.clone());
+        let checkpoint = CheckpointBuilder::new(42).build();
+        let timestamp_ms = checkpoint.checkpoint_summary.timestamp_ms as i64;
+
+        let before = chrono::Utc::now().timestamp_millis();
+        handler.process(&checkpoint).unwrap();
+        let after = chrono::Utc::now().timestamp_millis();
+
+        assert_eq!(metrics.latest_checkpoint.get(), 42);
+        let lag = metrics.ingestion_lag_ms.get();
+        assert!((before - timestamp_ms..=after - timestamp_ms).contains(&lag), "lag {lag}ms");
+    }
+}
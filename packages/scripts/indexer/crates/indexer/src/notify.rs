@@ -0,0 +1,229 @@
+//! Postgres `NOTIFY` after each `unxv_events` commit, and a reconnecting `LISTEN` helper for
+//! downstream services, so they can fetch new events when told instead of polling.
+
+use diesel::sql_types::Text;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_postgres::{AsyncMessage, NoTls};
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+use unxv_schema::models::UnxvEvent;
+
+/// Channel notified after each commit of new events.
+pub const CHANNEL: &str = "unxv_events";
+
+/// Postgres rejects `NOTIFY` payloads of 8000 bytes or more.
+const MAX_PAYLOAD: usize = 7999;
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turn on notifications for this process (`--enable-notify`). Commits are associated functions
+/// without access to the handler, so they check this instead.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// What a listener receives. When the full summary would exceed the payload limit only
+/// `checkpoint` is sent.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Payload {
+    /// Highest checkpoint in the batch.
+    pub checkpoint: i64,
+    /// Lowest checkpoint in the batch; concurrent batches may span several and land out of order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_checkpoint: Option<i64>,
+    /// Events committed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    /// Distinct modules of those events, sorted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modules: Option<Vec<String>>,
+}
+
+impl Payload {
+    /// None for an empty batch, or when notifications aren't [`enabled`].
+    pub fn summarize(events: &[&UnxvEvent]) -> Option<Self> {
+        if !enabled() {
+            return None;
+        }
+        let checkpoint = events.iter().map(|e| e.checkpoint).max()?;
+        let first_checkpoint = events.iter().map(|e| e.checkpoint).min();
+        let modules: BTreeSet<&str> = events.iter().map(|e| e.module.as_str()).collect();
+        Some(Self {
+            checkpoint,
+            first_checkpoint,
+            count: Some(events.len()),
+            modules: Some(modules.into_iter().map(String::from).collect()),
+        })
+    }
+
+    /// JSON, cut down to just the checkpoint if it doesn't fit in a notification.
+    pub fn encode(&self) -> String {
+        let full = serde_json::to_string(self).expect("payload serializes");
+        if full.len() <= MAX_PAYLOAD {
+            return full;
+        }
+        serde_json::json!({ "checkpoint": self.checkpoint }).to_string()
+    }
+}
+
+/// Notify [`CHANNEL`] with `payload`. Inside a transaction the notification is delivered when
+/// it commits.
+pub async fn send(payload: &Payload, conn: &mut AsyncPgConnection) -> anyhow::Result<()> {
+    diesel::sql_query("SELECT pg_notify($1, $2)")
+        .bind::<Text, _>(CHANNEL)
+        .bind::<Text, _>(payload.encode())
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// `LISTEN` on `channel` until cancelled, forwarding payloads to the returned receiver. Lost
+/// connections are re-established with exponential backoff; notifications sent while
+/// disconnected are missed, so listeners should re-fetch from their last seen checkpoint after
+/// reconnecting. Connects without TLS.
+pub fn listen(database_url: String, channel: &str, cancel: CancellationToken) -> mpsc::Receiver<String> {
+    let (tx, rx) = mpsc::channel(1024);
+    let listen = format!("LISTEN \"{}\"", channel.replace('"', "\"\""));
+    tokio::spawn(async move {
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            let session = async {
+                let (client, mut connection) = tokio_postgres::connect(&database_url, NoTls).await?;
+                let (messages_tx, mut messages) = mpsc::unbounded_channel();
+                // The connection only makes progress while polled; it hands notifications over.
+                let driver = tokio::spawn(async move {
+                    loop {
+                        match std::future::poll_fn(|cx| connection.poll_message(cx)).await {
+                            Some(Ok(AsyncMessage::Notification(n))) => {
+                                if messages_tx.send(n.payload().to_string()).is_err() {
+                                    return Ok(());
+                                }
+                            }
+                            Some(Ok(_)) => {}
+                            Some(Err(e)) => return Err(e),
+                            None => return Ok(()),
+                        }
+                    }
+                });
+                client.batch_execute(&listen).await?;
+                backoff = INITIAL_BACKOFF;
+                while let Some(payload) = messages.recv().await {
+                    if tx.send(payload).await.is_err() {
+                        break;
+                    }
+                }
+                driver.abort();
+                anyhow::Ok(())
+            };
+            tokio::select! {
+                _ = cancel.cancelled() => return,
+                result = session => {
+                    if tx.is_closed() {
+                        return;
+                    }
+                    if let Err(e) = result {
+                        warn!(?backoff, "LISTEN connection failed, reconnecting: {e:#}");
+                    }
+                }
+            }
+            tokio::select! {
+                _ = cancel.cancelled() => return,
+                _ = tokio::time::sleep(backoff) => {}
+            }
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{stored_event, TestDb};
+    use diesel_async::scoped_futures::ScopedFutureExt;
+    use diesel_async::AsyncConnection;
+
+    fn payload(checkpoint: i64) -> Payload {
+        Payload { checkpoint, first_checkpoint: Some(checkpoint), count: Some(1), modules: Some(vec!["dex".into()]) }
+    }
+
+    #[test]
+    fn batches_are_summarized() {
+        enable();
+        let events =
+            [stored_event("a", "lending", 9, 0), stored_event("b", "dex", 7, 0), stored_event("c", "dex", 8, 0)];
+        let summary = Payload::summarize(&events.iter().collect::<Vec<_>>()).unwrap();
+        assert_eq!(
+            summary,
+            Payload {
+                checkpoint: 9,
+                first_checkpoint: Some(7),
+                count: Some(3),
+                modules: Some(vec!["dex".into(), "lending".into()]),
+            }
+        );
+        assert_eq!(serde_json::from_str::<Payload>(&summary.encode()).unwrap(), summary);
+        assert_eq!(Payload::summarize(&[]), None);
+    }
+
+    #[test]
+    fn oversized_payloads_are_cut_to_the_checkpoint() {
+        let modules: Vec<String> = (0..1000).map(|i| format!("module_{i}")).collect();
+        let payload = Payload { modules: Some(modules), ..payload(9) };
+        let encoded = payload.encode();
+        assert!(encoded.len() <= MAX_PAYLOAD);
+        assert_eq!(
+            serde_json::from_str::<Payload>(&encoded).unwrap(),
+            Payload { checkpoint: 9, first_checkpoint: None, count: None, modules: None }
+        );
+    }
+
+    #[tokio::test]
+    async fn listeners_receive_committed_notifications() {
+        let db = TestDb::new().await;
+        let cancel = CancellationToken::new();
+        let mut rx = listen(db.url().to_string(), CHANNEL, cancel.clone());
+        let mut conn = db.db().connect().await.unwrap();
+
+        // LISTEN runs in the background; notify until the listener has caught one.
+        let first = loop {
+            send(&payload(1), &mut conn).await.unwrap();
+            if let Ok(received) = tokio::time::timeout(Duration::from_millis(100), rx.recv()).await {
+                break received.unwrap();
+            }
+        };
+        assert_eq!(serde_json::from_str::<Payload>(&first).unwrap(), payload(1));
+        while tokio::time::timeout(Duration::from_millis(100), rx.recv()).await.is_ok() {}
+
+        // A notification of a rolled back commit is never delivered.
+        let rolled_back = conn
+            .transaction::<(), anyhow::Error, _>(|conn| {
+                async move {
+                    send(&payload(2), conn).await?;
+                    anyhow::bail!("rolled back")
+                }
+                .scope_boxed()
+            })
+            .await;
+        assert!(rolled_back.is_err());
+        send(&payload(3), &mut conn).await.unwrap();
+        let next = tokio::time::timeout(Duration::from_secs(10), rx.recv()).await.unwrap().unwrap();
+        assert_eq!(serde_json::from_str::<Payload>(&next).unwrap(), payload(3));
+
+        cancel.cancel();
+        assert_eq!(tokio::time::timeout(Duration::from_secs(10), rx.recv()).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,1258 @@
+use async_trait::async_trait;
+use diesel::sql_types::{BigInt, Bool, Text};
+use diesel::QueryableByName;
+use diesel_async::RunQueryDsl;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use sui_indexer_alt_framework::pipeline::concurrent::ConcurrentConfig;
+use sui_indexer_alt_framework::pipeline::{concurrent, sequential, Processor};
+use sui_indexer_alt_framework::store::Store;
+use sui_indexer_alt_framework::{Indexer, IndexerArgs};
+use sui_pg_db::Db;
+use sui_types::full_checkpoint_content::CheckpointData;
+
+use crate::backpressure;
+use crate::decoder::{BcsLimits, JsonLimits, LayoutResolver, TypeParamLimits};
+use crate::gaps::{StreamGaps, REORDER_WINDOW};
+use crate::handlers::family_events_handler::{self, Family};
+use crate::handlers::unxv_events_handler::UnxvEventsHandler;
+use crate::handlers::PIPELINES;
+use crate::health;
+use crate::metrics::UnxvMetrics;
+use crate::{ModulesFilter, StructTagFilter};
+use tracing::{debug, warn};
+use unxv_schema::docs::TableDoc;
+
+/// Shared configuration handed to every pipeline's builder.
+pub struct PipelineContext {
+    pub modules: ModulesFilter,
+    pub package_allowlist: Option<Vec<String>>,
+    pub senders_allow: Option<HashSet<String>>,
+    pub senders_deny: Option<HashSet<String>>,
+    /// Canonical coin types for [`UnxvEventsHandler::with_type_param_filter`].
+    pub type_param_filter: Option<HashSet<String>>,
+    pub type_param_filter_drop_untyped: bool,
+    pub event_types: Option<HashMap<String, HashSet<String>>>,
+    pub event_struct_tags: Option<StructTagFilter>,
+    pub balance_filter: Option<HashMap<String, HashSet<String>>>,
+    pub layout_resolver: Arc<dyn LayoutResolver>,
+    pub json_limits: JsonLimits,
+    pub type_param_limits: TypeParamLimits,
+    pub bcs_limits: BcsLimits,
+    pub indexer_version: Option<String>,
+    /// Fill `unxv_events.tx_timestamp_ms`.
+    pub tx_timestamps: bool,
+    /// Maintain `sender_first_seen` and fill `unxv_events.is_new_sender`.
+    pub sender_first_seen: bool,
+    /// Opt-in `unxv_tx_event_bitmap` pipeline.
+    pub tx_event_bitmap: bool,
+    /// Committed watermark of each pipeline when the indexer started; see [`stored_watermarks`].
+    pub watermarks: HashMap<String, u64>,
+    /// Fraction of events sampled into `unxv_events_sampled`; None disables that pipeline.
+    pub event_sample_rate: Option<f64>,
+    /// Object types recorded in `unxv_object_changes`; None disables that pipeline.
+    pub object_types: Option<StructTagFilter>,
+    /// `--family` options, in slot order.
+    pub families: Vec<Family>,
+    pub metrics: Arc<UnxvMetrics>,
+}
+
+impl PipelineContext {
+    /// An [`UnxvEventsHandler`] with the configured event filters and nothing else. Fails on an
+    /// invalid package or sender address.
+    pub fn events_handler(&self) -> anyhow::Result<UnxvEventsHandler> {
+        Ok(UnxvEventsHandler::new(self.modules.clone(), self.package_allowlist.clone())?
+            .with_event_types(self.event_types.clone())
+            .with_struct_tags(self.event_struct_tags.clone())
+            .with_balance_filter(self.balance_filter.clone())
+            .with_sender_filter(self.senders_allow.clone(), self.senders_deny.clone())?
+            .with_type_param_filter(self.type_param_filter.clone(), self.type_param_filter_drop_untyped))
+    }
+}
+
+pub type RegisterFuture<'a> = Pin<Box<dyn Future<Output = anyhow::Result<()>> + 'a>>;
+
+/// A pipeline known to the indexer; see [`crate::handlers::PIPELINES`] and [`pipeline_entry!`].
+pub struct PipelineEntry {
+    pub name: &'static str,
+    /// Name the pipeline runs under in backfill mode, so its watermark is tracked separately.
+    pub backfill_name: &'static str,
+    /// Tables the pipeline writes; each must be documented in `unxv_schema::docs::TABLES`, naming
+    /// this pipeline as its writer if it is the main one (see [`validate_registry`]).
+    pub tables: &'static [&'static str],
+    /// `module::Struct` events this pipeline materializes into typed rows. Two enabled pipelines
+    /// claiming the same event would double count it; raw and derived pipelines leave it empty.
+    pub events: &'static [&'static str],
+    /// Bumped when the pipeline's decoding changes in a way that warrants re-indexing.
+    pub decoder_version: u32,
+    /// Always committed in checkpoint order, whatever `--sequential-pipelines` says.
+    pub sequential: bool,
+    /// Which part of `unxv_events` the pipeline indexes; None for every other pipeline.
+    pub events_pipeline: Option<EventsPipeline>,
+    /// Build the handler from the context and register it, unless it is disabled there.
+    pub register: for<'a> fn(&'a PipelineArgs, &'a mut Indexer<Db>, &'a PipelineContext) -> RegisterFuture<'a>,
+}
+
+/// Declare a [`PipelineEntry`] for `$handler`, built by
+/// `$build: fn(&PipelineContext) -> anyhow::Result<Option<$handler>>` (None leaves the pipeline
+/// unregistered, an error fails startup). `$handler` is a concurrent handler, or a sequential one
+/// when the entry starts with `sequential,`.
+macro_rules! pipeline_entry {
+    (sequential, $handler:ty, $($rest:tt)*) => {
+        $crate::pipeline::pipeline_entry!(@entry $handler, true, add_sequential, $($rest)*)
+    };
+    (
+        @entry $handler:ty, $sequential:literal, $add:ident,
+        tables: [$($table:literal),* $(,)?],
+        events: [$($event:literal),* $(,)?],
+        decoder_version: $version:expr,
+        backfill_name: $backfill:expr,
+        build: $build:expr $(,)?
+    ) => {{
+        impl $crate::pipeline::BackfillName for $handler {
+            const BACKFILL_NAME: &'static str = $backfill;
+        }
+        $crate::pipeline::PipelineEntry {
+            name: <$handler as sui_indexer_alt_framework::pipeline::Processor>::NAME,
+            backfill_name: $backfill,
+            tables: &[$($table),*],
+            events: &[$($event),*],
+            decoder_version: $version,
+            sequential: $sequential,
+            events_pipeline: None,
+            register: |args, indexer, ctx| {
+                Box::pin(async move {
+                    let build: fn(&$crate::pipeline::PipelineContext) -> anyhow::Result<Option<$handler>> = $build;
+                    match build(ctx)? {
+                        Some(handler) => args.$add(indexer, handler, ctx).await,
+                        None => Ok(()),
+                    }
+                })
+            },
+        }
+    }};
+    ($handler:ty, $($rest:tt)*) => {
+        $crate::pipeline::pipeline_entry!(@entry $handler, false, add, $($rest)*)
+    };
+}
+pub(crate) use pipeline_entry;
+
+/// The ways `unxv_events` is indexed. Exactly one applies to a run: a single pipeline by
+/// default, one per module under `--split-pipelines`, or one per `--family`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventsPipeline {
+    /// `unxv_events`.
+    All,
+    /// `unxv_events_<module>`.
+    Module,
+    /// `unxv_events_family_<n>`, for the family in this slot.
+    Family(usize),
+}
+
+/// Check the registry against the table docs: pipeline names are unique, every table a pipeline
+/// writes is documented, and every table documented as written by a pipeline is in its `tables`.
+pub fn validate_registry(docs: &[TableDoc]) -> anyhow::Result<()> {
+    let names: Vec<&str> = PIPELINES.iter().flat_map(|e| [e.name, e.backfill_name]).collect();
+    for (i, name) in names.iter().enumerate() {
+        anyhow::ensure!(!names[..i].contains(name), "Pipeline {name} is registered twice");
+    }
+    for entry in PIPELINES {
+        for table in entry.tables {
+            anyhow::ensure!(
+                docs.iter().any(|doc| doc.name == *table),
+                "Pipeline {} writes undocumented table {table}",
+                entry.name
+            );
+        }
+    }
+    for doc in docs {
+        if let Some(entry) = PIPELINES.iter().find(|e| e.name == doc.pipeline) {
+            anyhow::ensure!(
+                entry.tables.contains(&doc.name),
+                "Table {} is documented as written by pipeline {}, which doesn't list it",
+                doc.name,
+                entry.name
+            );
+        }
+    }
+    Ok(())
+}
+
+/// How a handler is registered with the framework.
+///
+/// Concurrent pipelines commit checkpoints out of order and are right for append-only tables
+/// keyed by event. Sequential pipelines commit strictly in checkpoint order, one batch at a
+/// time, and are required when a commit reads or accumulates state written by earlier
+/// checkpoints (running totals, latest-value upserts, per-checkpoint watermark tables).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipelineMode {
+    Concurrent,
+    Sequential,
+}
+
+/// What a pipeline does with a checkpoint at or below its stored watermark, which the framework
+/// shouldn't deliver unless the watermark was changed behind its back.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutOfOrderPolicy {
+    /// Process it again; commits are idempotent.
+    #[default]
+    Reprocess,
+    /// Drop it without processing.
+    Skip,
+}
+
+impl OutOfOrderPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OutOfOrderPolicy::Reprocess => "reprocess",
+            OutOfOrderPolicy::Skip => "skip",
+        }
+    }
+}
+
+#[derive(QueryableByName)]
+struct StoredWatermark {
+    #[diesel(sql_type = Text)]
+    pipeline: String,
+    #[diesel(sql_type = BigInt)]
+    checkpoint_hi_inclusive: i64,
+}
+
+/// Every pipeline's committed `checkpoint_hi_inclusive` from the framework's watermarks table.
+pub async fn stored_watermarks(db: &Db) -> anyhow::Result<HashMap<String, u64>> {
+    let mut conn = db.connect().await?;
+    let rows: Vec<StoredWatermark> = diesel::sql_query("SELECT pipeline, checkpoint_hi_inclusive FROM watermarks")
+        .load(&mut conn)
+        .await?;
+    Ok(rows.into_iter().map(|w| (w.pipeline, w.checkpoint_hi_inclusive.max(0) as u64)).collect())
+}
+
+#[derive(QueryableByName)]
+struct Migrated {
+    #[diesel(sql_type = Bool)]
+    migrated: bool,
+}
+
+/// Fail unless the database has been migrated, for `--dry-run`, which runs no migrations of its
+/// own but needs the framework's watermarks table and the indexer's tables to read from.
+pub async fn ensure_migrated(db: &Db) -> anyhow::Result<()> {
+    let mut conn = db.connect().await?;
+    let found: Migrated = diesel::sql_query(
+        "SELECT to_regclass('watermarks') IS NOT NULL AND to_regclass('unxv_events') IS NOT NULL AS migrated",
+    )
+    .get_result(&mut conn)
+    .await?;
+    anyhow::ensure!(found.migrated, "The database has not been migrated; run the indexer once without --dry-run");
+    Ok(())
+}
+
+/// Inclusive checkpoint bounds for one pipeline; either side may be open.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CheckpointRange {
+    pub start: Option<u64>,
+    pub end: Option<u64>,
+}
+
+impl CheckpointRange {
+    /// Why `checkpoint` falls outside the range, as a metric label; None if it is inside.
+    pub fn skip_reason(&self, checkpoint: u64) -> Option<&'static str> {
+        if self.start.is_some_and(|start| checkpoint < start) {
+            Some("below_start")
+        } else if self.end.is_some_and(|end| checkpoint > end) {
+            Some("above_end")
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Display for CheckpointRange {
+    /// The `START..END` form [`FromStr`] accepts.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(start) = self.start {
+            write!(f, "{start}")?;
+        }
+        f.write_str("..")?;
+        if let Some(end) = self.end {
+            write!(f, "{end}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for CheckpointRange {
+    type Err = anyhow::Error;
+
+    /// `START..`, `..END` or `START..END`, END inclusive.
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (start, end) = s
+            .split_once("..")
+            .ok_or_else(|| anyhow::anyhow!("Expected START..END, got {s:?}"))?;
+        let bound = |b: &str| -> anyhow::Result<Option<u64>> {
+            let b = b.trim();
+            Ok(if b.is_empty() { None } else { Some(b.parse()?) })
+        };
+        let range = CheckpointRange { start: bound(start)?, end: bound(end)? };
+        if let (Some(start), Some(end)) = (range.start, range.end) {
+            anyhow::ensure!(start <= end, "Empty checkpoint range {s:?}");
+        }
+        Ok(range)
+    }
+}
+
+/// Parse one `pipeline=START..END` entry of `--pipeline-range`.
+pub fn parse_pipeline_range(s: &str) -> anyhow::Result<(String, CheckpointRange)> {
+    let (pipeline, range) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Expected PIPELINE=START..END, got {s:?}"))?;
+    Ok((pipeline.trim().to_string(), range.parse()?))
+}
+
+/// Arguments of the `backfill` subcommand.
+#[derive(clap::Args, Debug, Clone)]
+pub struct BackfillArgs {
+    /// First checkpoint to re-index (inclusive)
+    #[clap(env = "BACKFILL_START", long)]
+    pub start: u64,
+    /// Last checkpoint to re-index (inclusive)
+    #[clap(env = "BACKFILL_END", long)]
+    pub end: u64,
+    /// Milliseconds between watermark writes of concurrent pipelines; rows still commit as soon
+    /// as they are processed. Framework default when unset
+    #[clap(env = "BACKFILL_WATERMARK_INTERVAL_MS", long)]
+    pub watermark_interval_ms: Option<u64>,
+}
+
+/// Per-pipeline registration settings.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct PipelineArgs {
+    /// Pipelines to run, comma-separated; all registered pipelines when empty
+    #[clap(env, long, value_delimiter = ',')]
+    pub pipelines: Vec<String>,
+    /// Fail at startup, instead of warning, when enabled pipelines materialize the same events
+    #[clap(env, long)]
+    pub strict_pipeline_overlap: bool,
+    /// Pipelines to run sequentially (in checkpoint order) instead of concurrently, comma-separated
+    #[clap(env, long, value_delimiter = ',')]
+    pub sequential_pipelines: Vec<String>,
+    /// Per-pipeline inclusive checkpoint bounds, e.g. `dex_trades=1000..,unxv_referrals=..5000`.
+    /// Checkpoints outside a pipeline's range are skipped before processing
+    #[clap(env, long, value_delimiter = ',', value_parser = parse_pipeline_range)]
+    pub pipeline_range: Vec<(String, CheckpointRange)>,
+    /// Index events as one pipeline per module (`unxv_events_<module>`), each with its own
+    /// watermark, instead of the single `unxv_events` pipeline
+    #[clap(env, long)]
+    pub split_pipelines: bool,
+    /// Index the events of a package family as a pipeline of its own instead of the single
+    /// `unxv_events` pipeline, as `NAME=0xPKG1,0xPKG2`; repeatable. Families run as
+    /// `unxv_events_family_1`, `unxv_events_family_2`, ... in the order given
+    #[clap(env = "UNXV_FAMILIES", long = "family", value_delimiter = ';')]
+    pub families: Vec<Family>,
+    /// Set by the `backfill` subcommand.
+    #[clap(skip)]
+    pub backfill: Option<BackfillArgs>,
+    /// What to do with a checkpoint at or below the pipeline's stored watermark: `reprocess` or
+    /// `skip`. Either way it is logged and counted
+    #[clap(env, long, value_enum, default_value_t = OutOfOrderPolicy::Reprocess)]
+    pub out_of_order_policy: OutOfOrderPolicy,
+    /// Process checkpoints and log what would be written, without writing rows, watermarks or
+    /// alerts, running migrations or serving the APIs. Natively sequential pipelines are skipped
+    #[clap(env, long)]
+    pub dry_run: bool,
+}
+
+impl PipelineArgs {
+    /// Reject settings that name a pipeline that isn't registered.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            !(self.split_pipelines && !self.families.is_empty()),
+            "--split-pipelines and --family are alternatives; give one of them"
+        );
+        family_events_handler::check_families(&self.families)?;
+        let known: Vec<&str> = PIPELINES.iter().map(|e| e.name).collect();
+        for name in &self.pipelines {
+            anyhow::ensure!(known.contains(&name.as_str()), "Unknown pipeline in --pipelines: {name}");
+            let events_pipeline = PIPELINES.iter().find(|e| e.name == name).and_then(|e| e.events_pipeline);
+            anyhow::ensure!(
+                events_pipeline.map_or(true, |p| self.runs(p)),
+                "Pipeline {name} in --pipelines doesn't run with {}",
+                self.events_layout()
+            );
+        }
+        for name in &self.sequential_pipelines {
+            anyhow::ensure!(known.contains(&name.as_str()), "Unknown pipeline in --sequential-pipelines: {name}");
+        }
+        for (name, _) in &self.pipeline_range {
+            anyhow::ensure!(known.contains(&name.as_str()), "Unknown pipeline in --pipeline-range: {name}");
+        }
+        self.check_overlap(PIPELINES)?;
+        Ok(())
+    }
+
+    /// Warn (or fail under `--strict-pipeline-overlap`) when two enabled pipelines of `entries`
+    /// claim the same event, since aggregates over their tables would count it twice. Returns
+    /// the warnings.
+    fn check_overlap(&self, entries: &[PipelineEntry]) -> anyhow::Result<Vec<String>> {
+        let mut claimed: HashMap<&str, &str> = HashMap::new();
+        let mut warnings = vec![];
+        for entry in entries.iter().filter(|e| self.enabled(e.name)) {
+            for event in entry.events {
+                let Some(other) = claimed.insert(event, entry.name) else { continue };
+                let message = format!("Pipelines {other} and {} both index {event}", entry.name);
+                anyhow::ensure!(!self.strict_pipeline_overlap, "{message}");
+                warn!("{message}");
+                warnings.push(message);
+            }
+        }
+        Ok(warnings)
+    }
+
+    /// Whether `pipeline` is selected by `--pipelines` and, for the `unxv_events` pipelines,
+    /// by `--split-pipelines` and `--family`.
+    pub fn enabled(&self, pipeline: &str) -> bool {
+        let events_pipeline = PIPELINES.iter().find(|e| e.name == pipeline).and_then(|e| e.events_pipeline);
+        events_pipeline.map_or(true, |p| self.runs(p))
+            && (self.pipelines.is_empty() || self.pipelines.iter().any(|p| p == pipeline))
+    }
+
+    /// Whether the configured layout of `unxv_events` includes `pipeline`.
+    fn runs(&self, pipeline: EventsPipeline) -> bool {
+        match pipeline {
+            EventsPipeline::All => self.single_events_pipeline(),
+            EventsPipeline::Module => self.split_pipelines,
+            EventsPipeline::Family(slot) => slot < self.families.len(),
+        }
+    }
+
+    /// Whether `unxv_events` is indexed by the one `unxv_events` pipeline, whose watermark
+    /// cascades, gap checks and `unxv_checkpoints` follow.
+    pub fn single_events_pipeline(&self) -> bool {
+        !self.split_pipelines && self.families.is_empty()
+    }
+
+    /// The options choosing how `unxv_events` is indexed, for messages.
+    fn events_layout(&self) -> &'static str {
+        if self.split_pipelines {
+            "--split-pipelines"
+        } else if !self.families.is_empty() {
+            "--family"
+        } else {
+            "neither --split-pipelines nor --family"
+        }
+    }
+
+    /// Whether any pipeline writing `unxv_events` is enabled, whatever the layout.
+    pub fn events_enabled(&self) -> bool {
+        PIPELINES.iter().any(|e| e.events_pipeline.is_some() && self.enabled(e.name))
+    }
+
+    /// Register every enabled pipeline in [`PIPELINES`].
+    pub async fn register_all(&self, indexer: &mut Indexer<Db>, ctx: &PipelineContext) -> anyhow::Result<()> {
+        for entry in PIPELINES.iter().filter(|e| self.enabled(e.name)) {
+            (entry.register)(self, indexer, ctx).await?;
+        }
+        Ok(())
+    }
+
+    /// Watermark rows this process writes: the enabled pipelines, under their backfill names
+    /// when backfilling.
+    pub fn watermark_names(&self) -> Vec<String> {
+        let backfill = self.backfill.is_some();
+        PIPELINES
+            .iter()
+            .filter(|e| self.enabled(e.name) && !(backfill && e.sequential))
+            .map(|e| if backfill { e.backfill_name } else { e.name }.to_string())
+            .collect()
+    }
+
+    /// Print every registered pipeline with its effective settings.
+    pub fn print_status(&self) {
+        println!("{:<24} {:<8} {:<11} {:<16} {:<8} TABLES", "PIPELINE", "ENABLED", "MODE", "RANGE", "DECODER");
+        for entry in PIPELINES {
+            let range = self.range(entry.name);
+            let bound = |b: Option<u64>| b.map_or(String::new(), |b| b.to_string());
+            println!(
+                "{:<24} {:<8} {:<11} {:<16} {:<8} {}",
+                entry.name,
+                self.enabled(entry.name),
+                format!("{:?}", self.mode(entry.name)).to_ascii_lowercase(),
+                format!("{}..{}", bound(range.start), bound(range.end)),
+                entry.decoder_version,
+                entry.tables.join(","),
+            );
+        }
+    }
+
+    pub fn mode(&self, pipeline: &str) -> PipelineMode {
+        let always = PIPELINES.iter().any(|e| e.name == pipeline && e.sequential);
+        if always || self.sequential_pipelines.iter().any(|p| p == pipeline) {
+            PipelineMode::Sequential
+        } else {
+            PipelineMode::Concurrent
+        }
+    }
+
+    /// Inclusive backfill bounds; None unless running `backfill`.
+    pub fn backfill(&self) -> anyhow::Result<Option<(u64, u64)>> {
+        let Some(&BackfillArgs { start, end, .. }) = self.backfill.as_ref() else { return Ok(None) };
+        anyhow::ensure!(start <= end, "Empty backfill range: --start {start} > --end {end}");
+        Ok(Some((start, end)))
+    }
+
+    /// Limit ingestion to the backfill range, if any. Backfill and live indexing share the
+    /// ingestion bounds, so they can't run in one invocation.
+    pub fn apply_backfill(&self, indexer_args: &mut IndexerArgs) -> anyhow::Result<()> {
+        let Some((start, end)) = self.backfill()? else { return Ok(()) };
+        anyhow::ensure!(
+            indexer_args.first_checkpoint.is_none() && indexer_args.last_checkpoint.is_none(),
+            "backfill --start/--end replace --first-checkpoint/--last-checkpoint; don't combine them"
+        );
+        anyhow::ensure!(
+            self.pipeline_range.is_empty(),
+            "--pipeline-range applies to live pipelines; narrow a backfill with --start/--end"
+        );
+        indexer_args.first_checkpoint = Some(start);
+        indexer_args.last_checkpoint = Some(end);
+        Ok(())
+    }
+
+    pub fn range(&self, pipeline: &str) -> CheckpointRange {
+        self.pipeline_range
+            .iter()
+            .find(|(p, _)| p == pipeline)
+            .map(|(_, range)| *range)
+            .unwrap_or_default()
+    }
+
+    /// Register `handler` with its configured mode and checkpoint range, or under its backfill
+    /// name when backfilling.
+    pub async fn add<H>(&self, indexer: &mut Indexer<H::Store>, handler: H, ctx: &PipelineContext) -> anyhow::Result<()>
+    where
+        H: concurrent::Handler + BackfillName + Send + Sync + 'static,
+    {
+        let mode = self.mode(H::NAME);
+        if self.dry_run {
+            // Nothing is committed, so checkpoint order doesn't matter.
+            let handler = self.bounded(DryRun(handler), ctx);
+            return register(indexer, handler, PipelineMode::Concurrent, ConcurrentConfig::default()).await;
+        }
+        if self.backfill()?.is_some() {
+            if mode == PipelineMode::Sequential && self.backfill_watermark_interval().is_some() {
+                warn!(pipeline = H::NAME, "backfill --watermark-interval-ms doesn't apply to sequential pipelines");
+            }
+            // Ingestion itself is bounded to the backfill range (see `apply_backfill`).
+            return register(indexer, Backfill(handler), mode, self.backfill_config()).await;
+        }
+        register(indexer, self.bounded(handler, ctx), mode, ConcurrentConfig::default()).await
+    }
+
+    /// Framework config of concurrent pipelines in a backfill. The committer writes rows as
+    /// they arrive but the watermark on a timer, so a longer interval means fewer watermark
+    /// updates and, after a crash, more checkpoints processed again from the last one written.
+    fn backfill_config(&self) -> ConcurrentConfig {
+        let mut config = ConcurrentConfig::default();
+        if let Some(interval_ms) = self.backfill_watermark_interval() {
+            config.committer.watermark_interval_ms = interval_ms;
+        }
+        config
+    }
+
+    fn backfill_watermark_interval(&self) -> Option<u64> {
+        self.backfill.as_ref().and_then(|b| b.watermark_interval_ms)
+    }
+
+    /// Register a natively sequential `handler` with its configured checkpoint range. Such
+    /// handlers accumulate into existing rows, so re-running a range would double count: they
+    /// are left out of backfills.
+    pub async fn add_sequential<H>(
+        &self,
+        indexer: &mut Indexer<H::Store>,
+        handler: H,
+        ctx: &PipelineContext,
+    ) -> anyhow::Result<()>
+    where
+        H: sequential::Handler + Send + Sync + 'static,
+    {
+        if self.backfill()?.is_some() {
+            warn!(pipeline = H::NAME, "Accumulating pipeline is not idempotent; skipped in backfill mode");
+            return Ok(());
+        }
+        if self.dry_run {
+            warn!(pipeline = H::NAME, "Sequential pipeline always writes its watermark; skipped in dry-run mode");
+            return Ok(());
+        }
+        indexer.sequential_pipeline(self.bounded(handler, ctx), Default::default()).await?;
+        Ok(())
+    }
+
+    /// `handler` limited to its configured range and guarded against checkpoints it has
+    /// already committed.
+    fn bounded<H: Processor>(&self, handler: H, ctx: &PipelineContext) -> Bounded<H> {
+        Bounded {
+            inner: handler,
+            range: self.range(H::NAME),
+            watermark: ProcessedWatermark::new(ctx.watermarks.get(H::NAME).copied()),
+            out_of_order: self.out_of_order_policy,
+            metrics: ctx.metrics.clone(),
+            gaps: StreamGaps::default(),
+        }
+    }
+}
+
+async fn register<H>(
+    indexer: &mut Indexer<H::Store>,
+    handler: H,
+    mode: PipelineMode,
+    config: ConcurrentConfig,
+) -> anyhow::Result<()>
+where
+    H: concurrent::Handler + Send + Sync + 'static,
+{
+    match mode {
+        PipelineMode::Concurrent => indexer.concurrent_pipeline(handler, config).await?,
+        PipelineMode::Sequential => indexer.sequential_pipeline(Sequential(handler), Default::default()).await?,
+    }
+    Ok(())
+}
+
+/// Pipeline name used in backfill mode; set through [`pipeline_entry!`].
+pub trait BackfillName: Processor {
+    const BACKFILL_NAME: &'static str;
+}
+
+/// Runs a handler under its [`BackfillName`], so its progress gets its own watermark row.
+pub struct Backfill<H>(pub H);
+
+impl<H: BackfillName> Processor for Backfill<H> {
+    const NAME: &'static str = H::BACKFILL_NAME;
+    type Value = H::Value;
+
+    fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<Self::Value>> {
+        health::observe_checkpoint(checkpoint.checkpoint_summary.sequence_number);
+        backpressure::throttle();
+        self.0.process(checkpoint)
+    }
+}
+
+#[async_trait]
+impl<H: concurrent::Handler + BackfillName> concurrent::Handler for Backfill<H> {
+    type Store = H::Store;
+
+    async fn commit<'a>(
+        values: &[Self::Value],
+        conn: &mut <Self::Store as Store>::Connection<'a>,
+    ) -> anyhow::Result<usize> {
+        H::commit(values, conn).await
+    }
+}
+
+/// Runs a handler without writing anything: commits only report the rows they would have
+/// written. Used by `--dry-run`, which also turns off the framework's watermark writes.
+pub struct DryRun<H>(pub H);
+
+impl<H: Processor> Processor for DryRun<H> {
+    const NAME: &'static str = H::NAME;
+    type Value = H::Value;
+
+    fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<Self::Value>> {
+        self.0.process(checkpoint)
+    }
+}
+
+#[async_trait]
+impl<H: concurrent::Handler> concurrent::Handler for DryRun<H> {
+    type Store = H::Store;
+
+    async fn commit<'a>(
+        values: &[Self::Value],
+        _conn: &mut <Self::Store as Store>::Connection<'a>,
+    ) -> anyhow::Result<usize> {
+        debug!(pipeline = H::NAME, rows = values.len(), "Dry run: commit skipped");
+        Ok(values.len())
+    }
+}
+
+/// Whether a checkpoint was delivered before, as [`ProcessedWatermark::observe`] sees it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Delivery {
+    New,
+    /// At or below `watermark`. Only the first of a run of such checkpoints is `first_in_run`.
+    Repeat { watermark: u64, first_in_run: bool },
+}
+
+/// The checkpoint up to which a pipeline has received everything, starting from its stored
+/// watermark and advancing as later checkpoints arrive, so checkpoints delivered again are
+/// recognised after startup too. Without a stored watermark nothing is tracked. If the stream
+/// skips a checkpoint, it waits at the gap for it until the stream is [`REORDER_WINDOW`] past
+/// it, then moves on, as [`StreamGaps`] reports the gap then.
+struct ProcessedWatermark {
+    state: Mutex<WatermarkState>,
+}
+
+struct WatermarkState {
+    watermark: Option<u64>,
+    /// Checkpoints received past `watermark + 1`.
+    ahead: BTreeSet<u64>,
+    /// The current run of repeated checkpoints, inclusive.
+    run: Option<(u64, u64)>,
+}
+
+impl ProcessedWatermark {
+    fn new(stored: Option<u64>) -> Self {
+        Self { state: Mutex::new(WatermarkState { watermark: stored, ahead: BTreeSet::new(), run: None }) }
+    }
+
+    fn observe(&self, checkpoint: u64) -> Delivery {
+        let mut state = self.state.lock().unwrap();
+        let Some(mut watermark) = state.watermark else {
+            return Delivery::New;
+        };
+        if checkpoint <= watermark {
+            let (first, last) = match state.run {
+                Some((first, last)) if first <= checkpoint + 1 && checkpoint <= last + 1 => {
+                    (first.min(checkpoint), last.max(checkpoint))
+                }
+                _ => {
+                    state.run = Some((checkpoint, checkpoint));
+                    return Delivery::Repeat { watermark, first_in_run: true };
+                }
+            };
+            state.run = Some((first, last));
+            return Delivery::Repeat { watermark, first_in_run: false };
+        }
+        state.ahead.insert(checkpoint);
+        loop {
+            while state.ahead.first() == Some(&(watermark + 1)) {
+                state.ahead.pop_first();
+                watermark += 1;
+            }
+            // A gap the stream is a reorder window past won't be filled, so move past it.
+            match (state.ahead.first(), state.ahead.last()) {
+                (Some(&first), Some(&last)) if last - watermark > REORDER_WINDOW => watermark = first - 1,
+                _ => break,
+            }
+        }
+        state.watermark = Some(watermark);
+        Delivery::New
+    }
+}
+
+/// Skips checkpoints outside `range` without calling the inner handler, so a pipeline that
+/// starts late (or stops early) does no work for checkpoints shared with other pipelines.
+/// Checkpoints at or below `watermark` are reported and handled per `out_of_order`. The rest
+/// wait out any [`backpressure`] first. Checkpoints the stream skips are logged and counted.
+pub struct Bounded<H> {
+    inner: H,
+    range: CheckpointRange,
+    watermark: ProcessedWatermark,
+    out_of_order: OutOfOrderPolicy,
+    metrics: Arc<UnxvMetrics>,
+    gaps: StreamGaps,
+}
+
+impl<H: Processor> Processor for Bounded<H> {
+    const NAME: &'static str = H::NAME;
+    type Value = H::Value;
+
+    fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<Self::Value>> {
+        let sequence_number = checkpoint.checkpoint_summary.sequence_number;
+        health::observe_checkpoint(sequence_number);
+        for (first, last) in self.gaps.observe(sequence_number) {
+            warn!(pipeline = H::NAME, first, last, "Checkpoints missing from the processed stream");
+            self.metrics.checkpoints_missed.with_label_values(&[H::NAME]).inc_by(last - first + 1);
+        }
+        let delivery = self.watermark.observe(sequence_number);
+        if let Some(reason) = self.range.skip_reason(sequence_number) {
+            self.metrics.checkpoints_skipped.with_label_values(&[H::NAME, reason]).inc();
+            return Ok(vec![]);
+        }
+        if let Delivery::Repeat { watermark, first_in_run } = delivery {
+            let policy = self.out_of_order.as_str();
+            if first_in_run {
+                warn!(
+                    pipeline = H::NAME,
+                    checkpoint = sequence_number,
+                    watermark,
+                    policy,
+                    "Checkpoint at or below the watermark; the rest of this run is logged at debug level"
+                );
+            } else {
+                let checkpoint = sequence_number;
+                debug!(pipeline = H::NAME, checkpoint, watermark, policy, "Checkpoint delivered again");
+            }
+            self.metrics.checkpoints_out_of_order.with_label_values(&[H::NAME, policy]).inc();
+            if self.out_of_order == OutOfOrderPolicy::Skip {
+                self.metrics.checkpoints_skipped.with_label_values(&[H::NAME, "below_watermark"]).inc();
+                return Ok(vec![]);
+            }
+        }
+        backpressure::throttle();
+        self.inner.process(checkpoint)
+    }
+}
+
+#[async_trait]
+impl<H: concurrent::Handler> concurrent::Handler for Bounded<H> {
+    type Store = H::Store;
+
+    async fn commit<'a>(
+        values: &[Self::Value],
+        conn: &mut <Self::Store as Store>::Connection<'a>,
+    ) -> anyhow::Result<usize> {
+        H::commit(values, conn).await
+    }
+}
+
+#[async_trait]
+impl<H: sequential::Handler> sequential::Handler for Bounded<H> {
+    type Store = H::Store;
+    type Batch = H::Batch;
+
+    fn batch(batch: &mut Self::Batch, values: Vec<Self::Value>) {
+        H::batch(batch, values)
+    }
+
+    async fn commit<'a>(
+        batch: &Self::Batch,
+        conn: &mut <Self::Store as Store>::Connection<'a>,
+    ) -> anyhow::Result<usize> {
+        H::commit(batch, conn).await
+    }
+}
+
+/// Runs a concurrent handler through the sequential pipeline: values are collected into one
+/// batch per commit and written with the handler's own `commit`.
+pub struct Sequential<H>(pub H);
+
+impl<H: Processor> Processor for Sequential<H> {
+    const NAME: &'static str = H::NAME;
+    type Value = H::Value;
+
+    fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<Self::Value>> {
+        self.0.process(checkpoint)
+    }
+}
+
+#[async_trait]
+impl<H: concurrent::Handler> sequential::Handler for Sequential<H> {
+    type Store = H::Store;
+    type Batch = Vec<H::Value>;
+
+    fn batch(batch: &mut Self::Batch, values: Vec<Self::Value>) {
+        batch.extend(values);
+    }
+
+    async fn commit<'a>(
+        batch: &Self::Batch,
+        conn: &mut <Self::Store as Store>::Connection<'a>,
+    ) -> anyhow::Result<usize> {
+        H::commit(batch, conn).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{event, write_checkpoint, CheckpointBuilder, TestDb, PACKAGE};
+    use prometheus::Registry;
+    use std::collections::{BTreeMap, BTreeSet};
+    use sui_indexer_alt_framework::ingestion::ClientArgs;
+    use unxv_schema::docs::TABLES;
+
+    #[test]
+    fn registry_matches_the_table_docs() {
+        validate_registry(TABLES).unwrap();
+    }
+
+    /// Tables the migrations create, other than the per-module copies made at runtime.
+    fn migrated_tables() -> BTreeSet<String> {
+        const CREATE: &str = "create table if not exists ";
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../schema/migrations");
+        let mut tables = BTreeSet::new();
+        for migration in std::fs::read_dir(dir).unwrap() {
+            let sql = std::fs::read_to_string(migration.unwrap().path().join("up.sql")).unwrap().to_ascii_lowercase();
+            for (i, _) in sql.match_indices(CREATE) {
+                let name: String =
+                    sql[i + CREATE.len()..].chars().take_while(|c| c.is_ascii_alphanumeric() || *c == '_').collect();
+                if !name.is_empty() {
+                    tables.insert(name);
+                }
+            }
+        }
+        tables
+    }
+
+    #[test]
+    fn every_table_is_documented_once() {
+        let documented: Vec<&str> = TABLES.iter().map(|t| t.name).collect();
+        let unique: BTreeSet<String> = documented.iter().map(|t| t.to_string()).collect();
+        assert_eq!(unique.len(), documented.len(), "A table is documented twice");
+        assert_eq!(unique, migrated_tables());
+    }
+
+    #[test]
+    fn pipeline_tables_exist() {
+        let tables = migrated_tables();
+        for entry in PIPELINES {
+            for table in entry.tables {
+                assert!(tables.contains(*table), "Pipeline {} writes {table}, which no migration creates", entry.name);
+            }
+        }
+    }
+
+    #[test]
+    fn overlapping_pipelines_are_reported() {
+        use crate::handlers::dex_trades_handler;
+
+        let args = PipelineArgs::default();
+        assert_eq!(args.check_overlap(PIPELINES).unwrap(), Vec::<String>::new());
+
+        let copy = PipelineEntry { name: "dex_trades_copy", ..dex_trades_handler::PIPELINE };
+        let entries = [dex_trades_handler::PIPELINE, copy];
+        let warning = "Pipelines dex_trades and dex_trades_copy both index order_info::OrderFilled";
+        assert_eq!(args.check_overlap(&entries).unwrap(), [warning]);
+
+        // Only enabled pipelines count, and strict mode fails instead.
+        let one = PipelineArgs { pipelines: vec!["dex_trades".into()], ..Default::default() };
+        assert!(one.check_overlap(&entries).unwrap().is_empty());
+        let strict = PipelineArgs { strict_pipeline_overlap: true, ..Default::default() };
+        assert_eq!(strict.check_overlap(&entries).unwrap_err().to_string(), warning);
+    }
+
+    #[tokio::test]
+    async fn dry_runs_count_rows_without_writing_them() {
+        let db = TestDb::new().await;
+        ensure_migrated(db.db()).await.unwrap();
+
+        let metrics = UnxvMetrics::new(&Registry::new());
+        let handler = DryRun(
+            UnxvEventsHandler::new(ModulesFilter::All, None)
+                .unwrap()
+                .with_checkpoint_rows(true)
+                .with_metrics(metrics.clone()),
+        );
+        let checkpoint = CheckpointBuilder::new(3)
+            .transaction(
+                1,
+                &[(PACKAGE, "vault", "open")],
+                vec![
+                    event(1, &format!("{PACKAGE}::vault::Opened"), vec![]),
+                    event(1, &format!("{PACKAGE}::dex::Swapped"), vec![]),
+                ],
+            )
+            .build();
+        let rows = handler.process(&checkpoint).unwrap();
+
+        let mut conn = db.db().connect().await.unwrap();
+        let committed = <DryRun<UnxvEventsHandler> as concurrent::Handler>::commit(&rows, &mut conn).await.unwrap();
+        assert_eq!(committed, 3);
+        assert_eq!(db.count("unxv_events").await, 0);
+        assert_eq!(db.count("unxv_checkpoints").await, 0);
+        assert_eq!(db.count("unxv_dead_letters").await, 0);
+        assert_eq!(
+            metrics.events_processed_by_module(),
+            BTreeMap::from([("dex".to_string(), 1), ("vault".to_string(), 1)])
+        );
+    }
+
+    /// Checkpoints [`Recorder`] commits, in commit order.
+    static RECORDED: Mutex<Vec<u64>> = Mutex::new(vec![]);
+
+    /// Records the checkpoints it commits, processing even ones slower so they finish out of
+    /// order.
+    struct Recorder;
+
+    impl Processor for Recorder {
+        const NAME: &'static str = "recorder";
+        type Value = u64;
+
+        fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<u64>> {
+            let sequence_number = checkpoint.checkpoint_summary.sequence_number;
+            if sequence_number % 2 == 0 {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+            Ok(vec![sequence_number])
+        }
+    }
+
+    #[async_trait]
+    impl concurrent::Handler for Recorder {
+        type Store = Db;
+
+        async fn commit<'a>(values: &[u64], _: &mut sui_pg_db::Connection<'a>) -> anyhow::Result<usize> {
+            RECORDED.lock().unwrap().extend(values);
+            Ok(values.len())
+        }
+    }
+
+    #[tokio::test]
+    async fn sequential_pipelines_commit_in_checkpoint_order() {
+        let db = TestDb::new().await;
+        let dir = tempfile::tempdir().unwrap();
+        for sequence_number in 0..=20 {
+            write_checkpoint(dir.path(), &CheckpointBuilder::new(sequence_number).build());
+        }
+        let client_args = ClientArgs {
+            remote_store_url: None,
+            local_ingestion_path: Some(dir.path().to_path_buf()),
+            rpc_api_url: None,
+            rpc_username: None,
+            rpc_password: None,
+        };
+        let indexer_args = IndexerArgs { last_checkpoint: Some(20), ..Default::default() };
+        let cancel = tokio_util::sync::CancellationToken::new();
+        let mut indexer =
+            Indexer::new(db.db().clone(), indexer_args, client_args, Default::default(), &Registry::new(), cancel)
+                .await
+                .unwrap();
+        register(&mut indexer, Recorder, PipelineMode::Sequential, ConcurrentConfig::default()).await.unwrap();
+        indexer.run().await.unwrap().await.unwrap();
+
+        assert_eq!(*RECORDED.lock().unwrap(), (0..=20).collect::<Vec<_>>());
+    }
+
+    /// Checkpoints [`Committed`] has committed.
+    static COMMITTED: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+    /// Commits each checkpoint's sequence number, counting them in [`COMMITTED`].
+    struct Committed;
+
+    impl Processor for Committed {
+        const NAME: &'static str = "committed";
+        type Value = u64;
+
+        fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<u64>> {
+            Ok(vec![checkpoint.checkpoint_summary.sequence_number])
+        }
+    }
+
+    impl BackfillName for Committed {
+        const BACKFILL_NAME: &'static str = "committed_backfill";
+    }
+
+    #[async_trait]
+    impl concurrent::Handler for Committed {
+        type Store = Db;
+
+        async fn commit<'a>(values: &[u64], _: &mut sui_pg_db::Connection<'a>) -> anyhow::Result<usize> {
+            COMMITTED.fetch_add(values.len() as u64, std::sync::atomic::Ordering::SeqCst);
+            Ok(values.len())
+        }
+    }
+
+    #[derive(QueryableByName)]
+    struct Watermark {
+        #[diesel(sql_type = BigInt)]
+        checkpoint_hi_inclusive: i64,
+    }
+
+    /// Backfill checkpoints 0..=20 through [`Committed`] with `args`' watermark interval, and
+    /// return its watermark once all of them have committed and then `wait` has passed, or as
+    /// soon as it reaches 20.
+    async fn backfill_watermark(args: &PipelineArgs, wait: std::time::Duration) -> Option<i64> {
+        COMMITTED.store(0, std::sync::atomic::Ordering::SeqCst);
+        let db = TestDb::new().await;
+        let dir = tempfile::tempdir().unwrap();
+        for sequence_number in 0..=20 {
+            write_checkpoint(dir.path(), &CheckpointBuilder::new(sequence_number).build());
+        }
+        let client_args = ClientArgs {
+            remote_store_url: None,
+            local_ingestion_path: Some(dir.path().to_path_buf()),
+            rpc_api_url: None,
+            rpc_username: None,
+            rpc_password: None,
+        };
+        // Unbounded, so the indexer is still running, and its watermark task still ticking,
+        // once the rows are in.
+        let cancel = tokio_util::sync::CancellationToken::new();
+        let mut indexer = Indexer::new(
+            db.db().clone(),
+            IndexerArgs::default(),
+            client_args,
+            Default::default(),
+            &Registry::new(),
+            cancel.clone(),
+        )
+        .await
+        .unwrap();
+        register(&mut indexer, Backfill(Committed), PipelineMode::Concurrent, args.backfill_config()).await.unwrap();
+        let running = indexer.run().await.unwrap();
+
+        tokio::time::timeout(std::time::Duration::from_secs(30), async {
+            while COMMITTED.load(std::sync::atomic::Ordering::SeqCst) < 21 {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("every checkpoint commits");
+
+        let mut conn = db.db().connect().await.unwrap();
+        let deadline = std::time::Instant::now() + wait;
+        let watermark = loop {
+            let watermark = diesel::sql_query("SELECT checkpoint_hi_inclusive FROM watermarks WHERE pipeline = $1")
+                .bind::<Text, _>(Committed::BACKFILL_NAME)
+                .get_result::<Watermark>(&mut conn)
+                .await
+                .ok()
+                .map(|w| w.checkpoint_hi_inclusive);
+            if watermark == Some(20) || std::time::Instant::now() >= deadline {
+                break watermark;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        };
+        cancel.cancel();
+        running.await.unwrap();
+        watermark
+    }
+
+    #[tokio::test]
+    async fn backfill_watermark_writes_are_throttled_while_rows_commit() {
+        let backfill = |watermark_interval_ms| PipelineArgs {
+            backfill: Some(BackfillArgs { start: 0, end: 20, watermark_interval_ms: Some(watermark_interval_ms) }),
+            ..Default::default()
+        };
+        let wait = std::time::Duration::from_secs(2);
+
+        // The watermark task ticks once at startup, before anything committed, and not again
+        // for an hour: every row is in but the watermark was never written.
+        assert_eq!(backfill_watermark(&backfill(3_600_000), wait).await, None);
+        // On a short interval it catches up with the rows.
+        assert_eq!(backfill_watermark(&backfill(10), wait).await, Some(20));
+    }
+
+    /// Returns each checkpoint's sequence number.
+    struct Echo;
+
+    impl Processor for Echo {
+        const NAME: &'static str = "echo";
+        type Value = u64;
+
+        fn process(&self, checkpoint: &Arc<CheckpointData>) -> anyhow::Result<Vec<u64>> {
+            Ok(vec![checkpoint.checkpoint_summary.sequence_number])
+        }
+    }
+
+    fn bounded(watermark: Option<u64>, out_of_order: OutOfOrderPolicy) -> Bounded<Echo> {
+        Bounded {
+            inner: Echo,
+            range: CheckpointRange::default(),
+            watermark: ProcessedWatermark::new(watermark),
+            out_of_order,
+            metrics: UnxvMetrics::new(&Registry::new()),
+            gaps: StreamGaps::default(),
+        }
+    }
+
+    /// What `pipeline` processes of `checkpoints`, and how many it counted as out of order.
+    fn feed(pipeline: &Bounded<Echo>, checkpoints: &[u64]) -> (Vec<u64>, u64) {
+        let processed = checkpoints
+            .iter()
+            .flat_map(|c| pipeline.process(&CheckpointBuilder::new(*c).build()).unwrap())
+            .collect();
+        let policy = pipeline.out_of_order.as_str();
+        (processed, pipeline.metrics.checkpoints_out_of_order.with_label_values(&["echo", policy]).get())
+    }
+
+    #[test]
+    fn out_of_order_checkpoints_are_reprocessed() {
+        let pipeline = bounded(Some(10), OutOfOrderPolicy::Reprocess);
+        assert_eq!(feed(&pipeline, &[11, 9, 12]), (vec![11, 9, 12], 1));
+    }
+
+    #[test]
+    fn out_of_order_checkpoints_can_be_skipped() {
+        let pipeline = bounded(Some(10), OutOfOrderPolicy::Skip);
+        assert_eq!(feed(&pipeline, &[11, 9, 12]), (vec![11, 12], 1));
+        let skipped = pipeline.metrics.checkpoints_skipped.with_label_values(&["echo", "below_watermark"]).get();
+        assert_eq!(skipped, 1);
+    }
+
+    /// Counts the checkpoints it is asked to process.
+    #[derive(Default)]
+    struct Counter(std::sync::atomic::AtomicU64);
+
+    impl Processor for Counter {
+        const NAME: &'static str = "counter";
+        type Value = u64;
+
+        fn process(&self, _: &Arc<CheckpointData>) -> anyhow::Result<Vec<u64>> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Ok(vec![])
+        }
+    }
+
+    #[test]
+    fn checkpoints_below_the_range_never_reach_the_handler() {
+        let counted = |range: CheckpointRange| {
+            let pipeline = Bounded {
+                inner: Counter::default(),
+                range,
+                watermark: ProcessedWatermark::new(None),
+                out_of_order: OutOfOrderPolicy::Reprocess,
+                metrics: UnxvMetrics::new(&Registry::new()),
+                gaps: StreamGaps::default(),
+            };
+            for checkpoint in 1..=1000 {
+                assert!(pipeline.process(&CheckpointBuilder::new(checkpoint).build()).unwrap().is_empty());
+            }
+            let skipped = pipeline.metrics.checkpoints_skipped.with_label_values(&["counter", "below_start"]).get();
+            (pipeline.inner.0.into_inner(), skipped)
+        };
+        assert_eq!(counted("1000..".parse().unwrap()), (1, 999));
+        assert_eq!(counted(CheckpointRange::default()), (1000, 0));
+    }
+
+    #[test]
+    fn the_watermark_advances_past_processed_checkpoints() {
+        // 12 may arrive before 11 under concurrent processing; the second 12 is a redelivery.
+        let pipeline = bounded(Some(10), OutOfOrderPolicy::Skip);
+        assert_eq!(feed(&pipeline, &[12, 11, 13, 12]), (vec![12, 11, 13], 1));
+
+        let untracked = bounded(None, OutOfOrderPolicy::Skip);
+        assert_eq!(feed(&untracked, &[5, 3, 5]), (vec![5, 3, 5], 0));
+    }
+
+    #[test]
+    fn repeats_are_reported_once_per_run() {
+        let watermark = ProcessedWatermark::new(Some(10));
+        let repeat = |watermark, first_in_run| Delivery::Repeat { watermark, first_in_run };
+        let deliveries: Vec<_> = [5, 6, 7, 11, 8, 2].into_iter().map(|c| watermark.observe(c)).collect();
+        assert_eq!(
+            deliveries,
+            [repeat(10, true), repeat(10, false), repeat(10, false), Delivery::New, repeat(11, false), repeat(11, true)]
+        );
+    }
+
+    #[test]
+    fn the_watermark_waits_at_stream_gaps_within_the_reorder_window() {
+        let watermark = ProcessedWatermark::new(Some(0));
+        for checkpoint in 2..=REORDER_WINDOW {
+            assert_eq!(watermark.observe(checkpoint), Delivery::New);
+        }
+        // Checkpoint 1 arrives late but was never processed, and nothing past it counts as seen.
+        assert_eq!(watermark.observe(1), Delivery::New);
+        assert_eq!(watermark.observe(1), Delivery::Repeat { watermark: REORDER_WINDOW, first_in_run: true });
+    }
+
+    #[test]
+    fn the_watermark_moves_past_stream_gaps_beyond_the_reorder_window() {
+        let watermark = ProcessedWatermark::new(Some(0));
+        for checkpoint in 2..=REORDER_WINDOW + 1 {
+            assert_eq!(watermark.observe(checkpoint), Delivery::New);
+        }
+        assert_eq!(watermark.observe(1), Delivery::Repeat { watermark: REORDER_WINDOW + 1, first_in_run: true });
+        assert_eq!(watermark.observe(REORDER_WINDOW + 2), Delivery::New);
+
+        // Each gap is moved past in turn.
+        let watermark = ProcessedWatermark::new(Some(0));
+        assert_eq!(watermark.observe(3), Delivery::New);
+        assert_eq!(watermark.observe(REORDER_WINDOW + 5), Delivery::New);
+        let repeat = Delivery::Repeat { watermark: REORDER_WINDOW + 5, first_in_run: true };
+        assert_eq!(watermark.observe(REORDER_WINDOW + 4), repeat);
+        assert_eq!(watermark.observe(REORDER_WINDOW + 6), Delivery::New);
+    }
+}
@@ -0,0 +1,541 @@
+use diesel::sql_types::{Array, BigInt, Jsonb, Nullable, Text};
+use diesel::QueryableByName;
+use diesel_async::RunQueryDsl;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sui_pg_db::{Connection, Db};
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::alerts::{Alert, AlertSender, Severity};
+use crate::metrics::UnxvMetrics;
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct PrunerArgs {
+    /// Keep only events from the latest N committed checkpoints of the unxv_events pipeline
+    #[clap(env, long)]
+    pub prune_keep_checkpoints: Option<u64>,
+    /// Keep only events whose checkpoint is at most this many days old
+    #[clap(env, long, alias = "retention-days")]
+    pub prune_max_age_days: Option<u64>,
+    /// Per-module retention, e.g. `dex=30d,staking=forever,default=90d`. Modules without an
+    /// entry use `default`, which is `forever` unless given
+    #[clap(env, long, value_delimiter = ',', value_parser = parse_module_policy)]
+    pub prune_module_retention: Vec<(String, Policy)>,
+    /// Seconds between pruning rounds
+    #[clap(env, long, default_value_t = 300)]
+    pub prune_interval_secs: u64,
+    /// Rows deleted per statement, to keep locks short
+    #[clap(env, long, default_value_t = 10_000)]
+    pub prune_batch_size: i64,
+    /// Milliseconds to pause between delete batches, so pruning doesn't starve the writer
+    #[clap(env, long, default_value_t = 100)]
+    pub prune_batch_sleep_ms: u64,
+    /// Only log how many rows each round would delete
+    #[clap(env, long)]
+    pub prune_dry_run: bool,
+}
+
+/// How long one module's events are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    Days(u64),
+    Forever,
+}
+
+impl FromStr for Policy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("forever") {
+            return Ok(Policy::Forever);
+        }
+        s.strip_suffix('d')
+            .and_then(|days| days.parse().ok())
+            .map(Policy::Days)
+            .ok_or_else(|| anyhow::anyhow!("Invalid retention {s:?}: expected `<days>d` or `forever`"))
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Policy::Days(days) => write!(f, "{days}d"),
+            Policy::Forever => write!(f, "forever"),
+        }
+    }
+}
+
+/// Parse one `module=policy` entry of `--prune-module-retention`.
+pub fn parse_module_policy(s: &str) -> anyhow::Result<(String, Policy)> {
+    let (module, policy) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Expected MODULE=POLICY, got {s:?}"))?;
+    let module = module.trim().to_ascii_lowercase();
+    anyhow::ensure!(!module.is_empty(), "Missing module in retention entry {s:?}");
+    Ok((module, policy.parse()?))
+}
+
+/// Retention per event module, with a fallback for modules that aren't listed.
+#[derive(Debug, Clone)]
+pub struct ModuleRetention {
+    pub default: Policy,
+    pub modules: BTreeMap<String, Policy>,
+}
+
+impl ModuleRetention {
+    pub fn from_entries(entries: &[(String, Policy)]) -> anyhow::Result<Self> {
+        let mut default = None;
+        let mut modules = BTreeMap::new();
+        for (module, policy) in entries {
+            let previous = if module == "default" {
+                default.replace(*policy)
+            } else {
+                modules.insert(module.clone(), *policy)
+            };
+            anyhow::ensure!(previous.is_none(), "Retention for {module:?} is given more than once");
+        }
+        Ok(Self { default: default.unwrap_or(Policy::Forever), modules })
+    }
+
+    pub fn policy(&self, module: &str) -> Policy {
+        self.modules.get(module).copied().unwrap_or(self.default)
+    }
+}
+
+/// Lower bound below which rows are deleted.
+#[derive(Debug, Clone)]
+pub enum Retention {
+    Checkpoints(u64),
+    MaxAgeDays(u64),
+    PerModule(ModuleRetention),
+}
+
+impl Retention {
+    /// Effective policy for `module`, as shown by `status` and recorded in the changelog.
+    pub fn describe(&self, module: &str) -> String {
+        match self {
+            Retention::Checkpoints(keep) => format!("latest {keep} checkpoints"),
+            Retention::MaxAgeDays(days) => Policy::Days(*days).to_string(),
+            Retention::PerModule(retention) => retention.policy(module).to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PrunerConfig {
+    pub retention: Retention,
+    pub interval: Duration,
+    pub batch_size: i64,
+    pub batch_sleep: Duration,
+    pub dry_run: bool,
+}
+
+impl PrunerArgs {
+    /// None when no retention is configured: the default is to never prune.
+    pub fn config(&self) -> anyhow::Result<Option<PrunerConfig>> {
+        let per_module = !self.prune_module_retention.is_empty();
+        let retention = match (self.prune_keep_checkpoints, self.prune_max_age_days, per_module) {
+            (None, None, false) => return Ok(None),
+            (Some(n), None, false) => Retention::Checkpoints(n),
+            (None, Some(days), false) => Retention::MaxAgeDays(days),
+            (None, None, true) => Retention::PerModule(ModuleRetention::from_entries(&self.prune_module_retention)?),
+            _ => anyhow::bail!(
+                "--prune-keep-checkpoints, --prune-max-age-days and --prune-module-retention are mutually exclusive"
+            ),
+        };
+        anyhow::ensure!(self.prune_batch_size > 0, "--prune-batch-size must be positive");
+        Ok(Some(PrunerConfig {
+            retention,
+            interval: Duration::from_secs(self.prune_interval_secs),
+            batch_size: self.prune_batch_size,
+            batch_sleep: Duration::from_millis(self.prune_batch_sleep_ms),
+            dry_run: self.prune_dry_run,
+        }))
+    }
+}
+
+/// Effective retention as JSON: `default` plus any per-module overrides.
+fn policies_json(retention: Option<&Retention>) -> serde_json::Value {
+    let mut policies = serde_json::Map::new();
+    let default = retention.map_or_else(|| Policy::Forever.to_string(), |r| r.describe("default"));
+    policies.insert("default".into(), default.into());
+    if let Some(Retention::PerModule(retention)) = retention {
+        for (module, policy) in &retention.modules {
+            policies.insert(module.clone(), policy.to_string().into());
+        }
+    }
+    policies.into()
+}
+
+/// Append the effective retention to `unxv_retention_changelog` when it differs from the
+/// latest entry, so policy changes can be audited.
+pub async fn record_policy(db: &Db, config: Option<&PrunerConfig>) -> anyhow::Result<()> {
+    let policies = policies_json(config.map(|c| &c.retention));
+    let mut conn = db.connect().await?;
+    let inserted = diesel::sql_query(
+        "INSERT INTO unxv_retention_changelog (policies) \
+         SELECT $1 WHERE $1 IS DISTINCT FROM \
+         (SELECT policies FROM unxv_retention_changelog ORDER BY id DESC LIMIT 1)",
+    )
+    .bind::<Jsonb, _>(&policies)
+    .execute(&mut conn)
+    .await?;
+    if inserted > 0 {
+        info!(%policies, "Retention policy changed");
+    }
+    Ok(())
+}
+
+#[derive(QueryableByName)]
+struct OldestRow {
+    #[diesel(sql_type = Text)]
+    module: String,
+    #[diesel(sql_type = BigInt)]
+    checkpoint: i64,
+    #[diesel(sql_type = BigInt)]
+    checkpoint_timestamp_ms: i64,
+}
+
+/// Print each module's effective retention and the oldest event still stored for it.
+pub async fn print_status(db: &Db, config: Option<&PrunerConfig>) -> anyhow::Result<()> {
+    let mut conn = db.connect().await?;
+    let rows: Vec<OldestRow> = diesel::sql_query(
+        "SELECT module, min(checkpoint) AS checkpoint, \
+         min(checkpoint_timestamp_ms) AS checkpoint_timestamp_ms \
+         FROM unxv_events GROUP BY module ORDER BY module",
+    )
+    .load(&mut conn)
+    .await?;
+
+    let describe = |module: &str| config.map_or_else(|| Policy::Forever.to_string(), |c| c.retention.describe(module));
+    println!("{:<16} {:<24} {:>14} {:>16}", "MODULE", "RETENTION", "OLDEST CP", "OLDEST TS (ms)");
+    println!("{:<16} {:<24} {:>14} {:>16}", "(default)", describe("default"), "-", "-");
+    for row in &rows {
+        println!(
+            "{:<16} {:<24} {:>14} {:>16}",
+            row.module,
+            describe(&row.module),
+            row.checkpoint,
+            row.checkpoint_timestamp_ms
+        );
+    }
+    if let Some(Retention::PerModule(retention)) = config.map(|c| &c.retention) {
+        for (module, policy) in &retention.modules {
+            if !rows.iter().any(|r| &r.module == module) {
+                println!("{:<16} {:<24} {:>14} {:>16}", module, policy.to_string(), "-", "-");
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(QueryableByName)]
+struct Hi {
+    #[diesel(sql_type = Nullable<BigInt>)]
+    hi: Option<i64>,
+}
+
+#[derive(QueryableByName)]
+struct Count {
+    #[diesel(sql_type = BigInt)]
+    n: i64,
+}
+
+#[derive(QueryableByName)]
+struct ModuleCount {
+    #[diesel(sql_type = Text)]
+    module: String,
+    #[diesel(sql_type = BigInt)]
+    deleted: i64,
+}
+
+/// Delete `unxv_events` rows below the retention watermark on an interval until cancelled.
+pub async fn run(
+    db: Db,
+    config: PrunerConfig,
+    metrics: Arc<UnxvMetrics>,
+    alerts: AlertSender,
+    cancel: CancellationToken,
+) {
+    let mut interval = tokio::time::interval(config.interval);
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = interval.tick() => {}
+        }
+        match prune(&db, &config, &metrics, &cancel).await {
+            Ok(n) if config.dry_run => info!(rows = n, "Dry run: would prune unxv_events"),
+            Ok(0) => {}
+            Ok(n) => info!(rows = n, "Pruned unxv_events"),
+            Err(e) => alerts.send(Alert::new(
+                "pruner",
+                Severity::Warning,
+                "pruner:round_failed",
+                format!("Pruning round failed: {e:#}"),
+            )),
+        }
+    }
+}
+
+/// One pruning round, for the `prune` subcommand: rows deleted, or that would be under
+/// `--prune-dry-run`.
+pub async fn prune_once(db: &Db, config: &PrunerConfig) -> anyhow::Result<u64> {
+    let metrics = UnxvMetrics::new(&prometheus::Registry::new());
+    prune(db, config, &metrics, &CancellationToken::new()).await
+}
+
+/// Pause between batches; false if cancelled meanwhile.
+async fn pause(config: &PrunerConfig, cancel: &CancellationToken) -> bool {
+    tokio::select! {
+        _ = cancel.cancelled() => false,
+        _ = tokio::time::sleep(config.batch_sleep) => true,
+    }
+}
+
+fn now_ms() -> anyhow::Result<i64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64)
+}
+
+async fn prune(
+    db: &Db,
+    config: &PrunerConfig,
+    metrics: &UnxvMetrics,
+    cancel: &CancellationToken,
+) -> anyhow::Result<u64> {
+    let mut conn = db.connect().await?;
+    let (column, bound) = match &config.retention {
+        Retention::Checkpoints(keep) => {
+            // Measured from the committed watermark, not from what's in the table.
+            let hi: Option<Hi> = diesel::sql_query(
+                "SELECT checkpoint_hi_inclusive AS hi FROM watermarks WHERE pipeline = 'unxv_events'",
+            )
+            .get_result(&mut conn)
+            .await
+            .ok();
+            let Some(hi) = hi.and_then(|h| h.hi) else { return Ok(0) };
+            ("checkpoint", hi - *keep as i64)
+        }
+        Retention::MaxAgeDays(days) => ("checkpoint_timestamp_ms", now_ms()? - *days as i64 * MS_PER_DAY),
+        Retention::PerModule(retention) => {
+            return prune_per_module(&mut conn, retention, config, metrics, cancel).await;
+        }
+    };
+
+    if config.dry_run {
+        let count: Count = diesel::sql_query(format!("SELECT count(*) AS n FROM unxv_events WHERE {column} < $1"))
+            .bind::<BigInt, _>(bound)
+            .get_result(&mut conn)
+            .await?;
+        return Ok(count.n as u64);
+    }
+
+    let stmt = format!(
+        "DELETE FROM unxv_events WHERE ctid IN \
+         (SELECT ctid FROM unxv_events WHERE {column} < $1 LIMIT $2)"
+    );
+    let mut total = 0u64;
+    while !cancel.is_cancelled() {
+        let deleted = diesel::sql_query(&stmt)
+            .bind::<BigInt, _>(bound)
+            .bind::<BigInt, _>(config.batch_size)
+            .execute(&mut conn)
+            .await?;
+        metrics.pruned_rows.with_label_values(&["unxv_events"]).inc_by(deleted as u64);
+        total += deleted as u64;
+        if (deleted as i64) < config.batch_size || !pause(config, cancel).await {
+            break;
+        }
+    }
+    Ok(total)
+}
+
+/// Age out each module with a finite policy, then every unlisted module under the default.
+async fn prune_per_module(
+    conn: &mut Connection<'_>,
+    retention: &ModuleRetention,
+    config: &PrunerConfig,
+    metrics: &UnxvMetrics,
+    cancel: &CancellationToken,
+) -> anyhow::Result<u64> {
+    let now_ms = now_ms()?;
+    let listed: Vec<String> = retention.modules.keys().cloned().collect();
+    let mut targets = vec![];
+    for (module, policy) in &retention.modules {
+        if let Policy::Days(days) = policy {
+            targets.push(("module = ANY($1)", vec![module.clone()], *days));
+        }
+    }
+    if let Policy::Days(days) = retention.default {
+        targets.push(("NOT (module = ANY($1))", listed, days));
+    }
+
+    let mut total = 0u64;
+    for (filter, modules, days) in targets {
+        let bound = now_ms - days as i64 * MS_PER_DAY;
+        if config.dry_run {
+            let counts: Vec<ModuleCount> = diesel::sql_query(format!(
+                "SELECT module, count(*) AS deleted FROM unxv_events \
+                 WHERE {filter} AND checkpoint_timestamp_ms < $2 GROUP BY module"
+            ))
+            .bind::<Array<Text>, _>(&modules)
+            .bind::<BigInt, _>(bound)
+            .load(conn)
+            .await?;
+            for c in &counts {
+                info!(module = %c.module, rows = c.deleted, "Dry run: would prune");
+                total += c.deleted as u64;
+            }
+            continue;
+        }
+
+        let stmt = format!(
+            "WITH deleted AS (\
+               DELETE FROM unxv_events WHERE ctid IN \
+               (SELECT ctid FROM unxv_events WHERE {filter} AND checkpoint_timestamp_ms < $2 LIMIT $3) \
+               RETURNING module) \
+             SELECT module, count(*) AS deleted FROM deleted GROUP BY module"
+        );
+        while !cancel.is_cancelled() {
+            let counts: Vec<ModuleCount> = diesel::sql_query(&stmt)
+                .bind::<Array<Text>, _>(&modules)
+                .bind::<BigInt, _>(bound)
+                .bind::<BigInt, _>(config.batch_size)
+                .load(conn)
+                .await?;
+            let deleted: i64 = counts.iter().map(|c| c.deleted).sum();
+            for c in &counts {
+                metrics.pruned_module_rows.with_label_values(&[&c.module]).inc_by(c.deleted as u64);
+            }
+            metrics.pruned_rows.with_label_values(&["unxv_events"]).inc_by(deleted as u64);
+            total += deleted as u64;
+            if deleted < config.batch_size || !pause(config, cancel).await {
+                break;
+            }
+        }
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{stored_event, TestDb};
+
+    fn config(retention: Retention) -> PrunerConfig {
+        PrunerConfig {
+            retention,
+            interval: Duration::from_secs(300),
+            batch_size: 3,
+            batch_sleep: Duration::ZERO,
+            dry_run: false,
+        }
+    }
+
+    async fn checkpoints(db: &TestDb) -> Vec<i64> {
+        #[derive(QueryableByName)]
+        struct Checkpoint {
+            #[diesel(sql_type = BigInt)]
+            checkpoint: i64,
+        }
+        let mut conn = db.db().connect().await.unwrap();
+        let rows: Vec<Checkpoint> =
+            diesel::sql_query("SELECT checkpoint FROM unxv_events ORDER BY checkpoint").load(&mut conn).await.unwrap();
+        rows.into_iter().map(|r| r.checkpoint).collect()
+    }
+
+    #[tokio::test]
+    async fn only_rows_below_the_kept_checkpoints_are_deleted() {
+        let db = TestDb::new().await;
+        let events: Vec<_> = (1..=10).map(|cp| stored_event(&format!("d{cp}"), "dex", cp, 0)).collect();
+        db.insert_events(&events).await;
+
+        // Nothing is pruned until the pipeline has committed a watermark.
+        assert_eq!(prune_once(db.db(), &config(Retention::Checkpoints(5))).await.unwrap(), 0);
+
+        db.set_watermark("unxv_events", 10).await;
+        let dry_run = PrunerConfig { dry_run: true, ..config(Retention::Checkpoints(5)) };
+        assert_eq!(prune_once(db.db(), &dry_run).await.unwrap(), 4);
+        assert_eq!(checkpoints(&db).await.len(), 10);
+
+        // Over two batches of at most 3 rows.
+        assert_eq!(prune_once(db.db(), &config(Retention::Checkpoints(5))).await.unwrap(), 4);
+        assert_eq!(checkpoints(&db).await, (5..=10).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn only_rows_older_than_the_max_age_are_deleted() {
+        let db = TestDb::new().await;
+        let now = now_ms().unwrap();
+        let events: Vec<_> = [40, 31, 29, 1]
+            .iter()
+            .enumerate()
+            .map(|(i, days)| stored_event(&format!("d{i}"), "dex", i as i64, now - days * MS_PER_DAY))
+            .collect();
+        db.insert_events(&events).await;
+
+        assert_eq!(prune_once(db.db(), &config(Retention::MaxAgeDays(30))).await.unwrap(), 2);
+        assert_eq!(checkpoints(&db).await, [2, 3]);
+    }
+
+    #[test]
+    fn retention_policies_parse() {
+        assert_eq!("30d".parse::<Policy>().unwrap(), Policy::Days(30));
+        assert_eq!(" Forever ".parse::<Policy>().unwrap(), Policy::Forever);
+        for invalid in ["30", "d", "-1d", "30 days", ""] {
+            assert!(invalid.parse::<Policy>().is_err(), "{invalid:?}");
+        }
+        assert_eq!(Policy::Days(7).to_string(), "7d");
+
+        assert_eq!(parse_module_policy("DEX=30d").unwrap(), ("dex".to_string(), Policy::Days(30)));
+        assert!(parse_module_policy("dex").is_err());
+        assert!(parse_module_policy("=30d").is_err());
+        assert!(parse_module_policy("dex=soon").is_err());
+
+        let entries = ["dex=30d", "staking=forever", "default=90d"].map(|e| parse_module_policy(e).unwrap());
+        let retention = ModuleRetention::from_entries(&entries).unwrap();
+        assert_eq!(retention.policy("dex"), Policy::Days(30));
+        assert_eq!(retention.policy("staking"), Policy::Forever);
+        assert_eq!(retention.policy("lending"), Policy::Days(90));
+        assert_eq!(ModuleRetention::from_entries(&entries[..1]).unwrap().policy("lending"), Policy::Forever);
+        assert!(ModuleRetention::from_entries(&[entries[0].clone(), entries[0].clone()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn each_module_is_pruned_under_its_own_policy() {
+        let db = TestDb::new().await;
+        let now = now_ms().unwrap();
+        let mut events = vec![];
+        for (i, module) in ["dex", "staking", "lending"].into_iter().enumerate() {
+            for days in [100, 60, 10] {
+                let timestamp_ms = now - days * MS_PER_DAY;
+                events.push(stored_event(&format!("{module}{days}"), module, i as i64, timestamp_ms));
+            }
+        }
+        db.insert_events(&events).await;
+
+        let entries = ["dex=30d", "staking=forever", "default=90d"].map(|e| parse_module_policy(e).unwrap());
+        let retention = Retention::PerModule(ModuleRetention::from_entries(&entries).unwrap());
+        let dry_run = PrunerConfig { dry_run: true, ..config(retention.clone()) };
+        assert_eq!(prune_once(db.db(), &dry_run).await.unwrap(), 3);
+        assert_eq!(prune_once(db.db(), &config(retention)).await.unwrap(), 3);
+
+        #[derive(QueryableByName)]
+        struct Digest {
+            #[diesel(sql_type = Text)]
+            digest: String,
+        }
+        let mut conn = db.db().connect().await.unwrap();
+        let kept: Vec<Digest> =
+            diesel::sql_query("SELECT digest FROM unxv_events ORDER BY digest").load(&mut conn).await.unwrap();
+        let kept: Vec<String> = kept.into_iter().map(|d| d.digest).collect();
+        // dex keeps 30 days, staking everything, and lending the default 90 days.
+        assert_eq!(kept, ["dex10", "lending10", "lending60", "staking10", "staking100", "staking60"]);
+    }
+}
@@ -0,0 +1,199 @@
+use diesel::QueryDsl;
+use diesel_async::scoped_futures::{ScopedBoxFuture, ScopedFutureExt};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::Serialize;
+use sui_pg_db::Db;
+use tracing::warn;
+
+use crate::{db, metrics};
+use unxv_schema::models::UnxvQuarantinedRow;
+use unxv_schema::schema::unxv_events_quarantine;
+
+/// The quarantine row for `row`, which failed to commit to `target_table` with `error`.
+///
+/// Postgres can't store NUL characters in text or JSONB, and they are a common reason for the
+/// row to have been rejected in the first place, so they are written as a literal `\u0000`.
+pub fn quarantined<R: Serialize>(
+    pipeline: &str,
+    target_table: &str,
+    digest: &str,
+    event_index: i64,
+    checkpoint: i64,
+    row: &R,
+    error: &str,
+) -> anyhow::Result<UnxvQuarantinedRow> {
+    let json = serde_json::to_string(row)?.replace("\\u0000", "\\\\u0000");
+    Ok(UnxvQuarantinedRow {
+        pipeline: pipeline.to_string(),
+        target_table: target_table.to_string(),
+        digest: digest.to_string(),
+        event_index,
+        checkpoint,
+        row: serde_json::from_str(&json)?,
+        error: error.replace('\0', "\\u0000"),
+    })
+}
+
+/// Commit what can be committed of `values`, whose commit as a whole failed with `err`, a
+/// [`db::data_error_reason`] error. The batch is bisected and each part written with `write`
+/// (retried like any commit), down to the single values that still fail. Those are written to
+/// `unxv_events_quarantine` by way of `quarantined`, so the rest commit and the watermark
+/// advances. Returns the rows written and the values that committed, in batch order. A part
+/// failing with any other error, or the quarantine insert failing, fails the commit as before.
+///
+/// `write` must be safe to repeat after a partial write, as for [`db::retry_commit`]: parts
+/// of the batch may have been written before the failing statement.
+pub async fn isolate<'v, T, W, Q>(
+    pipeline: &str,
+    values: &'v [T],
+    conn: &mut AsyncPgConnection,
+    err: anyhow::Error,
+    mut write: W,
+    quarantined: Q,
+) -> anyhow::Result<(usize, Vec<&'v T>)>
+where
+    T: Sync,
+    W: for<'r> FnMut(&'v [T], &'r mut AsyncPgConnection) -> ScopedBoxFuture<'v, 'r, anyhow::Result<usize>>,
+    Q: Fn(&T, &str) -> anyhow::Result<UnxvQuarantinedRow>,
+{
+    let mut rows = 0;
+    let mut committed = vec![];
+    let mut poison = vec![];
+    let mut pending = vec![];
+    match values {
+        [value] => poison.push(quarantined(value, &format!("{err:#}"))?),
+        _ => pending.extend(halves(values)),
+    }
+    while let Some(part) = pending.pop() {
+        let err = match db::retry_commit(pipeline, conn, |conn| write(part, conn)).await {
+            Ok(n) => {
+                rows += n;
+                // Parts are popped first half first, so this keeps the batch's order.
+                committed.extend(part);
+                continue;
+            }
+            Err(err) => err,
+        };
+        if db::data_error_reason(&err).is_none() {
+            return Err(err);
+        }
+        match part {
+            [value] => poison.push(quarantined(value, &format!("{err:#}"))?),
+            _ => pending.extend(halves(part)),
+        }
+    }
+
+    let poison = &poison;
+    rows += db::retry_commit(pipeline, conn, |conn| {
+        async move {
+            Ok(diesel::insert_into(unxv_events_quarantine::table)
+                .values(poison)
+                .on_conflict_do_nothing()
+                .execute(conn)
+                .await?)
+        }
+        .scope_boxed()
+    })
+    .await?;
+    for row in poison {
+        warn!(
+            pipeline,
+            digest = %row.digest,
+            event_index = row.event_index,
+            "Quarantined a row that failed to commit to {}: {}",
+            row.target_table,
+            row.error
+        );
+    }
+    if let Some(metrics) = metrics::global() {
+        metrics.quarantined_rows.with_label_values(&[pipeline]).inc_by(poison.len() as u64);
+    }
+    Ok((rows, committed))
+}
+
+/// `part` split in two, the first half last so it is popped first.
+fn halves<T>(part: &[T]) -> [&[T]; 2] {
+    let (first, second) = part.split_at(part.len() / 2);
+    [second, first]
+}
+
+/// Warn at startup if earlier runs quarantined rows, which are missing from their tables until
+/// someone deals with them.
+pub async fn warn_if_nonempty(db: &Db) -> anyhow::Result<()> {
+    let mut conn = db.connect().await?;
+    let count: i64 = unxv_events_quarantine::table.count().get_result(&mut conn).await?;
+    if count > 0 {
+        warn!(count, "unxv_events_quarantine holds rows that failed to commit; inspect and re-insert or delete them");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{stored_event, TestDb};
+    use diesel::SelectableHelper;
+    use unxv_schema::models::UnxvEvent;
+    use unxv_schema::schema::unxv_events;
+
+    async fn insert(events: &[UnxvEvent], conn: &mut AsyncPgConnection) -> anyhow::Result<usize> {
+        Ok(diesel::insert_into(unxv_events::table).values(events).on_conflict_do_nothing().execute(conn).await?)
+    }
+
+    #[tokio::test]
+    async fn a_failing_row_is_quarantined_and_its_neighbours_commit() {
+        let db = TestDb::new().await;
+        let mut conn = db.db().connect().await.unwrap();
+        let mut events: Vec<UnxvEvent> =
+            (0..5).map(|i| UnxvEvent { event_index: i, ..stored_event("tx", "dex", 9, 9_000) }).collect();
+        // Postgres rejects a NUL in text, whichever rows it's batched with.
+        events[2].event_type.push('\0');
+        let err = insert(&events, &mut conn).await.unwrap_err();
+        assert!(db::data_error_reason(&err).is_some());
+
+        let (rows, committed) = isolate(
+            "unxv_events",
+            &events,
+            &mut conn,
+            err,
+            |part, conn| insert(part, conn).scope_boxed(),
+            |event, error| {
+                quarantined(
+                    "unxv_events",
+                    "unxv_events",
+                    &event.digest,
+                    event.event_index,
+                    event.checkpoint,
+                    event,
+                    error,
+                )
+            },
+        )
+        .await
+        .unwrap();
+
+        // The four neighbours and the quarantine row.
+        assert_eq!(rows, 5);
+        let indexes = |events: &[&UnxvEvent]| events.iter().map(|e| e.event_index).collect::<Vec<_>>();
+        assert_eq!(indexes(&committed), [0, 1, 3, 4]);
+        let stored: Vec<i64> = unxv_events::table
+            .select(unxv_events::event_index)
+            .order_by(unxv_events::event_index)
+            .load(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(stored, [0, 1, 3, 4]);
+
+        let quarantine: Vec<UnxvQuarantinedRow> =
+            unxv_events_quarantine::table.select(UnxvQuarantinedRow::as_select()).load(&mut conn).await.unwrap();
+        let [row] = &quarantine[..] else { panic!("one quarantined row, got {quarantine:?}") };
+        assert_eq!(
+            (row.pipeline.as_str(), row.target_table.as_str(), row.digest.as_str(), row.event_index, row.checkpoint),
+            ("unxv_events", "unxv_events", "tx", 2, 9)
+        );
+        // The NUL is kept as a literal escape, so the row reads back as it was rejected.
+        assert_eq!(row.row["event_type"], format!("{}\\u0000", stored_event("tx", "dex", 9, 9_000).event_type));
+        assert_eq!(row.row["event_index"], 2);
+        assert!(row.error.contains("invalid byte sequence"), "{}", row.error);
+    }
+}
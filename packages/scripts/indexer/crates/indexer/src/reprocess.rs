@@ -0,0 +1,259 @@
+use diesel::dsl::sql;
+use diesel::sql_types::{Array, BigInt, Bool, Jsonb, Nullable, Text};
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, QueryableByName, SelectableHelper};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use std::ops::RangeInclusive;
+use std::time::Duration;
+use sui_pg_db::Db;
+use tracing::{info, warn};
+
+use crate::handlers::unxv_events_handler::UnxvEventsHandler;
+use unxv_schema::models::UnxvEvent;
+use unxv_schema::schema::unxv_events;
+
+/// Which stored events a [`run`] re-decodes. Each distinct selection is a run of its own in
+/// `reprocess_progress`.
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    /// Module schema to read `unxv_events` from (`--module-schemas`); the default one when None.
+    pub schema: Option<String>,
+    pub module: Option<String>,
+    pub event_type: Option<String>,
+    pub checkpoints: Option<RangeInclusive<u64>>,
+}
+
+impl Selection {
+    /// The run's key in `reprocess_progress`.
+    fn run(&self) -> String {
+        let range = self.checkpoints.as_ref().map(|r| format!("{}..={}", r.start(), r.end()));
+        format!(
+            "schema={} module={} event_type={} checkpoints={}",
+            self.schema.as_deref().unwrap_or("default"),
+            self.module.as_deref().unwrap_or("*"),
+            self.event_type.as_deref().unwrap_or("*"),
+            range.as_deref().unwrap_or("*"),
+        )
+    }
+}
+
+/// Outcome of [`run`], counting rows of earlier interrupted attempts too.
+#[derive(Debug, Default)]
+pub struct ReprocessSummary {
+    pub scanned: u64,
+    /// Rows whose decoded columns changed.
+    pub updated: u64,
+    /// Rows that still don't decode, left as they were.
+    pub failed: u64,
+    /// Whether the run picked up after an interrupted one.
+    pub resumed: bool,
+}
+
+#[derive(QueryableByName)]
+struct Progress {
+    #[diesel(sql_type = Nullable<BigInt>)]
+    after_checkpoint: Option<i64>,
+    #[diesel(sql_type = Nullable<Text>)]
+    after_digest: Option<String>,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    after_event_index: Option<i64>,
+    #[diesel(sql_type = BigInt)]
+    scanned: i64,
+    #[diesel(sql_type = BigInt)]
+    updated: i64,
+    #[diesel(sql_type = BigInt)]
+    failed: i64,
+    #[diesel(sql_type = Bool)]
+    finished: bool,
+}
+
+const LOAD_PROGRESS: &str = "\
+    SELECT after_checkpoint, after_digest, after_event_index, scanned, updated, failed, \
+           finished_at IS NOT NULL AS finished \
+    FROM reprocess_progress WHERE run = $1";
+
+/// Start `$1` from the first row, whether or not it ran before.
+const RESET_PROGRESS: &str = "\
+    INSERT INTO reprocess_progress (run) VALUES ($1) \
+    ON CONFLICT (run) DO UPDATE SET \
+        after_checkpoint = NULL, after_digest = NULL, after_event_index = NULL, \
+        scanned = 0, updated = 0, failed = 0, started_at = now(), updated_at = now(), finished_at = NULL";
+
+const SAVE_PROGRESS: &str = "\
+    UPDATE reprocess_progress SET \
+        after_checkpoint = $2, after_digest = $3, after_event_index = $4, \
+        scanned = scanned + $5, updated = updated + $6, failed = failed + $7, updated_at = now() \
+    WHERE run = $1";
+
+const FINISH_PROGRESS: &str = "UPDATE reprocess_progress SET finished_at = now() WHERE run = $1";
+
+/// Overwrite the decoded columns of the rows in the arrays, skipping rows they wouldn't change
+/// so that only real updates are written and counted.
+const UPDATE_DECODED: &str = "\
+    UPDATE unxv_events AS e SET
+        contents_json = u.contents_json, emitter_object = u.emitter_object, type_params = u.type_params,
+        type_params_truncated = u.type_params_truncated, type_param_0 = u.type_param_0,
+        type_param_1 = u.type_param_1, type_params_key = u.type_params_key, type_hash = u.type_hash
+    FROM UNNEST($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) AS u(
+        digest, event_index, contents_json, emitter_object, type_params, type_params_truncated,
+        type_param_0, type_param_1, type_params_key, type_hash
+    )
+    WHERE e.digest = u.digest AND e.event_index = u.event_index
+      AND (e.contents_json, e.emitter_object, e.type_params, e.type_params_truncated, e.type_param_0,
+           e.type_param_1, e.type_params_key, e.type_hash)
+          IS DISTINCT FROM
+          (u.contents_json, u.emitter_object, u.type_params, u.type_params_truncated, u.type_param_0,
+           u.type_param_1, u.type_params_key, u.type_hash)";
+
+/// Re-run `handler`'s decoding over the `contents_bcs` of the stored events in `selection`, in
+/// batches of `batch_size` sleeping `sleep` in between, and update their decoded columns.
+/// Each batch commits together with the run's cursor in `reprocess_progress`, so an
+/// interrupted run resumes after its last batch; `restart` starts over instead. Watermarks,
+/// typed tables and the transaction columns of the rows are left alone.
+pub async fn run(
+    db: &Db,
+    handler: &UnxvEventsHandler,
+    selection: &Selection,
+    batch_size: i64,
+    sleep: Duration,
+    restart: bool,
+) -> anyhow::Result<ReprocessSummary> {
+    let mut conn = db.connect().await?;
+    if let Some(schema) = &selection.schema {
+        // Schemas are validated identifiers (see `schemas::parse_module_schema`); `public` stays
+        // on the path for reprocess_progress.
+        diesel::sql_query(format!("SET search_path TO {schema}, public")).execute(&mut conn).await?;
+    }
+    let run = selection.run();
+    let mut progress: Option<Progress> =
+        diesel::sql_query(LOAD_PROGRESS).bind::<Text, _>(&run).get_result(&mut conn).await.optional()?;
+    if restart || progress.as_ref().map_or(true, |p| p.finished) {
+        diesel::sql_query(RESET_PROGRESS).bind::<Text, _>(&run).execute(&mut conn).await?;
+        progress = None;
+    }
+    let mut summary = ReprocessSummary::default();
+    let mut after = None;
+    if let Some(p) = progress {
+        summary = ReprocessSummary {
+            scanned: p.scanned as u64,
+            updated: p.updated as u64,
+            failed: p.failed as u64,
+            resumed: true,
+        };
+        after = match (p.after_checkpoint, p.after_digest, p.after_event_index) {
+            (Some(checkpoint), Some(digest), Some(event_index)) => Some((checkpoint, digest, event_index)),
+            _ => None,
+        };
+        info!(run, after_checkpoint = p.after_checkpoint, "Resuming reprocess run");
+    }
+
+    loop {
+        let mut query = unxv_events::table.select(UnxvEvent::as_select()).into_boxed();
+        if let Some(module) = &selection.module {
+            query = query.filter(unxv_events::module.eq(module.clone()));
+        }
+        if let Some(event_type) = &selection.event_type {
+            query = query.filter(unxv_events::event_type.eq(event_type.clone()));
+        }
+        if let Some(range) = &selection.checkpoints {
+            query = query.filter(unxv_events::checkpoint.between(*range.start() as i64, *range.end() as i64));
+        }
+        if let Some((checkpoint, digest, event_index)) = &after {
+            query = query.filter(
+                sql::<Bool>("(checkpoint, digest, event_index) > (")
+                    .bind::<BigInt, _>(*checkpoint)
+                    .sql(", ")
+                    .bind::<Text, _>(digest.clone())
+                    .sql(", ")
+                    .bind::<BigInt, _>(*event_index)
+                    .sql(")"),
+            );
+        }
+        let batch: Vec<UnxvEvent> = query
+            .order((unxv_events::checkpoint, unxv_events::digest, unxv_events::event_index))
+            .limit(batch_size)
+            .load(&mut conn)
+            .await?;
+        let Some(last) = batch.last() else { break };
+        let cursor = (last.checkpoint, last.digest.clone(), last.event_index);
+
+        let mut rows = vec![];
+        let mut failed = 0;
+        for event in &batch {
+            match handler.redecode(event) {
+                Ok(row) => rows.push(row),
+                Err(reason) => {
+                    warn!(digest = %event.digest, event_index = event.event_index, "Still failing to decode: {reason}");
+                    failed += 1;
+                }
+            }
+        }
+
+        // The updates and the cursor commit together, so a resumed run neither skips nor
+        // recounts rows.
+        let (rows, run, cursor, scanned) = (&rows, &run, &cursor, batch.len() as i64);
+        let updated = conn
+            .transaction(|conn| {
+                async move {
+                    let updated = update_decoded(rows, conn).await?;
+                    diesel::sql_query(SAVE_PROGRESS)
+                        .bind::<Text, _>(run)
+                        .bind::<BigInt, _>(cursor.0)
+                        .bind::<Text, _>(&cursor.1)
+                        .bind::<BigInt, _>(cursor.2)
+                        .bind::<BigInt, _>(scanned)
+                        .bind::<BigInt, _>(updated as i64)
+                        .bind::<BigInt, _>(failed)
+                        .execute(conn)
+                        .await?;
+                    Ok::<_, anyhow::Error>(updated)
+                }
+                .scope_boxed()
+            })
+            .await?;
+        summary.scanned += scanned as u64;
+        summary.updated += updated as u64;
+        summary.failed += failed as u64;
+        info!(
+            checkpoint = cursor.0,
+            scanned = summary.scanned,
+            updated = summary.updated,
+            failed = summary.failed,
+            "Reprocessing unxv_events"
+        );
+        after = Some(cursor.clone());
+        if !sleep.is_zero() {
+            tokio::time::sleep(sleep).await;
+        }
+    }
+    diesel::sql_query(FINISH_PROGRESS).bind::<Text, _>(&run).execute(&mut conn).await?;
+    if selection.schema.is_some() {
+        diesel::sql_query("RESET search_path").execute(&mut conn).await?;
+    }
+    Ok(summary)
+}
+
+/// Write the decoded columns of `rows` in one statement. Returns the rows that changed.
+async fn update_decoded(rows: &[UnxvEvent], conn: &mut AsyncPgConnection) -> anyhow::Result<usize> {
+    if rows.is_empty() {
+        return Ok(0);
+    }
+    macro_rules! column {
+        ($field:ident) => {
+            rows.iter().map(|e| e.$field.clone()).collect::<Vec<_>>()
+        };
+    }
+    Ok(diesel::sql_query(UPDATE_DECODED)
+        .bind::<Array<Text>, _>(column!(digest))
+        .bind::<Array<BigInt>, _>(column!(event_index))
+        .bind::<Array<Nullable<Jsonb>>, _>(column!(contents_json))
+        .bind::<Array<Nullable<Text>>, _>(column!(emitter_object))
+        .bind::<Array<Jsonb>, _>(column!(type_params))
+        .bind::<Array<Bool>, _>(column!(type_params_truncated))
+        .bind::<Array<Nullable<Text>>, _>(column!(type_param_0))
+        .bind::<Array<Nullable<Text>>, _>(column!(type_param_1))
+        .bind::<Array<Nullable<Text>>, _>(column!(type_params_key))
+        .bind::<Array<Nullable<Text>>, _>(column!(type_hash))
+        .execute(conn)
+        .await?)
+}
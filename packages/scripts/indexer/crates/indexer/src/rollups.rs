@@ -0,0 +1,80 @@
+use diesel_async::RunQueryDsl;
+use std::sync::Arc;
+use std::time::Duration;
+use sui_pg_db::Db;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::alerts::{Alert, AlertSender, Severity};
+use crate::metrics::UnxvMetrics;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct RollupArgs {
+    /// Seconds between daily participant rollups (0 = rollup task disabled)
+    #[clap(env, long, default_value_t = 300)]
+    pub participants_rollup_interval_secs: u64,
+}
+
+impl RollupArgs {
+    /// None when the rollup task is disabled.
+    pub fn config(&self) -> Option<RollupConfig> {
+        (self.participants_rollup_interval_secs > 0).then(|| RollupConfig {
+            interval: Duration::from_secs(self.participants_rollup_interval_secs),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RollupConfig {
+    pub interval: Duration,
+}
+
+/// Finalize every staged (pool, day) that isn't yet rolled up and whose UTC day ended before the
+/// `dex_trades` watermark, i.e. every fill up to the end of the day has been committed. Fills that
+/// arrive for a finalized day later (backfills, range reruns) are recounted by the handler.
+const FINALIZE_PARTICIPANTS: &str = "\
+    INSERT INTO dex_daily_participants (pool_id, day, unique_makers, unique_takers) \
+    SELECT m.pool_id, m.day, \
+           count(*) FILTER (WHERE m.role = 'maker'), \
+           count(*) FILTER (WHERE m.role = 'taker') \
+    FROM dex_market_participants m \
+    WHERE m.day < (SELECT (to_timestamp(timestamp_ms_hi_inclusive / 1000.0) AT TIME ZONE 'UTC')::date \
+                   FROM watermarks WHERE pipeline = 'dex_trades') \
+      AND NOT EXISTS (SELECT 1 FROM dex_daily_participants d WHERE d.pool_id = m.pool_id AND d.day = m.day) \
+    GROUP BY m.pool_id, m.day \
+    ON CONFLICT DO NOTHING";
+
+/// Roll up finished days on an interval until cancelled.
+pub async fn run(
+    db: Db,
+    config: RollupConfig,
+    metrics: Arc<UnxvMetrics>,
+    alerts: AlertSender,
+    cancel: CancellationToken,
+) {
+    let mut interval = tokio::time::interval(config.interval);
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = interval.tick() => {}
+        }
+        match finalize(&db).await {
+            Ok(0) => {}
+            Ok(n) => {
+                metrics.rollup_days_finalized.with_label_values(&["dex_daily_participants"]).inc_by(n as u64);
+                info!(days = n, "Finalized daily participant counts");
+            }
+            Err(e) => alerts.send(Alert::new(
+                "rollups",
+                Severity::Warning,
+                "rollups:round_failed",
+                format!("Participant rollup failed: {e:#}"),
+            )),
+        }
+    }
+}
+
+async fn finalize(db: &Db) -> anyhow::Result<usize> {
+    let mut conn = db.connect().await?;
+    Ok(diesel::sql_query(FINALIZE_PARTICIPANTS).execute(&mut conn).await?)
+}
@@ -0,0 +1,177 @@
+use diesel::sql_types::{Bool, Text};
+use diesel::QueryableByName;
+use diesel_async::RunQueryDsl;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use sui_pg_db::Db;
+use tracing::info;
+
+/// `module=schema`, e.g. `dex=market`.
+pub fn parse_module_schema(s: &str) -> anyhow::Result<(String, String)> {
+    let (module, schema) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Expected MODULE=SCHEMA, got {s:?}"))?;
+    let (module, schema) = (module.trim(), schema.trim());
+    // Schemas end up unquoted in `SET search_path`, so only plain identifiers are accepted.
+    anyhow::ensure!(
+        schema.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+            && schema.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+            && schema.len() <= 63,
+        "Invalid schema name {schema:?}: use lowercase letters, digits and underscores"
+    );
+    anyhow::ensure!(!module.is_empty(), "Missing module in {s:?}");
+    Ok((module.to_string(), schema.to_string()))
+}
+
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct ModuleSchemaArgs {
+    /// Write these modules' `unxv_events` rows to another Postgres schema, e.g.
+    /// `dex=market,futures=market,lending=credit`. Unlisted modules stay in the default schema
+    #[clap(env, long, value_delimiter = ',', value_parser = parse_module_schema)]
+    pub module_schemas: Vec<(String, String)>,
+    /// Create missing module schemas and their tables on startup instead of failing
+    #[clap(env, long)]
+    pub create_module_schemas: bool,
+}
+
+impl ModuleSchemaArgs {
+    pub fn schemas(&self) -> anyhow::Result<ModuleSchemas> {
+        let mut by_module = BTreeMap::new();
+        for (module, schema) in &self.module_schemas {
+            if let Some(previous) = by_module.insert(module.clone(), schema.clone()) {
+                anyhow::ensure!(previous == *schema, "Module {module} mapped to both {previous} and {schema}");
+            }
+        }
+        Ok(ModuleSchemas { by_module })
+    }
+}
+
+/// Which schema each module's events are written to.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleSchemas {
+    by_module: BTreeMap<String, String>,
+}
+
+impl ModuleSchemas {
+    /// None for the default schema.
+    pub fn schema(&self, module: &str) -> Option<&str> {
+        self.by_module.get(module).map(String::as_str)
+    }
+
+    /// Distinct target schemas, sorted.
+    pub fn targets(&self) -> Vec<&str> {
+        let mut targets: Vec<_> = self.by_module.values().map(String::as_str).collect();
+        targets.sort_unstable();
+        targets.dedup();
+        targets
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_module.is_empty()
+    }
+}
+
+static SCHEMAS: OnceLock<ModuleSchemas> = OnceLock::new();
+
+/// The mapping used by the write path. Pipeline commits are associated functions without
+/// access to the handler, so they read it from here; empty until [`install`] is called.
+pub fn global() -> &'static ModuleSchemas {
+    SCHEMAS.get_or_init(ModuleSchemas::default)
+}
+
+/// Set the process-wide mapping. Must run before pipelines start; later calls are ignored.
+pub fn install(schemas: ModuleSchemas) {
+    let _ = SCHEMAS.set(schemas);
+}
+
+#[derive(QueryableByName)]
+struct Exists {
+    #[diesel(sql_type = Bool)]
+    exists: bool,
+}
+
+/// Make sure every target schema has an `unxv_events` table. With `create`, missing schemas and
+/// tables are created (and columns added to the default table since are brought over) by
+/// `unxv_ensure_module_schema`; otherwise a missing one is an error.
+pub async fn prepare(db: &Db, schemas: &ModuleSchemas, create: bool) -> anyhow::Result<()> {
+    let mut conn = db.connect().await?;
+    for schema in schemas.targets() {
+        if create {
+            diesel::sql_query("SELECT unxv_ensure_module_schema($1)")
+                .bind::<Text, _>(schema)
+                .execute(&mut conn)
+                .await?;
+            info!(schema, "Prepared module schema");
+            continue;
+        }
+        let found: Exists = diesel::sql_query("SELECT to_regclass(format('%I.unxv_events', $1)) IS NOT NULL AS exists")
+            .bind::<Text, _>(schema)
+            .get_result(&mut conn)
+            .await?;
+        anyhow::ensure!(
+            found.exists,
+            "Schema {schema} has no unxv_events table; create it or pass --create-module-schemas"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestDb;
+
+    fn args(module_schemas: &[(&str, &str)]) -> ModuleSchemaArgs {
+        ModuleSchemaArgs {
+            module_schemas: module_schemas.iter().map(|(m, s)| (m.to_string(), s.to_string())).collect(),
+            create_module_schemas: false,
+        }
+    }
+
+    #[test]
+    fn module_schemas_parse_plain_identifiers_only() {
+        assert_eq!(parse_module_schema("dex=market").unwrap(), ("dex".to_string(), "market".to_string()));
+        assert_eq!(
+            parse_module_schema(" lending = _credit_2 ").unwrap(),
+            ("lending".to_string(), "_credit_2".to_string())
+        );
+
+        let too_long = format!("dex={}", "m".repeat(64));
+        for bad in [
+            "dex",
+            "=market",
+            "dex=",
+            "dex=Market",
+            "dex=2market",
+            "dex=market-data",
+            "dex=market; DROP TABLE unxv_events",
+            "dex=\"market\"",
+            too_long.as_str(),
+        ] {
+            assert!(parse_module_schema(bad).is_err(), "{bad:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn a_module_maps_to_one_schema() {
+        let schemas = args(&[("dex", "market"), ("futures", "market"), ("lending", "credit"), ("dex", "market")])
+            .schemas()
+            .unwrap();
+        assert_eq!(schemas.schema("dex"), Some("market"));
+        assert_eq!(schemas.schema("vault"), None);
+        assert_eq!(schemas.targets(), ["credit", "market"]);
+
+        assert!(args(&[("dex", "market"), ("dex", "credit")]).schemas().is_err());
+    }
+
+    #[tokio::test]
+    async fn missing_schemas_fail_unless_created() {
+        let db = TestDb::new().await;
+        let schemas = args(&[("dex", "market")]).schemas().unwrap();
+
+        assert!(prepare(db.db(), &schemas, false).await.is_err());
+        prepare(db.db(), &schemas, true).await.unwrap();
+        prepare(db.db(), &schemas, false).await.unwrap();
+        assert_eq!(db.count("market.unxv_events").await, 0);
+    }
+}
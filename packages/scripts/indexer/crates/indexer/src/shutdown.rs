@@ -0,0 +1,158 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// How [`coordinate`] ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shutdown {
+    /// The indexer finished on its own (e.g. `--last-checkpoint`).
+    Finished,
+    /// Cancelled on this signal; the pipelines drained in time.
+    Drained(&'static str),
+    /// Cancelled on this signal; the pipelines were still draining when the timeout ran out.
+    TimedOut(&'static str),
+    /// This second signal arrived while draining; the caller should exit immediately.
+    Interrupted(&'static str),
+}
+
+/// SIGINT and SIGTERM, by name, as they arrive.
+pub fn signals() -> mpsc::UnboundedReceiver<&'static str> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move { while tx.send(signal().await).is_ok() {} });
+    rx
+}
+
+/// Wait for `run` to finish or for the first of `signals`. On a signal, `cancel` lets the
+/// pipelines commit what they have in flight and write their watermarks; the drain is bounded
+/// by `drain` so a stuck commit can't hold up a pod's termination, and a second signal skips it.
+pub async fn coordinate<F: Future + Unpin>(
+    run: &mut F,
+    signals: &mut mpsc::UnboundedReceiver<&'static str>,
+    cancel: &CancellationToken,
+    drain: Duration,
+) -> Shutdown {
+    let signal = tokio::select! {
+        _ = &mut *run => {
+            info!("Indexer finished, shutting down");
+            return Shutdown::Finished;
+        }
+        Some(signal) = signals.recv() => signal,
+    };
+    info!("Received {signal}, shutting down (send it again to exit immediately)");
+    cancel.cancel();
+    tokio::select! {
+        drained = tokio::time::timeout(drain, &mut *run) => match drained {
+            Ok(_) => Shutdown::Drained(signal),
+            Err(_) => {
+                warn!("Pipelines did not drain within {drain:?}; exiting anyway");
+                Shutdown::TimedOut(signal)
+            }
+        },
+        Some(again) = signals.recv() => {
+            warn!("Received {again} again, exiting without draining");
+            Shutdown::Interrupted(again)
+        }
+    }
+}
+
+/// Resolves with the name of the next SIGINT or SIGTERM received.
+pub async fn signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut sigterm = match signal(SignalKind::terminate()) {
+            Ok(sigterm) => sigterm,
+            Err(e) => {
+                warn!("Failed to install SIGTERM handler: {e}");
+                let _ = tokio::signal::ctrl_c().await;
+                return "SIGINT";
+            }
+        };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => "SIGINT",
+            _ = sigterm.recv() => "SIGTERM",
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "SIGINT"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// A stand-in for the indexer: stops once cancelled, after `drain_for`.
+    fn pipelines(cancel: &CancellationToken, drain_for: Duration) -> tokio::task::JoinHandle<()> {
+        let cancel = cancel.child_token();
+        tokio::spawn(async move {
+            cancel.cancelled().await;
+            tokio::time::sleep(drain_for).await;
+        })
+    }
+
+    #[tokio::test]
+    async fn finishing_on_its_own_cancels_nothing() {
+        let cancel = CancellationToken::new();
+        let (_tx, mut signals) = mpsc::unbounded_channel();
+        let mut run = tokio::spawn(async {});
+
+        assert_eq!(coordinate(&mut run, &mut signals, &cancel, Duration::from_secs(60)).await, Shutdown::Finished);
+        assert!(!cancel.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn a_signal_cancels_the_pipelines_and_waits_for_them() {
+        let cancel = CancellationToken::new();
+        let (tx, mut signals) = mpsc::unbounded_channel();
+        let mut run = pipelines(&cancel, Duration::from_millis(50));
+        tx.send("SIGTERM").unwrap();
+
+        let shutdown = coordinate(&mut run, &mut signals, &cancel, Duration::from_secs(60)).await;
+        assert_eq!(shutdown, Shutdown::Drained("SIGTERM"));
+        assert!(cancel.is_cancelled());
+        assert!(run.is_finished());
+    }
+
+    #[tokio::test]
+    async fn the_drain_is_bounded_by_the_timeout() {
+        let cancel = CancellationToken::new();
+        let (tx, mut signals) = mpsc::unbounded_channel();
+        // A commit stuck well past the timeout.
+        let mut run = pipelines(&cancel, Duration::from_secs(3600));
+        tx.send("SIGINT").unwrap();
+
+        let start = Instant::now();
+        let drain = Duration::from_millis(100);
+        let shutdown =
+            tokio::time::timeout(Duration::from_secs(10), coordinate(&mut run, &mut signals, &cancel, drain))
+                .await
+                .expect("coordinate returns once the drain times out");
+        assert_eq!(shutdown, Shutdown::TimedOut("SIGINT"));
+        assert!(start.elapsed() >= drain);
+        assert!(!run.is_finished());
+    }
+
+    #[tokio::test]
+    async fn a_second_signal_skips_the_drain() {
+        let cancel = CancellationToken::new();
+        let (tx, mut signals) = mpsc::unbounded_channel();
+        let mut run = pipelines(&cancel, Duration::from_secs(3600));
+        tx.send("SIGTERM").unwrap();
+        tx.send("SIGINT").unwrap();
+
+        let shutdown = tokio::time::timeout(
+            Duration::from_secs(10),
+            coordinate(&mut run, &mut signals, &cancel, Duration::from_secs(3600)),
+        )
+        .await
+        .expect("the second signal ends the drain");
+        assert_eq!(shutdown, Shutdown::Interrupted("SIGINT"));
+        assert!(cancel.is_cancelled());
+    }
+}
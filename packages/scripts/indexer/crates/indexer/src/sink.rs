@@ -0,0 +1,329 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate};
+use clap::ValueEnum;
+use object_store::aws::AmazonS3Builder;
+use object_store::local::LocalFileSystem;
+use object_store::path::Path as ObjectPath;
+use object_store::prefix::PrefixStore;
+use object_store::{ObjectStore, PutPayload};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use sui_indexer_alt_framework::pipeline::Processor;
+use sui_types::full_checkpoint_content::CheckpointData;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::export::{parquet_schema, record_batch};
+use crate::handlers::unxv_events_handler::{EventRow, UnxvEventsHandler};
+use crate::inspect::CheckpointSource;
+use unxv_schema::models::UnxvEvent;
+
+/// Object holding the parquet sink's progress, next to the data files.
+const WATERMARK_FILE: &str = "_unxv_watermark.json";
+
+/// How long to wait before asking again for a checkpoint that isn't available yet.
+const FETCH_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum SinkKind {
+    /// The indexer's pipelines, writing Postgres
+    #[default]
+    Postgres,
+    /// unxv_events rows only, as Parquet files in a directory or S3 bucket
+    Parquet,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct SinkArgs {
+    /// Where processed events go: `postgres`, or `parquet` files under --sink-output-dir or
+    /// --sink-s3-uri instead of any database
+    #[clap(env, long, value_enum, default_value_t = SinkKind::Postgres)]
+    pub sink: SinkKind,
+    /// Directory the parquet sink writes to
+    #[clap(env, long, conflicts_with = "sink_s3_uri")]
+    pub sink_output_dir: Option<PathBuf>,
+    /// `s3://bucket/prefix` the parquet sink writes to, with credentials from the AWS_*
+    /// environment variables
+    #[clap(env, long)]
+    pub sink_s3_uri: Option<Url>,
+    /// Write a Parquet file once its buffered rows reach about this many MiB
+    #[clap(env, long, default_value_t = 128)]
+    pub sink_file_target_mb: usize,
+    /// Write a Parquet file after this many checkpoints, whatever its size
+    #[clap(env, long, default_value_t = 10_000)]
+    pub sink_file_max_checkpoints: u64,
+    /// Checkpoints fetched ahead of processing
+    #[clap(env, long, default_value_t = 16)]
+    pub sink_fetch_concurrency: usize,
+}
+
+impl SinkArgs {
+    /// None for the Postgres sink, which is the framework's pipelines.
+    pub fn config(&self) -> anyhow::Result<Option<ParquetSinkConfig>> {
+        if self.sink == SinkKind::Postgres {
+            return Ok(None);
+        }
+        let (store, location): (Arc<dyn ObjectStore>, String) = match (&self.sink_output_dir, &self.sink_s3_uri) {
+            (Some(dir), _) => {
+                std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+                (Arc::new(LocalFileSystem::new_with_prefix(dir)?), dir.display().to_string())
+            }
+            (None, Some(uri)) => {
+                anyhow::ensure!(uri.scheme() == "s3", "--sink-s3-uri must be an s3:// URI, got {uri}");
+                let bucket = AmazonS3Builder::from_env().with_url(uri.as_str()).build()?;
+                let prefix = uri.path().trim_matches('/');
+                let store: Arc<dyn ObjectStore> = if prefix.is_empty() {
+                    Arc::new(bucket)
+                } else {
+                    Arc::new(PrefixStore::new(bucket, prefix))
+                };
+                (store, uri.to_string())
+            }
+            (None, None) => anyhow::bail!("--sink parquet needs --sink-output-dir or --sink-s3-uri"),
+        };
+        Ok(Some(ParquetSinkConfig {
+            store,
+            location,
+            target_bytes: self.sink_file_target_mb.max(1) << 20,
+            max_checkpoints: self.sink_file_max_checkpoints.max(1),
+            fetch_concurrency: self.sink_fetch_concurrency.max(1),
+        }))
+    }
+}
+
+#[derive(Clone)]
+pub struct ParquetSinkConfig {
+    pub store: Arc<dyn ObjectStore>,
+    /// Directory or URI of `store`, for logs.
+    pub location: String,
+    /// Approximate uncompressed size of the rows in one file.
+    pub target_bytes: usize,
+    /// Most checkpoints covered by one file.
+    pub max_checkpoints: u64,
+    pub fetch_concurrency: usize,
+}
+
+/// Destination of processed `unxv_events` rows other than the Postgres pipelines, which write
+/// through the framework's committer and watermarks instead. Rows are handed over one
+/// checkpoint at a time, in checkpoint order, and only count as written once flushed.
+#[async_trait]
+pub trait EventSink: Send {
+    /// Last checkpoint flushed by an earlier run, after cleaning up anything that run left
+    /// half written.
+    async fn watermark(&mut self) -> anyhow::Result<Option<u64>>;
+
+    /// Take the rows of checkpoint `sequence_number`, which may be none. May flush.
+    async fn write(&mut self, sequence_number: u64, timestamp_ms: i64, events: Vec<UnxvEvent>) -> anyhow::Result<()>;
+
+    /// Write out every row taken so far and advance the watermark past its checkpoints.
+    async fn flush(&mut self) -> anyhow::Result<()>;
+}
+
+/// Contents of [`WATERMARK_FILE`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Watermark {
+    checkpoint_hi_inclusive: Option<u64>,
+    /// Data file being written when this watermark was saved. If it is still set on startup,
+    /// the run died before advancing the watermark past it, so the file is removed and its
+    /// checkpoints processed again.
+    pending: Option<String>,
+}
+
+/// Writes rows as Snappy-compressed Parquet files of the [`crate::export`] schema, named
+/// `date=YYYY-MM-DD/{first}_{last}.parquet` after the UTC date of their checkpoints and the
+/// checkpoint range they cover. A file never spans two dates.
+///
+/// Each file is encoded in memory and stored with one put, which local directories implement
+/// as a write to a temporary file and a rename, and S3 as a single object upload, so readers
+/// never see a partial file.
+pub struct ParquetSink {
+    config: ParquetSinkConfig,
+    watermark: Option<u64>,
+    rows: Vec<UnxvEvent>,
+    bytes: usize,
+    /// Checkpoints taken since the last flush, and the date of their rows.
+    range: Option<(u64, u64)>,
+    date: Option<NaiveDate>,
+}
+
+impl ParquetSink {
+    pub fn new(config: ParquetSinkConfig) -> Self {
+        Self { config, watermark: None, rows: vec![], bytes: 0, range: None, date: None }
+    }
+
+    async fn save_watermark(&self, watermark: &Watermark) -> anyhow::Result<()> {
+        let json = serde_json::to_vec(watermark)?;
+        self.config.store.put(&ObjectPath::from(WATERMARK_FILE), PutPayload::from(json)).await?;
+        Ok(())
+    }
+
+    fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        let schema = parquet_schema();
+        let props = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+        let mut writer = ArrowWriter::try_new(vec![], schema.clone(), Some(props))?;
+        writer.write(&record_batch(&schema, &self.rows)?)?;
+        Ok(writer.into_inner()?)
+    }
+}
+
+/// Rough uncompressed size of `event` in a file, to aim files at the target size.
+fn approx_size(event: &UnxvEvent) -> usize {
+    512 + 2 * event.contents_bcs.len()
+}
+
+fn utc_date(timestamp_ms: i64) -> NaiveDate {
+    DateTime::from_timestamp_millis(timestamp_ms).unwrap_or_default().date_naive()
+}
+
+#[async_trait]
+impl EventSink for ParquetSink {
+    async fn watermark(&mut self) -> anyhow::Result<Option<u64>> {
+        let stored = match self.config.store.get(&ObjectPath::from(WATERMARK_FILE)).await {
+            Ok(result) => serde_json::from_slice::<Watermark>(&result.bytes().await?)?,
+            Err(object_store::Error::NotFound { .. }) => Watermark::default(),
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(pending) = &stored.pending {
+            warn!(file = %pending, "Removing a file left by an interrupted flush");
+            match self.config.store.delete(&ObjectPath::from(pending.as_str())).await {
+                Ok(()) | Err(object_store::Error::NotFound { .. }) => {}
+                Err(e) => return Err(e.into()),
+            }
+            self.save_watermark(&Watermark { pending: None, ..stored }).await?;
+        }
+        self.watermark = stored.checkpoint_hi_inclusive;
+        Ok(self.watermark)
+    }
+
+    async fn write(&mut self, sequence_number: u64, timestamp_ms: i64, events: Vec<UnxvEvent>) -> anyhow::Result<()> {
+        let date = utc_date(timestamp_ms);
+        if !events.is_empty() {
+            if self.date.is_some_and(|d| d != date) && !self.rows.is_empty() {
+                self.flush().await?;
+            }
+            self.date = Some(date);
+        }
+        self.bytes += events.iter().map(approx_size).sum::<usize>();
+        self.rows.extend(events);
+        let first = self.range.map_or(sequence_number, |(first, _)| first);
+        self.range = Some((first, sequence_number));
+
+        if self.bytes >= self.config.target_bytes || sequence_number - first + 1 >= self.config.max_checkpoints {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        let Some((first, last)) = self.range else { return Ok(()) };
+        if let Some(date) = self.date.filter(|_| !self.rows.is_empty()) {
+            let file = format!("date={date}/{first:012}_{last:012}.parquet");
+            let bytes = self.encode()?;
+            let size = bytes.len();
+            self.save_watermark(&Watermark { checkpoint_hi_inclusive: self.watermark, pending: Some(file.clone()) })
+                .await?;
+            self.config.store.put(&ObjectPath::from(file.as_str()), PutPayload::from(bytes)).await?;
+            info!(file, rows = self.rows.len(), bytes = size, "Wrote {}", self.config.location);
+        }
+        self.save_watermark(&Watermark { checkpoint_hi_inclusive: Some(last), pending: None }).await?;
+        self.watermark = Some(last);
+        self.rows.clear();
+        self.bytes = 0;
+        self.range = None;
+        self.date = None;
+        Ok(())
+    }
+}
+
+/// Outcome of [`run`].
+#[derive(Debug, Default)]
+pub struct SinkSummary {
+    pub checkpoints: u64,
+    pub events: usize,
+    /// Events `process` set aside as dead letters, which only the Postgres sink stores.
+    pub dead_letters: usize,
+}
+
+/// Feed checkpoints from `source` through `handler`'s `process` into `sink`, from just past
+/// the sink's watermark (or `first` on a fresh sink) to `last`, or until cancelled when there
+/// is no `last`. Checkpoints not published yet are waited for. Buffered rows are flushed on
+/// the way out, cancelled or not.
+pub async fn run<S: EventSink>(
+    source: &CheckpointSource,
+    handler: &UnxvEventsHandler,
+    sink: &mut S,
+    first: Option<u64>,
+    last: Option<u64>,
+    fetch_concurrency: usize,
+    cancel: CancellationToken,
+) -> anyhow::Result<SinkSummary> {
+    let start = match (sink.watermark().await?, first) {
+        (Some(watermark), _) => watermark + 1,
+        (None, Some(first)) => first,
+        (None, None) => anyhow::bail!("The sink has no watermark yet; pass --first-checkpoint"),
+    };
+    info!(start, ?last, "Starting the event sink");
+
+    let client = reqwest::Client::new();
+    let mut summary = SinkSummary::default();
+    let mut next = start;
+    let mut fetches: VecDeque<JoinHandle<anyhow::Result<CheckpointData>>> = VecDeque::new();
+    loop {
+        while fetches.len() < fetch_concurrency && last.map_or(true, |last| next <= last) {
+            fetches.push_back(tokio::spawn(fetch(source.clone(), client.clone(), next, cancel.clone())));
+            next += 1;
+        }
+        let Some(fetching) = fetches.pop_front() else { break };
+        let checkpoint = tokio::select! {
+            _ = cancel.cancelled() => break,
+            checkpoint = fetching => checkpoint??,
+        };
+        let sequence_number = checkpoint.checkpoint_summary.sequence_number;
+        let timestamp_ms = checkpoint.checkpoint_summary.timestamp_ms as i64;
+
+        let mut events = vec![];
+        for row in handler.process(&Arc::new(checkpoint))? {
+            match row {
+                EventRow::Event(event) => events.push(event),
+                EventRow::DeadLetter(_) => summary.dead_letters += 1,
+                EventRow::Checkpoint(_) => {}
+            }
+        }
+        summary.events += events.len();
+        sink.write(sequence_number, timestamp_ms, events).await?;
+        summary.checkpoints += 1;
+    }
+    for fetching in fetches {
+        fetching.abort();
+    }
+    sink.flush().await?;
+    Ok(summary)
+}
+
+/// Checkpoint `sequence_number`, waiting until it is published or `cancel` fires.
+async fn fetch(
+    source: CheckpointSource,
+    client: reqwest::Client,
+    sequence_number: u64,
+    cancel: CancellationToken,
+) -> anyhow::Result<CheckpointData> {
+    loop {
+        match source.fetch(&client, sequence_number).await {
+            Ok(checkpoint) => return Ok(checkpoint),
+            Err(e) => debug!(sequence_number, "Checkpoint not available yet: {e:#}"),
+        }
+        tokio::select! {
+            _ = cancel.cancelled() => anyhow::bail!("Cancelled while fetching checkpoint {sequence_number}"),
+            _ = tokio::time::sleep(FETCH_RETRY_INTERVAL) => {}
+        }
+    }
+}
@@ -0,0 +1,348 @@
+//! In-memory live statistics for the public status page. Fed by the `unxv_events` pipeline
+//! without touching the database; hourly snapshots are persisted to `live_stats_history` so
+//! "today" and the 24h sparkline survive restarts.
+
+use chrono::{DateTime, DurationRound, NaiveDate, TimeDelta, Utc};
+use diesel::sql_types::{BigInt, Date, Jsonb, Nullable, Timestamptz};
+use diesel::QueryableByName;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use sui_pg_db::Db;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::alerts::{Alert, AlertSender, Severity};
+
+/// Window of the events-per-second rate.
+const RATE_WINDOW_SECS: i64 = 60;
+/// Hourly snapshots kept in memory for the sparkline.
+const HISTORY_HOURS: usize = 24;
+
+/// Hourly snapshot, as stored in `live_stats_history`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HourlySnapshot {
+    pub hour: DateTime<Utc>,
+    pub day: NaiveDate,
+    pub checkpoints_today: u64,
+    pub events_today: u64,
+    pub lag_ms: Option<i64>,
+    pub per_module: BTreeMap<String, u64>,
+}
+
+/// What `GET /stats/live` returns.
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveSnapshot {
+    pub day: NaiveDate,
+    pub checkpoints_today: u64,
+    pub events_today: u64,
+    pub latest_checkpoint: Option<u64>,
+    /// Wall clock minus the latest checkpoint's timestamp.
+    pub lag_ms: Option<i64>,
+    pub events_per_second: f64,
+    /// Events today per module.
+    pub per_module: BTreeMap<String, u64>,
+    /// Up to the last 24 hourly snapshots, oldest first.
+    pub history: Vec<HourlySnapshot>,
+}
+
+/// Counters for one UTC day.
+#[derive(Default, Clone)]
+struct DayTotals {
+    day: Option<NaiveDate>,
+    checkpoints: u64,
+    events: u64,
+    per_module: BTreeMap<String, u64>,
+}
+
+#[derive(Default)]
+struct State {
+    today: DayTotals,
+    /// The day before `today`, kept so the snapshot closing its last hour is still complete
+    /// after midnight has rolled the counters.
+    previous: DayTotals,
+    latest_checkpoint: Option<u64>,
+    latest_timestamp_ms: Option<i64>,
+    /// `(unix second, events)`, oldest first, covering at most [`RATE_WINDOW_SECS`].
+    per_second: VecDeque<(i64, u64)>,
+    history: VecDeque<HourlySnapshot>,
+}
+
+impl State {
+    /// Start a new day's counters once the UTC date has moved on.
+    fn roll(&mut self, today: NaiveDate) {
+        if self.today.day != Some(today) {
+            let fresh = DayTotals { day: Some(today), ..Default::default() };
+            self.previous = std::mem::replace(&mut self.today, fresh);
+        }
+    }
+
+    /// Totals of `day`, if they are still held.
+    fn totals(&self, day: NaiveDate) -> DayTotals {
+        [&self.today, &self.previous]
+            .into_iter()
+            .find(|t| t.day == Some(day))
+            .cloned()
+            .unwrap_or(DayTotals { day: Some(day), ..Default::default() })
+    }
+
+    fn trim_rate(&mut self, now_secs: i64) {
+        while self.per_second.front().is_some_and(|(s, _)| *s <= now_secs - RATE_WINDOW_SECS) {
+            self.per_second.pop_front();
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LiveStats {
+    state: Mutex<State>,
+}
+
+/// The process-wide aggregator. Pipeline commits are associated functions without access to
+/// the handler, so they reach it through here.
+pub fn global() -> &'static LiveStats {
+    static STATS: OnceLock<LiveStats> = OnceLock::new();
+    STATS.get_or_init(LiveStats::default)
+}
+
+impl LiveStats {
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn record_checkpoint(&self, sequence_number: u64, timestamp_ms: i64, now: DateTime<Utc>) {
+        let mut state = self.state();
+        state.roll(now.date_naive());
+        state.today.checkpoints += 1;
+        if state.latest_checkpoint.map_or(true, |latest| sequence_number > latest) {
+            state.latest_checkpoint = Some(sequence_number);
+            state.latest_timestamp_ms = Some(timestamp_ms);
+        }
+    }
+
+    /// Count committed events, one module name per event.
+    pub fn record_events<'a>(&self, modules: impl IntoIterator<Item = &'a str>, now: DateTime<Utc>) {
+        let mut state = self.state();
+        state.roll(now.date_naive());
+        let mut n = 0;
+        for module in modules {
+            *state.today.per_module.entry(module.to_string()).or_default() += 1;
+            n += 1;
+        }
+        state.today.events += n;
+        let now_secs = now.timestamp();
+        match state.per_second.back_mut() {
+            Some((s, count)) if *s == now_secs => *count += n,
+            _ => state.per_second.push_back((now_secs, n)),
+        }
+        state.trim_rate(now_secs);
+    }
+
+    pub fn snapshot(&self, now: DateTime<Utc>) -> LiveSnapshot {
+        let mut state = self.state();
+        state.roll(now.date_naive());
+        state.trim_rate(now.timestamp());
+        let events: u64 = state.per_second.iter().map(|(_, n)| n).sum();
+        LiveSnapshot {
+            day: now.date_naive(),
+            checkpoints_today: state.today.checkpoints,
+            events_today: state.today.events,
+            latest_checkpoint: state.latest_checkpoint,
+            lag_ms: state.latest_timestamp_ms.map(|ts| now.timestamp_millis() - ts),
+            events_per_second: events as f64 / RATE_WINDOW_SECS as f64,
+            per_module: state.today.per_module.clone(),
+            history: state.history.iter().cloned().collect(),
+        }
+    }
+
+    /// Close the hour starting at `hour`, with the totals of that hour's day.
+    fn hourly(&self, hour: DateTime<Utc>, now: DateTime<Utc>) -> HourlySnapshot {
+        let mut state = self.state();
+        state.roll(now.date_naive());
+        let totals = state.totals(hour.date_naive());
+        let snapshot = HourlySnapshot {
+            hour,
+            day: hour.date_naive(),
+            checkpoints_today: totals.checkpoints,
+            events_today: totals.events,
+            lag_ms: state.latest_timestamp_ms.map(|ts| now.timestamp_millis() - ts),
+            per_module: totals.per_module,
+        };
+        state.history.push_back(snapshot.clone());
+        while state.history.len() > HISTORY_HOURS {
+            state.history.pop_front();
+        }
+        snapshot
+    }
+
+    /// Seed from persisted snapshots (oldest first): the sparkline, and today's counters from
+    /// the latest snapshot of today, so a restart doesn't zero the day.
+    fn restore(&self, history: Vec<HourlySnapshot>, now: DateTime<Utc>) {
+        let mut state = self.state();
+        state.roll(now.date_naive());
+        if let Some(last) = history.last().filter(|s| s.day == now.date_naive()) {
+            state.today.checkpoints += last.checkpoints_today;
+            state.today.events += last.events_today;
+            for (module, n) in &last.per_module {
+                *state.today.per_module.entry(module.clone()).or_default() += n;
+            }
+        }
+        state.history = history.into_iter().rev().take(HISTORY_HOURS).rev().collect();
+    }
+}
+
+#[derive(QueryableByName)]
+struct HistoryRow {
+    #[diesel(sql_type = Timestamptz)]
+    hour: DateTime<Utc>,
+    #[diesel(sql_type = Date)]
+    day: NaiveDate,
+    #[diesel(sql_type = BigInt)]
+    checkpoints_today: i64,
+    #[diesel(sql_type = BigInt)]
+    events_today: i64,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    lag_ms: Option<i64>,
+    #[diesel(sql_type = Jsonb)]
+    per_module: serde_json::Value,
+}
+
+async fn load_history(db: &Db) -> anyhow::Result<Vec<HourlySnapshot>> {
+    let mut conn = db.connect().await?;
+    let rows: Vec<HistoryRow> = diesel::sql_query(
+        "SELECT hour, day, checkpoints_today, events_today, lag_ms, per_module FROM live_stats_history \
+         WHERE hour > now() - interval '24 hours' ORDER BY hour",
+    )
+    .load(&mut conn)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|r| HourlySnapshot {
+            hour: r.hour,
+            day: r.day,
+            checkpoints_today: r.checkpoints_today as u64,
+            events_today: r.events_today as u64,
+            lag_ms: r.lag_ms,
+            per_module: serde_json::from_value(r.per_module).unwrap_or_default(),
+        })
+        .collect())
+}
+
+async fn persist(db: &Db, s: &HourlySnapshot) -> anyhow::Result<()> {
+    let mut conn = db.connect().await?;
+    diesel::sql_query(
+        "INSERT INTO live_stats_history (hour, day, checkpoints_today, events_today, lag_ms, per_module) \
+         VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT (hour) DO UPDATE SET \
+         day = excluded.day, checkpoints_today = excluded.checkpoints_today, events_today = excluded.events_today, \
+         lag_ms = excluded.lag_ms, per_module = excluded.per_module",
+    )
+    .bind::<Timestamptz, _>(s.hour)
+    .bind::<Date, _>(s.day)
+    .bind::<BigInt, _>(s.checkpoints_today as i64)
+    .bind::<BigInt, _>(s.events_today as i64)
+    .bind::<Nullable<BigInt>, _>(s.lag_ms)
+    .bind::<Jsonb, _>(serde_json::to_value(&s.per_module)?)
+    .execute(&mut conn)
+    .await?;
+    Ok(())
+}
+
+/// Restore from `live_stats_history`, then persist a snapshot at the top of every hour until
+/// cancelled.
+pub async fn run_snapshots(db: Db, alerts: AlertSender, cancel: CancellationToken) {
+    match load_history(&db).await {
+        Ok(history) => {
+            info!(snapshots = history.len(), "Restored live stats history");
+            global().restore(history, Utc::now());
+        }
+        Err(e) => warn!("Failed to restore live stats history: {e:#}"),
+    }
+    loop {
+        let now = Utc::now();
+        let next = now.duration_trunc(TimeDelta::hours(1)).unwrap_or(now) + TimeDelta::hours(1);
+        let wait = (next - now).to_std().unwrap_or(Duration::from_secs(1));
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = tokio::time::sleep(wait) => {}
+        }
+        // Stamped with the hour it closes, so the 23:00 snapshot carries that day's totals.
+        let hour = next - TimeDelta::hours(1);
+        let snapshot = global().hourly(hour, Utc::now());
+        if let Err(e) = persist(&db, &snapshot).await {
+            alerts.send(Alert::new(
+                "stats",
+                Severity::Warning,
+                "stats:snapshot_failed",
+                format!("Failed to persist live stats snapshot: {e:#}"),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestDb;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, min: u32, sec: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, day, hour, min, sec).unwrap()
+    }
+
+    #[test]
+    fn midnight_rolls_the_day_but_closes_the_last_hour_complete() {
+        let stats = LiveStats::default();
+        stats.record_checkpoint(100, at(1, 23, 30, 0).timestamp_millis(), at(1, 23, 30, 0));
+        stats.record_events(["dex", "dex", "lending"], at(1, 23, 30, 0));
+
+        // The snapshot task wakes just after midnight to close 23:00.
+        let closed = stats.hourly(at(1, 23, 0, 0), at(2, 0, 0, 1));
+        assert_eq!(closed.day, at(1, 0, 0, 0).date_naive());
+        assert_eq!((closed.checkpoints_today, closed.events_today), (1, 3));
+        assert_eq!(closed.per_module, BTreeMap::from([("dex".to_string(), 2), ("lending".to_string(), 1)]));
+
+        let live = stats.snapshot(at(2, 0, 0, 1));
+        assert_eq!(live.day, at(2, 0, 0, 0).date_naive());
+        assert_eq!((live.checkpoints_today, live.events_today), (0, 0));
+        assert!(live.per_module.is_empty());
+        assert_eq!(live.latest_checkpoint, Some(100));
+        assert_eq!(live.history.len(), 1);
+
+        stats.record_checkpoint(101, at(2, 0, 0, 2).timestamp_millis(), at(2, 0, 0, 2));
+        stats.record_events(["vault"], at(2, 0, 0, 2));
+        let live = stats.snapshot(at(2, 0, 0, 3));
+        assert_eq!((live.checkpoints_today, live.events_today), (1, 1));
+        assert_eq!(live.per_module, BTreeMap::from([("vault".to_string(), 1)]));
+    }
+
+    #[tokio::test]
+    async fn a_restart_continues_today_from_the_history_table() {
+        let db = TestDb::new().await;
+        let now = Utc::now();
+        let hour = now.duration_trunc(TimeDelta::hours(1)).unwrap();
+
+        let before = LiveStats::default();
+        before.record_checkpoint(7, now.timestamp_millis(), now);
+        before.record_checkpoint(8, now.timestamp_millis(), now);
+        before.record_events(["dex", "dex", "lending"], now);
+        persist(db.db(), &before.hourly(hour, now)).await.unwrap();
+        // Older than the sparkline's 24 hours: not restored.
+        let stale = HourlySnapshot { hour: hour - TimeDelta::hours(30), ..before.hourly(hour, now) };
+        persist(db.db(), &stale).await.unwrap();
+
+        let after = LiveStats::default();
+        after.restore(load_history(db.db()).await.unwrap(), now);
+        let live = after.snapshot(now);
+        assert_eq!((live.checkpoints_today, live.events_today), (2, 3));
+        assert_eq!(live.per_module, BTreeMap::from([("dex".to_string(), 2), ("lending".to_string(), 1)]));
+        assert_eq!(live.history.iter().map(|s| s.hour).collect::<Vec<_>>(), [hour]);
+
+        // Counting carries on from the restored totals.
+        after.record_events(["dex"], now);
+        let live = after.snapshot(now);
+        assert_eq!(live.events_today, 4);
+        assert_eq!(live.per_module["dex"], 3);
+    }
+}
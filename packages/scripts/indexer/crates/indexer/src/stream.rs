@@ -0,0 +1,182 @@
+//! In-process feed of committed `unxv_events` rows for the `--stream-address` SSE endpoint, so
+//! subscribers are pushed new events instead of polling Postgres.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
+use tokio::sync::broadcast;
+
+use unxv_schema::models::UnxvEvent;
+use unxv_schema::query::EventCursor;
+
+static EVENTS: OnceLock<broadcast::Sender<Arc<UnxvEvent>>> = OnceLock::new();
+
+/// Start publishing committed events, each subscriber buffering up to `capacity` of them.
+/// Commits are associated functions without access to the handler, so they look the channel
+/// up here.
+pub fn enable(capacity: usize) {
+    let _ = EVENTS.set(broadcast::channel(capacity.max(1)).0);
+}
+
+/// The channel, once [`enable`]d.
+pub fn sender() -> Option<broadcast::Sender<Arc<UnxvEvent>>> {
+    EVENTS.get().cloned()
+}
+
+/// Hand committed events to the subscribers. Never blocks: a subscriber that has fallen
+/// `capacity` events behind loses its oldest ones, and nobody else is affected.
+pub fn publish<'a>(events: impl IntoIterator<Item = &'a UnxvEvent>) {
+    let Some(tx) = EVENTS.get() else { return };
+    if tx.receiver_count() == 0 {
+        return;
+    }
+    for event in events {
+        let _ = tx.send(Arc::new(event.clone()));
+    }
+}
+
+/// A subscriber's `module` and `event_type` query parameters, each a comma-separated list.
+/// An absent or empty parameter matches everything.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    modules: Option<HashSet<String>>,
+    event_types: Option<HashSet<String>>,
+}
+
+impl Filter {
+    pub fn new(modules: Option<&str>, event_types: Option<&str>) -> Self {
+        let list = |spec: Option<&str>, lowercase: bool| {
+            let set: HashSet<String> = spec?
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| if lowercase { s.to_ascii_lowercase() } else { s.to_string() })
+                .collect();
+            (!set.is_empty()).then_some(set)
+        };
+        Self { modules: list(modules, true), event_types: list(event_types, false) }
+    }
+
+    pub fn matches(&self, event: &UnxvEvent) -> bool {
+        self.modules.as_ref().map_or(true, |m| m.contains(&event.module))
+            && self.event_types.as_ref().map_or(true, |t| t.contains(&event.event_type))
+    }
+
+    /// Lowercased modules to match; None for any.
+    pub fn modules(&self) -> Option<&HashSet<String>> {
+        self.modules.as_ref()
+    }
+
+    /// Event struct names to match; None for any.
+    pub fn event_types(&self) -> Option<&HashSet<String>> {
+        self.event_types.as_ref()
+    }
+}
+
+/// An event of an ordered stream, as sent.
+#[derive(Debug, Serialize)]
+pub struct Sequenced<'a> {
+    /// Events sent on this connection so far, this one included.
+    pub seq: u64,
+    /// Events of this one's market (its `emitter_object`) sent on this connection so far, this
+    /// one included; absent for events without one.
+    pub market_seq: Option<u64>,
+    /// Position of the event, to resume from.
+    pub cursor: String,
+    pub event: &'a UnxvEvent,
+}
+
+/// Latest position of an ordered stream, sent on the `live` marker and on heartbeats.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct Position {
+    pub seq: u64,
+    /// Cursor of the last event sent; absent before the first.
+    pub cursor: Option<String>,
+}
+
+/// Numbers the events of one ordered connection, overall and per market, so a client can tell
+/// when it missed any. Numbers start at 1 for every connection, resumed or not: they detect
+/// gaps within a connection, and the cursor carries position across connections.
+#[derive(Debug, Default)]
+pub struct Sequencer {
+    seq: u64,
+    markets: HashMap<String, u64>,
+    cursor: Option<String>,
+}
+
+impl Sequencer {
+    pub fn next<'a>(&mut self, event: &'a UnxvEvent) -> Sequenced<'a> {
+        self.seq += 1;
+        let market_seq = event.emitter_object.as_ref().map(|market| {
+            let seq = self.markets.entry(market.clone()).or_default();
+            *seq += 1;
+            *seq
+        });
+        let cursor = EventCursor::from(event).encode();
+        self.cursor = Some(cursor.clone());
+        Sequenced { seq: self.seq, market_seq, cursor, event }
+    }
+
+    pub fn position(&self) -> Position {
+        Position { seq: self.seq, cursor: self.cursor.clone() }
+    }
+}
+
+/// Events a resumed connection replayed from the database. Its subscription starts before the
+/// replay, so an event committed meanwhile can be both replayed and received live: the live
+/// copy is dropped. Holds one key per replayed event for the life of the connection.
+#[derive(Debug, Default)]
+pub struct Replayed(HashSet<(String, i64)>);
+
+impl Replayed {
+    pub fn insert(&mut self, event: &UnxvEvent) {
+        self.0.insert((event.digest.clone(), event.event_index));
+    }
+
+    /// Whether a live `event` wasn't replayed. Each event is received live at most once, so a
+    /// match is forgotten.
+    pub fn is_new(&mut self, event: &UnxvEvent) -> bool {
+        !self.0.remove(&(event.digest.clone(), event.event_index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::stored_event;
+
+    #[test]
+    fn events_are_numbered_overall_and_per_market() {
+        let pool = |digest: &str, pool: &str| UnxvEvent {
+            emitter_object: Some(pool.to_string()),
+            ..stored_event(digest, "dex", 4, 4_000)
+        };
+        let events = [pool("a", "0x1"), pool("b", "0x2"), stored_event("c", "vault", 5, 5_000), pool("d", "0x1")];
+        let mut sequencer = Sequencer::default();
+        assert_eq!(sequencer.position(), Position { seq: 0, cursor: None });
+
+        let sent: Vec<(u64, Option<u64>, String)> =
+            events.iter().map(|e| sequencer.next(e)).map(|s| (s.seq, s.market_seq, s.cursor)).collect();
+        assert_eq!(
+            sent,
+            [
+                (1, Some(1), "4:a:0".to_string()),
+                (2, Some(1), "4:b:0".to_string()),
+                (3, None, "5:c:0".to_string()),
+                (4, Some(2), "4:d:0".to_string()),
+            ]
+        );
+        assert_eq!(sequencer.position(), Position { seq: 4, cursor: Some("4:d:0".into()) });
+    }
+
+    #[test]
+    fn replayed_events_are_dropped_once_when_received_live() {
+        let (a, b) = (stored_event("a", "dex", 4, 4_000), stored_event("b", "dex", 4, 4_000));
+        let mut replayed = Replayed::default();
+        replayed.insert(&a);
+        assert!(!replayed.is_new(&a));
+        assert!(replayed.is_new(&b));
+        // A second live copy is a recommit, which the stream passes on as usual.
+        assert!(replayed.is_new(&a));
+    }
+}
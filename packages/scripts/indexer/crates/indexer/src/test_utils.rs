@@ -0,0 +1,215 @@
+//! Test support: synthetic checkpoints, checkpoint fixtures and a throwaway database with the
+//! indexer's migrations applied.
+
+use diesel::sql_types::{BigInt, Text};
+use diesel::QueryableByName;
+use diesel_async::RunQueryDsl;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use sui_pg_db::temp::TempDb;
+use sui_pg_db::{Db, DbArgs};
+use sui_types::base_types::{ObjectID, SuiAddress};
+use sui_types::event::Event;
+use sui_types::full_checkpoint_content::CheckpointData;
+use sui_types::test_checkpoint_data_builder::TestCheckpointDataBuilder;
+use sui_types::TypeTag;
+use unxv_schema::models::UnxvEvent;
+use unxv_schema::schema::unxv_events;
+use unxv_schema::MIGRATIONS;
+
+use crate::inspect::{CheckpointSource, BLOB_ENCODING_BCS};
+use crate::parse_struct_tag;
+
+/// Package the synthetic Unxversal events are defined in.
+pub const PACKAGE: &str = "0xa11ce";
+
+/// An event of struct type `tag` (e.g. `0xa11ce::dex::ProtocolFeeTaken`) with BCS `contents`,
+/// emitted by test sender `sender` from the struct's own module.
+pub fn event(sender: u8, tag: &str, contents: Vec<u8>) -> Event {
+    let type_ = parse_struct_tag(tag).expect("valid struct tag");
+    Event {
+        package_id: ObjectID::from(type_.address),
+        transaction_module: type_.module.clone(),
+        sender: sender_address(sender),
+        type_,
+        contents,
+    }
+}
+
+/// The address of test sender `sender`, as [`CheckpointBuilder::transaction`] signs with it.
+pub fn sender_address(sender: u8) -> SuiAddress {
+    TestCheckpointDataBuilder::derive_address(sender)
+}
+
+/// Builds a [`CheckpointData`] one transaction at a time.
+pub struct CheckpointBuilder {
+    inner: TestCheckpointDataBuilder,
+}
+
+impl CheckpointBuilder {
+    pub fn new(sequence_number: u64) -> Self {
+        Self { inner: TestCheckpointDataBuilder::new(sequence_number) }
+    }
+
+    /// Add a transaction from test sender `sender` making the Move `calls`
+    /// (`(package, module, function)`) and emitting `events`, in order.
+    pub fn transaction(self, sender: u8, calls: &[(&str, &str, &str)], events: Vec<Event>) -> Self {
+        let mut tx = self.inner.start_transaction(sender);
+        for (package, module, function) in calls {
+            tx = tx.add_move_call(ObjectID::from_hex_literal(package).expect("valid package id"), module, function);
+        }
+        if !events.is_empty() {
+            tx = tx.with_events(events);
+        }
+        Self { inner: tx.finish_transaction() }
+    }
+
+    /// Add a transaction from test sender `sender` creating a `0x2::coin::Coin` of each
+    /// `(object_idx, coin type, balance)`, owned by the sender. The coins' ids are
+    /// [`TestCheckpointDataBuilder::derive_object_id`] of their indexes.
+    pub fn coins(self, sender: u8, coins: &[(u64, &str, u64)]) -> Self {
+        let mut tx = self.inner.start_transaction(sender);
+        for (object_idx, coin_type, balance) in coins {
+            let coin_type = TypeTag::Struct(Box::new(parse_struct_tag(coin_type).expect("valid coin type")));
+            tx = tx.create_coin_object(*object_idx, sender, *balance, coin_type);
+        }
+        Self { inner: tx.finish_transaction() }
+    }
+
+    pub fn build(mut self) -> Arc<CheckpointData> {
+        Arc::new(self.inner.build_checkpoint())
+    }
+}
+
+/// Checkpoint files recorded from a live network, `{sequence_number}.chk` as the checkpoint
+/// stores serve them (see `tests/fixtures/README.md`).
+pub fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// Sequence numbers of the checkpoints in [`fixtures_dir`], in order.
+pub fn fixtures() -> Vec<u64> {
+    let mut checkpoints: Vec<u64> = std::fs::read_dir(fixtures_dir())
+        .expect("readable fixtures directory")
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            (path.extension()? == "chk").then_some(())?;
+            path.file_stem()?.to_str()?.parse().ok()
+        })
+        .collect();
+    checkpoints.sort_unstable();
+    checkpoints
+}
+
+/// Read checkpoint `sequence_number` from `dir` the way `inspect` and `repair` do.
+pub async fn load_checkpoint(dir: &Path, sequence_number: u64) -> Arc<CheckpointData> {
+    let source = CheckpointSource::Local(dir.to_path_buf());
+    Arc::new(source.fetch(&reqwest::Client::new(), sequence_number).await.expect("loadable checkpoint"))
+}
+
+/// Write `checkpoint` to `dir` in the checkpoint store's file format.
+pub fn write_checkpoint(dir: &Path, checkpoint: &CheckpointData) -> PathBuf {
+    let path = dir.join(format!("{}.chk", checkpoint.checkpoint_summary.sequence_number));
+    let mut bytes = vec![BLOB_ENCODING_BCS];
+    bytes.extend(bcs::to_bytes(checkpoint).expect("serializable checkpoint"));
+    std::fs::write(&path, bytes).expect("writable fixture");
+    path
+}
+
+/// An `unxv_events` row of a `PACKAGE` event in `module`, as stored, for seeding tables
+/// directly. Optional columns are NULL; set others with struct update syntax.
+pub fn stored_event(digest: &str, module: &str, checkpoint: i64, checkpoint_timestamp_ms: i64) -> UnxvEvent {
+    UnxvEvent {
+        digest: digest.to_string(),
+        event_index: 0,
+        sender: sender_address(1).to_string(),
+        checkpoint,
+        checkpoint_timestamp_ms,
+        package: crate::normalize_address(PACKAGE).expect("valid package"),
+        module: module.to_string(),
+        event_type: "Event".to_string(),
+        type_params: serde_json::json!([]),
+        contents_bcs: vec![],
+        contents_json: None,
+        indexer_version: None,
+        tx_timestamp_ms: None,
+        gas_used: None,
+        tx_success: None,
+        type_params_truncated: false,
+        type_hash: None,
+        call_packages: None,
+        is_new_sender: None,
+        type_param_0: None,
+        type_param_1: None,
+        type_params_key: None,
+        emitter_object: None,
+        contents_bcs_truncated: false,
+        event_type_full: None,
+    }
+}
+
+#[derive(QueryableByName)]
+struct Count {
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+/// A Postgres database of its own with the framework's and the indexer's migrations applied,
+/// removed on drop. Needs `initdb` and `postgres` on the PATH.
+pub struct TestDb {
+    // Declared first so the pool closes before the database stops.
+    db: Db,
+    temp: TempDb,
+}
+
+impl TestDb {
+    pub async fn new() -> Self {
+        let temp = TempDb::new().expect("Failed to start a temporary Postgres; are initdb and postgres on the PATH?");
+        let db = Db::for_write(temp.database().url().clone(), DbArgs::default())
+            .await
+            .expect("Failed to connect to the temporary database");
+        db.run_migrations(Some(&MIGRATIONS)).await.expect("Failed to run migrations");
+        Self { db, temp }
+    }
+
+    pub fn db(&self) -> &Db {
+        &self.db
+    }
+
+    /// For clients connecting on their own.
+    pub fn url(&self) -> &url::Url {
+        self.temp.database().url()
+    }
+
+    /// Rows in `table`.
+    pub async fn count(&self, table: &str) -> i64 {
+        let mut conn = self.db.connect().await.expect("connection");
+        diesel::sql_query(format!("SELECT COUNT(*) AS count FROM {table}"))
+            .get_result::<Count>(&mut conn)
+            .await
+            .expect("count query")
+            .count
+    }
+
+    /// Insert `events` into `unxv_events` as they are.
+    pub async fn insert_events(&self, events: &[UnxvEvent]) {
+        let mut conn = self.db.connect().await.expect("connection");
+        diesel::insert_into(unxv_events::table).values(events).execute(&mut conn).await.expect("insert events");
+    }
+
+    /// Set `pipeline`'s committed watermark to `checkpoint`, as the framework's committer does.
+    pub async fn set_watermark(&self, pipeline: &str, checkpoint: i64) {
+        let mut conn = self.db.connect().await.expect("connection");
+        diesel::sql_query(
+            "INSERT INTO watermarks (pipeline, epoch_hi_inclusive, checkpoint_hi_inclusive, tx_hi, \
+             timestamp_ms_hi_inclusive, reader_lo, pruner_timestamp, pruner_hi) \
+             VALUES ($1, 0, $2, 0, 0, 0, now(), 0) \
+             ON CONFLICT (pipeline) DO UPDATE SET checkpoint_hi_inclusive = EXCLUDED.checkpoint_hi_inclusive",
+        )
+        .bind::<Text, _>(pipeline)
+        .bind::<BigInt, _>(checkpoint)
+        .execute(&mut conn)
+        .await
+        .expect("set watermark");
+    }
+}
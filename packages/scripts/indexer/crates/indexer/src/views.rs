@@ -0,0 +1,159 @@
+use anyhow::Context;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, SimpleAsyncConnection};
+use std::path::{Path, PathBuf};
+use sui_pg_db::Db;
+use tracing::info;
+
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct ViewArgs {
+    /// Directory of `*.sql` files of `CREATE OR REPLACE VIEW` statements, applied in file name
+    /// order after migrations on every startup (default: none)
+    #[clap(env, long)]
+    pub views_dir: Option<PathBuf>,
+}
+
+/// A view definition file.
+#[derive(Debug)]
+pub struct ViewFile {
+    pub path: PathBuf,
+    pub sql: String,
+}
+
+/// Lines starting with `--` removed, the rest kept as is.
+fn strip_comments(sql: &str) -> String {
+    sql.lines()
+        .filter(|line| !line.trim_start().starts_with("--"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Every statement must be a `CREATE OR REPLACE VIEW`, so applying a file twice is harmless
+/// and a file can't smuggle in other DDL. Statements are split on `;`, which therefore can't
+/// appear inside string literals.
+fn check_statements(sql: &str) -> anyhow::Result<usize> {
+    let mut n = 0;
+    for statement in strip_comments(sql).split(';').map(str::trim).filter(|s| !s.is_empty()) {
+        let words: Vec<String> = statement.split_whitespace().take(4).map(str::to_ascii_lowercase).collect();
+        anyhow::ensure!(
+            words == ["create", "or", "replace", "view"],
+            "Only CREATE OR REPLACE VIEW statements are allowed, found: {}",
+            statement.lines().next().unwrap_or_default()
+        );
+        n += 1;
+    }
+    anyhow::ensure!(n > 0, "No CREATE OR REPLACE VIEW statement");
+    Ok(n)
+}
+
+/// The `*.sql` files of `dir`, sorted by file name and checked.
+pub fn load(dir: &Path) -> anyhow::Result<Vec<ViewFile>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read views directory {}", dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    paths.retain(|p| p.extension().is_some_and(|ext| ext == "sql"));
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let sql = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            check_statements(&sql).with_context(|| format!("Invalid view file {}", path.display()))?;
+            Ok(ViewFile { path, sql })
+        })
+        .collect()
+}
+
+/// Apply `files` in one transaction: either every view is (re)created or, if any file fails
+/// to load in Postgres, none is and the error names the file.
+pub async fn apply(db: &Db, files: &[ViewFile]) -> anyhow::Result<()> {
+    if files.is_empty() {
+        return Ok(());
+    }
+    let mut conn = db.connect().await?;
+    conn.transaction(|conn| {
+        async move {
+            for file in files {
+                conn.batch_execute(&file.sql)
+                    .await
+                    .with_context(|| format!("Failed to apply view file {}", file.path.display()))?;
+            }
+            Ok::<_, anyhow::Error>(())
+        }
+        .scope_boxed()
+    })
+    .await?;
+    info!(files = files.len(), "Applied views");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestDb;
+    use diesel_async::RunQueryDsl;
+
+    /// The example views shipped with the indexer.
+    fn repo_views() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../../views")
+    }
+
+    /// Names of the views in the default schema.
+    async fn views(db: &TestDb) -> Vec<String> {
+        #[derive(diesel::QueryableByName)]
+        struct View {
+            #[diesel(sql_type = diesel::sql_types::Text)]
+            viewname: String,
+        }
+        let mut conn = db.db().connect().await.unwrap();
+        let views: Vec<View> =
+            diesel::sql_query("SELECT viewname::TEXT FROM pg_views WHERE schemaname = 'public' ORDER BY viewname")
+                .load(&mut conn)
+                .await
+                .unwrap();
+        views.into_iter().map(|v| v.viewname).collect()
+    }
+
+    #[tokio::test]
+    async fn shipped_views_exist_after_startup() {
+        let db = TestDb::new().await;
+        let files = load(&repo_views()).unwrap();
+        assert!(!files.is_empty());
+
+        // As on every startup, after the migrations; reapplying is harmless.
+        apply(db.db(), &files).await.unwrap();
+        apply(db.db(), &files).await.unwrap();
+        assert!(views(&db).await.contains(&"dex_trades_daily".to_string()));
+        assert_eq!(db.count("dex_trades_daily").await, 0);
+    }
+
+    #[tokio::test]
+    async fn a_failing_file_applies_no_view() {
+        let db = TestDb::new().await;
+        let before = views(&db).await;
+        let mut files = load(&repo_views()).unwrap();
+        let broken = "CREATE OR REPLACE VIEW dex_trades_broken AS SELECT missing FROM dex_trades";
+        files.push(ViewFile { path: PathBuf::from("zz_broken.sql"), sql: broken.to_string() });
+
+        let err = apply(db.db(), &files).await.unwrap_err();
+        assert!(format!("{err:#}").contains("zz_broken.sql"), "{err:#}");
+        assert_eq!(views(&db).await, before);
+    }
+
+    #[test]
+    fn only_create_or_replace_view_statements_are_accepted() {
+        assert_eq!(
+            check_statements("-- daily\ncreate or replace view a AS SELECT 1;\nCREATE OR REPLACE VIEW b AS SELECT 2")
+                .unwrap(),
+            2
+        );
+        for sql in [
+            "",
+            "-- nothing",
+            "CREATE VIEW a AS SELECT 1",
+            "CREATE OR REPLACE VIEW a AS SELECT 1; DROP TABLE unxv_events",
+        ] {
+            assert!(check_statements(sql).is_err(), "{sql:?}");
+        }
+    }
+}
@@ -0,0 +1,3 @@
+UPDATE unxv_events
+SET event_digest = digest || substr(event_digest, length(digest) + 2)
+WHERE position(':' in event_digest) > 0;
@@ -0,0 +1,5 @@
+-- Event keys used to be `{digest}{idx}`, which is ambiguous (digest "A" + 12 vs "A1" + 2).
+-- The tx digest is stored alongside, so existing keys can be rewritten exactly.
+UPDATE unxv_events
+SET event_digest = digest || ':' || substr(event_digest, length(digest) + 1)
+WHERE position(':' in event_digest) = 0;
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS unxv_referrals (
+    event_digest TEXT PRIMARY KEY,
+    digest TEXT NOT NULL,
+    checkpoint BIGINT NOT NULL,
+    checkpoint_timestamp_ms BIGINT NOT NULL,
+    package TEXT NOT NULL,
+    referrer TEXT NOT NULL,
+    referee TEXT NOT NULL,
+    reward_amount BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS idx_unxv_referrals_referrer ON unxv_referrals (referrer);
+CREATE INDEX IF NOT EXISTS idx_unxv_referrals_referee ON unxv_referrals (referee);
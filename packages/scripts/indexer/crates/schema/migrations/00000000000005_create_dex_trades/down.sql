@@ -0,0 +1 @@
+DROP TABLE IF EXISTS dex_trades;
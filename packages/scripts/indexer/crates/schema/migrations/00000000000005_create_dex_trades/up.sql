@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS dex_trades (
+    event_digest TEXT PRIMARY KEY,
+    digest TEXT NOT NULL,
+    checkpoint BIGINT NOT NULL,
+    checkpoint_timestamp_ms BIGINT NOT NULL,
+    package TEXT NOT NULL,
+    pool_id TEXT NOT NULL,
+    maker TEXT NOT NULL,
+    taker TEXT NOT NULL,
+    price BIGINT NOT NULL,
+    base_quantity BIGINT NOT NULL,
+    quote_quantity BIGINT NOT NULL,
+    is_bid BOOLEAN NOT NULL,
+    timestamp_ms BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_dex_trades_pool_time ON dex_trades (pool_id, checkpoint_timestamp_ms);
+CREATE INDEX IF NOT EXISTS idx_dex_trades_checkpoint ON dex_trades (checkpoint);
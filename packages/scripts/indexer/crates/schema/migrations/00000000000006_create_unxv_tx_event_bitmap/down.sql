@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS unxv_tx_event_bitmap;
+DROP TABLE IF EXISTS unxv_event_types;
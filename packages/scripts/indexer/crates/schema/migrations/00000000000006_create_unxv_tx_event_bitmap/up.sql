@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS unxv_event_types (
+    id SERIAL PRIMARY KEY,
+    event_type TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS unxv_tx_event_bitmap (
+    digest TEXT PRIMARY KEY,
+    checkpoint BIGINT NOT NULL,
+    event_type_ids INTEGER[] NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_unxv_tx_event_bitmap_types ON unxv_tx_event_bitmap USING GIN (event_type_ids);
+CREATE INDEX IF NOT EXISTS idx_unxv_tx_event_bitmap_checkpoint ON unxv_tx_event_bitmap (checkpoint);
@@ -0,0 +1,2 @@
+-- Nullable, so adding it does not rewrite the table; filled by the next migration.
+ALTER TABLE unxv_events ADD COLUMN IF NOT EXISTS event_index BIGINT;
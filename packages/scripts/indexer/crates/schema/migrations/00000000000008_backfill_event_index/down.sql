@@ -0,0 +1,2 @@
+-- Nothing to undo: the column itself is dropped by 00000000000007's down migration.
+SELECT 1;
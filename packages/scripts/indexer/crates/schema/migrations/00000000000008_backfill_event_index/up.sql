@@ -0,0 +1,25 @@
+-- Split `{digest}:{idx}` keys into event_index in batches, walking the primary key and
+-- committing after each batch so large tables are never locked or bloated in one go.
+DO $$
+DECLARE
+    last_key TEXT := '';
+    batch_last TEXT;
+BEGIN
+    LOOP
+        WITH batch AS (
+            SELECT event_digest FROM unxv_events
+            WHERE event_digest > last_key
+            ORDER BY event_digest
+            LIMIT 50000
+        ), updated AS (
+            UPDATE unxv_events e
+            SET event_index = split_part(e.event_digest, ':', 2)::BIGINT
+            FROM batch b
+            WHERE e.event_digest = b.event_digest AND e.event_index IS NULL
+        )
+        SELECT max(event_digest) INTO batch_last FROM batch;
+        EXIT WHEN batch_last IS NULL;
+        last_key := batch_last;
+        COMMIT;
+    END LOOP;
+END $$;
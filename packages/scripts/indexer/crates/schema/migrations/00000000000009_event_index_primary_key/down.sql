@@ -0,0 +1,9 @@
+ALTER TABLE unxv_events ADD COLUMN event_digest TEXT;
+UPDATE unxv_events SET event_digest = digest || ':' || event_index;
+ALTER TABLE unxv_events ALTER COLUMN event_digest SET NOT NULL;
+ALTER TABLE unxv_events DROP CONSTRAINT unxv_events_pkey;
+ALTER TABLE unxv_events ADD PRIMARY KEY (event_digest);
+ALTER TABLE unxv_events ALTER COLUMN event_index DROP NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_unxv_events_checkpoint ON unxv_events (checkpoint);
+DROP INDEX IF EXISTS idx_unxv_events_checkpoint_order;
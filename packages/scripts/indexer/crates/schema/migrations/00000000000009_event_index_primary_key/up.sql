@@ -0,0 +1,8 @@
+ALTER TABLE unxv_events ALTER COLUMN event_index SET NOT NULL;
+ALTER TABLE unxv_events DROP CONSTRAINT unxv_events_pkey;
+ALTER TABLE unxv_events ADD PRIMARY KEY (digest, event_index);
+ALTER TABLE unxv_events DROP COLUMN event_digest;
+
+-- Ordered iteration by (checkpoint, tx, event_index); supersedes the plain checkpoint index.
+CREATE INDEX IF NOT EXISTS idx_unxv_events_checkpoint_order ON unxv_events (checkpoint, digest, event_index);
+DROP INDEX IF EXISTS idx_unxv_events_checkpoint;
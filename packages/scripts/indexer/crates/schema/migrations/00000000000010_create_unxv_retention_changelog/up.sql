@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS unxv_retention_changelog (
+    id BIGSERIAL PRIMARY KEY,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    policies JSONB NOT NULL
+);
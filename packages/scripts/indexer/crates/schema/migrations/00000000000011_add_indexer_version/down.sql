@@ -0,0 +1 @@
+ALTER TABLE unxv_events DROP COLUMN IF EXISTS indexer_version;
@@ -0,0 +1 @@
+ALTER TABLE unxv_events ADD COLUMN IF NOT EXISTS indexer_version TEXT;
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS unxv_dead_letters (
+    pipeline TEXT NOT NULL,
+    digest TEXT NOT NULL,
+    event_index BIGINT NOT NULL,
+    sender TEXT NOT NULL,
+    checkpoint BIGINT NOT NULL,
+    checkpoint_timestamp_ms BIGINT NOT NULL,
+    package TEXT NOT NULL,
+    module TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    type_params JSONB NOT NULL,
+    contents_bcs BYTEA NOT NULL,
+    reason TEXT NOT NULL,
+    PRIMARY KEY (pipeline, digest, event_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_unxv_dead_letters_checkpoint ON unxv_dead_letters (checkpoint);
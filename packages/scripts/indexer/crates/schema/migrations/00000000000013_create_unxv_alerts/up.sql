@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS unxv_alerts (
+    id BIGSERIAL PRIMARY KEY,
+    source TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    key TEXT NOT NULL,
+    message TEXT NOT NULL,
+    context JSONB NOT NULL,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    occurrences INTEGER NOT NULL DEFAULT 1,
+    acknowledged_at TIMESTAMPTZ,
+    acknowledged_by TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_unxv_alerts_unacknowledged ON unxv_alerts (first_seen_at) WHERE acknowledged_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_unxv_alerts_key ON unxv_alerts (key);
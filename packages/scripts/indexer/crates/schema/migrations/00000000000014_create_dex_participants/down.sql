@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS dex_daily_participants;
+DROP TABLE IF EXISTS dex_market_participants;
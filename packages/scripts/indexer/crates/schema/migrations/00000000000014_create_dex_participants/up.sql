@@ -0,0 +1,19 @@
+-- Staging: one row per distinct (market, UTC day, role, account). Inserted with ON CONFLICT DO
+-- NOTHING, so the row count per (pool_id, day, role) is the exact distinct participant count.
+CREATE TABLE IF NOT EXISTS dex_market_participants (
+    pool_id TEXT NOT NULL,
+    day DATE NOT NULL,
+    role TEXT NOT NULL,
+    account TEXT NOT NULL,
+    PRIMARY KEY (pool_id, day, role, account)
+);
+
+CREATE TABLE IF NOT EXISTS dex_daily_participants (
+    pool_id TEXT NOT NULL,
+    day DATE NOT NULL,
+    unique_makers BIGINT NOT NULL,
+    unique_takers BIGINT NOT NULL,
+    finalized_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    recounted_at TIMESTAMPTZ,
+    PRIMARY KEY (pool_id, day)
+);
@@ -0,0 +1 @@
+ALTER TABLE unxv_events DROP COLUMN IF EXISTS tx_timestamp_ms;
@@ -0,0 +1 @@
+ALTER TABLE unxv_events ADD COLUMN IF NOT EXISTS tx_timestamp_ms BIGINT;
@@ -0,0 +1,2 @@
+ALTER TABLE unxv_events DROP COLUMN IF EXISTS tx_success;
+ALTER TABLE unxv_events DROP COLUMN IF EXISTS gas_used;
@@ -0,0 +1,2 @@
+ALTER TABLE unxv_events ADD COLUMN IF NOT EXISTS gas_used BIGINT;
+ALTER TABLE unxv_events ADD COLUMN IF NOT EXISTS tx_success BOOLEAN;
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_unxv_events_type_params;
+DROP INDEX IF EXISTS idx_unxv_events_type_hash;
+ALTER TABLE unxv_events DROP COLUMN IF EXISTS type_hash;
+ALTER TABLE unxv_events DROP COLUMN IF EXISTS type_params_truncated;
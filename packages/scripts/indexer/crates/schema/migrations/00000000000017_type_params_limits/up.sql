@@ -0,0 +1,7 @@
+ALTER TABLE unxv_events ADD COLUMN IF NOT EXISTS type_params_truncated BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE unxv_events ADD COLUMN IF NOT EXISTS type_hash TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_unxv_events_type_hash ON unxv_events (type_hash);
+-- Truncated entries are only meant to be found by type_hash.
+CREATE INDEX IF NOT EXISTS idx_unxv_events_type_params ON unxv_events USING GIN (type_params)
+    WHERE NOT type_params_truncated;
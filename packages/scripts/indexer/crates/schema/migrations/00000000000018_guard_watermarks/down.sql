@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS unxv_guard_watermark ON watermarks;
+DROP FUNCTION IF EXISTS unxv_guard_watermark();
+ALTER TABLE watermarks DROP COLUMN IF EXISTS instance_seen_at;
+ALTER TABLE watermarks DROP COLUMN IF EXISTS instance_id;
@@ -0,0 +1,32 @@
+-- `watermarks` belongs to the framework; these additions only guard it. Each connection tags
+-- itself with the writing instance via `application_name`.
+ALTER TABLE watermarks ADD COLUMN IF NOT EXISTS instance_id TEXT;
+ALTER TABLE watermarks ADD COLUMN IF NOT EXISTS instance_seen_at TIMESTAMPTZ;
+
+-- Committer watermarks never move backwards: an update carrying a lower checkpoint keeps the
+-- existing committer fields, i.e. GREATEST(existing, new). Reader/pruner fields are untouched.
+CREATE OR REPLACE FUNCTION unxv_guard_watermark() RETURNS trigger AS $$
+DECLARE
+    instance TEXT := NULLIF(current_setting('application_name', true), '');
+BEGIN
+    IF TG_OP = 'UPDATE' AND NEW.checkpoint_hi_inclusive < OLD.checkpoint_hi_inclusive THEN
+        RAISE WARNING 'Watermark of % would regress from % to % (instance %, last written by %); keeping %',
+            NEW.pipeline, OLD.checkpoint_hi_inclusive, NEW.checkpoint_hi_inclusive,
+            instance, OLD.instance_id, OLD.checkpoint_hi_inclusive;
+        NEW.epoch_hi_inclusive := OLD.epoch_hi_inclusive;
+        NEW.checkpoint_hi_inclusive := OLD.checkpoint_hi_inclusive;
+        NEW.tx_hi := OLD.tx_hi;
+        NEW.timestamp_ms_hi_inclusive := OLD.timestamp_ms_hi_inclusive;
+    END IF;
+    IF TG_OP = 'INSERT' OR NEW.checkpoint_hi_inclusive IS DISTINCT FROM OLD.checkpoint_hi_inclusive
+        OR instance IS DISTINCT FROM OLD.instance_id THEN
+        NEW.instance_id := instance;
+        NEW.instance_seen_at := now();
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS unxv_guard_watermark ON watermarks;
+CREATE TRIGGER unxv_guard_watermark BEFORE INSERT OR UPDATE ON watermarks
+    FOR EACH ROW EXECUTE FUNCTION unxv_guard_watermark();
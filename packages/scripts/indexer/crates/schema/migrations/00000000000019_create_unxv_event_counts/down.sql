@@ -0,0 +1 @@
+DROP TABLE IF EXISTS unxv_event_counts;
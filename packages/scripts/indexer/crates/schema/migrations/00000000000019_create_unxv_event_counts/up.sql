@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS unxv_event_counts (
+    module TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    day DATE NOT NULL,
+    count BIGINT NOT NULL,
+    PRIMARY KEY (module, event_type, day)
+);
+
+CREATE INDEX IF NOT EXISTS idx_unxv_event_counts_day ON unxv_event_counts (day);
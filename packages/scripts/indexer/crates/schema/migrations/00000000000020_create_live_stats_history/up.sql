@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS live_stats_history (
+    hour TIMESTAMPTZ PRIMARY KEY,
+    day DATE NOT NULL,
+    checkpoints_today BIGINT NOT NULL,
+    events_today BIGINT NOT NULL,
+    lag_ms BIGINT,
+    per_module JSONB NOT NULL DEFAULT '{}'::jsonb
+);
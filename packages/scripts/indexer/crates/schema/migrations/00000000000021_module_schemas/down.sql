@@ -0,0 +1 @@
+DROP FUNCTION IF EXISTS unxv_ensure_module_schema(TEXT);
@@ -0,0 +1,37 @@
+-- Create (or bring up to date) `target.unxv_events` as a copy of the default schema's table, for
+-- modules whose events are written to their own schema. Indexes and defaults are copied when the
+-- table is created; columns added to the default table later are added on the next call.
+CREATE OR REPLACE FUNCTION unxv_ensure_module_schema(target TEXT) RETURNS void AS $$
+DECLARE
+    source TEXT := format('%I.unxv_events', current_schema());
+    c RECORD;
+BEGIN
+    IF target = current_schema() THEN
+        RAISE EXCEPTION 'Module schema % is the default schema', target;
+    END IF;
+    EXECUTE format('CREATE SCHEMA IF NOT EXISTS %I', target);
+    EXECUTE format('CREATE TABLE IF NOT EXISTS %I.unxv_events (LIKE %s INCLUDING ALL)', target, source);
+    FOR c IN
+        SELECT a.attname,
+               format_type(a.atttypid, a.atttypmod) AS type,
+               pg_get_expr(d.adbin, d.adrelid) AS dflt,
+               a.attnotnull
+        FROM pg_attribute a
+        LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+        WHERE a.attrelid = source::regclass AND a.attnum > 0 AND NOT a.attisdropped
+          AND NOT EXISTS (
+              SELECT 1 FROM pg_attribute t
+              WHERE t.attrelid = format('%I.unxv_events', target)::regclass
+                AND t.attname = a.attname AND NOT t.attisdropped
+          )
+        ORDER BY a.attnum
+    LOOP
+        EXECUTE format(
+            'ALTER TABLE %I.unxv_events ADD COLUMN %I %s%s%s',
+            target, c.attname, c.type,
+            COALESCE(' DEFAULT ' || c.dflt, ''),
+            CASE WHEN c.attnotnull AND c.dflt IS NOT NULL THEN ' NOT NULL' ELSE '' END
+        );
+    END LOOP;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS unxv_transactions (
+    digest TEXT PRIMARY KEY,
+    sender TEXT NOT NULL,
+    checkpoint BIGINT NOT NULL,
+    checkpoint_timestamp_ms BIGINT NOT NULL,
+    success BOOLEAN NOT NULL,
+    abort_code BIGINT,
+    error TEXT,
+    computation_cost BIGINT NOT NULL,
+    storage_cost BIGINT NOT NULL,
+    storage_rebate BIGINT NOT NULL,
+    move_calls JSONB NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_unxv_transactions_checkpoint ON unxv_transactions (checkpoint);
+CREATE INDEX IF NOT EXISTS idx_unxv_transactions_sender ON unxv_transactions (sender);
+CREATE INDEX IF NOT EXISTS idx_unxv_transactions_failed ON unxv_transactions (checkpoint) WHERE NOT success;
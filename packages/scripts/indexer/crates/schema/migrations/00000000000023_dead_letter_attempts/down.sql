@@ -0,0 +1,3 @@
+ALTER TABLE unxv_dead_letters
+    DROP COLUMN IF EXISTS attempts,
+    DROP COLUMN IF EXISTS last_attempt_at;
@@ -0,0 +1,3 @@
+ALTER TABLE unxv_dead_letters
+    ADD COLUMN IF NOT EXISTS attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS last_attempt_at TIMESTAMPTZ;
@@ -0,0 +1 @@
+ALTER TABLE unxv_events ADD COLUMN IF NOT EXISTS call_packages JSONB;
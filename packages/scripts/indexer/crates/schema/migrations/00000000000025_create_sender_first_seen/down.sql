@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_unxv_events_new_sender;
+ALTER TABLE unxv_events DROP COLUMN IF EXISTS is_new_sender;
+DROP TABLE IF EXISTS sender_first_seen;
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS sender_first_seen (
+    sender TEXT PRIMARY KEY,
+    first_checkpoint BIGINT NOT NULL,
+    first_timestamp_ms BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_sender_first_seen_checkpoint ON sender_first_seen (first_checkpoint);
+
+ALTER TABLE unxv_events ADD COLUMN IF NOT EXISTS is_new_sender BOOLEAN;
+
+-- Serves un-stamping when a sender's first checkpoint moves earlier.
+CREATE INDEX IF NOT EXISTS idx_unxv_events_new_sender ON unxv_events (sender) WHERE is_new_sender;
@@ -0,0 +1 @@
+DROP TABLE IF EXISTS unxv_events_sampled;
@@ -0,0 +1,18 @@
+-- A deterministic sample of unxv_events for cheap long-horizon dashboards. Not pruned with
+-- unxv_events; counts over it estimate totals only after dividing by the sample rate.
+CREATE TABLE IF NOT EXISTS unxv_events_sampled (
+    digest TEXT NOT NULL,
+    event_index BIGINT NOT NULL,
+    sender TEXT NOT NULL,
+    checkpoint BIGINT NOT NULL,
+    checkpoint_timestamp_ms BIGINT NOT NULL,
+    package TEXT NOT NULL,
+    module TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    contents_json JSONB,
+    tx_success BOOLEAN,
+    PRIMARY KEY (digest, event_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_unxv_events_sampled_checkpoint ON unxv_events_sampled (checkpoint);
+CREATE INDEX IF NOT EXISTS idx_unxv_events_sampled_module_time ON unxv_events_sampled (module, checkpoint_timestamp_ms);
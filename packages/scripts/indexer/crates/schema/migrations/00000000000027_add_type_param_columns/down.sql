@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_unxv_events_type_param_1;
+DROP INDEX IF EXISTS idx_unxv_events_type_param_0;
+ALTER TABLE unxv_events DROP COLUMN IF EXISTS type_param_1;
+ALTER TABLE unxv_events DROP COLUMN IF EXISTS type_param_0;
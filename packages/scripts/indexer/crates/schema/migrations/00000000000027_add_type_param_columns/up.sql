@@ -0,0 +1,7 @@
+-- The first two event type parameters in canonical form (0x-prefixed, 32-byte addresses), so
+-- asset filters don't need to unpack type_params. NULL when the event has fewer parameters.
+ALTER TABLE unxv_events ADD COLUMN IF NOT EXISTS type_param_0 TEXT;
+ALTER TABLE unxv_events ADD COLUMN IF NOT EXISTS type_param_1 TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_unxv_events_type_param_0 ON unxv_events (type_param_0, checkpoint) WHERE type_param_0 IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_unxv_events_type_param_1 ON unxv_events (type_param_1, checkpoint) WHERE type_param_1 IS NOT NULL;
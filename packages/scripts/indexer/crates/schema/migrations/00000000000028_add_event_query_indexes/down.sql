@@ -0,0 +1,6 @@
+CREATE INDEX IF NOT EXISTS idx_unxv_events_sender ON unxv_events (sender);
+CREATE INDEX IF NOT EXISTS idx_unxv_events_module ON unxv_events (module);
+DROP INDEX IF EXISTS idx_unxv_events_checkpoint_timestamp_ms;
+DROP INDEX IF EXISTS idx_unxv_events_module_event_type_order;
+DROP INDEX IF EXISTS idx_unxv_events_module_order;
+DROP INDEX IF EXISTS idx_unxv_events_sender_order;
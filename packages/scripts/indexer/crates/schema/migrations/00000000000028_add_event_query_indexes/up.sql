@@ -0,0 +1,10 @@
+-- Keyset pagination of unxv_events by sender, module and struct (`unxv_schema::query`): each
+-- index ends in the (checkpoint, digest, event_index) page order. The sender and module ones
+-- supersede the single-column indexes.
+CREATE INDEX IF NOT EXISTS idx_unxv_events_sender_order ON unxv_events (sender, checkpoint, digest, event_index);
+CREATE INDEX IF NOT EXISTS idx_unxv_events_module_order ON unxv_events (module, checkpoint, digest, event_index);
+CREATE INDEX IF NOT EXISTS idx_unxv_events_module_event_type_order
+    ON unxv_events (module, event_type, checkpoint, digest, event_index);
+CREATE INDEX IF NOT EXISTS idx_unxv_events_checkpoint_timestamp_ms ON unxv_events (checkpoint_timestamp_ms);
+DROP INDEX IF EXISTS idx_unxv_events_sender;
+DROP INDEX IF EXISTS idx_unxv_events_module;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS liquidation_cascade_progress;
+DROP TABLE IF EXISTS liquidation_cascades;
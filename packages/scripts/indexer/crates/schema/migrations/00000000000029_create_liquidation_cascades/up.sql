@@ -0,0 +1,24 @@
+-- Liquidations in more than one module within a few checkpoints of each other, found by the
+-- liquidation cascade job from unxv_events.
+CREATE TABLE IF NOT EXISTS liquidation_cascades (
+    cascade_id TEXT PRIMARY KEY,
+    first_checkpoint BIGINT NOT NULL,
+    last_checkpoint BIGINT NOT NULL,
+    first_timestamp_ms BIGINT NOT NULL,
+    last_timestamp_ms BIGINT NOT NULL,
+    modules TEXT[] NOT NULL,
+    digests TEXT[] NOT NULL,
+    liquidations BIGINT NOT NULL,
+    qty_closed BIGINT NOT NULL,
+    notional BIGINT NOT NULL,
+    penalty_collat BIGINT NOT NULL,
+    detected_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_liquidation_cascades_first_checkpoint ON liquidation_cascades (first_checkpoint);
+
+-- Single row: the last checkpoint whose liquidations the job has fully grouped.
+CREATE TABLE IF NOT EXISTS liquidation_cascade_progress (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    checkpoint BIGINT NOT NULL
+);
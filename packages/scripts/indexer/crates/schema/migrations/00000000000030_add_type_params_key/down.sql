@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_unxv_events_type_params_key;
+ALTER TABLE unxv_events DROP COLUMN IF EXISTS type_params_key;
@@ -0,0 +1,6 @@
+-- type_params as one string, for equality matches on the whole parameter list and GROUP BY.
+-- Containment matches on single parameters keep using the GIN index on type_params.
+ALTER TABLE unxv_events ADD COLUMN IF NOT EXISTS type_params_key TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_unxv_events_type_params_key ON unxv_events (type_params_key, checkpoint)
+    WHERE type_params_key IS NOT NULL;
@@ -0,0 +1,15 @@
+-- Rows the database rejected at commit (e.g. a NUL in a text column), isolated by bisecting the
+-- batch so the rest of it could commit. `row` is the rejected row as JSON.
+CREATE TABLE IF NOT EXISTS unxv_events_quarantine (
+    pipeline TEXT NOT NULL,
+    target_table TEXT NOT NULL,
+    digest TEXT NOT NULL,
+    event_index BIGINT NOT NULL,
+    checkpoint BIGINT NOT NULL,
+    row JSONB NOT NULL,
+    error TEXT NOT NULL,
+    quarantined_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (target_table, digest, event_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_unxv_events_quarantine_checkpoint ON unxv_events_quarantine (checkpoint);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_unxv_events_emitter_object;
+ALTER TABLE unxv_events DROP COLUMN IF EXISTS emitter_object;
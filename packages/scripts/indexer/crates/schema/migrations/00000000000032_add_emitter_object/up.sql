@@ -0,0 +1,6 @@
+-- The object an event concerns (its first 0x2::object::ID field), for per-object histories
+-- such as every event of one DEX pool.
+ALTER TABLE unxv_events ADD COLUMN IF NOT EXISTS emitter_object TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_unxv_events_emitter_object ON unxv_events (emitter_object, checkpoint)
+    WHERE emitter_object IS NOT NULL;
@@ -0,0 +1,3 @@
+-- Set when contents_bcs was cut to --contents-bcs-max-bytes, so analytics can tell stored
+-- payloads from complete ones.
+ALTER TABLE unxv_events ADD COLUMN IF NOT EXISTS contents_bcs_truncated BOOLEAN NOT NULL DEFAULT false;
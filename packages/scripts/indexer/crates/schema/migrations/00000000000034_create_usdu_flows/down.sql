@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS usdu_supply;
+DROP TABLE IF EXISTS usdu_flows;
@@ -0,0 +1,27 @@
+-- USDU supply changes, decoded from usdu module events. Amounts are NUMERIC although Move
+-- amounts are u64, so sums never overflow.
+CREATE TABLE IF NOT EXISTS usdu_flows (
+    event_digest TEXT PRIMARY KEY,
+    digest TEXT NOT NULL,
+    checkpoint BIGINT NOT NULL,
+    checkpoint_timestamp_ms BIGINT NOT NULL,
+    package TEXT NOT NULL,
+    flow_type TEXT NOT NULL,
+    amount NUMERIC NOT NULL,
+    from_address TEXT,
+    to_address TEXT,
+    timestamp_ms BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_usdu_flows_checkpoint ON usdu_flows (checkpoint);
+CREATE INDEX IF NOT EXISTS idx_usdu_flows_type_checkpoint ON usdu_flows (flow_type, checkpoint);
+CREATE INDEX IF NOT EXISTS idx_usdu_flows_to ON usdu_flows (to_address) WHERE to_address IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_usdu_flows_from ON usdu_flows (from_address) WHERE from_address IS NOT NULL;
+
+-- Running totals over usdu_flows, written in the same transaction as the pipeline's watermark.
+CREATE TABLE IF NOT EXISTS usdu_supply (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    minted NUMERIC NOT NULL,
+    burned NUMERIC NOT NULL,
+    supply NUMERIC NOT NULL
+);
@@ -0,0 +1,8 @@
+-- One row per checkpoint the unxv_events pipeline processed, including checkpoints without a
+-- single accepted event, so a range missing from unxv_events can be told apart from a quiet one.
+CREATE TABLE IF NOT EXISTS unxv_checkpoints (
+    checkpoint BIGINT PRIMARY KEY,
+    tx_count BIGINT NOT NULL,
+    event_count BIGINT NOT NULL,
+    processed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_unxv_events_event_type_full;
+ALTER TABLE unxv_events DROP COLUMN IF EXISTS event_type_full;
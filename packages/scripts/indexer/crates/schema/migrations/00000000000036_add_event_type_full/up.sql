@@ -0,0 +1,6 @@
+-- The fully qualified event type, `package::module::Struct<params>`, for joins against type
+-- registries and telling same-named structs of different packages apart. A hash index, because
+-- nested type parameters can make the string longer than a B-tree entry allows.
+ALTER TABLE unxv_events ADD COLUMN IF NOT EXISTS event_type_full TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_unxv_events_event_type_full ON unxv_events USING hash (event_type_full);
@@ -0,0 +1,15 @@
+-- New versions of objects whose type is in --object-types, written by the unxv_object_changes
+-- pipeline. One row per object version, so an object's history is ordered by version.
+CREATE TABLE IF NOT EXISTS unxv_object_changes (
+    object_id TEXT NOT NULL,
+    version BIGINT NOT NULL,
+    object_type TEXT NOT NULL,
+    digest TEXT NOT NULL,
+    checkpoint BIGINT NOT NULL,
+    timestamp_ms BIGINT NOT NULL,
+    contents_bcs BYTEA NOT NULL,
+    PRIMARY KEY (object_id, version)
+);
+
+CREATE INDEX IF NOT EXISTS idx_unxv_object_changes_checkpoint ON unxv_object_changes (checkpoint);
+CREATE INDEX IF NOT EXISTS idx_unxv_object_changes_type_checkpoint ON unxv_object_changes (object_type, checkpoint);
@@ -0,0 +1,14 @@
+-- Cursor of each `reprocess` run, keyed by its filters, so an interrupted run resumes after the
+-- last row it committed. Rows of a run are visited in (checkpoint, digest, event_index) order.
+CREATE TABLE IF NOT EXISTS reprocess_progress (
+    run TEXT PRIMARY KEY,
+    after_checkpoint BIGINT,
+    after_digest TEXT,
+    after_event_index BIGINT,
+    scanned BIGINT NOT NULL DEFAULT 0,
+    updated BIGINT NOT NULL DEFAULT 0,
+    failed BIGINT NOT NULL DEFAULT 0,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    finished_at TIMESTAMPTZ
+);
@@ -0,0 +1,422 @@
+//! Column-level documentation and provenance for every table, kept next to the models so it
+//! can't drift. Rendered by the indexer's `schema-docs` subcommand.
+
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct TableDoc {
+    pub name: &'static str,
+    /// Pipeline (`Processor::NAME`) or background task that writes the table.
+    pub pipeline: &'static str,
+    pub description: &'static str,
+    pub columns: &'static [ColumnDoc],
+}
+
+#[derive(Debug, Serialize)]
+pub struct ColumnDoc {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Where the value comes from: checkpoint/transaction metadata, or `module::Event.field`.
+    pub source: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<&'static str>,
+}
+
+const fn col(name: &'static str, description: &'static str, source: &'static str) -> ColumnDoc {
+    ColumnDoc { name, description, source, unit: None }
+}
+
+const fn col_unit(
+    name: &'static str,
+    description: &'static str,
+    source: &'static str,
+    unit: &'static str,
+) -> ColumnDoc {
+    ColumnDoc { name, description, source, unit: Some(unit) }
+}
+
+const EVENT_DIGEST: ColumnDoc = col("event_digest", "`{digest}:{event index}`", "transaction digest + event index");
+const DIGEST: ColumnDoc = col("digest", "Transaction digest", "transaction");
+const CHECKPOINT: ColumnDoc = col("checkpoint", "Checkpoint sequence number", "checkpoint summary");
+const CHECKPOINT_TIMESTAMP_MS: ColumnDoc =
+    col_unit("checkpoint_timestamp_ms", "Checkpoint timestamp", "checkpoint summary", "ms since epoch");
+
+pub const TABLES: &[TableDoc] = &[
+    TableDoc {
+        name: "unxv_events",
+        pipeline: "unxv_events",
+        description: "Every accepted Unxversal event, raw and decoded",
+        columns: &[
+            DIGEST,
+            col("event_index", "Position of the event within the transaction", "event index"),
+            col("sender", "Transaction sender", "transaction"),
+            CHECKPOINT,
+            CHECKPOINT_TIMESTAMP_MS,
+            col("package", "Package defining the event struct", "event type address"),
+            col("module", "Module defining the event struct", "event type module"),
+            col("event_type", "Event struct name", "event type name"),
+            col("type_params", "Event type parameters as strings, possibly truncated with `...`", "event type params"),
+            col("contents_bcs", "Raw BCS event payload", "event contents"),
+            col("contents_json", "Decoded payload; NULL when no decoder/layout", "event contents via decoder registry or layout"),
+            col("indexer_version", "`{crate version}-{git revision}` of the writer; NULL unless stamping is enabled", "indexer build"),
+            col_unit(
+                "tx_timestamp_ms",
+                "Transaction time: the preceding consensus commit time in the checkpoint, else the checkpoint time; NULL unless --tx-timestamps",
+                "consensus commit prologue",
+                "ms since epoch",
+            ),
+            col_unit(
+                "gas_used",
+                "Net gas of the emitting transaction: computation + storage - storage rebate",
+                "transaction effects gas summary",
+                "MIST",
+            ),
+            col("tx_success", "Whether the emitting transaction succeeded", "transaction effects status"),
+            col("type_params_truncated", "Whether `type_params` was cut at --type-params-max-depth/-bytes", "event type params"),
+            col("type_hash", "Hex SHA-256 of the full canonical event type, for exact matches", "event type"),
+            col("call_packages", "Distinct packages of the transaction's Move calls, in call order", "transaction data"),
+            col("is_new_sender", "Event is in its sender's first checkpoint (--sender-first-seen)", "sender_first_seen"),
+            col("type_param_0", "First type parameter, canonical (e.g. the base asset); NULL if none", "event type params"),
+            col("type_param_1", "Second type parameter, canonical (e.g. the quote asset); NULL if none", "event type params"),
+            col("type_params_key", "`type_params` joined with \", \"; empty if none, NULL on older rows", "event type params"),
+            col("emitter_object", "First top-level `0x2::object::ID` field (pool, market, vault); NULL if none", "event contents via layout"),
+            col("contents_bcs_truncated", "Whether `contents_bcs` was cut at --contents-bcs-max-bytes", "event contents"),
+            col("event_type_full", "Canonical `package::module::Struct<params>`, untruncated; NULL on older rows", "event type"),
+        ],
+    },
+    TableDoc {
+        name: "unxv_events_sampled",
+        pipeline: "unxv_events_sampled",
+        description: "A deterministic sample (--event-sample-rate, default 1%) of unxv_events, never pruned; divide counts by the rate to estimate totals",
+        columns: &[
+            DIGEST,
+            col("event_index", "Position of the event within the transaction", "event index"),
+            col("sender", "Transaction sender", "transaction"),
+            CHECKPOINT,
+            CHECKPOINT_TIMESTAMP_MS,
+            col("package", "Package defining the event struct", "event type address"),
+            col("module", "Module defining the event struct", "event type module"),
+            col("event_type", "Event struct name", "event type name"),
+            col("contents_json", "Decoded payload; NULL when no decoder/layout", "event contents via decoder registry or layout"),
+            col("tx_success", "Whether the emitting transaction succeeded", "transaction effects status"),
+        ],
+    },
+    TableDoc {
+        name: "sender_first_seen",
+        pipeline: "unxv_events",
+        description: "Earliest checkpoint with accepted events per sender (--sender-first-seen); only ever moves earlier",
+        columns: &[
+            col("sender", "Normalized transaction sender", "transaction data"),
+            col("first_checkpoint", "Earliest checkpoint with an accepted event from the sender", "checkpoint summary"),
+            col_unit("first_timestamp_ms", "Timestamp of that checkpoint", "checkpoint summary", "ms since epoch"),
+        ],
+    },
+    TableDoc {
+        name: "unxv_referrals",
+        pipeline: "unxv_referrals",
+        description: "Referral bindings",
+        columns: &[
+            EVENT_DIGEST,
+            DIGEST,
+            CHECKPOINT,
+            CHECKPOINT_TIMESTAMP_MS,
+            col("package", "Package defining the event struct", "event type address"),
+            col("referrer", "Referring address", "rewards::ReferralSet.parent"),
+            col("referee", "Referred address", "rewards::ReferralSet.child"),
+            col("reward_amount", "Referral reward (not emitted yet)", "none"),
+        ],
+    },
+    TableDoc {
+        name: "dex_trades",
+        pipeline: "dex_trades",
+        description: "DeepBook fills routed through unxvdex::dex",
+        columns: &[
+            EVENT_DIGEST,
+            DIGEST,
+            CHECKPOINT,
+            CHECKPOINT_TIMESTAMP_MS,
+            col("package", "Unxversal package the trade was routed through", "event package_id"),
+            col("pool_id", "DeepBook pool", "order_info::OrderFilled.pool_id"),
+            col("maker", "Maker balance manager", "order_info::OrderFilled.maker_balance_manager_id"),
+            col("taker", "Taker balance manager", "order_info::OrderFilled.taker_balance_manager_id"),
+            col_unit("price", "Fill price", "order_info::OrderFilled.price", "DeepBook price units"),
+            col_unit("base_quantity", "Filled base quantity", "order_info::OrderFilled.base_quantity", "base units"),
+            col_unit("quote_quantity", "Filled quote quantity", "order_info::OrderFilled.quote_quantity", "quote units"),
+            col("is_bid", "Taker side", "order_info::OrderFilled.taker_is_bid"),
+            col_unit("timestamp_ms", "Fill timestamp", "order_info::OrderFilled.timestamp", "ms since epoch"),
+        ],
+    },
+    TableDoc {
+        name: "dex_market_participants",
+        pipeline: "dex_trades",
+        description: "Distinct makers and takers per market per UTC day; staging for dex_daily_participants",
+        columns: &[
+            col("pool_id", "DeepBook pool", "order_info::OrderFilled.pool_id"),
+            col("day", "UTC day of the fill", "checkpoint summary"),
+            col("role", "`maker` or `taker`", "order_info::OrderFilled"),
+            col("account", "Balance manager", "order_info::OrderFilled.{maker,taker}_balance_manager_id"),
+        ],
+    },
+    TableDoc {
+        name: "dex_daily_participants",
+        pipeline: "participants rollup",
+        description: "Unique makers and takers per market per finalized UTC day",
+        columns: &[
+            col("pool_id", "DeepBook pool", "order_info::OrderFilled.pool_id"),
+            col("day", "UTC day", "checkpoint summary"),
+            col("unique_makers", "Distinct maker balance managers", "dex_market_participants"),
+            col("unique_takers", "Distinct taker balance managers", "dex_market_participants"),
+            col("finalized_at", "When the day was rolled up", "participants rollup"),
+            col("recounted_at", "Last recount after late-arriving fills; NULL if none", "dex_trades"),
+        ],
+    },
+    TableDoc {
+        name: "usdu_flows",
+        pipeline: "usdu_flows",
+        description: "USDU mints, burns and transfers emitted by the usdu module",
+        columns: &[
+            EVENT_DIGEST,
+            DIGEST,
+            CHECKPOINT,
+            CHECKPOINT_TIMESTAMP_MS,
+            col("package", "Package defining the usdu module", "event type address"),
+            col("flow_type", "`mint`, `burn` or `transfer`", "event type name"),
+            col_unit("amount", "USDU moved, as NUMERIC", "usdu::Claimed.amount", "USDU base units"),
+            col("from_address", "Sender of a burn or transfer; NULL for mints", "event fields"),
+            col("to_address", "Recipient of a mint or transfer; NULL for burns", "usdu::Claimed.who"),
+            col_unit("timestamp_ms", "Time the module recorded", "usdu::Claimed.timestamp_ms", "ms since epoch"),
+        ],
+    },
+    TableDoc {
+        name: "usdu_supply",
+        pipeline: "usdu_flows",
+        description: "Single row: USDU supply as of the usdu_flows watermark, updated with it",
+        columns: &[
+            col("id", "Always true; keeps the table to one row", "constant"),
+            col_unit("minted", "Total minted", "usdu_flows", "USDU base units"),
+            col_unit("burned", "Total burned", "usdu_flows", "USDU base units"),
+            col_unit("supply", "minted - burned", "usdu_flows", "USDU base units"),
+        ],
+    },
+    TableDoc {
+        name: "liquidation_cascades",
+        pipeline: "liquidation cascade job",
+        description: "Liquidations across two or more modules in one transaction or within a few checkpoints",
+        columns: &[
+            col("cascade_id", "`{digest}:{event index}` of the first liquidation", "unxv_events"),
+            col("first_checkpoint", "Checkpoint of the first liquidation", "checkpoint summary"),
+            col("last_checkpoint", "Checkpoint of the last liquidation", "checkpoint summary"),
+            col_unit("first_timestamp_ms", "Timestamp of first_checkpoint", "checkpoint summary", "ms since epoch"),
+            col_unit("last_timestamp_ms", "Timestamp of last_checkpoint", "checkpoint summary", "ms since epoch"),
+            col("modules", "Distinct modules with a liquidation, sorted", "event type module"),
+            col("digests", "Distinct transactions with a liquidation, in checkpoint order", "transaction"),
+            col("liquidations", "Liquidation events in the cascade", "{futures,gas_futures,perpetuals,xperps}::Liquidated"),
+            col_unit("qty_closed", "Total position closed", "Liquidated.qty_closed", "contracts"),
+            col_unit(
+                "notional",
+                "Total qty_closed * exec_price_1e6 / 1e6",
+                "Liquidated.{qty_closed,exec_price_1e6}",
+                "collateral units",
+            ),
+            col_unit("penalty_collat", "Total liquidation penalty", "Liquidated.penalty_collat", "collateral units"),
+            col("detected_at", "When the job recorded the cascade", "liquidation cascade job"),
+        ],
+    },
+    TableDoc {
+        name: "liquidation_cascade_progress",
+        pipeline: "liquidation cascade job",
+        description: "Single row: last checkpoint whose liquidations have been grouped",
+        columns: &[
+            col("id", "Always true; keeps the table to one row", "constant"),
+            col("checkpoint", "Last fully grouped checkpoint", "liquidation cascade job"),
+        ],
+    },
+    TableDoc {
+        name: "unxv_event_counts",
+        pipeline: "unxv_event_counts",
+        description: "Accepted events per module, struct and UTC day; maintained in checkpoint order",
+        columns: &[
+            col("module", "Module defining the event struct", "event type module"),
+            col("event_type", "Event struct name", "event type name"),
+            col("day", "UTC day of the event's checkpoint", "checkpoint summary"),
+            col("count", "Events seen that day", "running count"),
+        ],
+    },
+    TableDoc {
+        name: "unxv_event_types",
+        pipeline: "unxv_tx_event_bitmap",
+        description: "Interned `module::Struct` event type names",
+        columns: &[
+            col("id", "Event type id", "assigned on first sight"),
+            col("event_type", "`module::Struct`", "event type"),
+        ],
+    },
+    TableDoc {
+        name: "unxv_tx_event_bitmap",
+        pipeline: "unxv_tx_event_bitmap",
+        description: "Event types emitted per transaction",
+        columns: &[
+            DIGEST,
+            CHECKPOINT,
+            col("event_type_ids", "Ids of the distinct event types emitted", "unxv_event_types.id"),
+        ],
+    },
+    TableDoc {
+        name: "unxv_transactions",
+        pipeline: "unxv_transactions",
+        description: "Transactions that emitted at least one accepted event, including failed ones",
+        columns: &[
+            DIGEST,
+            col("sender", "Normalized transaction sender", "transaction data"),
+            CHECKPOINT,
+            CHECKPOINT_TIMESTAMP_MS,
+            col("success", "Whether execution succeeded", "effects status"),
+            col("abort_code", "Move abort code of an aborted transaction (u64 bits)", "effects status"),
+            col("error", "Execution failure, NULL on success", "effects status"),
+            col_unit("computation_cost", "Computation gas", "effects gas summary", "MIST"),
+            col_unit("storage_cost", "Storage gas", "effects gas summary", "MIST"),
+            col_unit("storage_rebate", "Storage rebate", "effects gas summary", "MIST"),
+            col("move_calls", "`[{package, module, function}]` per Move call command", "transaction data"),
+        ],
+    },
+    TableDoc {
+        name: "unxv_checkpoints",
+        pipeline: "unxv_events",
+        description: "Every checkpoint unxv_events processed, with or without accepted events; checked by `verify`",
+        columns: &[
+            CHECKPOINT,
+            col("tx_count", "Transactions in the checkpoint", "checkpoint contents"),
+            col("event_count", "Rows written to unxv_events, dead letters excluded", "unxv_events"),
+            col("processed_at", "When the row was committed", "commit"),
+        ],
+    },
+    TableDoc {
+        name: "unxv_object_changes",
+        pipeline: "unxv_object_changes",
+        description: "New versions of objects whose type is in --object-types, one row per object version",
+        columns: &[
+            col("object_id", "Object id", "transaction output objects"),
+            col("version", "Object version written by the transaction", "transaction output objects"),
+            col("object_type", "Canonical struct tag, type parameters included", "object type"),
+            DIGEST,
+            CHECKPOINT,
+            col_unit("timestamp_ms", "Checkpoint timestamp", "checkpoint summary", "ms since epoch"),
+            col("contents_bcs", "Raw BCS object contents", "object contents"),
+        ],
+    },
+    TableDoc {
+        name: "unxv_dead_letters",
+        pipeline: "unxv_events",
+        description: "Events rejected by their pipeline (e.g. contents_json over the size/depth limits), kept for replay",
+        columns: &[
+            col("pipeline", "Pipeline that rejected the event", "Processor::NAME"),
+            DIGEST,
+            col("event_index", "Position of the event within the transaction", "event index"),
+            col("sender", "Transaction sender", "transaction"),
+            CHECKPOINT,
+            CHECKPOINT_TIMESTAMP_MS,
+            col("package", "Package defining the event struct", "event type address"),
+            col("module", "Module defining the event struct", "event type module"),
+            col("event_type", "Event struct name", "event type name"),
+            col("type_params", "Event type parameters as strings", "event type params"),
+            col("contents_bcs", "Raw BCS event payload", "event contents"),
+            col("reason", "Why the event was rejected, or still fails on replay", "pipeline, replay-dead-letters"),
+            col("attempts", "Failed replay attempts", "replay-dead-letters"),
+            col("last_attempt_at", "When it was last replayed", "replay-dead-letters"),
+        ],
+    },
+    TableDoc {
+        name: "unxv_events_quarantine",
+        pipeline: "unxv_events",
+        description: "Rows the database rejected at commit, isolated by bisecting the batch so the rest could commit",
+        columns: &[
+            col("pipeline", "Pipeline whose commit rejected the row", "Processor::NAME"),
+            col("target_table", "Table the row was meant for", "commit"),
+            DIGEST,
+            col("event_index", "Position of the event within the transaction", "event index"),
+            CHECKPOINT,
+            col("row", "The rejected row as JSON; NUL characters as a literal \\u0000", "commit"),
+            col("error", "Database error that rejected the row", "commit"),
+            col("quarantined_at", "When the row was quarantined", "commit"),
+        ],
+    },
+    TableDoc {
+        name: "unxv_alerts",
+        pipeline: "alert router",
+        description: "Every alert raised by the indexer, deduplicated by key within the dedup window",
+        columns: &[
+            col("id", "Alert id", "assigned on insert"),
+            col("source", "Component that raised the alert", "alert"),
+            col("severity", "`info`, `warning` or `critical`", "alert"),
+            col("key", "Deduplication key", "alert"),
+            col("message", "Human-readable description", "alert"),
+            col("context", "Structured details", "alert"),
+            col("first_seen_at", "When the alert was first raised", "alert router"),
+            col("last_seen_at", "When a duplicate was last raised", "alert router"),
+            col("occurrences", "Times raised, including suppressed duplicates", "alert router"),
+            col("acknowledged_at", "When an operator acknowledged it; NULL if open", "ack-alert"),
+            col("acknowledged_by", "Who acknowledged it", "ack-alert"),
+        ],
+    },
+    TableDoc {
+        name: "live_stats_history",
+        pipeline: "live stats",
+        description: "Hourly snapshots of the status page counters, restored on startup",
+        columns: &[
+            col("hour", "Start of the hour the snapshot closes", "live stats"),
+            col("day", "UTC day the counters belong to", "live stats"),
+            col("checkpoints_today", "Checkpoints processed that day by the end of the hour", "live stats"),
+            col("events_today", "Events committed that day by the end of the hour", "live stats"),
+            col("lag_ms", "Wall clock minus the latest checkpoint timestamp", "live stats"),
+            col("per_module", "Events that day per module", "live stats"),
+        ],
+    },
+    TableDoc {
+        name: "reprocess_progress",
+        pipeline: "reprocess",
+        description: "Resumable cursor and counts of each `reprocess` run, keyed by its filters",
+        columns: &[
+            col("run", "The run's schema, module, event type and checkpoint range", "reprocess flags"),
+            col("after_checkpoint", "Checkpoint of the last row processed; NULL before the first batch", "reprocess"),
+            col("after_digest", "Digest of the last row processed", "reprocess"),
+            col("after_event_index", "Event index of the last row processed", "reprocess"),
+            col("scanned", "Rows read so far", "reprocess"),
+            col("updated", "Rows whose decoded columns changed", "reprocess"),
+            col("failed", "Rows that still don't decode", "reprocess"),
+            col("started_at", "When the run first started, or last restarted", "reprocess"),
+            col("updated_at", "When the last batch committed", "reprocess"),
+            col("finished_at", "When the run reached its last row; NULL while unfinished", "reprocess"),
+        ],
+    },
+    TableDoc {
+        name: "unxv_retention_changelog",
+        pipeline: "pruner",
+        description: "Effective retention policy, appended at startup whenever it changes",
+        columns: &[
+            col("id", "Entry id", "assigned on insert"),
+            col("changed_at", "When the new policy was first seen", "indexer startup"),
+            col("policies", "`{\"default\": ..., <module>: ...}`", "--prune-* flags"),
+        ],
+    },
+];
+
+/// Render [`TABLES`] as Markdown.
+pub fn to_markdown() -> String {
+    let mut out = String::from("# Unxversal indexer schema\n");
+    for table in TABLES {
+        out.push_str(&format!(
+            "\n## `{}`\n\n{} (pipeline `{}`)\n\n| Column | Description | Source | Unit |\n|---|---|---|---|\n",
+            table.name, table.description, table.pipeline
+        ));
+        for c in table.columns {
+            out.push_str(&format!(
+                "| `{}` | {} | {} | {} |\n",
+                c.name,
+                c.description,
+                c.source,
+                c.unit.unwrap_or("")
+            ));
+        }
+    }
+    out
+}
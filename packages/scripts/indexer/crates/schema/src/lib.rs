@@ -1,7 +1,9 @@
 use diesel_migrations::{embed_migrations, EmbeddedMigrations};
 
+pub mod docs;
 pub mod schema;
 pub mod models;
+pub mod query;
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 
@@ -1,14 +1,207 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
 use diesel::{Identifiable, Insertable, Queryable, Selectable};
 use sui_field_count::FieldCount;
 use serde::Serialize;
 
-use crate::schema::unxv_events;
+use crate::schema::{
+    dex_trades, liquidation_cascades, unxv_checkpoints, unxv_dead_letters, unxv_event_counts, unxv_events,
+    unxv_events_quarantine, unxv_events_sampled, unxv_object_changes, unxv_referrals, unxv_transactions,
+    unxv_tx_event_bitmap, usdu_flows,
+};
 
-#[derive(Queryable, Selectable, Insertable, Identifiable, Debug, Serialize, FieldCount)]
-#[diesel(table_name = unxv_events, primary_key(event_digest))]
+#[derive(Queryable, Selectable, Insertable, Identifiable, Debug, Clone, Serialize, FieldCount)]
+#[diesel(table_name = unxv_events, primary_key(digest, event_index))]
 pub struct UnxvEvent {
+    pub digest: String,
+    /// Position of the event within its transaction's events.
+    pub event_index: i64,
+    pub sender: String,
+    pub checkpoint: i64,
+    pub checkpoint_timestamp_ms: i64,
+    pub package: String,
+    pub module: String,
+    pub event_type: String,
+    pub type_params: serde_json::Value,
+    pub contents_bcs: Vec<u8>,
+    pub contents_json: Option<serde_json::Value>,
+    /// Build that wrote the row (`--stamp-indexer-version`), NULL otherwise.
+    pub indexer_version: Option<String>,
+    /// Consensus commit time of the emitting transaction (`--tx-timestamps`), NULL otherwise.
+    pub tx_timestamp_ms: Option<i64>,
+    /// Net gas of the emitting transaction: computation + storage - storage rebate, in MIST.
+    pub gas_used: Option<i64>,
+    pub tx_success: Option<bool>,
+    /// Whether `type_params` was cut by the depth or size limit.
+    pub type_params_truncated: bool,
+    /// Hex SHA-256 of the full canonical event type; NULL on rows written before it was added.
+    pub type_hash: Option<String>,
+    /// Distinct packages of the transaction's Move calls, in call order; `package` is the
+    /// event's own. NULL on rows written before it was added and on replayed dead letters.
+    pub call_packages: Option<serde_json::Value>,
+    /// Whether this is the sender's first checkpoint with accepted events (`--sender-first-seen`),
+    /// NULL otherwise.
+    pub is_new_sender: Option<bool>,
+    /// First and second event type parameters in canonical form; NULL when there are fewer,
+    /// and on replayed dead letters whose parameters were truncated.
+    pub type_param_0: Option<String>,
+    pub type_param_1: Option<String>,
+    /// `type_params` joined into one string (`A, B`), kept in step with it for exact matches
+    /// and grouping. NULL on rows written before it was added.
+    pub type_params_key: Option<String>,
+    /// Object the event concerns (its first `0x2::object::ID` field), for per-object
+    /// histories. NULL when the event has none or its contents weren't decoded.
+    pub emitter_object: Option<String>,
+    /// `contents_bcs` was cut to `--contents-bcs-max-bytes`; `contents_json` was decoded from
+    /// the whole payload.
+    pub contents_bcs_truncated: bool,
+    /// Canonical `package::module::Struct<params>`, untruncated. NULL on rows written before
+    /// it was added and on replayed dead letters whose parameters were truncated.
+    pub event_type_full: Option<String>,
+}
+
+/// An event of the deterministic `--sample-events` sample: the queryable columns of
+/// [`UnxvEvent`], without the raw payload.
+#[derive(Queryable, Selectable, Insertable, Identifiable, Debug, Serialize, FieldCount)]
+#[diesel(table_name = unxv_events_sampled, primary_key(digest, event_index))]
+pub struct UnxvEventSampled {
+    pub digest: String,
+    pub event_index: i64,
+    pub sender: String,
+    pub checkpoint: i64,
+    pub checkpoint_timestamp_ms: i64,
+    pub package: String,
+    pub module: String,
+    pub event_type: String,
+    pub contents_json: Option<serde_json::Value>,
+    pub tx_success: Option<bool>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Identifiable, Debug, Serialize, FieldCount)]
+#[diesel(table_name = unxv_referrals, primary_key(event_digest))]
+pub struct UnxvReferral {
+    pub event_digest: String,
+    pub digest: String,
+    pub checkpoint: i64,
+    pub checkpoint_timestamp_ms: i64,
+    pub package: String,
+    pub referrer: String,
+    pub referee: String,
+    /// Not emitted by `rewards::ReferralSet` today; reserved for referral payout events.
+    pub reward_amount: Option<i64>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Identifiable, Debug, Serialize, FieldCount)]
+#[diesel(table_name = dex_trades, primary_key(event_digest))]
+pub struct DexTrade {
+    pub event_digest: String,
+    pub digest: String,
+    pub checkpoint: i64,
+    pub checkpoint_timestamp_ms: i64,
+    /// Unxversal dex package the trade was routed through.
+    pub package: String,
+    pub pool_id: String,
+    /// Maker balance manager id.
+    pub maker: String,
+    /// Taker balance manager id.
+    pub taker: String,
+    pub price: i64,
+    pub base_quantity: i64,
+    pub quote_quantity: i64,
+    /// Taker side.
+    pub is_bid: bool,
+    pub timestamp_ms: i64,
+}
+
+/// A USDU supply change. Amounts are u64 in Move but stored as NUMERIC, so sums and future
+/// wider amounts don't overflow.
+#[derive(Queryable, Selectable, Insertable, Identifiable, Debug, Clone, Serialize, FieldCount)]
+#[diesel(table_name = usdu_flows, primary_key(event_digest))]
+pub struct UsduFlow {
     pub event_digest: String,
     pub digest: String,
+    pub checkpoint: i64,
+    pub checkpoint_timestamp_ms: i64,
+    /// Package defining the `usdu` module.
+    pub package: String,
+    /// `mint`, `burn` or `transfer`.
+    pub flow_type: String,
+    pub amount: BigDecimal,
+    /// Sender of a burn or transfer; NULL for mints.
+    pub from_address: Option<String>,
+    /// Recipient of a mint or transfer; NULL for burns.
+    pub to_address: Option<String>,
+    pub timestamp_ms: i64,
+}
+
+/// Event type ids (see `unxv_event_types`) emitted by one transaction, for fast
+/// "transactions containing event type X" queries via `event_type_ids @> ARRAY[id]`.
+#[derive(Queryable, Selectable, Insertable, Identifiable, Debug, Serialize, FieldCount)]
+#[diesel(table_name = unxv_tx_event_bitmap, primary_key(digest))]
+pub struct UnxvTxEventBitmap {
+    pub digest: String,
+    pub checkpoint: i64,
+    pub event_type_ids: Vec<i32>,
+}
+
+/// A transaction that emitted at least one accepted Unxversal event, successful or not.
+#[derive(Queryable, Selectable, Insertable, Identifiable, Debug, Serialize, FieldCount)]
+#[diesel(table_name = unxv_transactions, primary_key(digest))]
+pub struct UnxvTransaction {
+    pub digest: String,
+    pub sender: String,
+    pub checkpoint: i64,
+    pub checkpoint_timestamp_ms: i64,
+    pub success: bool,
+    /// Move abort code when the transaction aborted, as the u64's bits (codes >= 2^63 read negative).
+    pub abort_code: Option<i64>,
+    /// Execution failure, NULL on success.
+    pub error: Option<String>,
+    pub computation_cost: i64,
+    pub storage_cost: i64,
+    pub storage_rebate: i64,
+    /// `[{"package", "module", "function"}]`, one per Move call command, in order.
+    pub move_calls: serde_json::Value,
+}
+
+/// A new version of an object whose type is in `--object-types`, created or mutated by a
+/// transaction.
+#[derive(Queryable, Selectable, Insertable, Identifiable, Debug, Clone, Serialize, FieldCount)]
+#[diesel(table_name = unxv_object_changes, primary_key(object_id, version))]
+pub struct UnxvObjectChange {
+    pub object_id: String,
+    pub version: i64,
+    /// Canonical struct tag, type parameters included.
+    pub object_type: String,
+    /// Transaction that wrote this version.
+    pub digest: String,
+    pub checkpoint: i64,
+    /// Checkpoint timestamp.
+    pub timestamp_ms: i64,
+    /// The Move object's BCS contents.
+    pub contents_bcs: Vec<u8>,
+}
+
+/// Bookkeeping for one checkpoint processed by `unxv_events`, written even when none of its
+/// events were accepted. `processed_at` is left to its default on insert.
+#[derive(Insertable, Debug, Clone, Serialize, FieldCount)]
+#[diesel(table_name = unxv_checkpoints)]
+pub struct UnxvCheckpoint {
+    pub checkpoint: i64,
+    pub tx_count: i64,
+    /// Rows written to `unxv_events`, dead letters excluded.
+    pub event_count: i64,
+}
+
+/// An event set aside instead of being written to its pipeline's table, with the reason.
+/// Carries everything needed to write the original row once the cause is fixed.
+/// `attempts` and `last_attempt_at` are left to their defaults on insert and maintained by replay.
+#[derive(Queryable, Selectable, Insertable, Identifiable, Debug, Serialize, FieldCount)]
+#[diesel(table_name = unxv_dead_letters, primary_key(pipeline, digest, event_index))]
+pub struct UnxvDeadLetter {
+    pub pipeline: String,
+    pub digest: String,
+    pub event_index: i64,
     pub sender: String,
     pub checkpoint: i64,
     pub checkpoint_timestamp_ms: i64,
@@ -17,5 +210,56 @@ pub struct UnxvEvent {
     pub event_type: String,
     pub type_params: serde_json::Value,
     pub contents_bcs: Vec<u8>,
+    pub reason: String,
 }
 
+/// A row the database rejected at commit, set aside so the rest of its batch could commit.
+/// `quarantined_at` is left to its default on insert.
+#[derive(Queryable, Selectable, Insertable, Debug, Serialize, FieldCount)]
+#[diesel(table_name = unxv_events_quarantine)]
+pub struct UnxvQuarantinedRow {
+    pub pipeline: String,
+    /// Table the row was meant for, e.g. `unxv_events` or `unxv_dead_letters`.
+    pub target_table: String,
+    pub digest: String,
+    pub event_index: i64,
+    pub checkpoint: i64,
+    /// The row as JSON, with NUL characters written as a literal `\u0000`.
+    pub row: serde_json::Value,
+    /// The database error that rejected it.
+    pub error: String,
+}
+
+/// Events per module, struct and UTC day (of `checkpoint_timestamp_ms`).
+#[derive(Queryable, Selectable, Insertable, Identifiable, Debug, Serialize, FieldCount)]
+#[diesel(table_name = unxv_event_counts, primary_key(module, event_type, day))]
+pub struct UnxvEventCount {
+    pub module: String,
+    pub event_type: String,
+    pub day: NaiveDate,
+    pub count: i64,
+}
+
+/// Liquidations in two or more modules, chained by transaction and by checkpoints at most the
+/// cascade window apart. `detected_at` is left to its default on insert.
+#[derive(Queryable, Selectable, Insertable, Identifiable, Debug, Serialize, FieldCount)]
+#[diesel(table_name = liquidation_cascades, primary_key(cascade_id))]
+pub struct LiquidationCascade {
+    /// `{digest}:{event_index}` of the first liquidation.
+    pub cascade_id: String,
+    pub first_checkpoint: i64,
+    pub last_checkpoint: i64,
+    pub first_timestamp_ms: i64,
+    pub last_timestamp_ms: i64,
+    /// Distinct modules with a liquidation in the cascade, sorted.
+    pub modules: Vec<String>,
+    /// Distinct transactions with a liquidation in the cascade, in checkpoint order.
+    pub digests: Vec<String>,
+    pub liquidations: i64,
+    /// Sum of `Liquidated.qty_closed`, in contracts.
+    pub qty_closed: i64,
+    /// Sum of `qty_closed * exec_price_1e6 / 1e6`, in collateral units per contract of size 1.
+    pub notional: i64,
+    /// Sum of `Liquidated.penalty_collat`, in collateral units.
+    pub penalty_collat: i64,
+}
@@ -0,0 +1,147 @@
+//! Paginated reads of `unxv_events` for downstream services.
+//!
+//! Pages run oldest first in `(checkpoint, digest, event_index)` order and continue from an
+//! [`EventCursor`] rather than an offset, so rows committed while a client pages through never
+//! shift the rows it has yet to read.
+
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Bool, Text};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+use crate::models::UnxvEvent;
+use crate::schema::unxv_events;
+
+/// Most events returned by one call, whatever `limit` asks for.
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// Position of the last event of a page; pass it back to read the next page. Encoded as
+/// `checkpoint:digest:event_index` for clients that carry it as a string.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventCursor {
+    pub checkpoint: i64,
+    pub digest: String,
+    pub event_index: i64,
+}
+
+impl EventCursor {
+    pub fn encode(&self) -> String {
+        format!("{}:{}:{}", self.checkpoint, self.digest, self.event_index)
+    }
+
+    pub fn decode(s: &str) -> Option<Self> {
+        let mut parts = s.splitn(3, ':');
+        let checkpoint = parts.next()?.parse().ok()?;
+        let digest = parts.next()?.to_string();
+        let event_index = parts.next()?.parse().ok()?;
+        Some(Self { checkpoint, digest, event_index })
+    }
+}
+
+impl From<&UnxvEvent> for EventCursor {
+    fn from(event: &UnxvEvent) -> Self {
+        Self { checkpoint: event.checkpoint, digest: event.digest.clone(), event_index: event.event_index }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventPage {
+    pub events: Vec<UnxvEvent>,
+    /// None on the last page.
+    pub next: Option<EventCursor>,
+}
+
+/// Events sent by `sender` (`0x` and 64 lowercase hex digits, as stored).
+pub async fn events_by_sender(
+    conn: &mut AsyncPgConnection,
+    sender: &str,
+    cursor: Option<&EventCursor>,
+    limit: usize,
+) -> QueryResult<EventPage> {
+    let query = unxv_events::table.filter(unxv_events::sender.eq(sender.to_owned())).into_boxed();
+    page(conn, query, cursor, limit).await
+}
+
+/// Events of `module`, optionally within `[from_ts, to_ts)` by `checkpoint_timestamp_ms`.
+pub async fn events_by_module(
+    conn: &mut AsyncPgConnection,
+    module: &str,
+    from_ts: Option<i64>,
+    to_ts: Option<i64>,
+    cursor: Option<&EventCursor>,
+    limit: usize,
+) -> QueryResult<EventPage> {
+    let query = unxv_events::table.filter(unxv_events::module.eq(module.to_owned())).into_boxed();
+    page(conn, time_range(query, from_ts, to_ts), cursor, limit).await
+}
+
+/// Events of the struct `module::event_type`, optionally within `[from_ts, to_ts)` by
+/// `checkpoint_timestamp_ms`.
+pub async fn events_by_type(
+    conn: &mut AsyncPgConnection,
+    module: &str,
+    event_type: &str,
+    from_ts: Option<i64>,
+    to_ts: Option<i64>,
+    cursor: Option<&EventCursor>,
+    limit: usize,
+) -> QueryResult<EventPage> {
+    let query = unxv_events::table
+        .filter(unxv_events::module.eq(module.to_owned()))
+        .filter(unxv_events::event_type.eq(event_type.to_owned()))
+        .into_boxed();
+    page(conn, time_range(query, from_ts, to_ts), cursor, limit).await
+}
+
+pub type EventsQuery<'a> = unxv_events::BoxedQuery<'a, diesel::pg::Pg>;
+
+/// Restrict `query` to events after `cursor`. A row comparison, so Postgres walks an index
+/// ending in `(checkpoint, digest, event_index)` from the cursor.
+pub fn after<'a>(query: EventsQuery<'a>, cursor: &EventCursor) -> EventsQuery<'a> {
+    query.filter(
+        sql::<Bool>("(checkpoint, digest, event_index) > (")
+            .bind::<BigInt, _>(cursor.checkpoint)
+            .sql(", ")
+            .bind::<Text, _>(cursor.digest.clone())
+            .sql(", ")
+            .bind::<BigInt, _>(cursor.event_index)
+            .sql(")"),
+    )
+}
+
+fn time_range(mut query: EventsQuery<'_>, from_ts: Option<i64>, to_ts: Option<i64>) -> EventsQuery<'_> {
+    if let Some(from_ts) = from_ts {
+        query = query.filter(unxv_events::checkpoint_timestamp_ms.ge(from_ts));
+    }
+    if let Some(to_ts) = to_ts {
+        query = query.filter(unxv_events::checkpoint_timestamp_ms.lt(to_ts));
+    }
+    query
+}
+
+// Fetches one row past the page to tell whether another page follows.
+async fn page(
+    conn: &mut AsyncPgConnection,
+    mut query: EventsQuery<'_>,
+    cursor: Option<&EventCursor>,
+    limit: usize,
+) -> QueryResult<EventPage> {
+    let limit = limit.clamp(1, MAX_PAGE_SIZE);
+    if let Some(cursor) = cursor {
+        query = after(query, cursor);
+    }
+    let mut events: Vec<UnxvEvent> = query
+        .order((unxv_events::checkpoint, unxv_events::digest, unxv_events::event_index))
+        .limit(limit as i64 + 1)
+        .select(UnxvEvent::as_select())
+        .load(conn)
+        .await?;
+    let next = if events.len() > limit {
+        events.truncate(limit);
+        events.last().map(EventCursor::from)
+    } else {
+        None
+    };
+    Ok(EventPage { events, next })
+}
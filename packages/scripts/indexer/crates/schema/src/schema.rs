@@ -1,8 +1,168 @@
 // @generated automatically by Diesel CLI.
 diesel::table! {
-    unxv_events (event_digest) {
+    unxv_events (digest, event_index) {
+        digest -> Text,
+        event_index -> BigInt,
+        sender -> Text,
+        checkpoint -> BigInt,
+        checkpoint_timestamp_ms -> BigInt,
+        package -> Text,
+        module -> Text,
+        event_type -> Text,
+        type_params -> Jsonb,
+        contents_bcs -> Bytea,
+        contents_json -> Nullable<Jsonb>,
+        indexer_version -> Nullable<Text>,
+        tx_timestamp_ms -> Nullable<BigInt>,
+        gas_used -> Nullable<BigInt>,
+        tx_success -> Nullable<Bool>,
+        type_params_truncated -> Bool,
+        type_hash -> Nullable<Text>,
+        call_packages -> Nullable<Jsonb>,
+        is_new_sender -> Nullable<Bool>,
+        type_param_0 -> Nullable<Text>,
+        type_param_1 -> Nullable<Text>,
+        type_params_key -> Nullable<Text>,
+        emitter_object -> Nullable<Text>,
+        contents_bcs_truncated -> Bool,
+        event_type_full -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    sender_first_seen (sender) {
+        sender -> Text,
+        first_checkpoint -> BigInt,
+        first_timestamp_ms -> BigInt,
+    }
+}
+
+diesel::table! {
+    unxv_event_counts (module, event_type, day) {
+        module -> Text,
+        event_type -> Text,
+        day -> Date,
+        count -> BigInt,
+    }
+}
+
+diesel::table! {
+    unxv_referrals (event_digest) {
+        event_digest -> Text,
+        digest -> Text,
+        checkpoint -> BigInt,
+        checkpoint_timestamp_ms -> BigInt,
+        package -> Text,
+        referrer -> Text,
+        referee -> Text,
+        reward_amount -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    dex_trades (event_digest) {
         event_digest -> Text,
         digest -> Text,
+        checkpoint -> BigInt,
+        checkpoint_timestamp_ms -> BigInt,
+        package -> Text,
+        pool_id -> Text,
+        maker -> Text,
+        taker -> Text,
+        price -> BigInt,
+        base_quantity -> BigInt,
+        quote_quantity -> BigInt,
+        is_bid -> Bool,
+        timestamp_ms -> BigInt,
+    }
+}
+
+diesel::table! {
+    usdu_flows (event_digest) {
+        event_digest -> Text,
+        digest -> Text,
+        checkpoint -> BigInt,
+        checkpoint_timestamp_ms -> BigInt,
+        package -> Text,
+        flow_type -> Text,
+        amount -> Numeric,
+        from_address -> Nullable<Text>,
+        to_address -> Nullable<Text>,
+        timestamp_ms -> BigInt,
+    }
+}
+
+diesel::table! {
+    usdu_supply (id) {
+        id -> Bool,
+        minted -> Numeric,
+        burned -> Numeric,
+        supply -> Numeric,
+    }
+}
+
+diesel::table! {
+    dex_market_participants (pool_id, day, role, account) {
+        pool_id -> Text,
+        day -> Date,
+        role -> Text,
+        account -> Text,
+    }
+}
+
+diesel::table! {
+    dex_daily_participants (pool_id, day) {
+        pool_id -> Text,
+        day -> Date,
+        unique_makers -> BigInt,
+        unique_takers -> BigInt,
+        finalized_at -> Timestamptz,
+        recounted_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    unxv_alerts (id) {
+        id -> Int8,
+        source -> Text,
+        severity -> Text,
+        key -> Text,
+        message -> Text,
+        context -> Jsonb,
+        first_seen_at -> Timestamptz,
+        last_seen_at -> Timestamptz,
+        occurrences -> Int4,
+        acknowledged_at -> Nullable<Timestamptz>,
+        acknowledged_by -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    unxv_object_changes (object_id, version) {
+        object_id -> Text,
+        version -> BigInt,
+        object_type -> Text,
+        digest -> Text,
+        checkpoint -> BigInt,
+        timestamp_ms -> BigInt,
+        contents_bcs -> Bytea,
+    }
+}
+
+diesel::table! {
+    unxv_checkpoints (checkpoint) {
+        checkpoint -> BigInt,
+        tx_count -> BigInt,
+        event_count -> BigInt,
+        processed_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    unxv_dead_letters (pipeline, digest, event_index) {
+        pipeline -> Text,
+        digest -> Text,
+        event_index -> BigInt,
         sender -> Text,
         checkpoint -> BigInt,
         checkpoint_timestamp_ms -> BigInt,
@@ -11,6 +171,143 @@ diesel::table! {
         event_type -> Text,
         type_params -> Jsonb,
         contents_bcs -> Bytea,
+        reason -> Text,
+        attempts -> Integer,
+        last_attempt_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    unxv_event_types (id) {
+        id -> Int4,
+        event_type -> Text,
+    }
+}
+
+diesel::table! {
+    unxv_tx_event_bitmap (digest) {
+        digest -> Text,
+        checkpoint -> BigInt,
+        event_type_ids -> Array<Int4>,
+    }
+}
+
+diesel::table! {
+    unxv_retention_changelog (id) {
+        id -> Int8,
+        changed_at -> Timestamptz,
+        policies -> Jsonb,
+    }
+}
+
+diesel::table! {
+    reprocess_progress (run) {
+        run -> Text,
+        after_checkpoint -> Nullable<BigInt>,
+        after_digest -> Nullable<Text>,
+        after_event_index -> Nullable<BigInt>,
+        scanned -> BigInt,
+        updated -> BigInt,
+        failed -> BigInt,
+        started_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        finished_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    live_stats_history (hour) {
+        hour -> Timestamptz,
+        day -> Date,
+        checkpoints_today -> BigInt,
+        events_today -> BigInt,
+        lag_ms -> Nullable<BigInt>,
+        per_module -> Jsonb,
+    }
+}
+
+diesel::table! {
+    unxv_events_quarantine (target_table, digest, event_index) {
+        pipeline -> Text,
+        target_table -> Text,
+        digest -> Text,
+        event_index -> BigInt,
+        checkpoint -> BigInt,
+        row -> Jsonb,
+        error -> Text,
+        quarantined_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    unxv_events_sampled (digest, event_index) {
+        digest -> Text,
+        event_index -> BigInt,
+        sender -> Text,
+        checkpoint -> BigInt,
+        checkpoint_timestamp_ms -> BigInt,
+        package -> Text,
+        module -> Text,
+        event_type -> Text,
+        contents_json -> Nullable<Jsonb>,
+        tx_success -> Nullable<Bool>,
+    }
+}
+
+diesel::table! {
+    unxv_transactions (digest) {
+        digest -> Text,
+        sender -> Text,
+        checkpoint -> BigInt,
+        checkpoint_timestamp_ms -> BigInt,
+        success -> Bool,
+        abort_code -> Nullable<BigInt>,
+        error -> Nullable<Text>,
+        computation_cost -> BigInt,
+        storage_cost -> BigInt,
+        storage_rebate -> BigInt,
+        move_calls -> Jsonb,
+    }
+}
+
+diesel::table! {
+    liquidation_cascades (cascade_id) {
+        cascade_id -> Text,
+        first_checkpoint -> BigInt,
+        last_checkpoint -> BigInt,
+        first_timestamp_ms -> BigInt,
+        last_timestamp_ms -> BigInt,
+        modules -> Array<Text>,
+        digests -> Array<Text>,
+        liquidations -> BigInt,
+        qty_closed -> BigInt,
+        notional -> BigInt,
+        penalty_collat -> BigInt,
+        detected_at -> Timestamptz,
     }
 }
 
+diesel::allow_tables_to_appear_in_same_query!(
+    dex_daily_participants,
+    dex_market_participants,
+    dex_trades,
+    liquidation_cascades,
+    live_stats_history,
+    reprocess_progress,
+    sender_first_seen,
+    unxv_alerts,
+    unxv_checkpoints,
+    unxv_dead_letters,
+    unxv_event_counts,
+    unxv_event_types,
+    unxv_events,
+    unxv_events_quarantine,
+    unxv_events_sampled,
+    unxv_object_changes,
+    unxv_referrals,
+    unxv_retention_changelog,
+    unxv_transactions,
+    unxv_tx_event_bitmap,
+    usdu_flows,
+    usdu_supply,
+);
@@ -0,0 +1,139 @@
+//! Paging through `unxv_events` with the `query` helpers against a throwaway Postgres, with
+//! pages that end mid-checkpoint and mid-transaction.
+
+use diesel_async::RunQueryDsl;
+use sui_pg_db::temp::TempDb;
+use sui_pg_db::{Db, DbArgs};
+use unxv_schema::models::UnxvEvent;
+use unxv_schema::query::{self, EventCursor, EventPage};
+use unxv_schema::schema::unxv_events;
+use unxv_schema::MIGRATIONS;
+
+const ALICE: &str = "0x00000000000000000000000000000000000000000000000000000000000a11ce";
+const BOB: &str = "0x0000000000000000000000000000000000000000000000000000000000000b0b";
+
+fn event(digest: &str, event_index: i64, checkpoint: i64, sender: &str, module: &str, event_type: &str) -> UnxvEvent {
+    UnxvEvent {
+        digest: digest.to_string(),
+        event_index,
+        sender: sender.to_string(),
+        checkpoint,
+        checkpoint_timestamp_ms: checkpoint * 1_000,
+        package: "0x000000000000000000000000000000000000000000000000000000000000beef".to_string(),
+        module: module.to_string(),
+        event_type: event_type.to_string(),
+        type_params: serde_json::json!([]),
+        contents_bcs: vec![],
+        contents_json: None,
+        indexer_version: None,
+        tx_timestamp_ms: None,
+        gas_used: None,
+        tx_success: None,
+        type_params_truncated: false,
+        type_hash: None,
+        call_packages: None,
+        is_new_sender: None,
+        type_param_0: None,
+        type_param_1: None,
+        type_params_key: None,
+        emitter_object: None,
+        contents_bcs_truncated: false,
+        event_type_full: None,
+    }
+}
+
+/// Alice's events, in page order: two checkpoints with two transactions each, one of them
+/// emitting three events. Bob's events interleave with them.
+fn seed() -> Vec<UnxvEvent> {
+    vec![
+        event("tx1", 0, 10, ALICE, "dex", "OrderFilled"),
+        event("tx1", 1, 10, ALICE, "dex", "ProtocolFeeTaken"),
+        event("tx1", 2, 10, ALICE, "lending", "DebtRepaid"),
+        event("tx2", 0, 10, BOB, "dex", "OrderFilled"),
+        event("tx3", 0, 10, ALICE, "dex", "OrderFilled"),
+        event("tx4", 0, 11, ALICE, "lending", "DebtRepaid"),
+        event("tx5", 0, 11, BOB, "dex", "OrderFilled"),
+        event("tx6", 0, 11, ALICE, "dex", "OrderFilled"),
+    ]
+}
+
+async fn database() -> (Db, TempDb) {
+    let temp = TempDb::new().unwrap();
+    let db = Db::for_write(temp.database().url().clone(), DbArgs::default()).await.unwrap();
+    db.run_migrations(Some(&MIGRATIONS)).await.unwrap();
+    let mut conn = db.connect().await.unwrap();
+    diesel::insert_into(unxv_events::table).values(seed()).execute(&mut conn).await.unwrap();
+    drop(conn);
+    (db, temp)
+}
+
+fn keys(page: &EventPage) -> Vec<(&str, i64)> {
+    page.events.iter().map(|e| (e.digest.as_str(), e.event_index)).collect()
+}
+
+#[tokio::test]
+async fn cursors_continue_across_page_boundaries() {
+    let (db, _temp) = database().await;
+    let mut conn = db.connect().await.unwrap();
+
+    // Pages of two split tx1's events and checkpoint 10.
+    let expected = [[("tx1", 0), ("tx1", 1)], [("tx1", 2), ("tx3", 0)], [("tx4", 0), ("tx6", 0)]];
+    let mut cursor: Option<EventCursor> = None;
+    for (n, expected) in expected.iter().enumerate() {
+        let page = query::events_by_sender(&mut conn, ALICE, cursor.as_ref(), 2).await.unwrap();
+        assert_eq!(keys(&page), expected, "page {n}");
+        // Clients carry the cursor as a string.
+        cursor = page.next.map(|next| EventCursor::decode(&next.encode()).unwrap());
+    }
+    assert_eq!(cursor, None);
+}
+
+#[tokio::test]
+async fn rows_committed_while_paging_do_not_shift_the_next_page() {
+    let (db, _temp) = database().await;
+    let mut conn = db.connect().await.unwrap();
+
+    let first = query::events_by_module(&mut conn, "dex", None, None, None, 3).await.unwrap();
+    assert_eq!(keys(&first), [("tx1", 0), ("tx1", 1), ("tx2", 0)]);
+
+    // A late row sorting before the cursor, and a new checkpoint after it.
+    let late = vec![event("tx0", 0, 10, BOB, "dex", "OrderFilled"), event("tx7", 0, 12, BOB, "dex", "OrderFilled")];
+    diesel::insert_into(unxv_events::table).values(late).execute(&mut conn).await.unwrap();
+
+    let second = query::events_by_module(&mut conn, "dex", None, None, first.next.as_ref(), 3).await.unwrap();
+    assert_eq!(keys(&second), [("tx3", 0), ("tx5", 0), ("tx6", 0)]);
+    let last = query::events_by_module(&mut conn, "dex", None, None, second.next.as_ref(), 3).await.unwrap();
+    assert_eq!(keys(&last), [("tx7", 0)]);
+    assert_eq!(last.next, None);
+}
+
+#[tokio::test]
+async fn an_exactly_full_last_page_has_no_cursor() {
+    let (db, _temp) = database().await;
+    let mut conn = db.connect().await.unwrap();
+
+    // `[from_ts, to_ts)`: checkpoint 11 only.
+    let page =
+        query::events_by_type(&mut conn, "dex", "OrderFilled", Some(11_000), Some(12_000), None, 2).await.unwrap();
+    assert_eq!(keys(&page), [("tx5", 0), ("tx6", 0)]);
+    assert_eq!(page.next, None);
+
+    let page = query::events_by_type(&mut conn, "dex", "OrderFilled", None, Some(11_000), None, 3).await.unwrap();
+    assert_eq!(keys(&page), [("tx1", 0), ("tx2", 0), ("tx3", 0)]);
+    assert_eq!(page.next, None);
+
+    // A zero limit still makes progress.
+    let page = query::events_by_sender(&mut conn, BOB, None, 0).await.unwrap();
+    assert_eq!(keys(&page), [("tx2", 0)]);
+    assert_eq!(page.next, Some(EventCursor { checkpoint: 10, digest: "tx2".to_string(), event_index: 0 }));
+}
+
+#[test]
+fn cursors_round_trip_as_strings() {
+    let cursor = EventCursor { checkpoint: 42, digest: "7Hq3".to_string(), event_index: 3 };
+    assert_eq!(cursor.encode(), "42:7Hq3:3");
+    assert_eq!(EventCursor::decode(&cursor.encode()), Some(cursor));
+    for invalid in ["", "42", "42:abc", "x:abc:3", "42:abc:x"] {
+        assert_eq!(EventCursor::decode(invalid), None, "{invalid:?}");
+    }
+}
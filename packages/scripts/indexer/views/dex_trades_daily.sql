@@ -0,0 +1,14 @@
+-- Daily volume and OHLC per DeepBook pool, by the UTC day of each fill.
+CREATE OR REPLACE VIEW dex_trades_daily AS
+SELECT
+    pool_id,
+    (to_timestamp(timestamp_ms / 1000.0) AT TIME ZONE 'UTC')::date AS day,
+    count(*) AS trades,
+    sum(base_quantity) AS base_volume,
+    sum(quote_quantity) AS quote_volume,
+    (array_agg(price ORDER BY checkpoint, event_digest))[1] AS open,
+    max(price) AS high,
+    min(price) AS low,
+    (array_agg(price ORDER BY checkpoint DESC, event_digest DESC))[1] AS close
+FROM dex_trades
+GROUP BY pool_id, day;